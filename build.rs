@@ -0,0 +1,9 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Use the vendored protoc so that building needs no system protobuf install
+    std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+    tonic_build::configure().compile_protos(
+        &["proto/signer/v1/signer.proto", "proto/keymanager/v1/keymanager.proto"],
+        &["proto"],
+    )?;
+    Ok(())
+}
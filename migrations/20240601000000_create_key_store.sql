@@ -0,0 +1,29 @@
+-- BLS key pairs indexed by operator EOA address.
+-- Private keys are encrypted with AES-256-GCM; the blob is nonce || ciphertext.
+CREATE TABLE IF NOT EXISTS key_pairs (
+    eoa_address TEXT PRIMARY KEY NOT NULL,
+    private_key_encrypted BLOB NOT NULL,
+    g1_x TEXT NOT NULL,
+    g1_y TEXT NOT NULL,
+    g2_x_a TEXT NOT NULL,
+    g2_x_b TEXT NOT NULL,
+    g2_y_a TEXT NOT NULL,
+    g2_y_b TEXT NOT NULL,
+    labels TEXT NOT NULL DEFAULT '[]',
+    created_at INTEGER NOT NULL
+);
+
+-- One row per signature produced by the service.
+CREATE TABLE IF NOT EXISTS signing_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    eoa_address TEXT NOT NULL REFERENCES key_pairs (eoa_address),
+    operation TEXT NOT NULL,
+    message_x TEXT NOT NULL,
+    message_y TEXT NOT NULL,
+    signature_x TEXT NOT NULL,
+    signature_y TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS signing_history_eoa_address
+    ON signing_history (eoa_address, created_at);
@@ -0,0 +1,14 @@
+-- API tokens authorizing clients of the key service.
+-- Only keccak256 of the token secret is stored.
+CREATE TABLE IF NOT EXISTS api_tokens (
+    id TEXT PRIMARY KEY NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    secret_hash TEXT NOT NULL,
+    -- JSON array of EOA addresses, or NULL for every key
+    eoa_addresses TEXT,
+    -- JSON array of operation names
+    operations TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    expires_at INTEGER,
+    revoked INTEGER NOT NULL DEFAULT 0
+);
@@ -0,0 +1,10 @@
+-- Nonces of EOA authorizations, kept until the authorization expires.
+-- Nonces are decimal strings since they range over the full u64.
+CREATE TABLE IF NOT EXISTS eoa_nonces (
+    eoa_address TEXT NOT NULL,
+    nonce TEXT NOT NULL,
+    expires_at INTEGER NOT NULL,
+    PRIMARY KEY (eoa_address, nonce)
+);
+
+CREATE INDEX IF NOT EXISTS eoa_nonces_expires_at ON eoa_nonces (expires_at);
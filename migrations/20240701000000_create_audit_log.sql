@@ -0,0 +1,25 @@
+-- Hash-chained audit log of signing requests, see src/web/audit.rs.
+-- Entries are only ever appended; the triggers reject edits and deletions.
+CREATE TABLE IF NOT EXISTS audit_log (
+    seq INTEGER PRIMARY KEY,
+    request_id TEXT NOT NULL,
+    timestamp INTEGER NOT NULL,
+    principal TEXT NOT NULL,
+    eoa_address TEXT NOT NULL,
+    operation TEXT NOT NULL,
+    input_hash TEXT,
+    output_hash TEXT,
+    result TEXT NOT NULL,
+    prev_hash TEXT NOT NULL,
+    hash TEXT NOT NULL
+);
+
+CREATE TRIGGER IF NOT EXISTS audit_log_no_update BEFORE UPDATE ON audit_log
+BEGIN
+    SELECT RAISE(ABORT, 'the audit log is append-only');
+END;
+
+CREATE TRIGGER IF NOT EXISTS audit_log_no_delete BEFORE DELETE ON audit_log
+BEGIN
+    SELECT RAISE(ABORT, 'the audit log is append-only');
+END;
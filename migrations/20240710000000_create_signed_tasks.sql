@@ -0,0 +1,10 @@
+-- The message signed by each key for each AVS task, see src/web/guard.rs.
+-- Task indices are decimal strings since they range over the full u64.
+CREATE TABLE IF NOT EXISTS signed_tasks (
+    eoa_address TEXT NOT NULL,
+    avs TEXT NOT NULL,
+    task_index TEXT NOT NULL,
+    message_hash TEXT NOT NULL,
+    signed_at INTEGER NOT NULL,
+    PRIMARY KEY (eoa_address, avs, task_index)
+);
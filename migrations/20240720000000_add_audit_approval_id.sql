@@ -0,0 +1,4 @@
+-- Links audit entries to the approval request they belong to, see src/web/approvals.rs.
+-- Adding a column does not fire the append-only triggers, and the column is
+-- NULL for every existing entry, which leaves their hashes unchanged.
+ALTER TABLE audit_log ADD COLUMN approval_id TEXT;
@@ -0,0 +1,4 @@
+-- Key lifecycle, see src/web/lifecycle.rs. Disabled keys refuse to sign, and the
+-- public keys replaced by rotations are kept as a JSON array of retired keys.
+ALTER TABLE key_pairs ADD COLUMN status TEXT NOT NULL DEFAULT 'active';
+ALTER TABLE key_pairs ADD COLUMN retired TEXT NOT NULL DEFAULT '[]';
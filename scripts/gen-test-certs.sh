@@ -0,0 +1,42 @@
+#!/usr/bin/env bash
+# Generates a throwaway CA, server certificate and client certificate for
+# running the key service with mutual TLS locally. Not for production use.
+#
+# Usage: scripts/gen-test-certs.sh [out_dir] [client_name]
+set -euo pipefail
+
+OUT="${1:-certs}"
+CLIENT="${2:-aggregator.local}"
+DAYS=30
+mkdir -p "$OUT"
+cd "$OUT"
+
+# Certificate authority for both the server and the clients
+openssl req -x509 -newkey ec -pkeyopt ec_paramgen_curve:P-256 -nodes \
+    -keyout ca.key -out ca.pem -days "$DAYS" -subj "/CN=bn254 test CA" \
+    -addext "basicConstraints=critical,CA:TRUE" -addext "keyUsage=critical,keyCertSign,cRLSign" 2>/dev/null
+
+issue() {
+    local name="$1" cn="$2" ext="$3"
+    openssl req -newkey ec -pkeyopt ec_paramgen_curve:P-256 -nodes \
+        -keyout "$name.key" -out "$name.csr" -subj "/CN=$cn" 2>/dev/null
+    printf '%s\n' "$ext" > "$name.ext"
+    openssl x509 -req -in "$name.csr" -CA ca.pem -CAkey ca.key -CAcreateserial \
+        -out "$name.pem" -days "$DAYS" -extfile "$name.ext" 2>/dev/null
+    rm "$name.csr" "$name.ext"
+}
+
+issue server localhost "subjectAltName=DNS:localhost,IP:127.0.0.1
+extendedKeyUsage=serverAuth"
+issue client "$CLIENT" "subjectAltName=DNS:$CLIENT
+extendedKeyUsage=clientAuth"
+
+cat <<MSG
+Wrote $(pwd)/{ca,server,client}.{pem,key}
+
+Run the service with:
+  cargo run -- --tls-cert $OUT/server.pem --tls-key $OUT/server.key --tls-client-ca $OUT/ca.pem
+
+and call it with:
+  curl --cacert $OUT/ca.pem --cert $OUT/client.pem --key $OUT/client.key https://localhost:8080/api/keys
+MSG
@@ -0,0 +1,483 @@
+//! Module for distributed key generation (DKG) on the BN254 curve.
+//!
+//! This implements the Joint-Feldman protocol of Pedersen's DKG: every participant
+//! acts as a dealer of a random secret using Feldman verifiable secret sharing, and
+//! the group secret is the sum of the secrets of all qualified dealers. The group
+//! secret never exists in one place; each participant ends up with a
+//! [`KeyShare`] that can be used with the [`threshold`](crate::threshold) module.
+//!
+//! Dealers commit to their polynomial coefficients in both G1 and G2, so the group
+//! public key is available in both groups and every share can be checked against
+//! the commitments with a multi-scalar multiplication.
+//!
+//! The protocol runs in four phases:
+//!
+//! 1. **Dealing**: each participant broadcasts a [`DealerCommitment`] and sends a
+//!    private [`Share`] to every other participant.
+//! 2. **Complaining**: each participant checks the shares it received and
+//!    broadcasts a [`Complaint`] for every missing or invalid one.
+//! 3. **Justifying**: each accused dealer broadcasts a [`Justification`] that
+//!    reveals the disputed share; dealers that fail to do so convincingly are
+//!    disqualified.
+//! 4. **Finished**: the qualified set is fixed and [`DkgOutput`] is derived.
+//!
+//! The module assumes authenticated private channels and a reliable broadcast
+//! channel, and only models the message flow. Every participant is an
+//! independent state machine, so the whole protocol can be run in-process.
+//!
+//! # Examples
+//!
+//! ```
+//! use bn254_rs::dkg::{DkgParams, Participant};
+//!
+//! let params = DkgParams::new(2, 3).unwrap();
+//! let mut rng = rand::thread_rng();
+//! let mut participants: Vec<Participant> = (1..=3)
+//!     .map(|i| Participant::new(i, params, &mut rng).unwrap())
+//!     .collect();
+//!
+//! // Dealing
+//! let deals: Vec<_> = participants.iter_mut().map(|p| p.deal().unwrap()).collect();
+//! for (commitment, shares) in &deals {
+//!     for p in participants.iter_mut() {
+//!         p.receive_commitment(commitment.clone()).unwrap();
+//!     }
+//!     for share in shares {
+//!         participants[share.recipient as usize - 1].receive_share(*share).unwrap();
+//!     }
+//! }
+//!
+//! // Nobody misbehaved, so there is nothing to complain about or justify
+//! for p in participants.iter_mut() {
+//!     assert!(p.complaints().unwrap().is_empty());
+//! }
+//! for p in participants.iter_mut() {
+//!     assert!(p.justifications().unwrap().is_empty());
+//! }
+//!
+//! let outputs: Vec<_> = participants.iter_mut().map(|p| p.finalize().unwrap()).collect();
+//! assert!(outputs.iter().all(|o| o.group_public_key_g2 == outputs[0].group_public_key_g2));
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use ark_bn254::Fr;
+use ark_ff::UniformRand;
+use rand::Rng;
+
+use crate::g1::G1Point;
+use crate::g2::G2Point;
+use crate::threshold::KeyShare;
+use crate::utils::{evaluate_polynomial, powers};
+
+/// Parameters shared by every participant of a DKG run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DkgParams {
+    /// Number of shares needed to sign with the resulting key
+    pub threshold: usize,
+    /// Number of participants, indexed `1..=participants`
+    pub participants: usize,
+}
+
+impl DkgParams {
+    /// Creates a new parameter set, checking that `1 <= threshold <= participants`.
+    pub fn new(threshold: usize, participants: usize) -> Result<Self, String> {
+        if threshold == 0 {
+            return Err("Threshold must be at least 1".to_string());
+        }
+        if threshold > participants {
+            return Err(format!(
+                "Threshold {} exceeds the number of participants {}",
+                threshold, participants
+            ));
+        }
+        Ok(Self { threshold, participants })
+    }
+
+    fn contains(&self, index: u64) -> bool {
+        index >= 1 && index <= self.participants as u64
+    }
+}
+
+/// The phase a participant is in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DkgPhase {
+    /// Exchanging commitments and shares
+    Dealing,
+    /// Checking received shares and exchanging complaints
+    Complaining,
+    /// Answering and checking complaints
+    Justifying,
+    /// The protocol has completed
+    Finished,
+}
+
+/// Feldman commitments to a dealer's polynomial coefficients, broadcast to everyone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DealerCommitment {
+    /// Index of the dealer
+    pub dealer: u64,
+    /// `a_k * G1` for every coefficient `a_k`
+    pub coefficients_g1: Vec<G1Point>,
+    /// `a_k * G2` for every coefficient `a_k`
+    pub coefficients_g2: Vec<G2Point>,
+}
+
+/// A share sent privately from a dealer to a recipient.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Share {
+    /// Index of the dealer
+    pub dealer: u64,
+    /// Index of the recipient, which is also the evaluation point
+    pub recipient: u64,
+    /// The dealer's polynomial evaluated at `recipient`
+    pub value: Fr,
+}
+
+/// A broadcast accusation that a dealer sent a missing or invalid share.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Complaint {
+    /// Index of the participant raising the complaint
+    pub accuser: u64,
+    /// Index of the accused dealer
+    pub dealer: u64,
+}
+
+/// A dealer's broadcast answer to a complaint, revealing the disputed share.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Justification {
+    /// The complaint being answered
+    pub complaint: Complaint,
+    /// The share the dealer claims to have sent
+    pub share: Share,
+}
+
+/// The result of a successful DKG run for one participant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DkgOutput {
+    /// This participant's share of the group secret
+    pub share: KeyShare,
+    /// The group public key in G1
+    pub group_public_key_g1: G1Point,
+    /// The group public key in G2
+    pub group_public_key_g2: G2Point,
+    /// Public keys in G2 for every participant's share, used to verify partial signatures
+    pub public_key_shares: BTreeMap<u64, G2Point>,
+    /// Indices of the dealers whose secrets make up the group key
+    pub qualified: Vec<u64>,
+}
+
+/// Evaluates G1 commitments at `index`, i.e. `sum(index^k * C_k)`.
+fn evaluate_commitment_g1(coefficients: &[G1Point], index: u64) -> Result<G1Point, String> {
+    G1Point::msm(coefficients, &powers(Fr::from(index), coefficients.len()))
+}
+
+/// Evaluates G2 commitments at `index`, i.e. `sum(index^k * D_k)`.
+fn evaluate_commitment_g2(coefficients: &[G2Point], index: u64) -> Result<G2Point, String> {
+    G2Point::msm(coefficients, &powers(Fr::from(index), coefficients.len()))
+}
+
+impl DealerCommitment {
+    /// Checks that a share is consistent with this commitment in both groups.
+    pub fn verify_share(&self, share: &Share) -> bool {
+        if share.dealer != self.dealer {
+            return false;
+        }
+        let expected_g1 = match evaluate_commitment_g1(&self.coefficients_g1, share.recipient) {
+            Ok(p) => p,
+            Err(_) => return false,
+        };
+        let expected_g2 = match evaluate_commitment_g2(&self.coefficients_g2, share.recipient) {
+            Ok(p) => p,
+            Err(_) => return false,
+        };
+        G1Point::generator().scalar_mul(share.value) == expected_g1
+            && G2Point::generator().scalar_mul(share.value) == expected_g2
+    }
+}
+
+/// A single participant of a DKG run.
+pub struct Participant {
+    index: u64,
+    params: DkgParams,
+    phase: DkgPhase,
+    polynomial: Vec<Fr>,
+    commitments: BTreeMap<u64, DealerCommitment>,
+    shares: BTreeMap<u64, Fr>,
+    complaints: BTreeSet<Complaint>,
+    answered: BTreeSet<Complaint>,
+    disqualified: BTreeSet<u64>,
+}
+
+impl Participant {
+    /// Creates a participant and samples its secret polynomial.
+    ///
+    /// # Arguments
+    /// * `index` - The participant's index in `1..=params.participants`
+    /// * `params` - The parameters shared by all participants
+    /// * `rng` - A cryptographically secure random number generator
+    pub fn new<R: Rng + ?Sized>(index: u64, params: DkgParams, rng: &mut R) -> Result<Self, String> {
+        if !params.contains(index) {
+            return Err(format!("Participant index {} is out of range", index));
+        }
+        Ok(Self {
+            index,
+            params,
+            phase: DkgPhase::Dealing,
+            polynomial: (0..params.threshold).map(|_| Fr::rand(rng)).collect(),
+            commitments: BTreeMap::new(),
+            shares: BTreeMap::new(),
+            complaints: BTreeSet::new(),
+            answered: BTreeSet::new(),
+            disqualified: BTreeSet::new(),
+        })
+    }
+
+    /// Returns this participant's index.
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+
+    /// Returns the phase this participant is in.
+    pub fn phase(&self) -> DkgPhase {
+        self.phase
+    }
+
+    fn expect_phase(&self, phase: DkgPhase) -> Result<(), String> {
+        if self.phase != phase {
+            return Err(format!("Expected phase {:?}, currently in {:?}", phase, self.phase));
+        }
+        Ok(())
+    }
+
+    /// Produces this participant's commitment and the shares for every participant.
+    ///
+    /// The share addressed to this participant is also included and should be
+    /// delivered back to it like any other.
+    pub fn deal(&self) -> Result<(DealerCommitment, Vec<Share>), String> {
+        self.expect_phase(DkgPhase::Dealing)?;
+        let commitment = DealerCommitment {
+            dealer: self.index,
+            coefficients_g1: self
+                .polynomial
+                .iter()
+                .map(|a| G1Point::generator().scalar_mul(*a))
+                .collect(),
+            coefficients_g2: self
+                .polynomial
+                .iter()
+                .map(|a| G2Point::generator().scalar_mul(*a))
+                .collect(),
+        };
+        let shares = (1..=self.params.participants as u64)
+            .map(|recipient| Share {
+                dealer: self.index,
+                recipient,
+                value: evaluate_polynomial(&self.polynomial, Fr::from(recipient)),
+            })
+            .collect();
+        Ok((commitment, shares))
+    }
+
+    /// Records a dealer's broadcast commitment.
+    pub fn receive_commitment(&mut self, commitment: DealerCommitment) -> Result<(), String> {
+        self.expect_phase(DkgPhase::Dealing)?;
+        if !self.params.contains(commitment.dealer) {
+            return Err(format!("Dealer index {} is out of range", commitment.dealer));
+        }
+        if self.commitments.contains_key(&commitment.dealer) {
+            return Err(format!("Duplicate commitment from dealer {}", commitment.dealer));
+        }
+        if commitment.coefficients_g1.len() != self.params.threshold
+            || commitment.coefficients_g2.len() != self.params.threshold
+        {
+            // A commitment of the wrong degree cannot be fixed by a justification
+            self.disqualified.insert(commitment.dealer);
+        }
+        self.commitments.insert(commitment.dealer, commitment);
+        Ok(())
+    }
+
+    /// Records a share sent privately to this participant.
+    pub fn receive_share(&mut self, share: Share) -> Result<(), String> {
+        self.expect_phase(DkgPhase::Dealing)?;
+        if share.recipient != self.index {
+            return Err(format!(
+                "Share for participant {} delivered to participant {}",
+                share.recipient, self.index
+            ));
+        }
+        if !self.params.contains(share.dealer) {
+            return Err(format!("Dealer index {} is out of range", share.dealer));
+        }
+        if self.shares.insert(share.dealer, share.value).is_some() {
+            return Err(format!("Duplicate share from dealer {}", share.dealer));
+        }
+        Ok(())
+    }
+
+    /// Ends the dealing phase and returns complaints against every dealer whose
+    /// share is missing or does not match its commitment.
+    ///
+    /// These complaints should be broadcast to every other participant.
+    pub fn complaints(&mut self) -> Result<Vec<Complaint>, String> {
+        self.expect_phase(DkgPhase::Dealing)?;
+        // Dealers that never committed are disqualified outright
+        for dealer in 1..=self.params.participants as u64 {
+            if !self.commitments.contains_key(&dealer) {
+                self.disqualified.insert(dealer);
+            }
+        }
+
+        let mut complaints = Vec::new();
+        for (&dealer, commitment) in &self.commitments {
+            if self.disqualified.contains(&dealer) {
+                continue;
+            }
+            let valid = self.shares.get(&dealer).is_some_and(|&value| {
+                commitment.verify_share(&Share {
+                    dealer,
+                    recipient: self.index,
+                    value,
+                })
+            });
+            if !valid {
+                complaints.push(Complaint {
+                    accuser: self.index,
+                    dealer,
+                });
+            }
+        }
+        for complaint in &complaints {
+            self.shares.remove(&complaint.dealer);
+            self.complaints.insert(*complaint);
+        }
+        self.phase = DkgPhase::Complaining;
+        Ok(complaints)
+    }
+
+    /// Records a complaint broadcast by another participant.
+    pub fn receive_complaint(&mut self, complaint: Complaint) -> Result<(), String> {
+        self.expect_phase(DkgPhase::Complaining)?;
+        if !self.params.contains(complaint.accuser) || !self.params.contains(complaint.dealer) {
+            return Err("Complaint refers to an unknown participant".to_string());
+        }
+        self.complaints.insert(complaint);
+        Ok(())
+    }
+
+    /// Ends the complaint phase and returns justifications for every complaint
+    /// raised against this participant.
+    ///
+    /// These justifications should be broadcast to every other participant.
+    pub fn justifications(&mut self) -> Result<Vec<Justification>, String> {
+        self.expect_phase(DkgPhase::Complaining)?;
+        self.phase = DkgPhase::Justifying;
+        Ok(self
+            .complaints
+            .iter()
+            .filter(|c| c.dealer == self.index)
+            .map(|&complaint| Justification {
+                complaint,
+                share: Share {
+                    dealer: self.index,
+                    recipient: complaint.accuser,
+                    value: evaluate_polynomial(&self.polynomial, Fr::from(complaint.accuser)),
+                },
+            })
+            .collect())
+    }
+
+    /// Records a justification broadcast by an accused dealer.
+    ///
+    /// If the revealed share is invalid the dealer is disqualified. If it is valid
+    /// and addressed to this participant, it replaces the disputed share.
+    pub fn receive_justification(&mut self, justification: Justification) -> Result<(), String> {
+        self.expect_phase(DkgPhase::Justifying)?;
+        let complaint = justification.complaint;
+        if !self.complaints.contains(&complaint) {
+            return Err(format!(
+                "Justification for unknown complaint by {} against {}",
+                complaint.accuser, complaint.dealer
+            ));
+        }
+        let share = justification.share;
+        let valid = share.dealer == complaint.dealer
+            && share.recipient == complaint.accuser
+            && self
+                .commitments
+                .get(&complaint.dealer)
+                .is_some_and(|c| c.verify_share(&share));
+        if !valid {
+            self.disqualified.insert(complaint.dealer);
+        } else if complaint.accuser == self.index {
+            self.shares.insert(complaint.dealer, share.value);
+        }
+        self.answered.insert(complaint);
+        Ok(())
+    }
+
+    /// Ends the protocol and derives this participant's share and the group key.
+    ///
+    /// Dealers with unanswered complaints are disqualified. The protocol fails if
+    /// fewer than `threshold` dealers remain qualified, or if this participant is
+    /// missing a share from a qualified dealer.
+    pub fn finalize(&mut self) -> Result<DkgOutput, String> {
+        self.expect_phase(DkgPhase::Justifying)?;
+        for complaint in self.complaints.difference(&self.answered) {
+            self.disqualified.insert(complaint.dealer);
+        }
+
+        let qualified: Vec<u64> = self
+            .commitments
+            .keys()
+            .copied()
+            .filter(|dealer| !self.disqualified.contains(dealer))
+            .collect();
+        if qualified.len() < self.params.threshold {
+            return Err(format!(
+                "Only {} qualified dealers, need at least {}",
+                qualified.len(),
+                self.params.threshold
+            ));
+        }
+
+        let mut secret = Fr::from(0u64);
+        let mut group_public_key_g1 = G1Point::zero();
+        let mut group_public_key_g2 = G2Point::zero();
+        for dealer in &qualified {
+            let value = self
+                .shares
+                .get(dealer)
+                .ok_or_else(|| format!("Missing share from qualified dealer {}", dealer))?;
+            secret += value;
+            let commitment = &self.commitments[dealer];
+            group_public_key_g1 = group_public_key_g1.add(&commitment.coefficients_g1[0]);
+            group_public_key_g2 = group_public_key_g2.add(&commitment.coefficients_g2[0]);
+        }
+
+        let mut public_key_shares = BTreeMap::new();
+        for index in 1..=self.params.participants as u64 {
+            let mut pk = G2Point::zero();
+            for dealer in &qualified {
+                pk = pk.add(&evaluate_commitment_g2(
+                    &self.commitments[dealer].coefficients_g2,
+                    index,
+                )?);
+            }
+            public_key_shares.insert(index, pk);
+        }
+
+        self.phase = DkgPhase::Finished;
+        Ok(DkgOutput {
+            share: KeyShare {
+                index: self.index,
+                secret,
+            },
+            group_public_key_g1,
+            group_public_key_g2,
+            public_key_shares,
+            qualified,
+        })
+    }
+}
@@ -0,0 +1,299 @@
+//! Module for byte encodings of BN254 curve points.
+//!
+//! Three encodings are supported:
+//!
+//! - **EVM**: uncompressed big-endian coordinates as used by the precompiles and
+//!   `BN254.sol`. G1 is `x || y` (64 bytes) and G2 is `x.c1 || x.c0 || y.c1 || y.c0`
+//!   (128 bytes), i.e. the imaginary component first. The point at infinity is
+//!   encoded as all zeros.
+//! - **gnark compressed**: the x coordinate only (32 bytes for G1, 64 bytes for G2
+//!   with the imaginary component first), with the two most significant bits of
+//!   the first byte selecting the y coordinate. This is the format used by gnark,
+//!   EigenDA's SRS files and many Go tools.
+//! - **gnark raw**: the EVM layout, except that the point at infinity is marked by
+//!   a flag in the two most significant bits of the first byte.
+//!
+//! Every decoder checks that the point is on the curve and in the prime order
+//! subgroup.
+//!
+//! # Examples
+//!
+//! ```
+//! use bn254_rs::G1Point;
+//! use bn254_rs::encoding::{g1_from_bytes, g1_from_compressed, g1_to_bytes, g1_to_compressed};
+//!
+//! let g = G1Point::generator();
+//! assert_eq!(g1_from_bytes(&g1_to_bytes(&g)).unwrap(), g);
+//! assert_eq!(g1_from_compressed(&g1_to_compressed(&g)).unwrap(), g);
+//! ```
+
+use ark_bn254::{Fq, Fq2, G1Affine, G2Affine};
+use ark_ec::short_weierstrass::SWCurveConfig;
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::{BigInteger, Field, PrimeField, Zero};
+
+use crate::g1::G1Point;
+use crate::g2::G2Point;
+
+/// Mask selecting the flag bits of a gnark encoded point.
+const FLAG_MASK: u8 = 0b11 << 6;
+/// gnark flag for an uncompressed point.
+const FLAG_UNCOMPRESSED: u8 = 0b00 << 6;
+/// gnark flag for the uncompressed point at infinity.
+const FLAG_UNCOMPRESSED_INFINITY: u8 = 0b01 << 6;
+/// gnark flag for a compressed point with the lexicographically smallest y.
+const FLAG_COMPRESSED_SMALLEST: u8 = 0b10 << 6;
+/// gnark flag for a compressed point with the lexicographically largest y.
+const FLAG_COMPRESSED_LARGEST: u8 = 0b11 << 6;
+/// gnark flag for the compressed point at infinity.
+const FLAG_COMPRESSED_INFINITY: u8 = 0b01 << 6;
+
+/// Parses a 32-byte big-endian value into Fq, rejecting values that are not
+/// fully reduced modulo p.
+pub fn fq_from_be_bytes(bytes: &[u8]) -> Result<Fq, String> {
+    if bytes.len() != 32 {
+        return Err(format!("Expected 32 bytes for a field element, got {}", bytes.len()));
+    }
+    let fq = Fq::from_be_bytes_mod_order(bytes);
+    if fq.into_bigint().to_bytes_be() != bytes {
+        return Err("Field element is not reduced modulo p".to_string());
+    }
+    Ok(fq)
+}
+
+/// Converts an Fq element to a 32-byte array in big-endian format.
+pub fn fq_to_be_bytes(f: &Fq) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    out.copy_from_slice(&f.into_bigint().to_bytes_be());
+    out
+}
+
+/// Returns whether `y > (p - 1) / 2`, gnark's definition of "largest".
+fn fq_is_largest(y: &Fq) -> bool {
+    y.into_bigint() > Fq::MODULUS_MINUS_ONE_DIV_TWO
+}
+
+/// gnark's ordering for Fq2: compare the imaginary part, falling back to the real part.
+fn fq2_is_largest(y: &Fq2) -> bool {
+    if y.c1.is_zero() {
+        fq_is_largest(&y.c0)
+    } else {
+        fq_is_largest(&y.c1)
+    }
+}
+
+fn check_g1(p: G1Affine) -> Result<G1Point, String> {
+    if !p.is_on_curve() {
+        return Err("G1 point is not on the curve".to_string());
+    }
+    Ok(G1Point(p.into()))
+}
+
+fn check_g2(p: G2Affine) -> Result<G2Point, String> {
+    if !p.is_on_curve() {
+        return Err("G2 point is not on the curve".to_string());
+    }
+    if !p.is_in_correct_subgroup_assuming_on_curve() {
+        return Err("G2 point is not in the prime order subgroup".to_string());
+    }
+    Ok(G2Point(p.into()))
+}
+
+/// Encodes a G1 point as 64 bytes in the EVM format.
+pub fn g1_to_bytes(p: &G1Point) -> [u8; 64] {
+    let mut out = [0u8; 64];
+    let aff = p.inner().into_affine();
+    if !aff.is_zero() {
+        out[..32].copy_from_slice(&fq_to_be_bytes(&aff.x));
+        out[32..].copy_from_slice(&fq_to_be_bytes(&aff.y));
+    }
+    out
+}
+
+/// Decodes a G1 point from 64 bytes in the EVM format.
+pub fn g1_from_bytes(bytes: &[u8]) -> Result<G1Point, String> {
+    if bytes.len() != 64 {
+        return Err(format!("Expected 64 bytes for a G1 point, got {}", bytes.len()));
+    }
+    if bytes.iter().all(|b| *b == 0) {
+        return Ok(G1Point::zero());
+    }
+    let x = fq_from_be_bytes(&bytes[..32])?;
+    let y = fq_from_be_bytes(&bytes[32..])?;
+    check_g1(G1Affine::new_unchecked(x, y))
+}
+
+/// Encodes a G2 point as 128 bytes in the EVM format.
+pub fn g2_to_bytes(p: &G2Point) -> [u8; 128] {
+    let mut out = [0u8; 128];
+    let aff = p.inner().into_affine();
+    if !aff.is_zero() {
+        out[..32].copy_from_slice(&fq_to_be_bytes(&aff.x.c1));
+        out[32..64].copy_from_slice(&fq_to_be_bytes(&aff.x.c0));
+        out[64..96].copy_from_slice(&fq_to_be_bytes(&aff.y.c1));
+        out[96..].copy_from_slice(&fq_to_be_bytes(&aff.y.c0));
+    }
+    out
+}
+
+/// Decodes a G2 point from 128 bytes in the EVM format.
+pub fn g2_from_bytes(bytes: &[u8]) -> Result<G2Point, String> {
+    if bytes.len() != 128 {
+        return Err(format!("Expected 128 bytes for a G2 point, got {}", bytes.len()));
+    }
+    if bytes.iter().all(|b| *b == 0) {
+        return Ok(G2Point::zero());
+    }
+    let x = Fq2::new(fq_from_be_bytes(&bytes[32..64])?, fq_from_be_bytes(&bytes[..32])?);
+    let y = Fq2::new(fq_from_be_bytes(&bytes[96..])?, fq_from_be_bytes(&bytes[64..96])?);
+    check_g2(G2Affine::new_unchecked(x, y))
+}
+
+/// Encodes a G1 point as 32 bytes in the gnark compressed format.
+pub fn g1_to_compressed(p: &G1Point) -> [u8; 32] {
+    let aff = p.inner().into_affine();
+    if aff.is_zero() {
+        let mut out = [0u8; 32];
+        out[0] = FLAG_COMPRESSED_INFINITY;
+        return out;
+    }
+    let mut out = fq_to_be_bytes(&aff.x);
+    out[0] |= if fq_is_largest(&aff.y) {
+        FLAG_COMPRESSED_LARGEST
+    } else {
+        FLAG_COMPRESSED_SMALLEST
+    };
+    out
+}
+
+/// Decodes a G1 point from 32 bytes in the gnark compressed format.
+pub fn g1_from_compressed(bytes: &[u8]) -> Result<G1Point, String> {
+    if bytes.len() != 32 {
+        return Err(format!(
+            "Expected 32 bytes for a compressed G1 point, got {}",
+            bytes.len()
+        ));
+    }
+    let flag = bytes[0] & FLAG_MASK;
+    let mut x_bytes = [0u8; 32];
+    x_bytes.copy_from_slice(bytes);
+    x_bytes[0] &= !FLAG_MASK;
+
+    match flag {
+        FLAG_COMPRESSED_INFINITY => {
+            if x_bytes.iter().any(|b| *b != 0) {
+                return Err("Invalid encoding of the point at infinity".to_string());
+            }
+            Ok(G1Point::zero())
+        }
+        FLAG_COMPRESSED_SMALLEST | FLAG_COMPRESSED_LARGEST => {
+            let x = fq_from_be_bytes(&x_bytes)?;
+            let mut y = (x.square() * x + Fq::from(3u64))
+                .sqrt()
+                .ok_or_else(|| "Compressed G1 x coordinate is not on the curve".to_string())?;
+            if fq_is_largest(&y) != (flag == FLAG_COMPRESSED_LARGEST) {
+                y = -y;
+            }
+            check_g1(G1Affine::new_unchecked(x, y))
+        }
+        FLAG_UNCOMPRESSED => Err("Point is not compressed".to_string()),
+        _ => unreachable!(),
+    }
+}
+
+/// Encodes a G2 point as 64 bytes in the gnark compressed format.
+pub fn g2_to_compressed(p: &G2Point) -> [u8; 64] {
+    let mut out = [0u8; 64];
+    let aff = p.inner().into_affine();
+    if aff.is_zero() {
+        out[0] = FLAG_COMPRESSED_INFINITY;
+        return out;
+    }
+    out[..32].copy_from_slice(&fq_to_be_bytes(&aff.x.c1));
+    out[32..].copy_from_slice(&fq_to_be_bytes(&aff.x.c0));
+    out[0] |= if fq2_is_largest(&aff.y) {
+        FLAG_COMPRESSED_LARGEST
+    } else {
+        FLAG_COMPRESSED_SMALLEST
+    };
+    out
+}
+
+/// Decodes a G2 point from 64 bytes in the gnark compressed format.
+pub fn g2_from_compressed(bytes: &[u8]) -> Result<G2Point, String> {
+    if bytes.len() != 64 {
+        return Err(format!(
+            "Expected 64 bytes for a compressed G2 point, got {}",
+            bytes.len()
+        ));
+    }
+    let flag = bytes[0] & FLAG_MASK;
+    let mut x_bytes = [0u8; 64];
+    x_bytes.copy_from_slice(bytes);
+    x_bytes[0] &= !FLAG_MASK;
+
+    match flag {
+        FLAG_COMPRESSED_INFINITY => {
+            if x_bytes.iter().any(|b| *b != 0) {
+                return Err("Invalid encoding of the point at infinity".to_string());
+            }
+            Ok(G2Point::zero())
+        }
+        FLAG_COMPRESSED_SMALLEST | FLAG_COMPRESSED_LARGEST => {
+            let x = Fq2::new(fq_from_be_bytes(&x_bytes[32..])?, fq_from_be_bytes(&x_bytes[..32])?);
+            let mut y = (x.square() * x + ark_bn254::g2::Config::COEFF_B)
+                .sqrt()
+                .ok_or_else(|| "Compressed G2 x coordinate is not on the curve".to_string())?;
+            if fq2_is_largest(&y) != (flag == FLAG_COMPRESSED_LARGEST) {
+                y = -y;
+            }
+            check_g2(G2Affine::new_unchecked(x, y))
+        }
+        FLAG_UNCOMPRESSED => Err("Point is not compressed".to_string()),
+        _ => unreachable!(),
+    }
+}
+
+/// Returns whether the first byte of a gnark encoded point carries a compressed flag.
+pub fn is_gnark_compressed(first_byte: u8) -> bool {
+    matches!(
+        first_byte & FLAG_MASK,
+        FLAG_COMPRESSED_SMALLEST | FLAG_COMPRESSED_LARGEST
+    )
+}
+
+/// Checks the flag of a gnark raw encoding, returning whether it is the point at infinity.
+fn gnark_raw_is_infinity(bytes: &[u8]) -> Result<bool, String> {
+    match bytes.first().map(|b| b & FLAG_MASK) {
+        Some(FLAG_UNCOMPRESSED) => Ok(false),
+        Some(FLAG_UNCOMPRESSED_INFINITY) => {
+            if bytes[0] & !FLAG_MASK != 0 || bytes[1..].iter().any(|b| *b != 0) {
+                return Err("Invalid encoding of the point at infinity".to_string());
+            }
+            Ok(true)
+        }
+        _ => Err("Point is not in the gnark raw encoding".to_string()),
+    }
+}
+
+/// Decodes a G1 point from 64 bytes in the gnark raw format.
+pub fn g1_from_gnark_raw(bytes: &[u8]) -> Result<G1Point, String> {
+    if bytes.len() != 64 {
+        return Err(format!("Expected 64 bytes for a G1 point, got {}", bytes.len()));
+    }
+    if gnark_raw_is_infinity(bytes)? {
+        return Ok(G1Point::zero());
+    }
+    g1_from_bytes(bytes)
+}
+
+/// Decodes a G2 point from 128 bytes in the gnark raw format.
+pub fn g2_from_gnark_raw(bytes: &[u8]) -> Result<G2Point, String> {
+    if bytes.len() != 128 {
+        return Err(format!("Expected 128 bytes for a G2 point, got {}", bytes.len()));
+    }
+    if gnark_raw_is_infinity(bytes)? {
+        return Ok(G2Point::zero());
+    }
+    g2_from_bytes(bytes)
+}
@@ -5,6 +5,7 @@
 //! - Point negation
 //! - Point addition
 //! - Scalar multiplication
+//! - Multi-scalar multiplication
 //! 
 //! The BN254 curve is a pairing-friendly elliptic curve that is widely used in
 //! zero-knowledge proof systems and other cryptographic applications.
@@ -30,7 +31,7 @@
 //! ```
 
 use ark_bn254::{G1Projective, Fr};
-use ark_ec::Group;
+use ark_ec::{CurveGroup, Group, VariableBaseMSM};
 use ark_ff::Zero;
 
 /// A point on the G1 group of the BN254 curve.
@@ -56,6 +57,20 @@ impl G1Point {
         Self(G1Projective::generator())
     }
 
+    /// Returns the point at infinity, the identity element of the G1 group.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use bn254_rs::G1Point;
+    /// 
+    /// let g = G1Point::generator();
+    /// assert_eq!(g.add(&G1Point::zero()), g);
+    /// ```
+    pub fn zero() -> Self {
+        Self(G1Projective::zero())
+    }
+
     /// Creates a new G1Point from a G1Projective.
     /// 
     /// This is primarily used in tests and for advanced operations.
@@ -128,6 +143,36 @@ impl G1Point {
         }
     }
 
+    /// Computes the multi-scalar multiplication `sum(scalars[i] * points[i])`.
+    /// Uses Pippenger's algorithm, which is much faster than repeated
+    /// [`scalar_mul`](Self::scalar_mul) and [`add`](Self::add) for long inputs.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use bn254_rs::G1Point;
+    /// use ark_bn254::Fr;
+    /// 
+    /// let g = G1Point::generator();
+    /// let sum = G1Point::msm(&[g, g], &[Fr::from(2u64), Fr::from(3u64)]).unwrap();
+    /// assert_eq!(sum, g.scalar_mul(Fr::from(5u64)));
+    /// ```
+    /// 
+    /// # Returns
+    /// The resulting point, or an error if the inputs differ in length
+    pub fn msm(points: &[Self], scalars: &[Fr]) -> Result<Self, String> {
+        if points.len() != scalars.len() {
+            return Err(format!(
+                "MSM length mismatch: {} points, {} scalars",
+                points.len(),
+                scalars.len()
+            ));
+        }
+        let bases: Vec<G1Projective> = points.iter().map(|p| p.0).collect();
+        let bases = G1Projective::normalize_batch(&bases);
+        Ok(Self(G1Projective::msm_unchecked(&bases, scalars)))
+    }
+
     /// Returns the underlying G1Projective point.
     /// 
     /// This is primarily used internally and for advanced operations.
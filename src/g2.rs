@@ -3,6 +3,9 @@
 //! This module provides basic operations for working with points in the G2 group:
 //! - Getting the generator point
 //! - Point negation
+//! - Point addition
+//! - Scalar multiplication
+//! - Multi-scalar multiplication
 //! 
 //! The G2 group is the second group in the BN254 pairing-friendly elliptic curve,
 //! which is used in conjunction with G1 for bilinear pairings.
@@ -19,8 +22,9 @@
 //! let neg_g2 = g2.negate();
 //! ```
 
-use ark_bn254::G2Projective;
-use ark_ec::Group;
+use ark_bn254::{G2Projective, Fr};
+use ark_ec::{CurveGroup, Group, VariableBaseMSM};
+use ark_ff::Zero;
 
 /// A point on the G2 group of the BN254 curve.
 /// 
@@ -45,6 +49,20 @@ impl G2Point {
         Self(G2Projective::generator())
     }
 
+    /// Returns the point at infinity, the identity element of the G2 group.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use bn254_rs::G2Point;
+    /// 
+    /// let g = G2Point::generator();
+    /// assert_eq!(g.add(&G2Point::zero()), g);
+    /// ```
+    pub fn zero() -> Self {
+        Self(G2Projective::zero())
+    }
+
     /// Creates a new G2Point from a G2Projective.
     /// 
     /// This is primarily used in tests and for advanced operations.
@@ -78,6 +96,73 @@ impl G2Point {
         Self(-self.0)
     }
 
+    /// Adds two points in the G2 group.
+    /// Implements the group operation for points on the BN254 twist curve.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use bn254_rs::G2Point;
+    /// 
+    /// let g2 = G2Point::generator();
+    /// let sum = g2.add(&g2);
+    /// ```
+    pub fn add(&self, other: &Self) -> Self {
+        Self(self.0 + other.0)
+    }
+
+    /// Performs scalar multiplication of a point in G2.
+    /// Multiplies a point by a scalar value (field element), returning the
+    /// point at infinity for a zero scalar.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use bn254_rs::G2Point;
+    /// use ark_bn254::Fr;
+    /// 
+    /// let g2 = G2Point::generator();
+    /// let doubled = g2.scalar_mul(Fr::from(2u64));
+    /// assert_eq!(doubled, g2.add(&g2));
+    /// ```
+    pub fn scalar_mul(&self, scalar: Fr) -> Self {
+        if scalar.is_zero() {
+            Self(G2Projective::zero())
+        } else {
+            Self(self.0 * scalar)
+        }
+    }
+
+    /// Computes the multi-scalar multiplication `sum(scalars[i] * points[i])`.
+    /// Uses Pippenger's algorithm, which is much faster than repeated
+    /// [`scalar_mul`](Self::scalar_mul) and [`add`](Self::add) for long inputs.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use bn254_rs::G2Point;
+    /// use ark_bn254::Fr;
+    /// 
+    /// let g = G2Point::generator();
+    /// let sum = G2Point::msm(&[g, g], &[Fr::from(2u64), Fr::from(3u64)]).unwrap();
+    /// assert_eq!(sum, g.scalar_mul(Fr::from(5u64)));
+    /// ```
+    /// 
+    /// # Returns
+    /// The resulting point, or an error if the inputs differ in length
+    pub fn msm(points: &[Self], scalars: &[Fr]) -> Result<Self, String> {
+        if points.len() != scalars.len() {
+            return Err(format!(
+                "MSM length mismatch: {} points, {} scalars",
+                points.len(),
+                scalars.len()
+            ));
+        }
+        let bases: Vec<G2Projective> = points.iter().map(|p| p.0).collect();
+        let bases = G2Projective::normalize_batch(&bases);
+        Ok(Self(G2Projective::msm_unchecked(&bases, scalars)))
+    }
+
     /// Returns the underlying G2Projective point.
     /// 
     /// This is primarily used internally and for advanced operations.
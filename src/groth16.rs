@@ -0,0 +1,329 @@
+//! Module for verifying Groth16 proofs on the BN254 curve.
+//!
+//! The verifier accepts the same inputs as the Solidity verifiers generated by
+//! snarkjs and gnark, so it can be used to check proofs off-chain before they are
+//! submitted to a contract:
+//!
+//! - snarkjs `verification_key.json`, `proof.json` and `public.json`
+//! - gnark's binary `VerifyingKey`, in both compressed and raw encodings
+//!
+//! Public inputs are folded into the input commitment with a G1 multi-scalar
+//! multiplication, and the proof is checked with a single four-pair
+//! [`pairing_check_multi`]:
+//!
+//! `e(-A, B) * e(alpha, beta) * e(vk_x, gamma) * e(C, delta) == 1`
+//!
+//! Like the Solidity verifiers, public inputs must be reduced modulo the scalar
+//! field order and every point must be a valid group element.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use bn254_rs::groth16::{parse_snarkjs_public_inputs, verify, Proof, VerifyingKey};
+//! use std::fs;
+//!
+//! let vk = VerifyingKey::from_snarkjs_json(&fs::read_to_string("verification_key.json").unwrap()).unwrap();
+//! let proof = Proof::from_snarkjs_json(&fs::read_to_string("proof.json").unwrap()).unwrap();
+//! let inputs = parse_snarkjs_public_inputs(&fs::read_to_string("public.json").unwrap()).unwrap();
+//!
+//! assert!(verify(&vk, &proof, &inputs).unwrap());
+//! ```
+
+use ark_bn254::{Fq, Fq2, Fr, G1Affine, G2Affine};
+use serde::Deserialize;
+
+use crate::encoding::{
+    g1_from_compressed, g1_from_gnark_raw, g2_from_compressed, g2_from_gnark_raw,
+    is_gnark_compressed,
+};
+use crate::g1::G1Point;
+use crate::g2::G2Point;
+use crate::pairing::pairing_check_multi;
+use crate::utils::parse_decimal_field;
+
+/// A Groth16 verification key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyingKey {
+    /// `[alpha]_1`
+    pub alpha_g1: G1Point,
+    /// `[beta]_2`
+    pub beta_g2: G2Point,
+    /// `[gamma]_2`
+    pub gamma_g2: G2Point,
+    /// `[delta]_2`
+    pub delta_g2: G2Point,
+    /// Input commitment bases; the first is the constant term, followed by one
+    /// point per public input
+    pub ic: Vec<G1Point>,
+}
+
+/// A Groth16 proof.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Proof {
+    /// `[A]_1`
+    pub a: G1Point,
+    /// `[B]_2`
+    pub b: G2Point,
+    /// `[C]_1`
+    pub c: G1Point,
+}
+
+/// A snarkjs verification key, as written by `snarkjs zkey export verificationkey`.
+#[derive(Debug, Deserialize)]
+struct SnarkjsVerifyingKey {
+    protocol: String,
+    curve: String,
+    #[serde(rename = "nPublic")]
+    n_public: usize,
+    vk_alpha_1: Vec<String>,
+    vk_beta_2: Vec<Vec<String>>,
+    vk_gamma_2: Vec<Vec<String>>,
+    vk_delta_2: Vec<Vec<String>>,
+    #[serde(rename = "IC")]
+    ic: Vec<Vec<String>>,
+}
+
+/// A snarkjs proof, as written by `snarkjs groth16 prove`.
+#[derive(Debug, Deserialize)]
+struct SnarkjsProof {
+    pi_a: Vec<String>,
+    pi_b: Vec<Vec<String>>,
+    pi_c: Vec<String>,
+    #[serde(default)]
+    protocol: Option<String>,
+}
+
+/// Checks the protocol and curve fields of a snarkjs file.
+fn check_snarkjs_header(protocol: &str, curve: Option<&str>) -> Result<(), String> {
+    if protocol != "groth16" {
+        return Err(format!("Unsupported protocol: {}", protocol));
+    }
+    if let Some(curve) = curve {
+        if curve != "bn128" && curve != "bn254" {
+            return Err(format!("Unsupported curve: {}", curve));
+        }
+    }
+    Ok(())
+}
+
+/// Parses a snarkjs G1 point given as projective `[x, y, z]` with `z` either 0 or 1.
+fn g1_from_snarkjs(coords: &[String]) -> Result<G1Point, String> {
+    if coords.len() != 3 {
+        return Err(format!("Expected 3 G1 coordinates, got {}", coords.len()));
+    }
+    match coords[2].as_str() {
+        "0" => Ok(G1Point::zero()),
+        "1" => {
+            let p = G1Affine::new_unchecked(
+                parse_decimal_field::<Fq>(&coords[0])?,
+                parse_decimal_field::<Fq>(&coords[1])?,
+            );
+            if !p.is_on_curve() {
+                return Err("G1 point is not on the curve".to_string());
+            }
+            Ok(G1Point(p.into()))
+        }
+        z => Err(format!("Unsupported G1 z coordinate: {}", z)),
+    }
+}
+
+/// Parses a snarkjs G2 point given as projective `[[x0, x1], [y0, y1], [z0, z1]]`.
+/// snarkjs orders Fq2 components as `[real, imaginary]`, unlike Solidity.
+fn g2_from_snarkjs(coords: &[Vec<String>]) -> Result<G2Point, String> {
+    if coords.len() != 3 || coords.iter().any(|c| c.len() != 2) {
+        return Err("Expected 3 G2 coordinates of 2 components each".to_string());
+    }
+    let fq2 = |c: &[String]| -> Result<Fq2, String> {
+        Ok(Fq2::new(
+            parse_decimal_field::<Fq>(&c[0])?,
+            parse_decimal_field::<Fq>(&c[1])?,
+        ))
+    };
+    match (coords[2][0].as_str(), coords[2][1].as_str()) {
+        ("0", "0") => Ok(G2Point::zero()),
+        ("1", "0") => {
+            let p = G2Affine::new_unchecked(fq2(&coords[0])?, fq2(&coords[1])?);
+            if !p.is_on_curve() {
+                return Err("G2 point is not on the curve".to_string());
+            }
+            if !p.is_in_correct_subgroup_assuming_on_curve() {
+                return Err("G2 point is not in the prime order subgroup".to_string());
+            }
+            Ok(G2Point(p.into()))
+        }
+        _ => Err("Unsupported G2 z coordinate".to_string()),
+    }
+}
+
+/// A cursor over a gnark binary verification key.
+struct GnarkReader<'a> {
+    bytes: &'a [u8],
+    compressed: bool,
+}
+
+impl GnarkReader<'_> {
+    fn take(&mut self, n: usize) -> Result<&[u8], String> {
+        if self.bytes.len() < n {
+            return Err("Unexpected end of gnark verification key".to_string());
+        }
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let b = self.take(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn g1(&mut self) -> Result<G1Point, String> {
+        if self.compressed {
+            g1_from_compressed(self.take(32)?)
+        } else {
+            g1_from_gnark_raw(self.take(64)?)
+        }
+    }
+
+    fn g2(&mut self) -> Result<G2Point, String> {
+        if self.compressed {
+            g2_from_compressed(self.take(64)?)
+        } else {
+            g2_from_gnark_raw(self.take(128)?)
+        }
+    }
+}
+
+impl VerifyingKey {
+    /// Parses a snarkjs `verification_key.json`.
+    pub fn from_snarkjs_json(json: &str) -> Result<Self, String> {
+        let vk: SnarkjsVerifyingKey =
+            serde_json::from_str(json).map_err(|e| format!("Invalid verification key: {}", e))?;
+        check_snarkjs_header(&vk.protocol, Some(&vk.curve))?;
+        if vk.ic.len() != vk.n_public + 1 {
+            return Err(format!(
+                "Verification key has {} IC points for {} public inputs",
+                vk.ic.len(),
+                vk.n_public
+            ));
+        }
+        Ok(Self {
+            alpha_g1: g1_from_snarkjs(&vk.vk_alpha_1)?,
+            beta_g2: g2_from_snarkjs(&vk.vk_beta_2)?,
+            gamma_g2: g2_from_snarkjs(&vk.vk_gamma_2)?,
+            delta_g2: g2_from_snarkjs(&vk.vk_delta_2)?,
+            ic: vk
+                .ic
+                .iter()
+                .map(|p| g1_from_snarkjs(p))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Parses a gnark binary verification key, as written by `VerifyingKey.WriteTo`
+    /// (compressed) or `VerifyingKey.WriteRawTo` (raw).
+    ///
+    /// The layout is `[alpha]_1, [beta]_1, [beta]_2, [gamma]_2, [delta]_1, [delta]_2`
+    /// followed by a length-prefixed list of `K` points. Keys that use gnark's
+    /// Pedersen commitment extension are rejected.
+    pub fn from_gnark_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let first = *bytes
+            .first()
+            .ok_or_else(|| "Empty gnark verification key".to_string())?;
+        let mut reader = GnarkReader {
+            bytes,
+            compressed: is_gnark_compressed(first),
+        };
+
+        let alpha_g1 = reader.g1()?;
+        let _beta_g1 = reader.g1()?;
+        let beta_g2 = reader.g2()?;
+        let gamma_g2 = reader.g2()?;
+        let _delta_g1 = reader.g1()?;
+        let delta_g2 = reader.g2()?;
+
+        let k_len = reader.u32()? as usize;
+        let ic = (0..k_len)
+            .map(|_| reader.g1())
+            .collect::<Result<Vec<_>, _>>()?;
+        if ic.is_empty() {
+            return Err("gnark verification key has no K points".to_string());
+        }
+
+        // Newer gnark versions append the commitment layout; only an empty one is supported
+        if !reader.bytes.is_empty() {
+            let committed = reader.u32()?;
+            let commitment_keys = reader.u32()?;
+            if committed != 0 || commitment_keys != 0 {
+                return Err(
+                    "gnark verification keys with commitments are not supported".to_string(),
+                );
+            }
+        }
+        if !reader.bytes.is_empty() {
+            return Err("Trailing bytes after gnark verification key".to_string());
+        }
+
+        Ok(Self {
+            alpha_g1,
+            beta_g2,
+            gamma_g2,
+            delta_g2,
+            ic,
+        })
+    }
+
+    /// Returns the number of public inputs this key expects.
+    pub fn num_public_inputs(&self) -> usize {
+        self.ic.len() - 1
+    }
+
+    /// Folds public inputs into the input commitment `IC[0] + sum(inputs[i] * IC[i + 1])`.
+    pub fn prepare_inputs(&self, inputs: &[Fr]) -> Result<G1Point, String> {
+        if inputs.len() != self.num_public_inputs() {
+            return Err(format!(
+                "Expected {} public inputs, got {}",
+                self.num_public_inputs(),
+                inputs.len()
+            ));
+        }
+        Ok(self.ic[0].add(&G1Point::msm(&self.ic[1..], inputs)?))
+    }
+}
+
+impl Proof {
+    /// Parses a snarkjs `proof.json`.
+    pub fn from_snarkjs_json(json: &str) -> Result<Self, String> {
+        let proof: SnarkjsProof =
+            serde_json::from_str(json).map_err(|e| format!("Invalid proof: {}", e))?;
+        if let Some(protocol) = &proof.protocol {
+            check_snarkjs_header(protocol, None)?;
+        }
+        Ok(Self {
+            a: g1_from_snarkjs(&proof.pi_a)?,
+            b: g2_from_snarkjs(&proof.pi_b)?,
+            c: g1_from_snarkjs(&proof.pi_c)?,
+        })
+    }
+}
+
+/// Parses a snarkjs `public.json`, an array of decimal strings.
+pub fn parse_snarkjs_public_inputs(json: &str) -> Result<Vec<Fr>, String> {
+    let inputs: Vec<String> =
+        serde_json::from_str(json).map_err(|e| format!("Invalid public inputs: {}", e))?;
+    inputs.iter().map(|s| parse_decimal_field(s)).collect()
+}
+
+/// Verifies a Groth16 proof against a verification key and public inputs.
+///
+/// # Returns
+/// `Ok(true)` if the proof is valid, `Ok(false)` if it is not, or an error if
+/// the number of public inputs does not match the key
+pub fn verify(vk: &VerifyingKey, proof: &Proof, inputs: &[Fr]) -> Result<bool, String> {
+    let vk_x = vk.prepare_inputs(inputs)?;
+    Ok(pairing_check_multi(&[
+        (proof.a.negate(), proof.b),
+        (vk.alpha_g1, vk.beta_g2),
+        (vk_x, vk.gamma_g2),
+        (proof.c, vk.delta_g2),
+    ]))
+}
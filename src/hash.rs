@@ -1,6 +1,7 @@
 //! Module for hashing operations on BN254 curve points.
 //! 
-//! This module provides functions for hashing points in the G1 group of the BN254 curve.
+//! This module provides functions for hashing points in the G1 and G2 groups of the
+//! BN254 curve, and for mapping a 32-byte digest onto G1 the same way `BN254.sol` does.
 //! It uses the Keccak-256 hash function (SHA-3) to produce a 32-byte hash output.
 //! 
 //! # Examples
@@ -12,12 +13,13 @@
 //! let hash = hash_g1_point(&g);
 //! ```
 
-use ark_bn254::G1Projective;
+use ark_bn254::{Fq, G1Affine, G1Projective};
 use ark_ec::CurveGroup;
-use ark_ff::{BigInteger, PrimeField}; 
+use ark_ff::{BigInteger, Field, One, PrimeField}; 
 use sha3::{Digest, Keccak256};
 
 use crate::g1::G1Point;
+use crate::g2::G2Point;
 
 /// Hashes a G1 point to a 32-byte array using Keccak-256.
 /// The hash is computed by concatenating the big-endian representations of
@@ -46,6 +48,76 @@ pub fn hash_g1_point(p: &G1Point) -> [u8; 32] {
     result.into()
 }
 
+/// Hashes a G2 point to a 32-byte array using Keccak-256.
+/// The coordinates are packed in the same order as `BN254.hashG2Point`, i.e.
+/// `X[0], X[1], Y[0], Y[1]` with the imaginary component first.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use bn254_rs::{G2Point, hash_g2_point};
+/// 
+/// let g2 = G2Point::generator();
+/// let hash = hash_g2_point(&g2);
+/// ```
+/// 
+/// # Arguments
+/// * `p` - The G2 point to hash
+/// 
+/// # Returns
+/// A 32-byte array containing the hash of the point
+pub fn hash_g2_point(p: &G2Point) -> [u8; 32] {
+    let aff = p.inner().into_affine();
+    let mut hasher = Keccak256::new();
+    // Solidity stores Fq2 elements as [imaginary, real]
+    hasher.update(aff.x.c1.into_bigint().to_bytes_be());
+    hasher.update(aff.x.c0.into_bigint().to_bytes_be());
+    hasher.update(aff.y.c1.into_bigint().to_bytes_be());
+    hasher.update(aff.y.c0.into_bigint().to_bytes_be());
+    let result = hasher.finalize();
+    result.into()
+}
+
+/// Maps a 32-byte digest to a point in G1.
+/// This mirrors `BN254.hashToG1`: the digest is reduced into Fq and used as a
+/// candidate x coordinate, which is incremented until `x^3 + 3` is a square.
+/// The y coordinate is computed as `(x^3 + 3)^((p + 1) / 4)`, so the result is
+/// identical to the point the contract derives.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use bn254_rs::hash_to_g1;
+/// use ark_ec::CurveGroup;
+/// 
+/// let p = hash_to_g1(&[0x42; 32]);
+/// assert!(p.inner().into_affine().is_on_curve());
+/// ```
+/// 
+/// # Arguments
+/// * `digest` - The 32-byte value to map, typically a Keccak-256 hash
+/// 
+/// # Returns
+/// A G1 point derived deterministically from the digest
+pub fn hash_to_g1(digest: &[u8; 32]) -> G1Point {
+    // (p + 1) / 4, the square root exponent used by `findYFromX`
+    let mut exponent = Fq::MODULUS;
+    exponent.add_with_carry(&1u64.into());
+    exponent.div2();
+    exponent.div2();
+
+    let b = Fq::from(3u64);
+    let mut x = Fq::from_be_bytes_mod_order(digest);
+    loop {
+        let beta = x.square() * x + b;
+        let y = beta.pow(exponent);
+        if y.square() == beta {
+            return G1Point(G1Affine::new_unchecked(x, y).into());
+        }
+        x += Fq::one();
+    }
+}
+
 // For backward compatibility
 pub fn hash_g1_point_raw(p: &G1Projective) -> [u8; 32] {
     hash_g1_point(&G1Point(*p))
@@ -0,0 +1,401 @@
+//! Module for KZG polynomial commitments on the BN254 curve.
+//!
+//! Polynomials over `Fr` are committed to with a G1 multi-scalar multiplication
+//! against a structured reference string (SRS) of powers of a secret `tau`, and
+//! evaluations are proven with a single G1 point checked by a [`pairing_check`].
+//! This is the scheme used by EigenDA-style blob dispersal, and it works directly
+//! with the crate's [`G1Point`] and [`G2Point`] types.
+//!
+//! Polynomials are given as coefficient slices, lowest degree first.
+//!
+//! Supported operations:
+//! - [`commit`] to a polynomial
+//! - [`open`] and [`verify`] a single evaluation
+//! - [`open_multi`] a polynomial at several points and check all proofs at once
+//!   with [`batch_verify`]
+//! - [`batch_open`] several polynomials at the same point with a single proof,
+//!   checked with [`verify_batch_opening`]
+//!
+//! Random linear combinations are derived with a Keccak-256 Fiat-Shamir transcript,
+//! so every proof is non-interactive.
+//!
+//! # Examples
+//!
+//! ```
+//! use bn254_rs::kzg::{commit, open, verify, Srs};
+//! use ark_bn254::Fr;
+//!
+//! // Only for testing: a real SRS comes from a trusted setup ceremony
+//! let srs = Srs::insecure_from_secret(Fr::from(123456u64), 8);
+//!
+//! // p(x) = 1 + 2x + 3x^2
+//! let poly = [Fr::from(1u64), Fr::from(2u64), Fr::from(3u64)];
+//! let commitment = commit(&srs, &poly).unwrap();
+//!
+//! let opening = open(&srs, &poly, Fr::from(5u64)).unwrap();
+//! assert_eq!(opening.value, Fr::from(86u64));
+//! assert!(verify(&srs, &commitment, &opening));
+//! ```
+
+use std::fs;
+use std::path::Path;
+
+use ark_bn254::Fr;
+use ark_ff::{PrimeField, Zero};
+use sha3::{Digest, Keccak256};
+
+use crate::encoding::{g1_from_compressed, g1_to_bytes, g2_from_compressed, g2_to_bytes};
+use crate::g1::G1Point;
+use crate::g2::G2Point;
+use crate::pairing::pairing_check;
+use crate::utils::{evaluate_polynomial, fr_to_be_bytes, powers};
+
+/// Domain separation tag for the Fiat-Shamir transcript.
+const TRANSCRIPT_DOMAIN: &[u8] = b"BN254_RS_KZG_V1";
+
+/// A structured reference string: `tau^i * G1` for `i` in `0..n` and `tau * G2`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Srs {
+    g1_powers: Vec<G1Point>,
+    g2_tau: G2Point,
+}
+
+/// A proof that a committed polynomial evaluates to `value` at `point`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Opening {
+    /// The evaluation point
+    pub point: Fr,
+    /// The claimed evaluation
+    pub value: Fr,
+    /// Commitment to the quotient `(p(x) - value) / (x - point)`
+    pub proof: G1Point,
+}
+
+/// A single proof for the evaluations of several polynomials at the same point.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchOpening {
+    /// The evaluation point
+    pub point: Fr,
+    /// The claimed evaluation of each polynomial, in commitment order
+    pub values: Vec<Fr>,
+    /// Commitment to the quotient of the combined polynomial
+    pub proof: G1Point,
+}
+
+impl Srs {
+    /// Creates an SRS from its G1 powers and `tau * G2`.
+    ///
+    /// The first power must be the G1 generator, and each further power must be
+    /// `tau` times the one before it, for the `tau` of `tau * G2`. All powers are
+    /// checked at once: with a Fiat-Shamir challenge `r` bound to every point,
+    /// `e(sum(r^i * P_(i+1)), G2) == e(sum(r^i * P_i), tau * G2)`.
+    pub fn new(g1_powers: Vec<G1Point>, g2_tau: G2Point) -> Result<Self, String> {
+        match g1_powers.first() {
+            None => return Err("SRS must contain at least one G1 power".to_string()),
+            Some(g) if *g != G1Point::generator() => {
+                return Err("First G1 power of the SRS must be the generator".to_string())
+            }
+            _ => {}
+        }
+        if g1_powers.len() > 1 {
+            let weights = powers(srs_challenge(&g1_powers, &g2_tau), g1_powers.len() - 1);
+            let shifted = G1Point::msm(&g1_powers[1..], &weights)?;
+            let base = G1Point::msm(&g1_powers[..g1_powers.len() - 1], &weights)?;
+            if !pairing_check(shifted, G2Point::generator().negate(), base, g2_tau) {
+                return Err("G1 and G2 powers of the SRS are not powers of the same tau".to_string());
+            }
+        }
+        Ok(Self { g1_powers, g2_tau })
+    }
+
+    /// Builds an SRS of `size` G1 powers from a known secret.
+    ///
+    /// Anyone who knows `tau` can forge openings, so this is only suitable for tests.
+    pub fn insecure_from_secret(tau: Fr, size: usize) -> Self {
+        Self {
+            g1_powers: powers(tau, size)
+                .into_iter()
+                .map(|t| G1Point::generator().scalar_mul(t))
+                .collect(),
+            g2_tau: G2Point::generator().scalar_mul(tau),
+        }
+    }
+
+    /// Parses an SRS from gnark compressed points, as found in EigenDA's
+    /// `g1.point` and `g2.point` files.
+    ///
+    /// # Arguments
+    /// * `g1_bytes` - Concatenated 32-byte compressed G1 powers
+    /// * `g2_tau_bytes` - The 64-byte compressed `tau * G2`, the second point of
+    ///   `g2.point`
+    pub fn from_compressed(g1_bytes: &[u8], g2_tau_bytes: &[u8]) -> Result<Self, String> {
+        if !g1_bytes.len().is_multiple_of(32) {
+            return Err(format!(
+                "G1 SRS length {} is not a multiple of 32 bytes",
+                g1_bytes.len()
+            ));
+        }
+        let g1_powers = g1_bytes
+            .chunks(32)
+            .map(g1_from_compressed)
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::new(g1_powers, g2_from_compressed(g2_tau_bytes)?)
+    }
+
+    /// Loads the first `size` G1 powers and `tau * G2` from files of gnark
+    /// compressed points, laid out like EigenDA's `g1.point` and `g2.point`.
+    ///
+    /// # Arguments
+    /// * `g1_path` - File of concatenated 32-byte compressed G1 powers
+    /// * `g2_path` - File of concatenated 64-byte compressed G2 powers, starting
+    ///   with the generator and then `tau * G2`
+    /// * `size` - Number of G1 powers to load
+    pub fn load<P: AsRef<Path>, Q: AsRef<Path>>(
+        g1_path: P,
+        g2_path: Q,
+        size: usize,
+    ) -> anyhow::Result<Self> {
+        let g1_bytes = fs::read(g1_path)?;
+        let g2_bytes = fs::read(g2_path)?;
+        if g1_bytes.len() < size * 32 {
+            anyhow::bail!(
+                "G1 SRS file holds {} points, {} requested",
+                g1_bytes.len() / 32,
+                size
+            );
+        }
+        if g2_bytes.len() < 128 {
+            anyhow::bail!("G2 SRS file is shorter than two compressed points");
+        }
+        if g2_from_compressed(&g2_bytes[..64]).map_err(anyhow::Error::msg)? != G2Point::generator() {
+            anyhow::bail!("G2 SRS file does not start with the generator");
+        }
+        Self::from_compressed(&g1_bytes[..size * 32], &g2_bytes[64..128]).map_err(anyhow::Error::msg)
+    }
+
+    /// Returns the highest polynomial degree this SRS can commit to.
+    pub fn max_degree(&self) -> usize {
+        self.g1_powers.len() - 1
+    }
+
+    /// Returns the G1 powers of tau.
+    pub fn g1_powers(&self) -> &[G1Point] {
+        &self.g1_powers
+    }
+
+    /// Returns `tau * G2`.
+    pub fn g2_tau(&self) -> &G2Point {
+        &self.g2_tau
+    }
+}
+
+/// Divides `poly(x) - poly(point)` by `x - point` using synthetic division.
+fn divide_by_linear(poly: &[Fr], point: Fr) -> Vec<Fr> {
+    if poly.len() < 2 {
+        return Vec::new();
+    }
+    let mut quotient = vec![Fr::zero(); poly.len() - 1];
+    let mut carry = Fr::zero();
+    for i in (1..poly.len()).rev() {
+        carry = poly[i] + carry * point;
+        quotient[i - 1] = carry;
+    }
+    quotient
+}
+
+/// Derives a Fiat-Shamir challenge from a list of G1 points and scalars.
+fn challenge(points: &[G1Point], scalars: &[Fr]) -> Fr {
+    let mut hasher = Keccak256::new();
+    hasher.update(TRANSCRIPT_DOMAIN);
+    for p in points {
+        hasher.update(g1_to_bytes(p));
+    }
+    for s in scalars {
+        hasher.update(fr_to_be_bytes(s));
+    }
+    Fr::from_be_bytes_mod_order(&hasher.finalize())
+}
+
+/// Commits to a polynomial.
+///
+/// # Returns
+/// `sum(poly[i] * tau^i * G1)`, or an error if the degree exceeds the SRS
+pub fn commit(srs: &Srs, poly: &[Fr]) -> Result<G1Point, String> {
+    if poly.len() > srs.g1_powers.len() {
+        return Err(format!(
+            "Polynomial degree {} exceeds the SRS maximum of {}",
+            poly.len() - 1,
+            srs.max_degree()
+        ));
+    }
+    G1Point::msm(&srs.g1_powers[..poly.len()], poly)
+}
+
+/// Opens a polynomial at a single point.
+pub fn open(srs: &Srs, poly: &[Fr], point: Fr) -> Result<Opening, String> {
+    Ok(Opening {
+        point,
+        value: evaluate_polynomial(poly, point),
+        proof: commit(srs, &divide_by_linear(poly, point))?,
+    })
+}
+
+/// Verifies a single-point opening against a commitment.
+///
+/// This function checks `e(proof, tau * G2 - point * G2) == e(C - value * G1, G2)`,
+/// rearranged as `e(C - value * G1 + point * proof, -G2) * e(proof, tau * G2) == 1`.
+pub fn verify(srs: &Srs, commitment: &G1Point, opening: &Opening) -> bool {
+    let lhs = commitment
+        .add(&G1Point::generator().scalar_mul(opening.value).negate())
+        .add(&opening.proof.scalar_mul(opening.point));
+    pairing_check(
+        lhs,
+        G2Point::generator().negate(),
+        opening.proof,
+        srs.g2_tau,
+    )
+}
+
+/// Opens a polynomial at several points, producing one proof per point.
+///
+/// The proofs can be checked together with [`batch_verify`].
+pub fn open_multi(srs: &Srs, poly: &[Fr], points: &[Fr]) -> Result<Vec<Opening>, String> {
+    points.iter().map(|&point| open(srs, poly, point)).collect()
+}
+
+/// Verifies many single-point openings, possibly against different commitments,
+/// with one pairing check.
+///
+/// The individual checks are combined with powers of a Fiat-Shamir challenge `r`:
+/// `e(sum(r^i * (C_i - y_i * G1 + z_i * pi_i)), -G2) * e(sum(r^i * pi_i), tau * G2) == 1`.
+///
+/// # Arguments
+/// * `items` - Pairs of a commitment and an opening of the committed polynomial
+///
+/// # Returns
+/// `true` if every opening is valid, `false` otherwise
+pub fn batch_verify(srs: &Srs, items: &[(G1Point, Opening)]) -> bool {
+    if items.is_empty() {
+        return true;
+    }
+
+    let mut transcript_points = Vec::with_capacity(items.len() * 2);
+    let mut transcript_scalars = Vec::with_capacity(items.len() * 2);
+    for (commitment, opening) in items {
+        transcript_points.extend([*commitment, opening.proof]);
+        transcript_scalars.extend([opening.point, opening.value]);
+    }
+    let r = powers(challenge(&transcript_points, &transcript_scalars), items.len());
+
+    let mut lhs_points = Vec::with_capacity(items.len() * 2 + 1);
+    let mut lhs_scalars = Vec::with_capacity(items.len() * 2 + 1);
+    let mut proofs = Vec::with_capacity(items.len());
+    let mut value_sum = Fr::zero();
+    for ((commitment, opening), r_i) in items.iter().zip(&r) {
+        lhs_points.extend([*commitment, opening.proof]);
+        lhs_scalars.extend([*r_i, *r_i * opening.point]);
+        proofs.push(opening.proof);
+        value_sum += *r_i * opening.value;
+    }
+    lhs_points.push(G1Point::generator());
+    lhs_scalars.push(-value_sum);
+
+    let (lhs, proof_sum) = match (
+        G1Point::msm(&lhs_points, &lhs_scalars),
+        G1Point::msm(&proofs, &r),
+    ) {
+        (Ok(lhs), Ok(proof_sum)) => (lhs, proof_sum),
+        _ => return false,
+    };
+    pairing_check(lhs, G2Point::generator().negate(), proof_sum, srs.g2_tau)
+}
+
+/// Derives the challenge that combines the powers of an SRS, bound to `tau * G2`
+/// and every G1 power.
+fn srs_challenge(g1_powers: &[G1Point], g2_tau: &G2Point) -> Fr {
+    let mut hasher = Keccak256::new();
+    hasher.update(TRANSCRIPT_DOMAIN);
+    hasher.update(g2_to_bytes(g2_tau));
+    for p in g1_powers {
+        hasher.update(g1_to_bytes(p));
+    }
+    Fr::from_be_bytes_mod_order(&hasher.finalize())
+}
+
+/// Derives the challenge used to combine polynomials in a batch opening.
+fn batch_challenge(commitments: &[G1Point], point: Fr, values: &[Fr]) -> Fr {
+    let mut scalars = Vec::with_capacity(values.len() + 1);
+    scalars.push(point);
+    scalars.extend_from_slice(values);
+    challenge(commitments, &scalars)
+}
+
+/// Opens several polynomials at the same point with a single proof.
+///
+/// The polynomials are combined as `sum(gamma^i * poly_i)` with a Fiat-Shamir
+/// challenge `gamma` bound to the commitments, the point and the evaluations,
+/// and the combination is opened at `point`.
+///
+/// # Arguments
+/// * `polys` - The polynomials to open
+/// * `commitments` - Their commitments, as produced by [`commit`]
+/// * `point` - The evaluation point
+pub fn batch_open(
+    srs: &Srs,
+    polys: &[Vec<Fr>],
+    commitments: &[G1Point],
+    point: Fr,
+) -> Result<BatchOpening, String> {
+    if polys.len() != commitments.len() {
+        return Err(format!(
+            "Got {} polynomials but {} commitments",
+            polys.len(),
+            commitments.len()
+        ));
+    }
+    let values: Vec<Fr> = polys.iter().map(|p| evaluate_polynomial(p, point)).collect();
+    let gamma = batch_challenge(commitments, point, &values);
+
+    let max_len = polys.iter().map(Vec::len).max().unwrap_or(0);
+    let mut combined = vec![Fr::zero(); max_len];
+    for (poly, g) in polys.iter().zip(powers(gamma, polys.len())) {
+        for (c, p) in combined.iter_mut().zip(poly) {
+            *c += g * p;
+        }
+    }
+
+    Ok(BatchOpening {
+        point,
+        values,
+        proof: commit(srs, &divide_by_linear(&combined, point))?,
+    })
+}
+
+/// Verifies a batch opening of several commitments at the same point.
+pub fn verify_batch_opening(srs: &Srs, commitments: &[G1Point], opening: &BatchOpening) -> bool {
+    if commitments.len() != opening.values.len() {
+        return false;
+    }
+    let gamma = batch_challenge(commitments, opening.point, &opening.values);
+    let gammas = powers(gamma, commitments.len());
+
+    let commitment = match G1Point::msm(commitments, &gammas) {
+        Ok(c) => c,
+        Err(_) => return false,
+    };
+    let value = opening
+        .values
+        .iter()
+        .zip(&gammas)
+        .fold(Fr::zero(), |acc, (v, g)| acc + *v * g);
+
+    verify(
+        srs,
+        &commitment,
+        &Opening {
+            point: opening.point,
+            value,
+            proof: opening.proof,
+        },
+    )
+}
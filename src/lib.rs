@@ -26,13 +26,21 @@ pub mod g1;
 pub mod g2;
 pub mod pairing;
 pub mod hash;
+pub mod dkg;
+pub mod encoding;
+pub mod groth16;
+pub mod kzg;
+pub mod operators;
+pub mod pop;
+pub mod threshold;
 pub mod utils;
 pub mod web;
 
 // Re-export the main types
 pub use g1::{G1Point, g1_generator, g1_negate, g1_add, g1_scalar_mul};
 pub use g2::{G2Point, g2_generator, g2_negate};
-pub use pairing::{pairing_check, pairing_check_raw};
-pub use hash::{hash_g1_point, hash_g1_point_raw};
+pub use pairing::{pairing_check, pairing_check_multi, pairing_check_raw, verify_pubkey_pair, verify_signature};
+pub use hash::{hash_g1_point, hash_g1_point_raw, hash_g2_point, hash_to_g1};
+pub use pop::{prove_possession, verify_possession};
 pub use utils::fr_to_be_bytes;
 
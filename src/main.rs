@@ -1,12 +1,37 @@
 use bn254_rs::web;
+use bn254_rs::web::config::{Cli, Command, Config};
+use clap::Parser;
 use log::info;
 
 #[tokio::main]
 async fn main() -> std::io::Result<()> {
+    let cli = Cli::parse();
+    let config = match Config::load(&cli) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            std::process::exit(2);
+        }
+    };
+
     // Initialize logging
-    env_logger::init();
+    web::init_logging(&config.log);
+
+    if let Some(command) = cli.command {
+        let result = match command {
+            Command::Token(command) => web::run_token_command(&config, command).await,
+            Command::Audit(command) => web::run_audit_command(&config, command).await,
+            Command::Guard(command) => web::run_guard_command(&config, command).await,
+            Command::Pkcs11(command) => web::run_pkcs11_command(&config, command),
+        };
+        if let Err(e) = result {
+            eprintln!("{:#}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
 
     // Start the server
     info!("Starting BN254 web service...");
-    web::start_server().await
-} 
\ No newline at end of file
+    web::start_server(config).await
+}
@@ -0,0 +1,174 @@
+//! Module for validating operator key files before on-chain registration.
+//!
+//! Operator lists use the same JSON layout as `testdata/operators.json`: each entry
+//! has an Ethereum `wallet` and a `blsWallet` holding the G1 and G2 public keys as
+//! decimal strings. G2 coordinates follow the Solidity convention of
+//! `[imaginary, real]`.
+//!
+//! Registration reverts if the G1 and G2 keys do not share a secret, so this module
+//! runs the same check off-chain with [`verify_pubkey_pair`] and reports every bad
+//! entry at once.
+//!
+//! # Examples
+//!
+//! ```
+//! use bn254_rs::operators::{load_operators, validate_operators};
+//!
+//! let operators = load_operators("testdata/operators.json").unwrap();
+//! assert!(validate_operators(&operators).is_empty());
+//! ```
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use ark_bn254::{Fq, Fq2, G1Affine, G2Affine};
+use ark_ec::AffineRepr;
+use serde::{Deserialize, Serialize};
+
+use crate::g1::G1Point;
+use crate::g2::G2Point;
+use crate::pairing::verify_pubkey_pair;
+use crate::utils::parse_decimal_field;
+
+/// A G1 point as it appears in an operator file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct G1Json {
+    #[serde(rename = "X")]
+    pub x: String,
+    #[serde(rename = "Y")]
+    pub y: String,
+}
+
+/// A G2 point as it appears in an operator file, with each Fq2 coordinate
+/// given as `[imaginary, real]`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct G2Json {
+    #[serde(rename = "X")]
+    pub x: [String; 2],
+    #[serde(rename = "Y")]
+    pub y: [String; 2],
+}
+
+/// The Ethereum wallet of an operator.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Wallet {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub private_key: Option<String>,
+    pub address: String,
+}
+
+/// The BLS wallet of an operator.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlsWallet {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub private_key: Option<String>,
+    pub public_key_g1: G1Json,
+    pub public_key_g2: G2Json,
+}
+
+/// An operator entry with both its Ethereum and BLS wallets.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Operator {
+    pub wallet: Wallet,
+    pub bls_wallet: BlsWallet,
+}
+
+/// A validation failure for a single operator entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperatorKeyError {
+    /// Position of the operator in the list
+    pub index: usize,
+    /// EOA address of the operator
+    pub address: String,
+    /// Why the entry was rejected
+    pub reason: String,
+}
+
+impl std::fmt::Display for OperatorKeyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "operator {} ({}): {}", self.index, self.address, self.reason)
+    }
+}
+
+/// Parses a canonical decimal string into an Fq element.
+/// Values that are not fully reduced modulo p are rejected rather than wrapped.
+fn parse_fq(s: &str, name: &str) -> Result<Fq, String> {
+    parse_decimal_field(s).map_err(|e| format!("{} coordinate: {}", name, e))
+}
+
+impl G1Json {
+    /// Converts the JSON coordinates into a G1 point, checking that it is on the
+    /// curve and not the point at infinity.
+    pub fn to_g1_point(&self) -> Result<G1Point, String> {
+        let p = G1Affine::new_unchecked(parse_fq(&self.x, "G1 x")?, parse_fq(&self.y, "G1 y")?);
+        if !p.is_on_curve() {
+            return Err("G1 public key is not on the curve".to_string());
+        }
+        if p.is_zero() {
+            return Err("G1 public key is the point at infinity".to_string());
+        }
+        Ok(G1Point(p.into()))
+    }
+}
+
+impl G2Json {
+    /// Converts the JSON coordinates into a G2 point, checking that it is on the
+    /// curve, in the prime order subgroup and not the point at infinity.
+    pub fn to_g2_point(&self) -> Result<G2Point, String> {
+        // Solidity gives [imaginary, real], Arkworks expects (real, imaginary)
+        let x = Fq2::new(parse_fq(&self.x[1], "G2 x")?, parse_fq(&self.x[0], "G2 x")?);
+        let y = Fq2::new(parse_fq(&self.y[1], "G2 y")?, parse_fq(&self.y[0], "G2 y")?);
+        let p = G2Affine::new_unchecked(x, y);
+        if !p.is_on_curve() {
+            return Err("G2 public key is not on the curve".to_string());
+        }
+        if !p.is_in_correct_subgroup_assuming_on_curve() {
+            return Err("G2 public key is not in the prime order subgroup".to_string());
+        }
+        if p.is_zero() {
+            return Err("G2 public key is the point at infinity".to_string());
+        }
+        Ok(G2Point(p.into()))
+    }
+}
+
+/// Loads an operator list from a JSON file.
+pub fn load_operators<P: AsRef<Path>>(path: P) -> Result<Vec<Operator>> {
+    let file_content = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&file_content)?)
+}
+
+/// Validates the BLS public keys of a single operator.
+///
+/// Both keys must be valid curve points and must correspond to the same secret
+/// as checked by [`verify_pubkey_pair`].
+pub fn validate_operator(operator: &Operator) -> Result<(), String> {
+    let g1 = operator.bls_wallet.public_key_g1.to_g1_point()?;
+    let g2 = operator.bls_wallet.public_key_g2.to_g2_point()?;
+    if !verify_pubkey_pair(&g1, &g2) {
+        return Err("G1 and G2 public keys do not share the same secret".to_string());
+    }
+    Ok(())
+}
+
+/// Validates every operator in a list.
+///
+/// # Returns
+/// One error per rejected operator; an empty vector means every entry is valid
+pub fn validate_operators(operators: &[Operator]) -> Vec<OperatorKeyError> {
+    operators
+        .iter()
+        .enumerate()
+        .filter_map(|(index, operator)| {
+            validate_operator(operator).err().map(|reason| OperatorKeyError {
+                index,
+                address: operator.wallet.address.clone(),
+                reason,
+            })
+        })
+        .collect()
+}
@@ -63,6 +63,102 @@ pub fn pairing_check(
     p1 * p2 == <Bn254 as Pairing>::TargetField::one()
 }
 
+/// Performs a pairing check over any number of pairs of points.
+/// 
+/// This function checks if the product of e(g1_i, g2_i) over all pairs is 1,
+/// using a single final exponentiation. It is the equivalent of calling the
+/// EVM pairing precompile with several pairs.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use bn254_rs::{G1Point, G2Point, pairing_check_multi};
+/// 
+/// let g1 = G1Point::generator();
+/// let g2 = G2Point::generator();
+/// let result = pairing_check_multi(&[(g1, g2), (g1, g2), (g1.add(&g1).negate(), g2)]);
+/// assert!(result);
+/// ```
+/// 
+/// # Arguments
+/// * `pairs` - The (G1, G2) pairs to multiply together
+/// 
+/// # Returns
+/// `true` if the pairing check passes, `false` otherwise
+pub fn pairing_check_multi(pairs: &[(G1Point, G2Point)]) -> bool {
+    let g1s = pairs.iter().map(|(a, _)| *a.inner());
+    let g2s = pairs.iter().map(|(_, b)| *b.inner());
+    Bn254::multi_pairing(g1s, g2s).0 == <Bn254 as Pairing>::TargetField::one()
+}
+
+/// Checks that a G1 and a G2 public key correspond to the same secret key.
+/// 
+/// This function checks if e(pk_g1, -G2) * e(G1, pk_g2) = 1, which holds exactly
+/// when pk_g1 = sk * G1 and pk_g2 = sk * G2 for the same sk. Operator registration
+/// reverts on-chain when this does not hold.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use bn254_rs::{G1Point, G2Point, verify_pubkey_pair};
+/// use ark_bn254::Fr;
+/// 
+/// let sk = Fr::from(5u64);
+/// let g1 = G1Point::generator().scalar_mul(sk);
+/// let g2 = G2Point::generator().scalar_mul(sk);
+/// assert!(verify_pubkey_pair(&g1, &g2));
+/// assert!(!verify_pubkey_pair(&g1, &G2Point::generator()));
+/// ```
+/// 
+/// # Arguments
+/// * `g1` - The public key in G1
+/// * `g2` - The public key in G2
+/// 
+/// # Returns
+/// `true` if both keys share the same secret, `false` otherwise
+pub fn verify_pubkey_pair(g1: &G1Point, g2: &G2Point) -> bool {
+    pairing_check(
+        *g1,
+        G2Point::generator().negate(),
+        G1Point::generator(),
+        *g2,
+    )
+}
+
+/// Verifies a BLS signature over a message point.
+/// 
+/// This function checks if e(signature, -G2) * e(message, pk_g2) = 1, i.e. that
+/// signature = sk * message for the secret behind pk_g2.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use bn254_rs::{G1Point, G2Point, verify_signature};
+/// use ark_bn254::Fr;
+/// 
+/// let sk = Fr::from(9u64);
+/// let message = G1Point::generator().scalar_mul(Fr::from(1234u64));
+/// let signature = message.scalar_mul(sk);
+/// let pk_g2 = G2Point::generator().scalar_mul(sk);
+/// assert!(verify_signature(&message, &signature, &pk_g2));
+/// ```
+/// 
+/// # Arguments
+/// * `message` - The G1 message point that was signed
+/// * `signature` - The G1 signature
+/// * `pk_g2` - The signer's public key in G2
+/// 
+/// # Returns
+/// `true` if the signature is valid, `false` otherwise
+pub fn verify_signature(message: &G1Point, signature: &G1Point, pk_g2: &G2Point) -> bool {
+    pairing_check(
+        *signature,
+        G2Point::generator().negate(),
+        *message,
+        *pk_g2,
+    )
+}
+
 // For backward compatibility
 pub fn pairing_check_raw(
     a1: G1Projective,
@@ -0,0 +1,162 @@
+//! Module for BLS proofs of possession on the BN254 curve.
+//!
+//! Aggregating public keys is only safe against rogue-key attacks when every key
+//! comes with a proof that its owner knows the matching secret. A proof of
+//! possession (PoP) is a BLS signature over a domain-separated message point
+//! derived from both the G1 and G2 public keys, so it also binds the two keys
+//! to the same secret.
+//!
+//! Verification follows the check performed by EigenLayer's
+//! `BLSApkRegistry.registerBLSPublicKey`: with a random linear combination
+//! `gamma`, the two equations
+//!
+//! - `e(pop, G2) == e(H(pk), pkG2)`
+//! - `e(pkG1, G2) == e(G1, pkG2)`
+//!
+//! are folded into a single [`pairing_check`].
+//!
+//! # Examples
+//!
+//! ```
+//! use bn254_rs::{G1Point, G2Point, prove_possession, verify_possession};
+//! use ark_bn254::Fr;
+//!
+//! let sk = Fr::from(42u64);
+//! let pk_g1 = G1Point::generator().scalar_mul(sk);
+//! let pk_g2 = G2Point::generator().scalar_mul(sk);
+//!
+//! let pop = prove_possession(sk);
+//! assert!(verify_possession(&pk_g1, &pk_g2, &pop));
+//! ```
+
+use ark_bn254::Fr;
+use ark_ec::CurveGroup;
+use ark_ff::{BigInteger, PrimeField};
+use sha3::{Digest, Keccak256};
+
+use crate::g1::G1Point;
+use crate::g2::G2Point;
+use crate::hash::{hash_g1_point, hash_g2_point, hash_to_g1};
+use crate::pairing::pairing_check;
+
+/// Domain separation tag prepended to the public keys before hashing to G1.
+///
+/// Using a dedicated tag guarantees that a proof of possession can never be
+/// replayed as a signature over an application message, and vice versa.
+pub const POP_DOMAIN: &[u8] = b"BN254_RS_POP_V1";
+
+/// Computes the message point signed by a proof of possession.
+///
+/// The point is `hashToG1(keccak256(POP_DOMAIN || hash(pkG1) || hash(pkG2)))`,
+/// which commits to both public keys.
+///
+/// # Arguments
+/// * `pk_g1` - The public key in G1
+/// * `pk_g2` - The public key in G2
+///
+/// # Returns
+/// The G1 point that the owner of the key pair must sign
+pub fn possession_message(pk_g1: &G1Point, pk_g2: &G2Point) -> G1Point {
+    let mut hasher = Keccak256::new();
+    hasher.update(POP_DOMAIN);
+    hasher.update(hash_g1_point(pk_g1));
+    hasher.update(hash_g2_point(pk_g2));
+    let digest: [u8; 32] = hasher.finalize().into();
+    hash_to_g1(&digest)
+}
+
+/// Produces a proof of possession for a secret key.
+///
+/// Both public keys are derived from `sk` and the resulting message point is
+/// multiplied by `sk`.
+///
+/// # Examples
+///
+/// ```
+/// use bn254_rs::prove_possession;
+/// use ark_bn254::Fr;
+///
+/// let pop = prove_possession(Fr::from(7u64));
+/// ```
+///
+/// # Arguments
+/// * `sk` - The BLS secret key
+///
+/// # Returns
+/// The proof of possession as a G1 point
+pub fn prove_possession(sk: Fr) -> G1Point {
+    let pk_g1 = G1Point::generator().scalar_mul(sk);
+    let pk_g2 = G2Point::generator().scalar_mul(sk);
+    possession_message(&pk_g1, &pk_g2).scalar_mul(sk)
+}
+
+/// Verifies a proof of possession against a G1/G2 public key pair.
+///
+/// This function checks that `pop` is a signature by the secret behind `pk_g2`
+/// over [`possession_message`], and that `pk_g1` and `pk_g2` share that secret.
+/// Keys at infinity are always rejected.
+///
+/// # Examples
+///
+/// ```
+/// use bn254_rs::{G1Point, G2Point, prove_possession, verify_possession};
+/// use ark_bn254::Fr;
+///
+/// let sk = Fr::from(7u64);
+/// let pk_g1 = G1Point::generator().scalar_mul(sk);
+/// let pk_g2 = G2Point::generator().scalar_mul(sk);
+///
+/// assert!(verify_possession(&pk_g1, &pk_g2, &prove_possession(sk)));
+/// assert!(!verify_possession(&pk_g1, &pk_g2, &prove_possession(Fr::from(8u64))));
+/// ```
+///
+/// # Arguments
+/// * `pk_g1` - The public key in G1
+/// * `pk_g2` - The public key in G2
+/// * `pop` - The proof of possession to check
+///
+/// # Returns
+/// `true` if the proof is valid for both keys, `false` otherwise
+pub fn verify_possession(pk_g1: &G1Point, pk_g2: &G2Point, pop: &G1Point) -> bool {
+    let pk_g1_aff = pk_g1.inner().into_affine();
+    let pk_g2_aff = pk_g2.inner().into_affine();
+    if pk_g1_aff.infinity || pk_g2_aff.infinity {
+        return false;
+    }
+
+    let message = possession_message(pk_g1, pk_g2);
+    let gamma = possession_challenge(pop, pk_g1, pk_g2, &message);
+
+    // e(pop + gamma * pkG1, -G2) * e(H + gamma * G1, pkG2) == 1
+    pairing_check(
+        pop.add(&pk_g1.scalar_mul(gamma)),
+        G2Point::generator().negate(),
+        message.add(&G1Point::generator().scalar_mul(gamma)),
+        *pk_g2,
+    )
+}
+
+/// Derives the random linear combination factor used in [`verify_possession`].
+fn possession_challenge(
+    pop: &G1Point,
+    pk_g1: &G1Point,
+    pk_g2: &G2Point,
+    message: &G1Point,
+) -> Fr {
+    let pop = pop.inner().into_affine();
+    let pk_g1 = pk_g1.inner().into_affine();
+    let pk_g2 = pk_g2.inner().into_affine();
+    let message = message.inner().into_affine();
+
+    let mut hasher = Keccak256::new();
+    for fq in [pop.x, pop.y, pk_g1.x, pk_g1.y] {
+        hasher.update(fq.into_bigint().to_bytes_be());
+    }
+    for fq in [pk_g2.x.c1, pk_g2.x.c0, pk_g2.y.c1, pk_g2.y.c0] {
+        hasher.update(fq.into_bigint().to_bytes_be());
+    }
+    for fq in [message.x, message.y] {
+        hasher.update(fq.into_bigint().to_bytes_be());
+    }
+    Fr::from_be_bytes_mod_order(&hasher.finalize())
+}
@@ -0,0 +1,213 @@
+//! Module for threshold BLS signatures on the BN254 curve.
+//!
+//! A secret key is split into `n` Shamir shares over `Fr` so that any `t` of them
+//! can reconstruct it, while fewer than `t` reveal nothing. Each share holder signs
+//! independently, producing a partial G1 signature, and any `t` partial signatures
+//! are combined with Lagrange interpolation in the exponent into a signature that
+//! verifies under the original G2 public key. The secret itself is never
+//! reassembled during signing.
+//!
+//! Share indices start at 1; index 0 is the evaluation point of the secret.
+//!
+//! # Examples
+//!
+//! ```
+//! use bn254_rs::{G1Point, G2Point, verify_signature};
+//! use bn254_rs::threshold::{combine_signatures, sign_share, split_secret};
+//! use ark_bn254::Fr;
+//!
+//! let sk = Fr::from(1234567u64);
+//! let shares = split_secret(sk, 2, 3, &mut rand::thread_rng()).unwrap();
+//!
+//! let message = G1Point::generator().scalar_mul(Fr::from(42u64));
+//! let partials: Vec<_> = shares[1..].iter().map(|s| sign_share(s, &message)).collect();
+//! let signature = combine_signatures(&partials, 2).unwrap();
+//!
+//! let pk_g2 = G2Point::generator().scalar_mul(sk);
+//! assert!(verify_signature(&message, &signature, &pk_g2));
+//! ```
+
+use ark_bn254::Fr;
+use ark_ff::{Field, One, UniformRand, Zero};
+use rand::Rng;
+
+use crate::g1::G1Point;
+use crate::g2::G2Point;
+use crate::pairing::verify_signature;
+use crate::utils::evaluate_polynomial;
+
+/// A single Shamir share of a BLS secret key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyShare {
+    /// The non-zero x coordinate at which the sharing polynomial was evaluated
+    pub index: u64,
+    /// The polynomial evaluated at `index`
+    pub secret: Fr,
+}
+
+/// A signature produced with a single key share.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartialSignature {
+    /// Index of the share that produced this signature
+    pub index: u64,
+    /// The share secret multiplied into the message point
+    pub signature: G1Point,
+}
+
+impl KeyShare {
+    /// Returns the public key of this share in G1.
+    pub fn public_key_g1(&self) -> G1Point {
+        G1Point::generator().scalar_mul(self.secret)
+    }
+
+    /// Returns the public key of this share in G2.
+    /// Partial signatures from this share verify under this key.
+    pub fn public_key_g2(&self) -> G2Point {
+        G2Point::generator().scalar_mul(self.secret)
+    }
+}
+
+/// Splits a secret into `shares` Shamir shares with reconstruction threshold `threshold`.
+///
+/// A random polynomial of degree `threshold - 1` is sampled with the secret as its
+/// constant term and evaluated at `1..=shares`.
+///
+/// # Arguments
+/// * `secret` - The secret key to split
+/// * `threshold` - Number of shares needed to sign or reconstruct
+/// * `shares` - Total number of shares to produce
+/// * `rng` - A cryptographically secure random number generator
+///
+/// # Returns
+/// The shares, ordered by index, or an error if the parameters are invalid
+pub fn split_secret<R: Rng + ?Sized>(
+    secret: Fr,
+    threshold: usize,
+    shares: usize,
+    rng: &mut R,
+) -> Result<Vec<KeyShare>, String> {
+    if threshold == 0 {
+        return Err("Threshold must be at least 1".to_string());
+    }
+    if threshold > shares {
+        return Err(format!(
+            "Threshold {} exceeds the number of shares {}",
+            threshold, shares
+        ));
+    }
+
+    let mut coefficients = Vec::with_capacity(threshold);
+    coefficients.push(secret);
+    coefficients.extend((1..threshold).map(|_| Fr::rand(rng)));
+
+    Ok((1..=shares as u64)
+        .map(|index| KeyShare {
+            index,
+            secret: evaluate_polynomial(&coefficients, Fr::from(index)),
+        })
+        .collect())
+}
+
+/// Computes the Lagrange coefficient of `index` for interpolation at zero.
+///
+/// # Arguments
+/// * `index` - The share index whose coefficient is computed
+/// * `indices` - All share indices taking part in the interpolation
+///
+/// # Returns
+/// The coefficient, or an error if the indices contain zero or duplicates
+pub fn lagrange_coefficient(index: u64, indices: &[u64]) -> Result<Fr, String> {
+    let x_i = Fr::from(index);
+    let mut numerator = Fr::one();
+    let mut denominator = Fr::one();
+    for &j in indices.iter().filter(|&&j| j != index) {
+        let x_j = Fr::from(j);
+        numerator *= x_j;
+        denominator *= x_j - x_i;
+    }
+    denominator
+        .inverse()
+        .ok_or_else(|| "Share indices must be distinct".to_string())
+        .map(|inv| numerator * inv)
+}
+
+/// Checks that a set of share indices is usable for interpolation.
+fn check_indices(indices: &[u64], threshold: usize) -> Result<(), String> {
+    if indices.len() < threshold {
+        return Err(format!(
+            "Need at least {} shares, got {}",
+            threshold,
+            indices.len()
+        ));
+    }
+    if indices.contains(&0) {
+        return Err("Share index 0 is reserved for the secret".to_string());
+    }
+    let mut sorted = indices.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+    if sorted.len() != indices.len() {
+        return Err("Share indices must be distinct".to_string());
+    }
+    Ok(())
+}
+
+/// Reconstructs the secret from at least `threshold` shares.
+///
+/// This is only needed for key recovery; signing never requires it.
+pub fn recover_secret(shares: &[KeyShare], threshold: usize) -> Result<Fr, String> {
+    let indices: Vec<u64> = shares.iter().map(|s| s.index).collect();
+    check_indices(&indices, threshold)?;
+
+    let mut secret = Fr::zero();
+    for share in shares {
+        secret += lagrange_coefficient(share.index, &indices)? * share.secret;
+    }
+    Ok(secret)
+}
+
+/// Signs a message point with a key share.
+pub fn sign_share(share: &KeyShare, message: &G1Point) -> PartialSignature {
+    PartialSignature {
+        index: share.index,
+        signature: message.scalar_mul(share.secret),
+    }
+}
+
+/// Verifies a partial signature against the public key of the share that made it.
+pub fn verify_partial_signature(
+    partial: &PartialSignature,
+    message: &G1Point,
+    share_pk_g2: &G2Point,
+) -> bool {
+    verify_signature(message, &partial.signature, share_pk_g2)
+}
+
+/// Combines at least `threshold` partial signatures into a full signature.
+///
+/// The partial signatures are interpolated at zero in the exponent:
+/// `sig = sum(lambda_i * sig_i)`. The result verifies under the public key of
+/// the original secret as long as every partial signature is valid, so callers
+/// handling untrusted input should check them with [`verify_partial_signature`]
+/// first.
+///
+/// # Arguments
+/// * `partials` - Partial signatures from distinct shares
+/// * `threshold` - The threshold the secret was split with
+///
+/// # Returns
+/// The combined signature, or an error if there are too few or duplicate shares
+pub fn combine_signatures(
+    partials: &[PartialSignature],
+    threshold: usize,
+) -> Result<G1Point, String> {
+    let indices: Vec<u64> = partials.iter().map(|p| p.index).collect();
+    check_indices(&indices, threshold)?;
+
+    let mut signature = G1Point::zero();
+    for partial in partials {
+        let lambda = lagrange_coefficient(partial.index, &indices)?;
+        signature = signature.add(&partial.signature.scalar_mul(lambda));
+    }
+    Ok(signature)
+}
@@ -1,9 +1,11 @@
 //! Module for utility functions related to BN254 curve operations.
 //! 
-//! This module provides helper functions for working with field elements
-//! and their byte representations in the BN254 curve.
+//! This module provides helper functions for working with field elements,
+//! their byte representations and polynomials over the BN254 scalar field.
 
-use ark_ff::{BigInteger, PrimeField};
+use std::str::FromStr;
+
+use ark_ff::{BigInteger, One, PrimeField, Zero};
 use ark_bn254::Fr;
 
 /// Converts a field element to a 32-byte array in big-endian format.
@@ -22,3 +24,47 @@ pub fn fr_to_be_bytes(f: &Fr) -> [u8; 32] {
     out[32 - bytes.len()..].copy_from_slice(&bytes);
     out
 }
+
+/// Evaluates a polynomial at a point using Horner's rule.
+/// 
+/// # Arguments
+/// * `coefficients` - The polynomial coefficients, lowest degree first
+/// * `x` - The point at which to evaluate
+/// 
+/// # Returns
+/// The value of the polynomial at `x`
+pub fn evaluate_polynomial(coefficients: &[Fr], x: Fr) -> Fr {
+    coefficients
+        .iter()
+        .rev()
+        .fold(Fr::zero(), |acc, c| acc * x + c)
+}
+
+/// Returns the first `n` powers of `x`, i.e. `[1, x, x^2, ..., x^(n-1)]`.
+pub fn powers(x: Fr, n: usize) -> Vec<Fr> {
+    let mut out = Vec::with_capacity(n);
+    let mut acc = Fr::one();
+    for _ in 0..n {
+        out.push(acc);
+        acc *= x;
+    }
+    out
+}
+
+/// Parses a canonical decimal string into a prime field element.
+/// 
+/// Unlike `FromStr`, values that are not fully reduced modulo the field order
+/// and strings with leading zeros are rejected rather than silently wrapped.
+/// 
+/// # Arguments
+/// * `s` - A string representing a decimal number
+/// 
+/// # Returns
+/// The field element, or an error if the string is not a canonical element
+pub fn parse_decimal_field<F: PrimeField + FromStr>(s: &str) -> Result<F, String> {
+    let f = F::from_str(s).map_err(|_| format!("Invalid decimal field element: {}", s))?;
+    if f.into_bigint().to_string() != s {
+        return Err(format!("Field element is not canonical: {}", s));
+    }
+    Ok(f)
+}
@@ -0,0 +1,448 @@
+//! Multi-party (m-of-n) approval of signatures.
+//!
+//! Keys whose policy sets `approval_required` refuse `/api/sign` and
+//! `/api/scalar_mul`. A signature is instead requested with `POST /api/approvals`
+//! and stays pending until `approvals.threshold` of the configured approvers,
+//! other than the requester, approve it with `POST /api/approvals/{id}/approve`.
+//! The approval that reaches the threshold signs the message, after the key
+//! policy of the requester and the task context are checked once more, and the
+//! requester collects the signature with `GET /api/approvals/{id}`. A single
+//! rejection closes a request.
+//!
+//! Requests not decided within `approvals.ttl_secs` expire. Closed requests are
+//! kept for another `ttl_secs` and then forgotten. Requests are held in memory,
+//! so a restart drops them. Every step is written to the audit log with the id
+//! of its request.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse};
+use rand::RngCore;
+
+use crate::web::audit::{append_record, AuditLog, AuditRecord, RequestId};
+use crate::web::auth::{Operation, Principal};
+use crate::web::config::ApprovalsConfig;
+use crate::web::error::ApiError;
+use crate::web::handlers::{resolve_message, sign_with_key_pair, signing_key_pair, SigningChecks};
+use crate::web::models::{
+    ApprovalRequest, ApprovalStatus, ApprovalView, ApprovalVote, G1Point, MessageMode, SignResponse, SigningContext,
+};
+use crate::web::store::KeyStore;
+
+/// Longest description accepted on a request
+const MAX_DESCRIPTION_LEN: usize = 1024;
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// A resolved message to sign once it is approved
+#[derive(Debug, Clone)]
+pub struct PendingMessage {
+    pub eoa_address: String,
+    pub mode: MessageMode,
+    pub message: crate::g1::G1Point,
+    /// The message source the message was derived through, if any
+    pub source: Option<String>,
+    pub context: Option<SigningContext>,
+}
+
+struct QueuedRequest {
+    view: ApprovalView,
+    requester: Principal,
+    pending: PendingMessage,
+}
+
+/// A request that reached its threshold and is ready to be signed
+#[derive(Debug)]
+pub struct Release {
+    pub id: String,
+    pub requester: Principal,
+    pub pending: PendingMessage,
+}
+
+/// The pending approval requests, registered as app data
+pub struct ApprovalQueue {
+    approvers: Vec<String>,
+    threshold: usize,
+    ttl_secs: i64,
+    requests: Mutex<HashMap<String, QueuedRequest>>,
+}
+
+fn not_found(id: &str) -> ApiError {
+    ApiError::not_found("approval_not_found", "id", format!("no approval request {}", id))
+}
+
+fn forbidden(error: &str, message: String) -> ApiError {
+    ApiError::new(StatusCode::FORBIDDEN, error, None, message)
+}
+
+/// Hides the signature from everyone but the requester
+fn redacted(view: &ApprovalView) -> ApprovalView {
+    ApprovalView {
+        signature: None,
+        ..view.clone()
+    }
+}
+
+impl ApprovalQueue {
+    pub fn new(approvers: Vec<String>, threshold: usize, ttl_secs: u64) -> Self {
+        Self {
+            approvers,
+            threshold,
+            ttl_secs: ttl_secs as i64,
+            requests: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn from_config(config: &ApprovalsConfig) -> Self {
+        Self::new(config.approvers.clone(), config.threshold, config.ttl_secs)
+    }
+
+    /// Whether a principal is one of the configured approvers
+    pub fn is_approver(&self, principal: &Principal) -> bool {
+        self.approvers.contains(&principal.name)
+    }
+
+    fn require_approver(&self, principal: &Principal) -> Result<(), ApiError> {
+        if !self.is_approver(principal) {
+            return Err(forbidden("not_an_approver", format!("{} is not an approver", principal.name)));
+        }
+        Ok(())
+    }
+
+    /// Checks that a principal may review the requests for a key
+    fn authorize_approver(&self, principal: &Principal, eoa_address: &str) -> Result<(), ApiError> {
+        self.require_approver(principal)?;
+        principal.authorize(Operation::Approve, eoa_address)
+    }
+
+    /// Queues a request for approval.
+    ///
+    /// # Returns
+    /// The new request, without approvals
+    pub fn create(&self, requester: &Principal, pending: PendingMessage, description: Option<&str>, now: i64) -> ApprovalView {
+        let mut id = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut id);
+        let view = ApprovalView {
+            id: hex::encode(id),
+            eoa_address: pending.eoa_address.clone(),
+            requester: requester.name.clone(),
+            description: description.map(str::to_string),
+            mode: pending.mode,
+            message: G1Point::from(&pending.message),
+            context: pending.context.clone(),
+            status: ApprovalStatus::Pending,
+            threshold: self.threshold,
+            approvals: Vec::new(),
+            rejected_by: None,
+            created_at: now,
+            expires_at: now + self.ttl_secs,
+            error: None,
+            signature: None,
+        };
+        self.requests.lock().unwrap().insert(
+            view.id.clone(),
+            QueuedRequest {
+                view: view.clone(),
+                requester: requester.clone(),
+                pending,
+            },
+        );
+        view
+    }
+
+    /// A request as the principal may see it; the signature is only shown to the
+    /// requester, and requests are only shown to their requester and approvers.
+    pub fn get(&self, id: &str, principal: &Principal) -> Option<ApprovalView> {
+        let requests = self.requests.lock().unwrap();
+        let request = requests.get(id)?;
+        if request.requester.name == principal.name {
+            Some(request.view.clone())
+        } else if self.authorize_approver(principal, &request.view.eoa_address).is_ok() {
+            Some(redacted(&request.view))
+        } else {
+            None
+        }
+    }
+
+    /// The requests an approver may review, oldest first
+    pub fn list(&self, approver: &Principal) -> Result<Vec<ApprovalView>, ApiError> {
+        self.require_approver(approver)?;
+        let mut views: Vec<ApprovalView> = self
+            .requests
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|request| approver.can_access(&request.view.eoa_address))
+            .map(|request| redacted(&request.view))
+            .collect();
+        views.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+        Ok(views)
+    }
+
+    /// Finds a request an approver may still decide on
+    fn open_request<'a>(
+        &self,
+        requests: &'a mut HashMap<String, QueuedRequest>,
+        id: &str,
+        approver: &Principal,
+        now: i64,
+    ) -> Result<&'a mut QueuedRequest, ApiError> {
+        self.require_approver(approver)?;
+        let request = requests.get_mut(id).ok_or_else(|| not_found(id))?;
+        self.authorize_approver(approver, &request.view.eoa_address)?;
+        if request.requester.name == approver.name {
+            return Err(forbidden("self_approval", "requesters may not decide on their own requests".to_string()));
+        }
+        let view = &request.view;
+        if view.status == ApprovalStatus::Pending && view.approvals.len() < self.threshold && now >= view.expires_at {
+            return Err(ApiError::new(StatusCode::GONE, "approval_expired", None, format!("approval request {} has expired", id)));
+        }
+        if view.status != ApprovalStatus::Pending || view.approvals.len() >= self.threshold {
+            return Err(ApiError::new(
+                StatusCode::CONFLICT,
+                "approval_closed",
+                None,
+                format!("approval request {} is no longer pending", id),
+            ));
+        }
+        Ok(request)
+    }
+
+    /// Records an approval.
+    ///
+    /// # Returns
+    /// The request, and the release to sign if this approval reached the threshold
+    pub fn approve(&self, id: &str, approver: &Principal, now: i64) -> Result<(ApprovalView, Option<Release>), ApiError> {
+        let mut requests = self.requests.lock().unwrap();
+        let request = self.open_request(&mut requests, id, approver, now)?;
+        if request.view.approvals.iter().any(|vote| vote.approver == approver.name) {
+            return Err(ApiError::new(
+                StatusCode::CONFLICT,
+                "already_approved",
+                None,
+                format!("{} has already approved request {}", approver.name, id),
+            ));
+        }
+        request.view.approvals.push(ApprovalVote {
+            approver: approver.name.clone(),
+            approved_at: now,
+        });
+        // Only the approval reaching the threshold releases the request; later
+        // approvals find it closed
+        let release = (request.view.approvals.len() == self.threshold).then(|| Release {
+            id: id.to_string(),
+            requester: request.requester.clone(),
+            pending: request.pending.clone(),
+        });
+        Ok((redacted(&request.view), release))
+    }
+
+    /// Rejects a request, closing it.
+    pub fn reject(&self, id: &str, approver: &Principal, now: i64) -> Result<ApprovalView, ApiError> {
+        let mut requests = self.requests.lock().unwrap();
+        let request = self.open_request(&mut requests, id, approver, now)?;
+        request.view.status = ApprovalStatus::Rejected;
+        request.view.rejected_by = Some(approver.name.clone());
+        Ok(redacted(&request.view))
+    }
+
+    /// Records the outcome of signing a released request.
+    pub fn complete(&self, id: &str, signed: Result<SignResponse, ApiError>) -> Option<ApprovalView> {
+        let mut requests = self.requests.lock().unwrap();
+        let request = requests.get_mut(id)?;
+        match signed {
+            Ok(signature) => {
+                request.view.status = ApprovalStatus::Approved;
+                request.view.signature = Some(signature);
+            }
+            Err(e) => {
+                request.view.status = ApprovalStatus::Failed;
+                request.view.error = Some(e.body);
+            }
+        }
+        Some(redacted(&request.view))
+    }
+
+    /// Expires the pending requests whose time is up and forgets closed requests
+    /// older than another `ttl_secs`.
+    ///
+    /// # Returns
+    /// The requests that expired now
+    pub fn expire(&self, now: i64) -> Vec<ApprovalView> {
+        let mut requests = self.requests.lock().unwrap();
+        let mut expired = Vec::new();
+        for request in requests.values_mut() {
+            let view = &mut request.view;
+            if view.status == ApprovalStatus::Pending && view.approvals.len() < self.threshold && now >= view.expires_at {
+                view.status = ApprovalStatus::Expired;
+                expired.push(view.clone());
+            }
+        }
+        // A request being signed is pending with enough approvals, and is kept
+        requests.retain(|_, request| request.view.status == ApprovalStatus::Pending || now < request.view.expires_at + self.ttl_secs);
+        expired
+    }
+}
+
+/// Sets the result of an audit record of a step that produces no signature
+fn set_result<T>(record: &mut AuditRecord, result: &Result<T, ApiError>) {
+    record.result = match result {
+        Ok(_) => "ok".to_string(),
+        Err(e) => e.body.error.clone(),
+    };
+}
+
+/// Expires overdue requests and audits each of them
+async fn expire_requests(approvals: &ApprovalQueue, audit: &dyn AuditLog, request_id: &RequestId) -> Result<(), ApiError> {
+    for view in approvals.expire(now()) {
+        let mut record = AuditRecord::new(request_id, &view.requester, &view.eoa_address, "approval_expire");
+        record.approval_id = Some(view.id);
+        record.result = "approval_expired".to_string();
+        append_record(audit, record).await?;
+    }
+    Ok(())
+}
+
+/// Request a signature that is released once approved
+pub async fn create_approval(
+    store: web::Data<dyn KeyStore>,
+    checks: SigningChecks,
+    approvals: web::Data<ApprovalQueue>,
+    audit: web::Data<dyn AuditLog>,
+    principal: Principal,
+    request_id: RequestId,
+    req: web::Json<ApprovalRequest>,
+) -> Result<HttpResponse, ApiError> {
+    expire_requests(&approvals, audit.get_ref(), &request_id).await?;
+    let sign = &req.request;
+    let mut record = AuditRecord::new(&request_id, &principal.name, &sign.eoa_address, "approval_request");
+    let result = async {
+        principal.authorize(Operation::Sign, &sign.eoa_address)?;
+        if req.description.as_ref().is_some_and(|d| d.len() > MAX_DESCRIPTION_LEN) {
+            return Err(ApiError::bad_request(
+                "invalid_description",
+                "description",
+                format!("description must be at most {} bytes", MAX_DESCRIPTION_LEN),
+            ));
+        }
+        // An unknown or disabled key fails before its EOA authorization is used up
+        signing_key_pair(store.get_ref(), &sign.eoa_address).await?;
+        let (mode, message) = resolve_message(&sign.message, checks.sources())?;
+        record.set_input(&message);
+        checks.check_authorization(&sign.eoa_address, &message, sign.authorization.as_ref()).await?;
+        let pending = PendingMessage {
+            eoa_address: sign.eoa_address.clone(),
+            mode,
+            message,
+            source: sign.message.message_source.as_ref().map(|sourced| sourced.source.clone()),
+            context: sign.context.clone(),
+        };
+        Ok(approvals.create(&principal, pending, req.description.as_deref(), now()))
+    }
+    .await;
+    set_result(&mut record, &result);
+    record.approval_id = result.as_ref().ok().map(|view| view.id.clone());
+    append_record(audit.get_ref(), record).await?;
+    Ok(HttpResponse::Accepted().json(result?))
+}
+
+/// List the requests the caller may approve
+pub async fn list_approvals(
+    approvals: web::Data<ApprovalQueue>,
+    audit: web::Data<dyn AuditLog>,
+    principal: Principal,
+    request_id: RequestId,
+) -> Result<HttpResponse, ApiError> {
+    expire_requests(&approvals, audit.get_ref(), &request_id).await?;
+    Ok(HttpResponse::Ok().json(approvals.list(&principal)?))
+}
+
+/// Get a request, with its signature for the requester once approved
+pub async fn get_approval(
+    approvals: web::Data<ApprovalQueue>,
+    audit: web::Data<dyn AuditLog>,
+    principal: Principal,
+    request_id: RequestId,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    expire_requests(&approvals, audit.get_ref(), &request_id).await?;
+    let view = approvals.get(&id, &principal).ok_or_else(|| not_found(&id))?;
+    Ok(HttpResponse::Ok().json(view))
+}
+
+/// Approve a request, signing it once the threshold is reached
+pub async fn approve(
+    store: web::Data<dyn KeyStore>,
+    checks: SigningChecks,
+    approvals: web::Data<ApprovalQueue>,
+    audit: web::Data<dyn AuditLog>,
+    principal: Principal,
+    request_id: RequestId,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    expire_requests(&approvals, audit.get_ref(), &request_id).await?;
+    let result = approvals.approve(&id, &principal, now());
+    let eoa_address = result.as_ref().map(|(view, _)| view.eoa_address.as_str()).unwrap_or_default();
+    let mut record = AuditRecord::new(&request_id, &principal.name, eoa_address, "approval_approve");
+    record.approval_id = Some(id.to_string());
+    set_result(&mut record, &result);
+    append_record(audit.get_ref(), record).await?;
+    let (view, release) = result?;
+    let Some(release) = release else {
+        return Ok(HttpResponse::Ok().json(view));
+    };
+
+    let pending = &release.pending;
+    let mut record = AuditRecord::new(&request_id, &release.requester.name, &pending.eoa_address, "approval_release");
+    record.approval_id = Some(release.id.clone());
+    record.set_input(&pending.message);
+    let signed = async {
+        // The key may have been disabled while the request waited for approval
+        let key_pair = signing_key_pair(store.get_ref(), &pending.eoa_address).await?;
+        checks
+            .release(
+                &release.requester,
+                &pending.eoa_address,
+                &pending.message,
+                pending.source.as_deref(),
+                pending.context.as_ref(),
+            )
+            .await?;
+        sign_with_key_pair(store.get_ref(), checks.signer(), key_pair, pending.mode, &pending.message).await
+    }
+    .await;
+    record.finish(signed.as_ref().map(|response| &response.product));
+    // The signature is only kept for the requester once its release is audited
+    let signed = match append_record(audit.get_ref(), record).await {
+        Ok(()) => signed,
+        Err(e) => Err(e),
+    };
+    let view = approvals.complete(&release.id, signed).ok_or_else(|| not_found(&release.id))?;
+    Ok(HttpResponse::Ok().json(view))
+}
+
+/// Reject a request
+pub async fn reject(
+    approvals: web::Data<ApprovalQueue>,
+    audit: web::Data<dyn AuditLog>,
+    principal: Principal,
+    request_id: RequestId,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    expire_requests(&approvals, audit.get_ref(), &request_id).await?;
+    let result = approvals.reject(&id, &principal, now());
+    let eoa_address = result.as_ref().map(|view| view.eoa_address.as_str()).unwrap_or_default();
+    let mut record = AuditRecord::new(&request_id, &principal.name, eoa_address, "approval_reject");
+    record.approval_id = Some(id.to_string());
+    set_result(&mut record, &result);
+    append_record(audit.get_ref(), record).await?;
+    Ok(HttpResponse::Ok().json(result?))
+}
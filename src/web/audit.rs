@@ -0,0 +1,331 @@
+//! Tamper-evident audit log of signing requests.
+//!
+//! Every request to a signing endpoint appends one [`AuditEntry`], whether it
+//! succeeds or not. Each entry stores the hash of the previous one and its own
+//! hash
+//!
+//! ```text
+//! hash = keccak256(prev_hash || json(seq, record))
+//! ```
+//!
+//! so that editing or removing any entry breaks the chain from that point on. The
+//! first entry chains to 32 zero bytes. Removing entries from the end leaves a
+//! valid but shorter chain, which [`verify_chain`] detects when given a head hash
+//! recorded earlier.
+//!
+//! The log is a JSON lines file or, for a `sqlite:` location, the `audit_log`
+//! table of a SQLite database.
+
+use std::fs::{File, OpenOptions};
+use std::future::{ready, Ready};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::dev::Payload;
+use actix_web::{FromRequest, HttpMessage, HttpRequest};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+
+use crate::hash::{hash_g1_point, hash_g1_point_raw};
+use crate::web::error::ApiError;
+use crate::web::models::G1Point;
+
+/// Header carrying the request id, set by the client or generated by the service
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// The id of an API request, attached to the request by the request id middleware.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    /// A random 16-byte hex id
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        Self(hex::encode(bytes))
+    }
+}
+
+impl FromRequest for RequestId {
+    type Error = ApiError;
+    type Future = Ready<Result<Self, ApiError>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Ok(req.extensions().get::<RequestId>().cloned().unwrap_or_else(RequestId::generate)))
+    }
+}
+
+/// What happened to one signing request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub request_id: String,
+    /// Seconds since the Unix epoch
+    pub timestamp: i64,
+    pub principal: String,
+    pub eoa_address: String,
+    pub operation: String,
+    /// Hex `keccak256(x, y)` of the message point, if the request got that far
+    pub input_hash: Option<String>,
+    /// Hex `keccak256(x, y)` of the signature, if one was produced
+    pub output_hash: Option<String>,
+    /// `ok`, or the error code returned to the client
+    pub result: String,
+    /// The approval request the record belongs to, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approval_id: Option<String>,
+}
+
+impl AuditRecord {
+    /// Starts the record of a request; the result is filled in by [`AuditRecord::finish`]
+    pub fn new(request_id: &RequestId, principal: &str, eoa_address: &str, operation: &str) -> Self {
+        Self {
+            request_id: request_id.0.clone(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or_default(),
+            principal: principal.to_string(),
+            eoa_address: eoa_address.to_string(),
+            operation: operation.to_string(),
+            input_hash: None,
+            output_hash: None,
+            result: String::new(),
+            approval_id: None,
+        }
+    }
+
+    /// Records the message point of the request
+    pub fn set_input(&mut self, message: &crate::g1::G1Point) {
+        self.input_hash = Some(format!("0x{}", hex::encode(hash_g1_point(message))));
+    }
+
+    /// Records the signature produced, or the error returned
+    pub fn finish(&mut self, result: Result<&G1Point, &ApiError>) {
+        match result {
+            Ok(signature) => {
+                self.output_hash = signature
+                    .to_g1_point()
+                    .ok()
+                    .map(|point| format!("0x{}", hex::encode(hash_g1_point_raw(&point))));
+                self.result = "ok".to_string();
+            }
+            Err(e) => self.result = e.body.error.clone(),
+        }
+    }
+}
+
+/// An entry of the audit log, chained to the previous one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Position in the log, starting at 0
+    pub seq: u64,
+    #[serde(flatten)]
+    pub record: AuditRecord,
+    /// Hex hash of the previous entry
+    pub prev_hash: String,
+    /// Hex hash of this entry
+    pub hash: String,
+}
+
+/// The `prev_hash` of the first entry
+pub fn genesis_hash() -> String {
+    format!("0x{}", hex::encode([0u8; 32]))
+}
+
+/// Computes the hash of an entry from its position, record and predecessor.
+pub fn entry_hash(seq: u64, record: &AuditRecord, prev_hash: &str) -> Result<String> {
+    #[derive(Serialize)]
+    struct Hashed<'a> {
+        seq: u64,
+        #[serde(flatten)]
+        record: &'a AuditRecord,
+    }
+    let prev = hex::decode(prev_hash.trim_start_matches("0x")).context("prev_hash is not hex")?;
+    let mut hasher = Keccak256::new();
+    hasher.update(&prev);
+    hasher.update(serde_json::to_vec(&Hashed { seq, record })?);
+    Ok(format!("0x{}", hex::encode(hasher.finalize())))
+}
+
+impl AuditEntry {
+    /// Chains a record to the entry before it, if any.
+    pub fn chain(previous: Option<&AuditEntry>, record: AuditRecord) -> Result<Self> {
+        let (seq, prev_hash) = match previous {
+            Some(previous) => (previous.seq + 1, previous.hash.clone()),
+            None => (0, genesis_hash()),
+        };
+        let hash = entry_hash(seq, &record, &prev_hash)?;
+        Ok(Self {
+            seq,
+            record,
+            prev_hash,
+            hash,
+        })
+    }
+}
+
+/// The outcome of verifying an audit log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainSummary {
+    pub entries: usize,
+    /// Hash of the last entry, or the genesis hash of an empty log
+    pub head: String,
+}
+
+/// Verifies that entries form an unbroken chain from the genesis hash.
+///
+/// # Arguments
+/// * `entries` - The entries of the log, in order
+/// * `expected_head` - The hash of an entry recorded earlier, which must still be in
+///   the chain; this detects entries removed from the end
+///
+/// # Returns
+/// The number of entries and the head hash, or a description of the first break
+pub fn verify_chain(entries: &[AuditEntry], expected_head: Option<&str>) -> Result<ChainSummary, String> {
+    let mut prev_hash = genesis_hash();
+    let mut found_head = expected_head.is_none();
+    for (i, entry) in entries.iter().enumerate() {
+        if entry.seq != i as u64 {
+            return Err(format!("entry {}: sequence number is {}, entries are missing or reordered", i, entry.seq));
+        }
+        if entry.prev_hash != prev_hash {
+            return Err(format!("entry {}: does not chain to the previous entry", i));
+        }
+        let hash = entry_hash(entry.seq, &entry.record, &entry.prev_hash).map_err(|e| format!("entry {}: {}", i, e))?;
+        if entry.hash != hash {
+            return Err(format!("entry {}: hash mismatch, the entry has been modified", i));
+        }
+        found_head |= expected_head.is_some_and(|head| head.eq_ignore_ascii_case(&entry.hash));
+        prev_hash = hash;
+    }
+    if !found_head {
+        return Err(format!(
+            "expected head {} is not in the log, entries have been removed from the end",
+            expected_head.unwrap_or_default()
+        ));
+    }
+    Ok(ChainSummary {
+        entries: entries.len(),
+        head: prev_hash,
+    })
+}
+
+/// An append-only audit log
+#[async_trait]
+pub trait AuditLog: Send + Sync {
+    /// Appends a record, chaining it to the last entry
+    async fn append(&self, record: AuditRecord) -> Result<AuditEntry>;
+
+    /// All entries, oldest first
+    async fn entries(&self) -> Result<Vec<AuditEntry>>;
+}
+
+/// Appends a finished record to the audit log.
+///
+/// A signature must not leave the service unless it has been logged, so a
+/// failure to append is an internal error.
+pub(crate) async fn append_record(log: &dyn AuditLog, record: AuditRecord) -> Result<(), ApiError> {
+    log.append(record)
+        .await
+        .map(|_| ())
+        .map_err(|e| ApiError::internal(format!("Failed to append to the audit log: {:#}", e)))
+}
+
+/// An audit log kept in memory, mostly useful in tests
+#[derive(Default)]
+pub struct MemoryAuditLog {
+    entries: Mutex<Vec<AuditEntry>>,
+}
+
+impl MemoryAuditLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl AuditLog for MemoryAuditLog {
+    async fn append(&self, record: AuditRecord) -> Result<AuditEntry> {
+        let mut entries = self.entries.lock().unwrap();
+        let entry = AuditEntry::chain(entries.last(), record)?;
+        entries.push(entry.clone());
+        Ok(entry)
+    }
+
+    async fn entries(&self) -> Result<Vec<AuditEntry>> {
+        Ok(self.entries.lock().unwrap().clone())
+    }
+}
+
+/// An audit log in a JSON lines file, one entry per line.
+pub struct FileAuditLog {
+    path: PathBuf,
+    /// The open file and its last entry; appends are serialized
+    state: Mutex<(File, Option<AuditEntry>)>,
+}
+
+fn read_entries(path: &Path) -> Result<Vec<AuditEntry>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to open audit log {}", path.display())),
+    };
+    BufReader::new(file)
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            serde_json::from_str(&line?).with_context(|| format!("Malformed entry on line {} of {}", i + 1, path.display()))
+        })
+        .collect()
+}
+
+impl FileAuditLog {
+    /// Opens an audit log file, creating it if needed.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let last = read_entries(&path)?.pop();
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open audit log {}", path.display()))?;
+        Ok(Self {
+            path,
+            state: Mutex::new((file, last)),
+        })
+    }
+}
+
+#[async_trait]
+impl AuditLog for FileAuditLog {
+    async fn append(&self, record: AuditRecord) -> Result<AuditEntry> {
+        let mut state = self.state.lock().unwrap();
+        let entry = AuditEntry::chain(state.1.as_ref(), record)?;
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        state.0.write_all(&line)?;
+        state.0.sync_data()?;
+        state.1 = Some(entry.clone());
+        Ok(entry)
+    }
+
+    async fn entries(&self) -> Result<Vec<AuditEntry>> {
+        read_entries(&self.path)
+    }
+}
+
+/// Opens the audit log at a file path or `sqlite:` URL.
+pub async fn open_audit_log(location: &str) -> Result<std::sync::Arc<dyn AuditLog>> {
+    if location.starts_with("sqlite:") {
+        Ok(std::sync::Arc::new(crate::web::sqlite::SqliteAuditLog::connect(location).await?))
+    } else if location.is_empty() {
+        Err(anyhow!("The audit log location must not be empty"))
+    } else {
+        Ok(std::sync::Arc::new(FileAuditLog::open(location)?))
+    }
+}
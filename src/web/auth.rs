@@ -0,0 +1,316 @@
+//! Authentication and authorization of API requests.
+//!
+//! Clients authenticate with a bearer token of the form `bn254_<id>_<secret>`. The
+//! service only stores `keccak256(secret)`, so the plaintext token is shown once
+//! when it is created and cannot be recovered from the token store.
+//!
+//! Each token is scoped to a set of [`Operation`]s and, optionally, a set of EOA
+//! addresses. The [`authenticate`](crate::web::authenticate) middleware wraps the whole `/api` scope: it
+//! rejects requests without a valid token, checks that the token allows the
+//! operation of the matched route, and attaches a [`Principal`] to the request.
+//! Handlers then check the principal against the EOA addresses they act on.
+
+use std::collections::{BTreeSet, HashMap};
+use std::future::{ready, Ready};
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::dev::Payload;
+use actix_web::http::{Method, StatusCode};
+use actix_web::{FromRequest, HttpMessage, HttpRequest};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use clap::ValueEnum;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+
+use crate::web::error::ApiError;
+
+/// Prefix of every bearer token
+const TOKEN_PREFIX: &str = "bn254_";
+
+/// Operations a token can be allowed to perform
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Operation {
+    /// Read public keys with `GET /api/keys`
+    ReadKeys,
+    /// Sign messages with `POST /api/sign` and `POST /api/sign/batch`
+    Sign,
+    /// Multiply points with `POST /api/scalar_mul`
+    ScalarMul,
+    /// Verify signatures with `POST /api/verify`
+    Verify,
+    /// Review, approve and reject pending requests under `/api/approvals`
+    Approve,
+    /// Generate, import, rotate, disable, enable and delete keys
+    ManageKeys,
+}
+
+/// The operation performed by a route, or `None` for routes that are not mapped.
+/// Unmapped routes are denied, so new routes must be added here.
+pub fn operation_for(method: &Method, pattern: &str) -> Option<Operation> {
+    match (method.as_str(), pattern) {
+        ("GET", "/api/keys") | ("GET", "/api/keys/{eoa_address}") => Some(Operation::ReadKeys),
+        ("GET", "/api/v1/eth2/publicKeys") => Some(Operation::ReadKeys),
+        ("POST", "/api/sign") | ("POST", "/api/sign/batch") | ("POST", "/api/v1/eth2/sign/{identifier}") => Some(Operation::Sign),
+        ("POST", "/api/scalar_mul") => Some(Operation::ScalarMul),
+        ("POST", "/api/verify") => Some(Operation::Verify),
+        // Requesting a signature and collecting it once approved
+        ("POST", "/api/approvals") | ("GET", "/api/approvals/{id}") => Some(Operation::Sign),
+        ("GET", "/api/approvals") | ("POST", "/api/approvals/{id}/approve") | ("POST", "/api/approvals/{id}/reject") => {
+            Some(Operation::Approve)
+        }
+        ("POST", "/api/keys/{eoa_address}/generate")
+        | ("POST", "/api/keys/{eoa_address}/import")
+        | ("POST", "/api/keys/{eoa_address}/rotate")
+        | ("POST", "/api/keys/{eoa_address}/disable")
+        | ("POST", "/api/keys/{eoa_address}/enable")
+        | ("DELETE", "/api/keys/{eoa_address}") => Some(Operation::ManageKeys),
+        _ => None,
+    }
+}
+
+/// An API token as kept in the token store.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub id: String,
+    pub description: String,
+    /// Hex `keccak256` of the token secret
+    pub secret_hash: String,
+    /// EOA addresses the token may act on; `None` allows every key
+    pub eoa_addresses: Option<Vec<String>>,
+    pub operations: Vec<Operation>,
+    /// Seconds since the Unix epoch
+    pub created_at: i64,
+    /// Seconds since the Unix epoch after which the token is rejected
+    pub expires_at: Option<i64>,
+    pub revoked: bool,
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+fn hash_secret(secret: &str) -> String {
+    hex::encode(Keccak256::digest(secret.as_bytes()))
+}
+
+/// Compares two byte strings in time independent of where they differ
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+impl ApiToken {
+    /// Generates a new token.
+    ///
+    /// # Returns
+    /// The bearer token to hand to the client, and the token record to store
+    pub fn generate(
+        description: &str,
+        eoa_addresses: Option<Vec<String>>,
+        operations: Vec<Operation>,
+        expires_at: Option<i64>,
+    ) -> (String, Self) {
+        let mut id = [0u8; 8];
+        let mut secret = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut id);
+        rand::thread_rng().fill_bytes(&mut secret);
+        let (id, secret) = (hex::encode(id), hex::encode(secret));
+
+        let token = Self {
+            id: id.clone(),
+            description: description.to_string(),
+            secret_hash: hash_secret(&secret),
+            eoa_addresses,
+            operations,
+            created_at: now(),
+            expires_at,
+            revoked: false,
+        };
+        (format!("{}{}_{}", TOKEN_PREFIX, id, secret), token)
+    }
+
+    /// The principal a request authenticated with this token acts as
+    pub fn principal(&self) -> Principal {
+        Principal {
+            name: format!("token:{}", self.id),
+            eoa_addresses: self
+                .eoa_addresses
+                .as_ref()
+                .map(|eoas| eoas.iter().map(|e| e.to_lowercase()).collect()),
+            operations: self.operations.iter().copied().collect(),
+        }
+    }
+}
+
+/// Storage for API tokens
+#[async_trait]
+pub trait TokenStore: Send + Sync {
+    /// Get a token by id
+    async fn get_token(&self, id: &str) -> Result<Option<ApiToken>>;
+
+    /// List all tokens
+    async fn list_tokens(&self) -> Result<Vec<ApiToken>>;
+
+    /// Insert a new token
+    async fn insert_token(&self, token: ApiToken) -> Result<()>;
+
+    /// Revoke a token
+    ///
+    /// # Returns
+    /// Whether the token exists
+    async fn revoke_token(&self, id: &str) -> Result<bool>;
+}
+
+/// An in-memory token store, mostly useful in tests
+#[derive(Default)]
+pub struct MemoryTokenStore {
+    tokens: RwLock<HashMap<String, ApiToken>>,
+}
+
+impl MemoryTokenStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl TokenStore for MemoryTokenStore {
+    async fn get_token(&self, id: &str) -> Result<Option<ApiToken>> {
+        Ok(self.tokens.read().unwrap().get(id).cloned())
+    }
+
+    async fn list_tokens(&self) -> Result<Vec<ApiToken>> {
+        let mut tokens: Vec<ApiToken> = self.tokens.read().unwrap().values().cloned().collect();
+        tokens.sort_by_key(|t| t.created_at);
+        Ok(tokens)
+    }
+
+    async fn insert_token(&self, token: ApiToken) -> Result<()> {
+        let mut tokens = self.tokens.write().unwrap();
+        if tokens.contains_key(&token.id) {
+            return Err(anyhow!("Token {} already exists", token.id));
+        }
+        tokens.insert(token.id.clone(), token);
+        Ok(())
+    }
+
+    async fn revoke_token(&self, id: &str) -> Result<bool> {
+        Ok(self
+            .tokens
+            .write()
+            .unwrap()
+            .get_mut(id)
+            .map(|token| token.revoked = true)
+            .is_some())
+    }
+}
+
+/// The identity a request is made as, with what it is allowed to do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Principal {
+    /// A name for logs and audit records, such as `token:<id>`
+    pub name: String,
+    /// Lowercase EOA addresses the principal may act on; `None` allows every key
+    pub eoa_addresses: Option<BTreeSet<String>>,
+    pub operations: BTreeSet<Operation>,
+}
+
+impl Principal {
+    /// A principal allowed to use every key, used when authentication is disabled.
+    /// It may not manage keys, which always needs an authenticated principal.
+    pub fn unrestricted(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            eoa_addresses: None,
+            operations: [Operation::ReadKeys, Operation::Sign, Operation::ScalarMul, Operation::Verify, Operation::Approve]
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    /// Whether the principal may perform an operation at all
+    pub fn can(&self, operation: Operation) -> bool {
+        self.operations.contains(&operation)
+    }
+
+    /// Whether the principal may act on the key of an EOA
+    pub fn can_access(&self, eoa_address: &str) -> bool {
+        match &self.eoa_addresses {
+            None => true,
+            Some(eoas) => eoas.contains(&eoa_address.to_lowercase()),
+        }
+    }
+
+    /// Checks that the principal may perform an operation on the key of an EOA
+    pub fn authorize(&self, operation: Operation, eoa_address: &str) -> Result<(), ApiError> {
+        if !self.can(operation) {
+            return Err(forbidden(format!("{} may not perform {:?}", self.name, operation)));
+        }
+        if !self.can_access(eoa_address) {
+            return Err(forbidden(format!("{} may not use the key of {}", self.name, eoa_address)));
+        }
+        Ok(())
+    }
+}
+
+impl FromRequest for Principal {
+    type Error = ApiError;
+    type Future = Ready<Result<Self, ApiError>>;
+
+    /// Fails closed: a handler reached without the [`authenticate`](crate::web::authenticate) middleware
+    /// gets an internal error rather than an unrestricted principal
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(
+            req.extensions()
+                .get::<Principal>()
+                .cloned()
+                .ok_or_else(|| ApiError::internal("Request reached a handler without authentication")),
+        )
+    }
+}
+
+/// How requests are authenticated, registered as app data
+pub enum Authenticator {
+    /// Every request acts as an unrestricted principal
+    Disabled,
+    /// Requests must carry a bearer token from the store
+    Tokens(Arc<dyn TokenStore>),
+}
+
+pub(crate) fn unauthorized(message: &str) -> ApiError {
+    ApiError::new(StatusCode::UNAUTHORIZED, "unauthorized", None, message)
+}
+
+pub(crate) fn forbidden(message: String) -> ApiError {
+    ApiError::new(StatusCode::FORBIDDEN, "forbidden", None, message)
+}
+
+/// Checks a bearer token against the token store.
+pub async fn authenticate_token(store: &dyn TokenStore, bearer: &str) -> Result<Principal, ApiError> {
+    let (id, secret) = bearer
+        .strip_prefix(TOKEN_PREFIX)
+        .and_then(|rest| rest.split_once('_'))
+        .ok_or_else(|| unauthorized("malformed token"))?;
+    let token = store
+        .get_token(id)
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to read token: {}", e)))?
+        .ok_or_else(|| unauthorized("invalid token"))?;
+
+    if !constant_time_eq(hash_secret(secret).as_bytes(), token.secret_hash.as_bytes()) {
+        return Err(unauthorized("invalid token"));
+    }
+    if token.revoked {
+        return Err(unauthorized("token has been revoked"));
+    }
+    if token.expires_at.is_some_and(|expires_at| expires_at <= now()) {
+        return Err(unauthorized("token has expired"));
+    }
+    Ok(token.principal())
+}
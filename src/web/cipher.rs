@@ -0,0 +1,57 @@
+//! Authenticated encryption of secrets stored by the key store backends.
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{anyhow, Result};
+
+/// Length in bytes of the AES-GCM nonce prepended to each ciphertext
+const NONCE_LEN: usize = 12;
+
+/// Encrypts secrets with AES-256-GCM under a fixed store key.
+///
+/// Each ciphertext is `nonce || ciphertext || tag` and is bound to associated data,
+/// typically the EOA address, so that it cannot be moved to a different key entry.
+#[derive(Clone)]
+pub struct SecretCipher {
+    cipher: Aes256Gcm,
+}
+
+impl SecretCipher {
+    /// Create a cipher from a 32-byte store key
+    pub fn new(key: &[u8; 32]) -> Self {
+        Self {
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key)),
+        }
+    }
+
+    /// Parse a 32-byte store key from hex, with or without a `0x` prefix
+    pub fn key_from_hex(key_hex: &str) -> Result<[u8; 32]> {
+        hex::decode(key_hex.trim().trim_start_matches("0x"))?
+            .try_into()
+            .map_err(|_| anyhow!("Store key must be 32 bytes"))
+    }
+
+    /// Encrypt `plaintext` bound to `aad`
+    pub fn encrypt(&self, aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, Payload { msg: plaintext, aad })
+            .map_err(|_| anyhow!("Failed to encrypt secret"))?;
+
+        let mut blob = nonce.to_vec();
+        blob.extend(ciphertext);
+        Ok(blob)
+    }
+
+    /// Decrypt a blob produced by [`SecretCipher::encrypt`] with the same `aad`
+    pub fn decrypt(&self, aad: &[u8], blob: &[u8]) -> Result<Vec<u8>> {
+        if blob.len() < NONCE_LEN {
+            return Err(anyhow!("Encrypted secret is truncated"));
+        }
+        let (nonce, ciphertext) = blob.split_at(NONCE_LEN);
+        self.cipher
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad })
+            .map_err(|_| anyhow!("Failed to decrypt secret"))
+    }
+}
@@ -0,0 +1,1253 @@
+//! Configuration of the key service.
+//!
+//! Settings are layered, with later sources overriding earlier ones:
+//!
+//! 1. Built-in defaults
+//! 2. A TOML file given by `--config` or `BN254_CONFIG`
+//! 3. `BN254_*` environment variables
+//! 4. Command line flags
+//!
+//! The merged configuration is validated once at startup so that mistakes are
+//! reported before the server binds.
+//!
+//! ```toml
+//! [server]
+//! host = "127.0.0.1"
+//! port = 8080
+//! workers = 4
+//!
+//! [grpc]
+//! port = 50051
+//!
+//! [store]
+//! backend = "sqlite"
+//! path = "sqlite://bn254-keys.db"
+//! key_file = "/etc/bn254/store.key"
+//!
+//! [store.kek]
+//! provider = "passphrase"
+//! path = "/etc/bn254/kek-params.json"
+//!
+//! [signer]
+//! backend = "pkcs11"
+//!
+//! [pkcs11]
+//! module = "/usr/lib/softhsm/libsofthsm2.so"
+//! token_label = "bn254"
+//! key_label = "bn254-kek"
+//!
+//! [log]
+//! level = "info"
+//! format = "json"
+//!
+//! [tls]
+//! cert = "/etc/bn254/server.pem"
+//! key = "/etc/bn254/server.key"
+//! client_ca = "/etc/bn254/clients-ca.pem"
+//!
+//! [[tls.clients]]
+//! name = "aggregator.example.com"
+//! eoa_addresses = ["0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"]
+//! operations = ["sign", "read_keys"]
+//!
+//! [auth]
+//! enabled = true
+//! database = "sqlite://bn254-tokens.db"
+//!
+//! [eoa_auth]
+//! mode = "required"
+//! max_validity_secs = 300
+//! chain_id = 1
+//! salt = "0x5d1c9a0e7b3f4a2c8e6d1b0f9a7c5e3d2b1a0f9e8d7c6b5a4f3e2d1c0b9a8f7e"
+//!
+//! [audit]
+//! path = "/var/lib/bn254/audit.jsonl"
+//!
+//! [guard]
+//! database = "sqlite:///var/lib/bn254/signed-tasks.db"
+//!
+//! [approvals]
+//! approvers = ["cert:alice.example.com", "cert:bob.example.com", "token:3f9a0c1d2e4b5a69"]
+//! threshold = 2
+//! ttl_secs = 3600
+//!
+//! [endpoints]
+//! keys = true
+//! signing = true
+//! verify = true
+//! metrics = true
+//! lifecycle = true
+//! web3signer = true
+//! max_batch_size = 100
+//!
+//! [[message_sources]]
+//! name = "incredible-squaring"
+//! domain = "0x8f0b0a6a52c2e3a3c7d4c5b5a8e4d2f1c3b2a1908f7e6d5c4b3a291807f6e5d4"
+//!
+//! [[policies]]
+//! eoa_address = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"
+//! max_per_hour = 60
+//! max_per_day = 1000
+//! principals = ["cert:aggregator.example.com"]
+//! expires_at = 1798761600
+//! message_sources = ["incredible-squaring"]
+//!
+//! [[policies]]
+//! eoa_address = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8"
+//! approval_required = true
+//!
+//! [[policies.time_windows]]
+//! days = ["mon", "tue", "wed", "thu", "fri"]
+//! start = "08:00"
+//! end = "18:00"
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::web::auth::{Operation, Principal};
+use crate::web::cipher::SecretCipher;
+use crate::web::envelope::{KekProvider, LocalKek};
+use crate::web::eoa_auth::AuthorizationDomain;
+use crate::web::pkcs11::Pkcs11Kek;
+use crate::web::store::DEFAULT_JSON_PATH;
+
+/// Key store backends selectable at startup
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum StoreBackend {
+    /// Key pairs in the `players.json` format
+    Json,
+    /// One encrypted file per key in a directory
+    Dir,
+    /// A SQLite database
+    Sqlite,
+    /// An empty in-memory store
+    Memory,
+}
+
+/// Providers of the key-encryption key wrapping the data keys of the JSON store
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum KekSource {
+    /// A 32-byte hex key in a file
+    KeyFile,
+    /// A passphrase from `BN254_KEK_PASSPHRASE`, stretched with Argon2id
+    Passphrase,
+    /// The AES key of the PKCS#11 token in `[pkcs11]`
+    Pkcs11,
+}
+
+/// Where private key operations happen
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SignerBackend {
+    /// Private keys are read from the key store as they are
+    #[default]
+    Local,
+    /// Private keys are stored wrapped by the AES key of the PKCS#11 token in
+    /// `[pkcs11]` and unwrapped for each operation
+    Pkcs11,
+}
+
+/// Log output formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human readable lines
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+/// Whether signing requests must be authorized by the operator's EOA
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum EoaAuthMode {
+    /// Authorizations are ignored
+    Off,
+    /// Authorizations are checked when a request carries one
+    Optional,
+    /// Every signing request must carry an authorization
+    Required,
+}
+
+/// Groups of endpoints that can be turned on and off
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum EndpointGroup {
+    /// `GET /api/keys` and `GET /api/keys/{eoa_address}`
+    Keys,
+    /// `POST /api/sign`, `POST /api/scalar_mul` and `/api/approvals`
+    Signing,
+    /// `POST /api/verify`
+    Verify,
+    /// `GET /metrics`
+    Metrics,
+    /// Key generation, import, rotation, disabling and deletion under `/api/keys`
+    Lifecycle,
+    /// `GET /api/v1/eth2/publicKeys` and `POST /api/v1/eth2/sign/{identifier}`
+    Web3signer,
+}
+
+/// Listener settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub host: String,
+    pub port: u16,
+    /// Number of worker threads; defaults to the number of CPUs
+    pub workers: Option<usize>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: "127.0.0.1".to_string(),
+            port: 8080,
+            workers: None,
+        }
+    }
+}
+
+/// Listener of the gRPC remote signer API, which is off unless a port is set.
+/// It binds the host of the HTTP server.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GrpcConfig {
+    pub port: Option<u16>,
+}
+
+/// Key store settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StoreConfig {
+    pub backend: StoreBackend,
+    /// The JSON file, key directory or SQLite URL, depending on the backend
+    pub path: Option<String>,
+    /// File holding the 32-byte hex store key of the encrypted backends.
+    /// The key can also be given in `BN254_STORE_KEY`, but never on the command line.
+    pub key_file: Option<PathBuf>,
+    /// JSON key file imported into the store on startup
+    pub import_json: Option<PathBuf>,
+    /// Envelope encryption of the private keys in the JSON store
+    pub kek: KekConfig,
+}
+
+impl Default for StoreConfig {
+    fn default() -> Self {
+        Self {
+            backend: StoreBackend::Json,
+            path: None,
+            key_file: None,
+            import_json: None,
+            kek: KekConfig::default(),
+        }
+    }
+}
+
+/// Signer settings
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SignerConfig {
+    pub backend: SignerBackend,
+}
+
+/// PKCS#11 token settings, used by the `pkcs11` signer and KEK provider.
+/// The user PIN is read from `BN254_PKCS11_PIN`, never from the configuration
+/// file or the command line.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Pkcs11Config {
+    /// The PKCS#11 module to load
+    pub module: Option<PathBuf>,
+    /// The label of the token
+    pub token_label: String,
+    /// The label of the AES key wrapping the private keys
+    pub key_label: String,
+}
+
+impl Default for Pkcs11Config {
+    fn default() -> Self {
+        Self {
+            module: None,
+            token_label: "bn254".to_string(),
+            key_label: "bn254-kek".to_string(),
+        }
+    }
+}
+
+/// Key-encryption key settings of the JSON store
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KekConfig {
+    /// Where the key-encryption key comes from; private keys are stored in
+    /// plaintext when unset
+    pub provider: Option<KekSource>,
+    /// The hex key file, or the Argon2 parameter file of a passphrase, which is
+    /// created on first start; unused by the `pkcs11` provider
+    pub path: Option<PathBuf>,
+}
+
+/// Logging settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// An `env_logger` filter such as `info` or `bn254_rs=debug,actix_web=warn`
+    pub level: String,
+    pub format: LogFormat,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            format: LogFormat::Text,
+        }
+    }
+}
+
+/// TLS material; TLS is enabled when a certificate and key are configured
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsConfig {
+    /// PEM certificate chain of the server
+    pub cert: Option<PathBuf>,
+    /// PEM private key of the server
+    pub key: Option<PathBuf>,
+    /// PEM bundle of CAs trusted to issue client certificates; when set, every
+    /// client must present one
+    pub client_ca: Option<PathBuf>,
+    /// Principals of clients authenticated by their certificate
+    pub clients: Vec<ClientPrincipal>,
+}
+
+/// What a client presenting a certificate with a given name may do
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClientPrincipal {
+    /// Common name, or DNS, URI or email alternative name of the client certificate
+    pub name: String,
+    /// EOA addresses the client may use; omit to allow every key
+    #[serde(default)]
+    pub eoa_addresses: Option<Vec<String>>,
+    pub operations: Vec<Operation>,
+}
+
+impl ClientPrincipal {
+    /// The principal a request over a connection with this client certificate acts as
+    pub fn principal(&self) -> Principal {
+        Principal {
+            name: format!("cert:{}", self.name),
+            eoa_addresses: self
+                .eoa_addresses
+                .as_ref()
+                .map(|eoas| eoas.iter().map(|e| e.to_lowercase()).collect()),
+            operations: self.operations.iter().copied().collect(),
+        }
+    }
+}
+
+impl TlsConfig {
+    /// Whether TLS is configured
+    pub fn enabled(&self) -> bool {
+        self.cert.is_some() || self.key.is_some()
+    }
+}
+
+/// API authentication settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    /// Whether `/api` requests need a bearer token
+    pub enabled: bool,
+    /// SQLite URL of the token database; the sqlite key store shares its database by default
+    pub database: Option<String>,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            database: None,
+        }
+    }
+}
+
+/// EOA authorization settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EoaAuthConfig {
+    pub mode: EoaAuthMode,
+    /// How far in the future the expiry of an authorization may be
+    pub max_validity_secs: u64,
+    /// EIP-155 chain id authorizations are bound to
+    pub chain_id: u64,
+    /// 32-byte hex salt binding authorizations to this deployment; zero if unset
+    pub salt: Option<String>,
+}
+
+impl Default for EoaAuthConfig {
+    fn default() -> Self {
+        Self {
+            mode: EoaAuthMode::Optional,
+            max_validity_secs: 300,
+            chain_id: 1,
+            salt: None,
+        }
+    }
+}
+
+/// Days of the week
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Weekday {
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+    Sun,
+}
+
+impl Weekday {
+    /// The UTC day of the week of a Unix timestamp
+    pub fn of_timestamp(timestamp: i64) -> Self {
+        // 1970-01-01 was a Thursday
+        match (timestamp.div_euclid(86_400) + 3).rem_euclid(7) {
+            0 => Weekday::Mon,
+            1 => Weekday::Tue,
+            2 => Weekday::Wed,
+            3 => Weekday::Thu,
+            4 => Weekday::Fri,
+            5 => Weekday::Sat,
+            _ => Weekday::Sun,
+        }
+    }
+}
+
+/// A daily period, in UTC, during which a key may sign
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TimeWindow {
+    /// Days the window applies to; every day if empty
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+    /// `HH:MM`, inclusive
+    pub start: String,
+    /// `HH:MM`, exclusive; `24:00` for the end of the day
+    pub end: String,
+}
+
+fn parse_time_of_day(time: &str) -> Result<u32> {
+    let (hours, minutes) = time
+        .split_once(':')
+        .ok_or_else(|| anyhow!("{:?} is not a HH:MM time", time))?;
+    let hours: u32 = hours.parse().map_err(|_| anyhow!("{:?} is not a HH:MM time", time))?;
+    let minutes: u32 = minutes.parse().map_err(|_| anyhow!("{:?} is not a HH:MM time", time))?;
+    if minutes >= 60 || hours * 60 + minutes > 24 * 60 {
+        return Err(anyhow!("{:?} is not a time of day", time));
+    }
+    Ok(hours * 60 + minutes)
+}
+
+impl TimeWindow {
+    /// The start and end of the window in minutes since midnight
+    pub fn minutes(&self) -> Result<(u32, u32)> {
+        let (start, end) = (parse_time_of_day(&self.start)?, parse_time_of_day(&self.end)?);
+        if start >= end {
+            return Err(anyhow!("start {} is not before end {}", self.start, self.end));
+        }
+        Ok((start, end))
+    }
+
+    /// Whether a Unix timestamp falls in the window
+    pub fn contains(&self, timestamp: i64) -> bool {
+        let Ok((start, end)) = self.minutes() else {
+            return false;
+        };
+        let minute = (timestamp.rem_euclid(86_400) / 60) as u32;
+        (self.days.is_empty() || self.days.contains(&Weekday::of_timestamp(timestamp)))
+            && start <= minute
+            && minute < end
+    }
+}
+
+/// A registered source of messages.
+///
+/// Data signed through a source is hashed as `keccak256(domain || data)`, so a
+/// message can only be attributed to the source whose domain produced it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MessageSource {
+    pub name: String,
+    /// 32-byte hex domain tag
+    pub domain: String,
+}
+
+/// Limits on the use of a key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KeyPolicy {
+    /// EOA address of the key, or `*` for every key without a policy of its own
+    pub eoa_address: String,
+    /// Signatures per key in any 60 minutes
+    #[serde(default)]
+    pub max_per_hour: Option<u32>,
+    /// Signatures per key in any 24 hours
+    #[serde(default)]
+    pub max_per_day: Option<u32>,
+    /// Principals that may use the key, such as `token:<id>` or `cert:<name>`; omit to allow any
+    #[serde(default)]
+    pub principals: Option<Vec<String>>,
+    /// Periods during which the key may sign; omit to allow any time
+    #[serde(default)]
+    pub time_windows: Vec<TimeWindow>,
+    /// Seconds since the Unix epoch from which the key may no longer sign
+    #[serde(default)]
+    pub expires_at: Option<i64>,
+    /// Message sources signed messages must come from; omit to allow any message
+    #[serde(default)]
+    pub message_sources: Option<Vec<String>>,
+    /// Whether signatures need the approval of `approvals.threshold` approvers
+    #[serde(default)]
+    pub approval_required: bool,
+}
+
+/// Audit log settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuditConfig {
+    /// JSON lines file, or SQLite URL, of the audit log
+    pub path: String,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            path: "bn254-audit.jsonl".to_string(),
+        }
+    }
+}
+
+/// Anti-equivocation settings
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GuardConfig {
+    /// SQLite URL of the signed task database; the token database by default
+    pub database: Option<String>,
+}
+
+/// Multi-party approval settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApprovalsConfig {
+    /// Principals that may approve requests, such as `token:<id>` or `cert:<name>`
+    pub approvers: Vec<String>,
+    /// Number of distinct approvers a request needs
+    pub threshold: usize,
+    /// Seconds a request waits for approval before it expires
+    pub ttl_secs: u64,
+}
+
+impl Default for ApprovalsConfig {
+    fn default() -> Self {
+        Self {
+            approvers: Vec::new(),
+            threshold: 2,
+            ttl_secs: 3600,
+        }
+    }
+}
+
+/// Endpoint group toggles
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EndpointsConfig {
+    pub keys: bool,
+    pub signing: bool,
+    pub verify: bool,
+    pub metrics: bool,
+    /// Off by default: key management needs tokens with the `manage_keys` operation
+    pub lifecycle: bool,
+    /// Off by default: Web3Signer-style key listing and signing
+    pub web3signer: bool,
+    /// Most items one `POST /api/sign/batch` request may sign
+    pub max_batch_size: usize,
+}
+
+impl Default for EndpointsConfig {
+    fn default() -> Self {
+        Self {
+            keys: true,
+            signing: true,
+            verify: true,
+            metrics: true,
+            lifecycle: false,
+            web3signer: false,
+            max_batch_size: 100,
+        }
+    }
+}
+
+impl EndpointsConfig {
+    fn set(&mut self, group: EndpointGroup, enabled: bool) {
+        match group {
+            EndpointGroup::Keys => self.keys = enabled,
+            EndpointGroup::Signing => self.signing = enabled,
+            EndpointGroup::Verify => self.verify = enabled,
+            EndpointGroup::Metrics => self.metrics = enabled,
+            EndpointGroup::Lifecycle => self.lifecycle = enabled,
+            EndpointGroup::Web3signer => self.web3signer = enabled,
+        }
+    }
+}
+
+/// The complete service configuration
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub grpc: GrpcConfig,
+    pub store: StoreConfig,
+    pub signer: SignerConfig,
+    pub pkcs11: Pkcs11Config,
+    pub log: LogConfig,
+    pub tls: TlsConfig,
+    pub auth: AuthConfig,
+    pub eoa_auth: EoaAuthConfig,
+    pub audit: AuditConfig,
+    pub guard: GuardConfig,
+    pub approvals: ApprovalsConfig,
+    pub endpoints: EndpointsConfig,
+    pub message_sources: Vec<MessageSource>,
+    pub policies: Vec<KeyPolicy>,
+}
+
+/// Command line flags of the key service
+#[derive(Debug, Default, Parser)]
+#[command(name = "bn254-key-service", about = "BN254 key management service")]
+pub struct Cli {
+    /// Run a management command instead of the server
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// TOML configuration file
+    #[arg(long, short)]
+    pub config: Option<PathBuf>,
+    /// Address to bind
+    #[arg(long)]
+    pub host: Option<String>,
+    /// Port to bind
+    #[arg(long, short)]
+    pub port: Option<u16>,
+    /// Number of worker threads
+    #[arg(long)]
+    pub workers: Option<usize>,
+    /// Port of the gRPC remote signer API
+    #[arg(long)]
+    pub grpc_port: Option<u16>,
+    /// Key store backend
+    #[arg(long, value_enum)]
+    pub store: Option<StoreBackend>,
+    /// JSON file, key directory or SQLite URL of the key store
+    #[arg(long)]
+    pub store_path: Option<String>,
+    /// File holding the hex store key
+    #[arg(long)]
+    pub store_key_file: Option<PathBuf>,
+    /// JSON key file imported into the store on startup
+    #[arg(long)]
+    pub import_json: Option<PathBuf>,
+    /// Provider of the key-encryption key of the JSON store
+    #[arg(long, value_enum)]
+    pub kek_provider: Option<KekSource>,
+    /// Key file or Argon2 parameter file of the key-encryption key
+    #[arg(long)]
+    pub kek_path: Option<PathBuf>,
+    /// Where private key operations happen
+    #[arg(long, value_enum)]
+    pub signer: Option<SignerBackend>,
+    /// Log filter, e.g. `info` or `bn254_rs=debug`
+    #[arg(long)]
+    pub log_level: Option<String>,
+    /// Log output format
+    #[arg(long, value_enum)]
+    pub log_format: Option<LogFormat>,
+    /// PEM certificate chain of the server
+    #[arg(long)]
+    pub tls_cert: Option<PathBuf>,
+    /// PEM private key of the server
+    #[arg(long)]
+    pub tls_key: Option<PathBuf>,
+    /// PEM bundle of CAs trusted to issue client certificates
+    #[arg(long)]
+    pub tls_client_ca: Option<PathBuf>,
+    /// Serve `/api` without authentication
+    #[arg(long)]
+    pub no_auth: bool,
+    /// SQLite URL of the token database
+    #[arg(long)]
+    pub auth_database: Option<String>,
+    /// Whether signing requests must be authorized by the operator's EOA
+    #[arg(long, value_enum)]
+    pub eoa_auth: Option<EoaAuthMode>,
+    /// JSON lines file, or SQLite URL, of the audit log
+    #[arg(long)]
+    pub audit_path: Option<String>,
+    /// SQLite URL of the signed task database
+    #[arg(long)]
+    pub guard_database: Option<String>,
+    /// Enable an endpoint group
+    #[arg(long, value_enum)]
+    pub enable: Vec<EndpointGroup>,
+    /// Disable an endpoint group
+    #[arg(long, value_enum)]
+    pub disable: Vec<EndpointGroup>,
+}
+
+/// Management commands
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Manage API tokens
+    #[command(subcommand)]
+    Token(TokenCommand),
+    /// Inspect the audit log
+    #[command(subcommand)]
+    Audit(AuditCommand),
+    /// Move signed AVS task records between services
+    #[command(subcommand)]
+    Guard(GuardCommand),
+    /// Prepare the PKCS#11 token
+    #[command(subcommand)]
+    Pkcs11(Pkcs11Command),
+}
+
+/// API token management commands
+#[derive(Debug, Subcommand)]
+pub enum TokenCommand {
+    /// Create a token and print it; the token cannot be shown again
+    Create {
+        /// What the token is for
+        #[arg(long, default_value = "")]
+        description: String,
+        /// EOA address the token may use; repeat for several keys
+        #[arg(long = "eoa")]
+        eoa_addresses: Vec<String>,
+        /// Allow the token to use every key
+        #[arg(long, conflicts_with = "eoa_addresses")]
+        all_keys: bool,
+        /// Operation the token may perform; repeat for several operations
+        #[arg(long = "operation", value_enum, required = true)]
+        operations: Vec<Operation>,
+        /// Number of days until the token expires
+        #[arg(long)]
+        expires_in_days: Option<u64>,
+    },
+    /// List tokens
+    List,
+    /// Revoke a token
+    Revoke {
+        /// Id of the token
+        id: String,
+    },
+}
+
+/// Audit log commands
+#[derive(Debug, Subcommand)]
+pub enum AuditCommand {
+    /// Check that the audit log has not been edited or truncated, and print its head
+    Verify {
+        /// Head hash printed by an earlier run, which must still be in the log
+        #[arg(long)]
+        head: Option<String>,
+    },
+    /// Print audit log entries as JSON lines
+    List {
+        /// Only entries for this EOA address
+        #[arg(long = "eoa")]
+        eoa_address: Option<String>,
+        /// Only entries at or after this Unix timestamp
+        #[arg(long)]
+        since: Option<i64>,
+    },
+}
+
+/// Signed task commands
+#[derive(Debug, Subcommand)]
+pub enum GuardCommand {
+    /// Write every signed task in the interchange format
+    Export {
+        /// File to write; standard output if omitted
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Record the signed tasks of an interchange file
+    Import {
+        /// Interchange file to read
+        file: PathBuf,
+    },
+}
+
+/// PKCS#11 token commands
+#[derive(Debug, Subcommand)]
+pub enum Pkcs11Command {
+    /// Generate the AES key wrapping the private keys on the token
+    GenerateKey,
+}
+
+fn parse_env<T: std::str::FromStr>(name: &str, value: &str) -> Result<T> {
+    value
+        .parse()
+        .map_err(|_| anyhow!("Invalid value for {}: {:?}", name, value))
+}
+
+fn parse_env_bool(name: &str, value: &str) -> Result<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" => Ok(false),
+        _ => Err(anyhow!("Invalid value for {}: {:?}, expected true or false", name, value)),
+    }
+}
+
+fn parse_env_enum<T: ValueEnum>(name: &str, value: &str) -> Result<T> {
+    T::from_str(value, true).map_err(|_| anyhow!("Invalid value for {}: {:?}", name, value))
+}
+
+impl Config {
+    /// Loads the configuration from every source, then validates it.
+    pub fn load(cli: &Cli) -> Result<Self> {
+        let config_path = cli
+            .config
+            .clone()
+            .or_else(|| std::env::var_os("BN254_CONFIG").map(PathBuf::from));
+        let mut config = match config_path {
+            Some(path) => Self::from_file(path)?,
+            None => Self::default(),
+        };
+        config.apply_env(std::env::vars())?;
+        config.apply_cli(cli);
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Reads a TOML configuration file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let content = fs::read_to_string(path.as_ref())
+            .with_context(|| format!("Failed to read config file {}", path.as_ref().display()))?;
+        toml::from_str(&content)
+            .with_context(|| format!("Invalid config file {}", path.as_ref().display()))
+    }
+
+    /// Applies `BN254_*` overrides from a set of environment variables.
+    /// Unrelated variables are ignored.
+    pub fn apply_env<I: IntoIterator<Item = (String, String)>>(&mut self, vars: I) -> Result<()> {
+        for (name, value) in vars {
+            match name.as_str() {
+                "BN254_HOST" => self.server.host = value,
+                "BN254_PORT" => self.server.port = parse_env(&name, &value)?,
+                "BN254_WORKERS" => self.server.workers = Some(parse_env(&name, &value)?),
+                "BN254_GRPC_PORT" => self.grpc.port = Some(parse_env(&name, &value)?),
+                "BN254_STORE" => self.store.backend = parse_env_enum(&name, &value)?,
+                "BN254_STORE_PATH" => self.store.path = Some(value),
+                "BN254_STORE_KEY_FILE" => self.store.key_file = Some(value.into()),
+                "BN254_IMPORT_JSON" => self.store.import_json = Some(value.into()),
+                "BN254_KEK_PROVIDER" => self.store.kek.provider = Some(parse_env_enum(&name, &value)?),
+                "BN254_KEK_PATH" => self.store.kek.path = Some(value.into()),
+                "BN254_SIGNER" => self.signer.backend = parse_env_enum(&name, &value)?,
+                "BN254_PKCS11_MODULE" => self.pkcs11.module = Some(value.into()),
+                "BN254_PKCS11_TOKEN" => self.pkcs11.token_label = value,
+                "BN254_PKCS11_KEY" => self.pkcs11.key_label = value,
+                "BN254_LOG_LEVEL" => self.log.level = value,
+                "BN254_LOG_FORMAT" => self.log.format = parse_env_enum(&name, &value)?,
+                "BN254_TLS_CERT" => self.tls.cert = Some(value.into()),
+                "BN254_TLS_KEY" => self.tls.key = Some(value.into()),
+                "BN254_TLS_CLIENT_CA" => self.tls.client_ca = Some(value.into()),
+                "BN254_AUTH_ENABLED" => self.auth.enabled = parse_env_bool(&name, &value)?,
+                "BN254_AUTH_DATABASE" => self.auth.database = Some(value),
+                "BN254_EOA_AUTH_MODE" => self.eoa_auth.mode = parse_env_enum(&name, &value)?,
+                "BN254_EOA_AUTH_MAX_VALIDITY_SECS" => self.eoa_auth.max_validity_secs = parse_env(&name, &value)?,
+                "BN254_EOA_AUTH_CHAIN_ID" => self.eoa_auth.chain_id = parse_env(&name, &value)?,
+                "BN254_EOA_AUTH_SALT" => self.eoa_auth.salt = Some(value),
+                "BN254_AUDIT_PATH" => self.audit.path = value,
+                "BN254_GUARD_DATABASE" => self.guard.database = Some(value),
+                "BN254_APPROVALS_THRESHOLD" => self.approvals.threshold = parse_env(&name, &value)?,
+                "BN254_APPROVALS_TTL_SECS" => self.approvals.ttl_secs = parse_env(&name, &value)?,
+                "BN254_ENDPOINTS_KEYS" => self.endpoints.keys = parse_env_bool(&name, &value)?,
+                "BN254_ENDPOINTS_SIGNING" => self.endpoints.signing = parse_env_bool(&name, &value)?,
+                "BN254_ENDPOINTS_VERIFY" => self.endpoints.verify = parse_env_bool(&name, &value)?,
+                "BN254_ENDPOINTS_METRICS" => self.endpoints.metrics = parse_env_bool(&name, &value)?,
+                "BN254_ENDPOINTS_LIFECYCLE" => self.endpoints.lifecycle = parse_env_bool(&name, &value)?,
+                "BN254_ENDPOINTS_WEB3SIGNER" => self.endpoints.web3signer = parse_env_bool(&name, &value)?,
+                "BN254_ENDPOINTS_MAX_BATCH_SIZE" => self.endpoints.max_batch_size = parse_env(&name, &value)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies command line flags.
+    pub fn apply_cli(&mut self, cli: &Cli) {
+        if let Some(host) = &cli.host {
+            self.server.host = host.clone();
+        }
+        if let Some(port) = cli.port {
+            self.server.port = port;
+        }
+        if let Some(workers) = cli.workers {
+            self.server.workers = Some(workers);
+        }
+        if let Some(port) = cli.grpc_port {
+            self.grpc.port = Some(port);
+        }
+        if let Some(backend) = cli.store {
+            self.store.backend = backend;
+        }
+        if let Some(path) = &cli.store_path {
+            self.store.path = Some(path.clone());
+        }
+        if let Some(key_file) = &cli.store_key_file {
+            self.store.key_file = Some(key_file.clone());
+        }
+        if let Some(import_json) = &cli.import_json {
+            self.store.import_json = Some(import_json.clone());
+        }
+        if let Some(provider) = cli.kek_provider {
+            self.store.kek.provider = Some(provider);
+        }
+        if let Some(path) = &cli.kek_path {
+            self.store.kek.path = Some(path.clone());
+        }
+        if let Some(backend) = cli.signer {
+            self.signer.backend = backend;
+        }
+        if let Some(level) = &cli.log_level {
+            self.log.level = level.clone();
+        }
+        if let Some(format) = cli.log_format {
+            self.log.format = format;
+        }
+        if let Some(cert) = &cli.tls_cert {
+            self.tls.cert = Some(cert.clone());
+        }
+        if let Some(key) = &cli.tls_key {
+            self.tls.key = Some(key.clone());
+        }
+        if let Some(client_ca) = &cli.tls_client_ca {
+            self.tls.client_ca = Some(client_ca.clone());
+        }
+        if cli.no_auth {
+            self.auth.enabled = false;
+        }
+        if let Some(database) = &cli.auth_database {
+            self.auth.database = Some(database.clone());
+        }
+        if let Some(mode) = cli.eoa_auth {
+            self.eoa_auth.mode = mode;
+        }
+        if let Some(path) = &cli.audit_path {
+            self.audit.path = path.clone();
+        }
+        if let Some(database) = &cli.guard_database {
+            self.guard.database = Some(database.clone());
+        }
+        for group in &cli.enable {
+            self.endpoints.set(*group, true);
+        }
+        for group in &cli.disable {
+            self.endpoints.set(*group, false);
+        }
+    }
+
+    /// The key store location, falling back to the default of the backend.
+    pub fn store_path(&self) -> Option<String> {
+        self.store.path.clone().or_else(|| match self.store.backend {
+            StoreBackend::Json => Some(DEFAULT_JSON_PATH.to_string()),
+            StoreBackend::Sqlite => Some("sqlite://bn254-keys.db".to_string()),
+            StoreBackend::Dir | StoreBackend::Memory => None,
+        })
+    }
+
+    /// The token database, which also keeps used EOA authorization nonces, shared with the sqlite key store unless set explicitly.
+    pub fn auth_database(&self) -> String {
+        self.auth.database.clone().unwrap_or_else(|| match self.store.backend {
+            StoreBackend::Sqlite => self.store_path().unwrap_or_default(),
+            _ => "sqlite://bn254-tokens.db".to_string(),
+        })
+    }
+
+    /// The signed task database, the token database unless set explicitly.
+    pub fn guard_database(&self) -> String {
+        self.guard.database.clone().unwrap_or_else(|| self.auth_database())
+    }
+
+    /// Whether the backend encrypts private keys and so needs a store key.
+    pub fn store_needs_key(&self) -> bool {
+        matches!(self.store.backend, StoreBackend::Dir | StoreBackend::Sqlite)
+    }
+
+    /// Reads the store key from `BN254_STORE_KEY` or the configured key file.
+    pub fn store_key(&self) -> Result<[u8; 32]> {
+        if let Ok(key_hex) = std::env::var("BN254_STORE_KEY") {
+            return SecretCipher::key_from_hex(&key_hex).context("Invalid BN254_STORE_KEY");
+        }
+        let path = self
+            .store
+            .key_file
+            .as_ref()
+            .ok_or_else(|| anyhow!("store.key_file or BN254_STORE_KEY must be set for the {:?} store", self.store.backend))?;
+        let key_hex = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read store key file {}", path.display()))?;
+        SecretCipher::key_from_hex(&key_hex)
+            .with_context(|| format!("Invalid store key in {}", path.display()))
+    }
+
+    /// The key-encryption key provider of the JSON store, if one is configured.
+    /// A passphrase is read from `BN254_KEK_PASSPHRASE`, never from the
+    /// configuration file or the command line.
+    pub fn kek_provider(&self) -> Result<Option<Arc<dyn KekProvider>>> {
+        let Some(provider) = self.store.kek.provider else {
+            return Ok(None);
+        };
+        let path = || {
+            self.store
+                .kek
+                .path
+                .as_ref()
+                .ok_or_else(|| anyhow!("store.kek.path must be set for the {:?} KEK provider", provider))
+        };
+        let kek: Arc<dyn KekProvider> = match provider {
+            KekSource::KeyFile => Arc::new(LocalKek::from_key_file(path()?)?),
+            KekSource::Passphrase => {
+                let passphrase = Zeroizing::new(
+                    std::env::var("BN254_KEK_PASSPHRASE")
+                        .map_err(|_| anyhow!("BN254_KEK_PASSPHRASE must be set for the passphrase KEK provider"))?,
+                );
+                Arc::new(LocalKek::from_passphrase(&passphrase, path()?)?)
+            }
+            KekSource::Pkcs11 => Arc::new(self.pkcs11_kek()?),
+        };
+        Ok(Some(kek))
+    }
+
+    /// Whether the signer or the KEK provider of the JSON store uses the PKCS#11 token.
+    pub fn uses_pkcs11(&self) -> bool {
+        self.signer.backend == SignerBackend::Pkcs11 || self.store.kek.provider == Some(KekSource::Pkcs11)
+    }
+
+    /// Logs in to the PKCS#11 token with the PIN in `BN254_PKCS11_PIN` and finds
+    /// its AES key.
+    pub fn pkcs11_kek(&self) -> Result<Pkcs11Kek> {
+        let (module, pin) = self.pkcs11_login()?;
+        Pkcs11Kek::open(module, &self.pkcs11.token_label, &pin, &self.pkcs11.key_label)
+    }
+
+    /// The PKCS#11 module and the user PIN from `BN254_PKCS11_PIN`.
+    pub fn pkcs11_login(&self) -> Result<(&Path, Zeroizing<String>)> {
+        let module = self
+            .pkcs11
+            .module
+            .as_deref()
+            .ok_or_else(|| anyhow!("pkcs11.module must be set to use the PKCS#11 token"))?;
+        let pin = Zeroizing::new(
+            std::env::var("BN254_PKCS11_PIN")
+                .map_err(|_| anyhow!("BN254_PKCS11_PIN must be set to use the PKCS#11 token"))?,
+        );
+        Ok((module, pin))
+    }
+
+    /// Checks the configuration, reporting every problem at once.
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = Vec::new();
+
+        if self.server.host.trim().is_empty() {
+            errors.push("server.host must not be empty".to_string());
+        }
+        if self.server.workers == Some(0) {
+            errors.push("server.workers must be at least 1".to_string());
+        }
+        if self.grpc.port == Some(self.server.port) {
+            errors.push(format!("grpc.port must differ from server.port {}", self.server.port));
+        }
+
+        match self.store.backend {
+            StoreBackend::Json => {
+                let path = self.store_path().unwrap_or_default();
+                if !Path::new(&path).is_file() {
+                    errors.push(format!("store.path: JSON key file {} does not exist", path));
+                }
+            }
+            StoreBackend::Dir => {
+                if self.store.path.is_none() {
+                    errors.push("store.path must name the key directory for the dir store".to_string());
+                }
+            }
+            StoreBackend::Sqlite => {
+                if !self.store_path().unwrap_or_default().starts_with("sqlite:") {
+                    errors.push("store.path must be a sqlite: URL for the sqlite store".to_string());
+                }
+            }
+            StoreBackend::Memory => {}
+        }
+        if self.store_needs_key() {
+            if let Err(e) = self.store_key() {
+                errors.push(format!("{:#}", e));
+            }
+        }
+        if let Some(provider) = self.store.kek.provider {
+            if self.store.backend != StoreBackend::Json {
+                errors.push(format!(
+                    "store.kek is only supported by the json store; the {:?} store encrypts with store.key_file",
+                    self.store.backend
+                ));
+            }
+            match (&self.store.kek.path, provider) {
+                (_, KekSource::Pkcs11) => {}
+                (None, _) => errors.push(format!("store.kek.path must be set for the {:?} KEK provider", provider)),
+                (Some(path), KekSource::KeyFile) if !path.is_file() => {
+                    errors.push(format!("store.kek.path: KEK file {} does not exist", path.display()))
+                }
+                (Some(_), KekSource::Passphrase) if std::env::var("BN254_KEK_PASSPHRASE").is_err() => {
+                    errors.push("BN254_KEK_PASSPHRASE must be set for the passphrase KEK provider".to_string())
+                }
+                _ => {}
+            }
+        }
+        if self.uses_pkcs11() {
+            match &self.pkcs11.module {
+                None => errors.push("pkcs11.module must be set to use the PKCS#11 token".to_string()),
+                Some(module) if !module.is_file() => {
+                    errors.push(format!("pkcs11.module: {} does not exist", module.display()))
+                }
+                _ => {}
+            }
+            if std::env::var("BN254_PKCS11_PIN").is_err() {
+                errors.push("BN254_PKCS11_PIN must be set to use the PKCS#11 token".to_string());
+            }
+        }
+        if let Some(path) = &self.store.import_json {
+            if !self.store_needs_key() {
+                errors.push(format!("store.import_json is only supported by the dir and sqlite stores, not {:?}", self.store.backend));
+            } else if !path.is_file() {
+                errors.push(format!("store.import_json: {} does not exist", path.display()));
+            }
+        }
+
+        if self.log.level.trim().is_empty() {
+            errors.push("log.level must not be empty".to_string());
+        }
+
+        if self.tls.enabled() {
+            for (name, path) in [("tls.cert", &self.tls.cert), ("tls.key", &self.tls.key)] {
+                match path {
+                    None => errors.push(format!("{} must be set when TLS is enabled", name)),
+                    Some(path) if !path.is_file() => {
+                        errors.push(format!("{}: {} does not exist", name, path.display()))
+                    }
+                    _ => {}
+                }
+            }
+        }
+        if let Some(path) = &self.tls.client_ca {
+            if !self.tls.enabled() {
+                errors.push("tls.client_ca requires tls.cert and tls.key".to_string());
+            } else if !path.is_file() {
+                errors.push(format!("tls.client_ca: {} does not exist", path.display()));
+            }
+        }
+        if !self.tls.clients.is_empty() && self.tls.client_ca.is_none() {
+            errors.push("tls.clients requires tls.client_ca".to_string());
+        }
+        for (i, client) in self.tls.clients.iter().enumerate() {
+            if client.name.trim().is_empty() {
+                errors.push(format!("tls.clients[{}].name must not be empty", i));
+            }
+            if client.operations.is_empty() {
+                errors.push(format!("tls.clients[{}].operations must not be empty", i));
+            }
+            if self.tls.clients[..i].iter().any(|other| other.name == client.name) {
+                errors.push(format!("tls.clients[{}]: duplicate name {}", i, client.name));
+            }
+        }
+
+        let needs_database = self.auth.enabled || self.eoa_auth.mode != EoaAuthMode::Off;
+        if needs_database && !self.auth_database().starts_with("sqlite:") {
+            errors.push("auth.database must be a sqlite: URL".to_string());
+        }
+        if self.endpoints.lifecycle && !self.auth.enabled {
+            errors.push("endpoints.lifecycle requires auth.enabled, since keys are only managed by authenticated principals".to_string());
+        }
+        if self.endpoints.max_batch_size == 0 {
+            errors.push("endpoints.max_batch_size must be at least 1".to_string());
+        }
+        if self.eoa_auth.max_validity_secs == 0 {
+            errors.push("eoa_auth.max_validity_secs must be at least 1".to_string());
+        }
+        if let Err(e) = AuthorizationDomain::new(self.eoa_auth.chain_id, self.eoa_auth.salt.as_deref()) {
+            errors.push(format!("eoa_auth: {}", e));
+        }
+        if !self.guard_database().starts_with("sqlite:") {
+            errors.push("guard.database must be a sqlite: URL".to_string());
+        }
+        if self.audit.path.trim().is_empty() {
+            errors.push("audit.path must not be empty".to_string());
+        }
+
+        let approvals_used = !self.approvals.approvers.is_empty() || self.policies.iter().any(|p| p.approval_required);
+        if approvals_used && (self.approvals.threshold == 0 || self.approvals.threshold > self.approvals.approvers.len()) {
+            errors.push(format!(
+                "approvals.threshold must be between 1 and the {} configured approvers",
+                self.approvals.approvers.len()
+            ));
+        }
+        if self.approvals.ttl_secs == 0 {
+            errors.push("approvals.ttl_secs must be at least 1".to_string());
+        }
+        for (i, approver) in self.approvals.approvers.iter().enumerate() {
+            if self.approvals.approvers[..i].contains(approver) {
+                errors.push(format!("approvals.approvers[{}]: duplicate approver {}", i, approver));
+            }
+        }
+
+        for (i, source) in self.message_sources.iter().enumerate() {
+            if source.name.trim().is_empty() {
+                errors.push(format!("message_sources[{}].name must not be empty", i));
+            }
+            if self.message_sources[..i].iter().any(|other| other.name == source.name) {
+                errors.push(format!("message_sources[{}]: duplicate name {}", i, source.name));
+            }
+            if !matches!(hex::decode(source.domain.trim_start_matches("0x")), Ok(domain) if domain.len() == 32) {
+                errors.push(format!("message_sources[{}].domain must be 32 bytes of hex", i));
+            }
+        }
+        for (i, policy) in self.policies.iter().enumerate() {
+            if self.policies[..i].iter().any(|other| other.eoa_address.eq_ignore_ascii_case(&policy.eoa_address)) {
+                errors.push(format!("policies[{}]: duplicate policy for {}", i, policy.eoa_address));
+            }
+            if policy.max_per_hour == Some(0) || policy.max_per_day == Some(0) {
+                errors.push(format!("policies[{}]: signature limits must be at least 1", i));
+            }
+            for (j, window) in policy.time_windows.iter().enumerate() {
+                if let Err(e) = window.minutes() {
+                    errors.push(format!("policies[{}].time_windows[{}]: {}", i, j, e));
+                }
+            }
+            for name in policy.message_sources.iter().flatten() {
+                if !self.message_sources.iter().any(|source| &source.name == name) {
+                    errors.push(format!("policies[{}]: unknown message source {}", i, name));
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!("Invalid configuration:\n  - {}", errors.join("\n  - ")))
+        }
+    }
+}
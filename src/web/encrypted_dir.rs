@@ -0,0 +1,232 @@
+//! A key store directory holding one encrypted file per key.
+//!
+//! Each key pair is written to `<eoa_address>.json`. Public keys are kept in
+//! plaintext so that the files can be inspected, while the private key is
+//! encrypted with a [`SecretCipher`] bound to the EOA address:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "eoa_address": "0xf39F...",
+//!   "public_key_g1": { "x": "...", "y": "..." },
+//!   "public_key_g2": { "x_a": "...", "x_b": "...", "y_a": "...", "y_b": "..." },
+//!   "crypto": { "cipher": "aes-256-gcm", "ciphertext": "<hex nonce || ciphertext>" }
+//! }
+//! ```
+//!
+//! A disabled key also has `"status": "disabled"`, and the public keys replaced by
+//! rotations are listed in `retired`. Deleting a key overwrites its file with
+//! zeros before removing it.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::web::cipher::SecretCipher;
+use crate::web::models::{G1Point, G2Point, KeyMetadata, KeyPair, KeyStatus, RetiredKey, SecretKey};
+use crate::web::store::{retire, KeyStore};
+
+/// Version of the key file format
+const FORMAT_VERSION: u32 = 1;
+
+/// Name of the only supported cipher
+const CIPHER: &str = "aes-256-gcm";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CryptoSection {
+    cipher: String,
+    ciphertext: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct KeyFile {
+    version: u32,
+    eoa_address: String,
+    public_key_g1: G1Point,
+    public_key_g2: G2Point,
+    crypto: CryptoSection,
+    #[serde(default, skip_serializing_if = "is_active")]
+    status: KeyStatus,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    retired: Vec<RetiredKey>,
+}
+
+fn is_active(status: &KeyStatus) -> bool {
+    *status == KeyStatus::Active
+}
+
+/// A directory of encrypted key files.
+pub struct EncryptedDirStore {
+    dir: PathBuf,
+    cipher: SecretCipher,
+    /// Serializes writers so that insert and rotate checks are not racy
+    lock: Mutex<()>,
+}
+
+impl EncryptedDirStore {
+    /// Opens a key store directory, creating it if needed.
+    ///
+    /// # Arguments
+    /// * `dir` - The directory holding the key files
+    /// * `store_key` - The 32-byte key used to encrypt private keys
+    pub fn open<P: AsRef<Path>>(dir: P, store_key: &[u8; 32]) -> Result<Self> {
+        fs::create_dir_all(dir.as_ref())
+            .with_context(|| format!("Failed to create key store directory {}", dir.as_ref().display()))?;
+        Ok(Self {
+            dir: dir.as_ref().to_path_buf(),
+            cipher: SecretCipher::new(store_key),
+            lock: Mutex::new(()),
+        })
+    }
+
+    fn path(&self, eoa_address: &str) -> Result<PathBuf> {
+        // The address becomes a file name, so keep it to a plain hex string
+        let valid = eoa_address.strip_prefix("0x").is_some_and(|hex| {
+            !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit())
+        });
+        if !valid {
+            return Err(anyhow!("Invalid EOA address: {}", eoa_address));
+        }
+        Ok(self.dir.join(format!("{}.json", eoa_address)))
+    }
+
+    fn read_file(&self, path: &Path) -> Result<KeyFile> {
+        let file: KeyFile = serde_json::from_str(&fs::read_to_string(path)?)
+            .with_context(|| format!("Malformed key file {}", path.display()))?;
+        if file.version != FORMAT_VERSION || file.crypto.cipher != CIPHER {
+            return Err(anyhow!("Unsupported key file format in {}", path.display()));
+        }
+        Ok(file)
+    }
+
+    fn read(&self, path: &Path) -> Result<KeyPair> {
+        let file = self.read_file(path)?;
+        let blob = hex::decode(&file.crypto.ciphertext)?;
+        let private_key = self
+            .cipher
+            .decrypt(file.eoa_address.as_bytes(), &blob)
+            .with_context(|| format!("Failed to decrypt {}", path.display()))?;
+        Ok(KeyPair {
+            private_key: SecretKey::new(String::from_utf8(private_key)?),
+            eoa_address: file.eoa_address,
+            public_key_g1: file.public_key_g1,
+            public_key_g2: file.public_key_g2,
+        })
+    }
+
+    fn write(&self, path: &Path, key_pair: &KeyPair, status: KeyStatus, retired: Vec<RetiredKey>) -> Result<()> {
+        let blob = self
+            .cipher
+            .encrypt(key_pair.eoa_address.as_bytes(), key_pair.private_key.expose_secret().map_err(anyhow::Error::msg)?.as_bytes())?;
+        let file = KeyFile {
+            version: FORMAT_VERSION,
+            eoa_address: key_pair.eoa_address.clone(),
+            public_key_g1: key_pair.public_key_g1.clone(),
+            public_key_g2: key_pair.public_key_g2.clone(),
+            crypto: CryptoSection {
+                cipher: CIPHER.to_string(),
+                ciphertext: hex::encode(blob),
+            },
+            status,
+            retired,
+        };
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(&file)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl KeyStore for EncryptedDirStore {
+    async fn get_key_pair(&self, eoa_address: &str) -> Result<Option<KeyPair>> {
+        let path = match self.path(eoa_address) {
+            Ok(path) => path,
+            Err(_) => return Ok(None),
+        };
+        if !path.exists() {
+            return Ok(None);
+        }
+        self.read(&path).map(Some)
+    }
+
+    async fn list_key_pairs(&self) -> Result<Vec<KeyPair>> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(&self.dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        paths.sort();
+        paths.iter().map(|path| self.read(path)).collect()
+    }
+
+    async fn insert_key_pair(&self, key_pair: KeyPair) -> Result<()> {
+        let _guard = self.lock.lock().unwrap();
+        let path = self.path(&key_pair.eoa_address)?;
+        if path.exists() {
+            return Err(anyhow!("A key pair already exists for {}", key_pair.eoa_address));
+        }
+        self.write(&path, &key_pair, KeyStatus::Active, Vec::new())
+    }
+
+    async fn delete_key_pair(&self, eoa_address: &str) -> Result<bool> {
+        let _guard = self.lock.lock().unwrap();
+        let path = self.path(eoa_address)?;
+        if !path.exists() {
+            return Ok(false);
+        }
+        // Overwrite the file in place so that the ciphertext does not linger on disk
+        let len = fs::metadata(&path)?.len() as usize;
+        let mut file = fs::OpenOptions::new().write(true).open(&path)?;
+        file.write_all(&vec![0u8; len])?;
+        file.sync_all()?;
+        drop(file);
+        fs::remove_file(path)?;
+        Ok(true)
+    }
+
+    async fn rotate_key_pair(&self, key_pair: KeyPair) -> Result<KeyPair> {
+        let _guard = self.lock.lock().unwrap();
+        let path = self.path(&key_pair.eoa_address)?;
+        if !path.exists() {
+            return Err(anyhow!("No key pair exists for {}", key_pair.eoa_address));
+        }
+        let file = self.read_file(&path)?;
+        let previous = self.read(&path)?;
+        let mut retired = file.retired;
+        retired.push(retire(&previous)?);
+        self.write(&path, &key_pair, file.status, retired)?;
+        Ok(previous)
+    }
+
+    async fn key_metadata(&self, eoa_address: &str) -> Result<KeyMetadata> {
+        let path = match self.path(eoa_address) {
+            Ok(path) if path.exists() => path,
+            _ => return Ok(KeyMetadata::default()),
+        };
+        let file = self.read_file(&path)?;
+        Ok(KeyMetadata {
+            status: file.status,
+            retired: file.retired,
+            ..KeyMetadata::default()
+        })
+    }
+
+    async fn set_key_status(&self, eoa_address: &str, status: KeyStatus) -> Result<bool> {
+        let _guard = self.lock.lock().unwrap();
+        let path = self.path(eoa_address)?;
+        if !path.exists() {
+            return Ok(false);
+        }
+        let mut file = self.read_file(&path)?;
+        file.status = status;
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(&file)?)?;
+        fs::rename(&tmp, &path)?;
+        Ok(true)
+    }
+}
@@ -0,0 +1,250 @@
+//! Envelope encryption of private keys at rest.
+//!
+//! Each secret is encrypted with its own random 32-byte data key using
+//! XChaCha20-Poly1305, and the data key is wrapped by a key-encryption key (KEK)
+//! obtained from a [`KekProvider`]. Only the wrapped data key is stored next to
+//! the ciphertext, so a copy of the store file is useless without the KEK:
+//!
+//! ```json
+//! {
+//!   "kek": "passphrase",
+//!   "wrapped_key": "<hex nonce || wrapped data key>",
+//!   "cipher": "xchacha20-poly1305",
+//!   "ciphertext": "<hex nonce || ciphertext>"
+//! }
+//! ```
+//!
+//! Both layers are bound to the same associated data, typically the EOA address,
+//! so an envelope cannot be moved to a different key entry.
+
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::web::cipher::SecretCipher;
+
+/// Name of the cipher encrypting the secrets
+const CIPHER: &str = "xchacha20-poly1305";
+
+/// Length in bytes of the XChaCha20-Poly1305 nonce prepended to each ciphertext
+const NONCE_LEN: usize = 24;
+
+/// Associated data of the check value in a passphrase parameter file
+const CHECK_AAD: &[u8] = b"bn254-rs kek check";
+
+/// A source of the key-encryption key that wraps the data keys of an envelope.
+///
+/// The KEK itself never leaves the provider, which lets a provider keep it in a
+/// hardware token and only expose wrap and unwrap operations.
+pub trait KekProvider: Send + Sync {
+    /// Short name recorded in each envelope, such as `passphrase`
+    fn name(&self) -> &str;
+
+    /// Wrap a data key bound to `aad`
+    fn wrap_key(&self, aad: &[u8], data_key: &[u8; 32]) -> Result<Vec<u8>>;
+
+    /// Unwrap a data key produced by [`KekProvider::wrap_key`] with the same `aad`
+    fn unwrap_key(&self, aad: &[u8], wrapped: &[u8]) -> Result<Zeroizing<[u8; 32]>>;
+}
+
+/// A KEK held in process memory, read from a key file or derived from a passphrase.
+/// Data keys are wrapped with AES-256-GCM.
+pub struct LocalKek {
+    name: &'static str,
+    cipher: SecretCipher,
+}
+
+impl LocalKek {
+    /// Reads a 32-byte hex KEK from a file
+    ///
+    /// # Arguments
+    /// * `path` - The key file, in the same format as the store key file
+    pub fn from_key_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let key_hex = Zeroizing::new(
+            fs::read_to_string(path).with_context(|| format!("Failed to read KEK file {}", path.display()))?,
+        );
+        let key = Zeroizing::new(
+            SecretCipher::key_from_hex(&key_hex).with_context(|| format!("Invalid KEK in {}", path.display()))?,
+        );
+        Ok(Self {
+            name: "key-file",
+            cipher: SecretCipher::new(&key),
+        })
+    }
+
+    /// Derives the KEK from a passphrase with Argon2id.
+    ///
+    /// The salt and cost parameters are kept in a parameter file, which is created
+    /// with a random salt and the default costs if it does not exist. The file also
+    /// holds a check value, so that a wrong passphrase is reported here rather than
+    /// when a key is first used.
+    ///
+    /// # Arguments
+    /// * `passphrase` - The passphrase
+    /// * `params_path` - The Argon2 parameter file
+    pub fn from_passphrase<P: AsRef<Path>>(passphrase: &str, params_path: P) -> Result<Self> {
+        let path = params_path.as_ref();
+        if passphrase.is_empty() {
+            return Err(anyhow!("The KEK passphrase must not be empty"));
+        }
+        if path.exists() {
+            let params: PassphraseParams = serde_json::from_str(
+                &fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?,
+            )
+            .with_context(|| format!("Malformed KEK parameter file {}", path.display()))?;
+            let cipher = params.derive(passphrase)?;
+            let check = hex::decode(&params.check).context("Malformed KEK check value")?;
+            cipher
+                .decrypt(CHECK_AAD, &check)
+                .map_err(|_| anyhow!("Wrong KEK passphrase for {}", path.display()))?;
+            return Ok(Self { name: "passphrase", cipher });
+        }
+
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        let mut params = PassphraseParams {
+            kdf: "argon2id".to_string(),
+            salt: hex::encode(salt),
+            m_cost: Params::DEFAULT_M_COST,
+            t_cost: Params::DEFAULT_T_COST,
+            p_cost: Params::DEFAULT_P_COST,
+            check: String::new(),
+        };
+        let cipher = params.derive(passphrase)?;
+        params.check = hex::encode(cipher.encrypt(CHECK_AAD, &[])?);
+        fs::write(path, serde_json::to_string_pretty(&params)?)
+            .with_context(|| format!("Failed to write KEK parameter file {}", path.display()))?;
+        Ok(Self { name: "passphrase", cipher })
+    }
+}
+
+impl KekProvider for LocalKek {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn wrap_key(&self, aad: &[u8], data_key: &[u8; 32]) -> Result<Vec<u8>> {
+        self.cipher.encrypt(aad, data_key)
+    }
+
+    fn unwrap_key(&self, aad: &[u8], wrapped: &[u8]) -> Result<Zeroizing<[u8; 32]>> {
+        let data_key = Zeroizing::new(
+            self.cipher
+                .decrypt(aad, wrapped)
+                .map_err(|_| anyhow!("Failed to unwrap data key; was it wrapped by another KEK?"))?,
+        );
+        let mut key = Zeroizing::new([0u8; 32]);
+        if data_key.len() != key.len() {
+            return Err(anyhow!("Wrapped data key must be 32 bytes"));
+        }
+        key.copy_from_slice(&data_key);
+        Ok(key)
+    }
+}
+
+/// Argon2id parameters of a passphrase KEK
+#[derive(Debug, Serialize, Deserialize)]
+struct PassphraseParams {
+    kdf: String,
+    salt: String,
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+    /// An empty message encrypted under the KEK
+    check: String,
+}
+
+impl PassphraseParams {
+    fn derive(&self, passphrase: &str) -> Result<SecretCipher> {
+        if self.kdf != "argon2id" {
+            return Err(anyhow!("Unsupported KEK derivation {}", self.kdf));
+        }
+        let salt = hex::decode(&self.salt).context("Malformed KEK salt")?;
+        let params = Params::new(self.m_cost, self.t_cost, self.p_cost, Some(32))
+            .map_err(|e| anyhow!("Invalid Argon2 parameters: {}", e))?;
+        let mut key = Zeroizing::new([0u8; 32]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), &salt, key.as_mut())
+            .map_err(|e| anyhow!("Failed to derive KEK: {}", e))?;
+        Ok(SecretCipher::new(&key))
+    }
+}
+
+/// An encrypted secret with its wrapped data key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Envelope {
+    /// Name of the provider whose KEK wrapped the data key
+    pub kek: String,
+    /// Hex of the wrapped data key
+    pub wrapped_key: String,
+    pub cipher: String,
+    /// Hex of `nonce || ciphertext || tag`
+    pub ciphertext: String,
+}
+
+/// Seals and opens [`Envelope`]s with data keys wrapped by a [`KekProvider`]
+#[derive(Clone)]
+pub struct EnvelopeCipher {
+    kek: Arc<dyn KekProvider>,
+}
+
+impl EnvelopeCipher {
+    pub fn new(kek: Arc<dyn KekProvider>) -> Self {
+        Self { kek }
+    }
+
+    /// Encrypt `plaintext` bound to `aad` under a fresh data key
+    pub fn seal(&self, aad: &[u8], plaintext: &[u8]) -> Result<Envelope> {
+        let mut data_key = Zeroizing::new([0u8; 32]);
+        OsRng.fill_bytes(data_key.as_mut());
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = XChaCha20Poly1305::new(data_key.as_ref().into())
+            .encrypt(&nonce, Payload { msg: plaintext, aad })
+            .map_err(|_| anyhow!("Failed to encrypt secret"))?;
+        let wrapped_key = self.kek.wrap_key(aad, &data_key)?;
+
+        let mut blob = nonce.to_vec();
+        blob.extend(ciphertext);
+        Ok(Envelope {
+            kek: self.kek.name().to_string(),
+            wrapped_key: hex::encode(wrapped_key),
+            cipher: CIPHER.to_string(),
+            ciphertext: hex::encode(blob),
+        })
+    }
+
+    /// Decrypt an envelope produced by [`EnvelopeCipher::seal`] with the same `aad`.
+    /// The plaintext is zeroized when dropped.
+    pub fn open(&self, aad: &[u8], envelope: &Envelope) -> Result<Zeroizing<Vec<u8>>> {
+        if envelope.kek != self.kek.name() {
+            return Err(anyhow!(
+                "Secret is wrapped by the {} KEK provider, not {}",
+                envelope.kek,
+                self.kek.name()
+            ));
+        }
+        if envelope.cipher != CIPHER {
+            return Err(anyhow!("Unsupported cipher {}", envelope.cipher));
+        }
+        let wrapped_key = hex::decode(&envelope.wrapped_key).context("Malformed wrapped data key")?;
+        let data_key = self.kek.unwrap_key(aad, &wrapped_key)?;
+        let blob = hex::decode(&envelope.ciphertext).context("Malformed ciphertext")?;
+        if blob.len() < NONCE_LEN {
+            return Err(anyhow!("Encrypted secret is truncated"));
+        }
+        let (nonce, ciphertext) = blob.split_at(NONCE_LEN);
+        XChaCha20Poly1305::new(data_key.as_ref().into())
+            .decrypt(XNonce::from_slice(nonce), Payload { msg: ciphertext, aad })
+            .map(Zeroizing::new)
+            .map_err(|_| anyhow!("Failed to decrypt secret"))
+    }
+}
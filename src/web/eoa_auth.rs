@@ -0,0 +1,340 @@
+//! Authorization of signing requests by the operator's EOA.
+//!
+//! Key pairs are indexed by the operator's Ethereum address, so the operator can
+//! prove that a signing request comes from them by signing it with that address.
+//! An [`EoaAuthorization`] carries a secp256k1 signature over the message point,
+//! a nonce and an expiry, in one of two encodings:
+//!
+//! * EIP-191: `personal_sign` over
+//!   `keccak256(abi.encode(x, y, nonce, expiry, chainId, salt))`
+//! * EIP-712: typed data with the domain
+//!   `EIP712Domain(string name,string version,uint256 chainId,bytes32 salt)` and the
+//!   struct `SignRequest(uint256 messageX,uint256 messageY,uint256 nonce,uint256 expiry)`
+//!
+//! The chain id and salt of the [`AuthorizationDomain`] bind an authorization to
+//! one deployment of the service, so it cannot be replayed against another. The
+//! recovered address must match the EOA of the request, and every nonce can be
+//! used once per EOA until its authorization expires.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::http::StatusCode;
+use anyhow::Result;
+use ark_ec::CurveGroup;
+use ark_ff::{BigInteger, PrimeField};
+use async_trait::async_trait;
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+use sha3::{Digest, Keccak256};
+
+use crate::g1::G1Point;
+use crate::web::config::EoaAuthMode;
+use crate::web::error::ApiError;
+use crate::web::models::{AuthScheme, EoaAuthorization};
+
+/// `name` of the EIP-712 domain
+pub const EIP712_DOMAIN_NAME: &str = "BN254 Key Service";
+
+/// `version` of the EIP-712 domain
+pub const EIP712_DOMAIN_VERSION: &str = "1";
+
+const EIP712_DOMAIN_TYPE: &str = "EIP712Domain(string name,string version,uint256 chainId,bytes32 salt)";
+const SIGN_REQUEST_TYPE: &str = "SignRequest(uint256 messageX,uint256 messageY,uint256 nonce,uint256 expiry)";
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn keccak(parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+fn u64_word(value: u64) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+/// The `abi.encode(x, y, nonce, expiry)` words of an authorization
+fn encode_words(message: &G1Point, nonce: u64, expiry: u64) -> Vec<u8> {
+    let affine = message.inner().into_affine();
+    let mut words = Vec::with_capacity(4 * 32);
+    words.extend(affine.x.into_bigint().to_bytes_be());
+    words.extend(affine.y.into_bigint().to_bytes_be());
+    words.extend(u64_word(nonce));
+    words.extend(u64_word(expiry));
+    words
+}
+
+/// The deployment of the service an authorization is bound to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuthorizationDomain {
+    /// EIP-155 id of the chain the operators are registered on
+    pub chain_id: u64,
+    /// 32 bytes chosen per service instance
+    pub salt: [u8; 32],
+}
+
+impl Default for AuthorizationDomain {
+    /// Ethereum mainnet with a zero salt
+    fn default() -> Self {
+        Self {
+            chain_id: 1,
+            salt: [0; 32],
+        }
+    }
+}
+
+impl AuthorizationDomain {
+    /// Creates a domain from its configured values.
+    ///
+    /// # Arguments
+    /// * `chain_id` - EIP-155 chain id, which must not be zero
+    /// * `salt` - 32-byte hex salt, or `None` for a zero salt
+    pub fn new(chain_id: u64, salt: Option<&str>) -> Result<Self, String> {
+        if chain_id == 0 {
+            return Err("chain id must not be zero".to_string());
+        }
+        let salt = match salt {
+            Some(salt) => hex::decode(salt.trim_start_matches("0x"))
+                .ok()
+                .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+                .ok_or("salt must be 32 bytes of hex")?,
+            None => [0; 32],
+        };
+        Ok(Self { chain_id, salt })
+    }
+
+    /// The EIP-712 domain separator
+    pub fn separator(&self) -> [u8; 32] {
+        keccak(&[
+            &keccak(&[EIP712_DOMAIN_TYPE.as_bytes()]),
+            &keccak(&[EIP712_DOMAIN_NAME.as_bytes()]),
+            &keccak(&[EIP712_DOMAIN_VERSION.as_bytes()]),
+            &u64_word(self.chain_id),
+            &self.salt,
+        ])
+    }
+}
+
+/// Computes the digest an EOA signs to authorize signing a message.
+///
+/// # Arguments
+/// * `scheme` - The signature encoding
+/// * `domain` - The deployment the authorization is bound to
+/// * `message` - The G1 message point to be signed with the BLS key
+/// * `nonce` - The single-use nonce
+/// * `expiry` - Seconds since the Unix epoch after which the authorization is invalid
+///
+/// # Returns
+/// The 32-byte prehash recovered against by secp256k1
+pub fn authorization_digest(
+    scheme: AuthScheme,
+    domain: &AuthorizationDomain,
+    message: &G1Point,
+    nonce: u64,
+    expiry: u64,
+) -> [u8; 32] {
+    let words = encode_words(message, nonce, expiry);
+    match scheme {
+        AuthScheme::Eip191 => {
+            let inner = keccak(&[&words, &u64_word(domain.chain_id), &domain.salt]);
+            keccak(&[b"\x19Ethereum Signed Message:\n32", &inner])
+        }
+        AuthScheme::Eip712 => {
+            let struct_hash = keccak(&[&keccak(&[SIGN_REQUEST_TYPE.as_bytes()]), &words]);
+            keccak(&[b"\x19\x01", &domain.separator(), &struct_hash])
+        }
+    }
+}
+
+/// Recovers the Ethereum address that produced a signature over a digest.
+///
+/// # Arguments
+/// * `digest` - The signed 32-byte prehash
+/// * `signature` - The 65-byte `r || s || v` signature, with `v` in `{0, 1, 27, 28}`
+///
+/// # Returns
+/// The lowercase `0x`-prefixed address
+pub fn recover_address(digest: &[u8; 32], signature: &[u8]) -> Result<String, String> {
+    if signature.len() != 65 {
+        return Err("signature must be 65 bytes".to_string());
+    }
+    let v = match signature[64] {
+        v @ (0 | 1) => v,
+        v @ (27 | 28) => v - 27,
+        v => return Err(format!("invalid recovery id {}", v)),
+    };
+    let parsed = Signature::from_slice(&signature[..64]).map_err(|e| e.to_string())?;
+    // Reject the malleable high-s twin of every signature, as Ethereum does
+    if parsed.normalize_s().is_some() {
+        return Err("signature s value is not normalized".to_string());
+    }
+    let recovery_id = RecoveryId::from_byte(v).ok_or("invalid recovery id")?;
+    let key = VerifyingKey::recover_from_prehash(digest, &parsed, recovery_id).map_err(|e| e.to_string())?;
+
+    let encoded = key.to_encoded_point(false);
+    let hash = keccak(&[&encoded.as_bytes()[1..]]);
+    Ok(format!("0x{}", hex::encode(&hash[12..])))
+}
+
+/// Tracks which authorization nonces have been used
+#[async_trait]
+pub trait NonceStore: Send + Sync {
+    /// Marks a nonce of an EOA as used until its authorization expires
+    ///
+    /// # Returns
+    /// `false` if the nonce has already been used
+    async fn use_nonce(&self, eoa_address: &str, nonce: u64, expiry: u64) -> Result<bool>;
+}
+
+/// Nonces kept in memory; they are forgotten on restart
+#[derive(Default)]
+pub struct MemoryNonceStore {
+    used: Mutex<HashMap<(String, u64), u64>>,
+}
+
+impl MemoryNonceStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl NonceStore for MemoryNonceStore {
+    async fn use_nonce(&self, eoa_address: &str, nonce: u64, expiry: u64) -> Result<bool> {
+        let mut used = self.used.lock().unwrap();
+        // An expired authorization is rejected anyway, so its nonce can be dropped
+        let now = now();
+        used.retain(|_, expires_at| *expires_at > now);
+        Ok(used.insert((eoa_address.to_lowercase(), nonce), expiry).is_none())
+    }
+}
+
+/// Checks EOA authorizations of signing requests, registered as app data
+pub struct EoaVerifier {
+    mode: EoaAuthMode,
+    max_validity_secs: u64,
+    domain: AuthorizationDomain,
+    nonces: Arc<dyn NonceStore>,
+}
+
+impl Default for EoaVerifier {
+    /// Optional authorizations valid for up to five minutes for the default
+    /// domain, with nonces in memory
+    fn default() -> Self {
+        Self::new(
+            EoaAuthMode::Optional,
+            300,
+            AuthorizationDomain::default(),
+            Arc::new(MemoryNonceStore::new()),
+        )
+    }
+}
+
+fn eoa_error(status: StatusCode, error: &str, message: impl Into<String>) -> ApiError {
+    ApiError::new(status, error, Some("authorization"), message)
+}
+
+impl EoaVerifier {
+    /// Creates a verifier.
+    ///
+    /// # Arguments
+    /// * `mode` - Whether authorizations are ignored, checked when present or required
+    /// * `max_validity_secs` - How far in the future an expiry may be
+    /// * `domain` - The deployment authorizations must be bound to
+    /// * `nonces` - Where used nonces are recorded
+    pub fn new(
+        mode: EoaAuthMode,
+        max_validity_secs: u64,
+        domain: AuthorizationDomain,
+        nonces: Arc<dyn NonceStore>,
+    ) -> Self {
+        Self {
+            mode,
+            max_validity_secs,
+            domain,
+            nonces,
+        }
+    }
+
+    pub fn mode(&self) -> EoaAuthMode {
+        self.mode
+    }
+
+    /// Checks the authorization of a request to sign `message` with the key of an EOA.
+    ///
+    /// A valid authorization consumes its nonce, so it cannot be replayed.
+    pub async fn check(
+        &self,
+        eoa_address: &str,
+        message: &G1Point,
+        authorization: Option<&EoaAuthorization>,
+    ) -> Result<(), ApiError> {
+        let authorization = match (self.mode, authorization) {
+            (EoaAuthMode::Off, _) | (EoaAuthMode::Optional, None) => return Ok(()),
+            (EoaAuthMode::Required, None) => {
+                return Err(eoa_error(
+                    StatusCode::UNAUTHORIZED,
+                    "eoa_authorization_required",
+                    "signing requests must be authorized by the EOA",
+                ))
+            }
+            (_, Some(authorization)) => authorization,
+        };
+
+        let now = now();
+        if authorization.expiry <= now {
+            return Err(eoa_error(StatusCode::UNAUTHORIZED, "authorization_expired", "authorization has expired"));
+        }
+        if authorization.expiry > now + self.max_validity_secs {
+            return Err(eoa_error(
+                StatusCode::BAD_REQUEST,
+                "expiry_too_far",
+                format!("expiry must be at most {} seconds from now", self.max_validity_secs),
+            ));
+        }
+
+        let signature = hex::decode(authorization.signature.trim_start_matches("0x"))
+            .map_err(|_| eoa_error(StatusCode::BAD_REQUEST, "invalid_eoa_signature", "signature must be hex"))?;
+        let digest = authorization_digest(
+            authorization.scheme,
+            &self.domain,
+            message,
+            authorization.nonce,
+            authorization.expiry,
+        );
+        let signer = recover_address(&digest, &signature)
+            .map_err(|e| eoa_error(StatusCode::UNAUTHORIZED, "invalid_eoa_signature", e))?;
+        if !signer.eq_ignore_ascii_case(eoa_address) {
+            return Err(eoa_error(
+                StatusCode::FORBIDDEN,
+                "eoa_mismatch",
+                format!("authorization was signed by {}, not {}", signer, eoa_address),
+            ));
+        }
+
+        // Only a valid signature may consume a nonce, or anyone could burn them
+        let fresh = self
+            .nonces
+            .use_nonce(eoa_address, authorization.nonce, authorization.expiry)
+            .await
+            .map_err(|e| ApiError::internal(format!("Failed to record nonce: {}", e)))?;
+        if !fresh {
+            return Err(eoa_error(
+                StatusCode::CONFLICT,
+                "nonce_reused",
+                format!("nonce {} has already been used", authorization.nonce),
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+//! Structured errors returned by the API handlers.
+
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+
+use crate::web::models::ErrorResponse;
+
+/// An error that is returned to the client as an [`ErrorResponse`].
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub body: ErrorResponse,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, error: &str, field: Option<&str>, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: ErrorResponse {
+                error: error.to_string(),
+                field: field.map(str::to_string),
+                message: message.into(),
+            },
+        }
+    }
+
+    /// A 400 error for an invalid request field
+    pub fn bad_request(error: &str, field: &str, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, error, Some(field), message)
+    }
+
+    /// A 404 error for a resource referenced by a request field
+    pub fn not_found(error: &str, field: &str, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, error, Some(field), message)
+    }
+
+    /// A 500 error; the details are logged rather than returned
+    pub fn internal(details: impl std::fmt::Display) -> Self {
+        log::error!("{}", details);
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, "internal_error", None, "Internal server error")
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.body.error, self.body.message)
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status).json(&self.body)
+    }
+}
@@ -0,0 +1,461 @@
+//! gRPC remote signer API following Cerberus, the remote BLS signer that
+//! eigensdk's `signer.remote` configuration talks to.
+//!
+//! The `signer.v1.Signer` and `keymanager.v1.KeyManager` services are served on
+//! `grpc.port` on top of the same key store, signer, checks and audit log as the
+//! HTTP API. Keys are addressed by their G1 public key in hex, either 32 bytes
+//! compressed or 64 bytes uncompressed, instead of by EOA address:
+//!
+//! | RPC | Needs | HTTP equivalent |
+//! |-----|-------|-----------------|
+//! | `SignGeneric` | `sign` | `POST /api/sign` with `message_hash` |
+//! | `SignG1` | `sign` | `POST /api/sign` with `message_point` or `message_compressed` |
+//! | `ListKeys` | `read_keys` | `GET /api/keys` |
+//! | `GenerateKeyPair` | `manage_keys` | `POST /api/keys/{eoa_address}/generate` |
+//! | `ImportKey` | `manage_keys` | `POST /api/keys/{eoa_address}/import` |
+//!
+//! When `[tls]` is configured the gRPC API is served with the same server
+//! certificate and client CA as the HTTP API, negotiating `h2`. Requests
+//! authenticate with an `authorization: Bearer <token>` metadata entry, or with a
+//! client certificate mapped by `[[tls.clients]]`, unless authentication is
+//! disabled.
+//!
+//! Cerberus requests have no field for the EOA authorization and task context of
+//! `POST /api/sign`, so signing RPCs take them as JSON in the
+//! `x-eoa-authorization` and `x-signing-context` metadata entries. Under
+//! `eoa_auth.mode = "required"` a signing RPC without an authorization is refused
+//! like an HTTP request without one.
+//!
+//! Every stored key belongs to an EOA, which Cerberus requests do not name.
+//! `GenerateKeyPair` and `ImportKey` take it from an `x-eoa-address` metadata
+//! entry if there is one, and otherwise derive it from the key: the last 20
+//! bytes of its key id, `keccak256(G1.x, G1.y)`. Nobody holds the private key of
+//! a derived address, so such keys cannot sign under `eoa_auth.mode =
+//! "required"`. Key passwords are ignored: private keys are protected by the key
+//! store and signer instead, and never leave the service.
+
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use actix_web::http::StatusCode;
+use actix_web::web;
+use rustls::ServerConfig;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsAcceptor;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::StreamExt;
+use tonic::metadata::MetadataMap;
+use tonic::service::Routes;
+use tonic::transport::server::{Connected, TcpIncoming};
+use tonic::{Extensions, Request, Response, Status};
+
+use crate::encoding::{g1_from_gnark_raw, g1_to_bytes, g1_to_compressed};
+use crate::web::audit::{append_record, AuditLog, AuditRecord, RequestId};
+use crate::web::auth::{self, unauthorized, Authenticator, Operation, Principal};
+use crate::web::config::EndpointsConfig;
+use crate::web::error::ApiError;
+use crate::web::handlers::{key_pair_by_public_key, resolve_message, sign_with_key_pair, usable_key_pair, SigningChecks};
+use crate::web::lifecycle::{check_eoa_address, insert_key_pair, parse_private_key};
+use crate::web::models::{EoaAuthorization, G1Point, KeyPair, MessageInput, SignResponse, SigningContext};
+use crate::web::store::KeyStore;
+use crate::web::tls::{peer_identity, ClientIdentity, ClientPrincipals};
+
+pub mod proto {
+    pub mod signer {
+        tonic::include_proto!("signer.v1");
+    }
+    pub mod keymanager {
+        tonic::include_proto!("keymanager.v1");
+    }
+}
+
+use proto::keymanager::key_manager_server::{KeyManager, KeyManagerServer};
+use proto::keymanager::{
+    GenerateKeyPairRequest, GenerateKeyPairResponse, ImportKeyRequest, ImportKeyResponse, ListKeysRequest,
+    ListKeysResponse,
+};
+use proto::signer::signer_server::{Signer as SignerRpc, SignerServer};
+use proto::signer::{SignG1Request, SignG1Response, SignGenericRequest, SignGenericResponse};
+
+/// Metadata entry naming the EOA of a generated or imported key, which is
+/// otherwise derived from the key
+pub const EOA_ADDRESS_METADATA: &str = "x-eoa-address";
+
+/// Metadata entry holding the [`EoaAuthorization`] of a signing RPC as JSON
+pub const EOA_AUTHORIZATION_METADATA: &str = "x-eoa-authorization";
+
+/// Metadata entry holding the [`SigningContext`] of a signing RPC as JSON
+pub const SIGNING_CONTEXT_METADATA: &str = "x-signing-context";
+
+/// Time a client has to complete the TLS handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// State shared by the gRPC services
+#[derive(Clone)]
+pub struct GrpcService {
+    store: web::Data<dyn KeyStore>,
+    checks: SigningChecks,
+    audit: web::Data<dyn AuditLog>,
+    authenticator: web::Data<Authenticator>,
+    client_principals: web::Data<ClientPrincipals>,
+    endpoints: EndpointsConfig,
+}
+
+impl GrpcService {
+    /// # Arguments
+    /// * `client_principals` - The principals of client certificates, used when
+    ///   the API is served with TLS
+    /// * `endpoints` - The enabled endpoint groups; `signing`, `keys` and
+    ///   `lifecycle` also gate the RPCs
+    pub fn new(
+        store: web::Data<dyn KeyStore>,
+        checks: SigningChecks,
+        audit: web::Data<dyn AuditLog>,
+        authenticator: web::Data<Authenticator>,
+        client_principals: web::Data<ClientPrincipals>,
+        endpoints: EndpointsConfig,
+    ) -> Self {
+        Self {
+            store,
+            checks,
+            audit,
+            authenticator,
+            client_principals,
+            endpoints,
+        }
+    }
+
+    /// The `Signer` and `KeyManager` services
+    pub fn routes(&self) -> Routes {
+        Routes::new(SignerServer::new(self.clone())).add_service(KeyManagerServer::new(self.clone()))
+    }
+
+    /// Serves the gRPC API on a bound listener until the process exits.
+    ///
+    /// # Arguments
+    /// * `tls` - The TLS configuration to accept connections with, from
+    ///   [`TlsReloader::server_config`](crate::web::tls::TlsReloader::server_config);
+    ///   plaintext without it
+    pub async fn serve(self, listener: tokio::net::TcpListener, tls: Option<Arc<ServerConfig>>) -> anyhow::Result<()> {
+        let incoming = TcpIncoming::from_listener(listener, true, Some(Duration::from_secs(60)))
+            .map_err(|e| anyhow::anyhow!("Failed to accept gRPC connections: {}", e))?;
+        let server = tonic::transport::Server::builder().add_routes(self.routes());
+        match tls {
+            Some(tls) => server.serve_with_incoming(tls_incoming(incoming, tls)).await?,
+            None => server.serve_with_incoming(incoming).await?,
+        }
+        Ok(())
+    }
+
+    /// The principal of a request, from its bearer token or client certificate
+    async fn authenticate(&self, metadata: &MetadataMap, extensions: &Extensions) -> Result<Principal, ApiError> {
+        match self.authenticator.as_ref() {
+            Authenticator::Disabled => Ok(Principal::unrestricted("anonymous")),
+            Authenticator::Tokens(tokens) => {
+                let bearer = metadata
+                    .get("authorization")
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.strip_prefix("Bearer "));
+                let certificate_principal = extensions
+                    .get::<Option<ClientIdentity>>()
+                    .and_then(Option::as_ref)
+                    .and_then(|identity| self.client_principals.principal_for(identity));
+                match (bearer, certificate_principal) {
+                    (Some(bearer), _) => auth::authenticate_token(tokens.as_ref(), bearer.trim()).await,
+                    (None, Some(principal)) => Ok(principal),
+                    (None, None) => Err(unauthorized("a bearer token or client certificate is required")),
+                }
+            }
+        }
+    }
+
+    /// Signs a message with the key of a G1 public key, like `POST /api/sign`
+    async fn sign(
+        &self,
+        metadata: &MetadataMap,
+        extensions: &Extensions,
+        public_key_g1: &str,
+        message: MessageInput,
+    ) -> Result<Vec<u8>, Status> {
+        if !self.endpoints.signing {
+            return Err(Status::unimplemented("signing is disabled"));
+        }
+        let principal = self.authenticate(metadata, extensions).await.map_err(status)?;
+        let key_pair = key_pair_by_public_key(self.store.get_ref(), public_key_g1, "public_key_g1")
+            .await
+            .map_err(status)?;
+        let eoa_address = key_pair.eoa_address.clone();
+
+        let mut record = AuditRecord::new(&RequestId::generate(), &principal.name, &eoa_address, "sign");
+        let response = async {
+            principal.authorize(Operation::Sign, &eoa_address)?;
+            let key_pair = usable_key_pair(self.store.get_ref(), &eoa_address, Some(key_pair)).await?;
+            let (mode, message) = resolve_message(&message, self.checks.sources())?;
+            record.set_input(&message);
+            let authorization: Option<EoaAuthorization> = json_metadata(metadata, EOA_AUTHORIZATION_METADATA)?;
+            let context: Option<SigningContext> = json_metadata(metadata, SIGNING_CONTEXT_METADATA)?;
+            self.checks
+                .run(&principal, &eoa_address, &message, None, authorization.as_ref(), context.as_ref())
+                .await?;
+            sign_with_key_pair(self.store.get_ref(), self.checks.signer(), key_pair, mode, &message).await
+        }
+        .await;
+        record.finish(response.as_ref().map(|response| &response.product));
+        append_record(self.audit.get_ref(), record).await.map_err(status)?;
+        response.and_then(signature_bytes).map_err(status)
+    }
+
+    /// Generates or imports a key pair, like the lifecycle endpoints. The key
+    /// belongs to the EOA of the `x-eoa-address` metadata entry, or to the
+    /// address derived from the key when the request names none.
+    async fn add_key(
+        &self,
+        metadata: &MetadataMap,
+        extensions: &Extensions,
+        operation: &str,
+        key_pair: impl FnOnce() -> Result<KeyPair, ApiError>,
+    ) -> Result<String, Status> {
+        if !self.endpoints.lifecycle {
+            return Err(Status::unimplemented("key management is disabled"));
+        }
+        let principal = self.authenticate(metadata, extensions).await.map_err(status)?;
+        let named = metadata
+            .get(EOA_ADDRESS_METADATA)
+            .map(|value| value.to_str().unwrap_or_default().to_string());
+        let key_pair = key_pair().and_then(|mut key_pair| {
+            key_pair.eoa_address = match &named {
+                Some(eoa_address) => eoa_address.clone(),
+                None => derived_eoa_address(&key_pair)?,
+            };
+            Ok(key_pair)
+        });
+        let eoa_address = match &key_pair {
+            Ok(key_pair) => key_pair.eoa_address.clone(),
+            Err(_) => named.unwrap_or_default(),
+        };
+
+        let mut record = AuditRecord::new(&RequestId::generate(), &principal.name, &eoa_address, operation);
+        let result = async {
+            let key_pair = key_pair?;
+            principal.authorize(Operation::ManageKeys, &eoa_address)?;
+            check_eoa_address(&eoa_address)?;
+            insert_key_pair(self.store.get_ref(), self.checks.signer(), key_pair).await
+        }
+        .await;
+        record.finish(result.as_ref().map(|view| &view.public_key_g1));
+        append_record(self.audit.get_ref(), record).await.map_err(status)?;
+        result.and_then(|view| public_key_hex(&view.public_key_g1)).map_err(status)
+    }
+}
+
+#[tonic::async_trait]
+impl SignerRpc for GrpcService {
+    async fn sign_generic(&self, request: Request<SignGenericRequest>) -> Result<Response<SignGenericResponse>, Status> {
+        let (metadata, extensions, req) = request.into_parts();
+        if req.data.len() != 32 {
+            return Err(Status::invalid_argument("data must be 32 bytes"));
+        }
+        let message = MessageInput {
+            message_hash: Some(hex::encode(&req.data)),
+            ..Default::default()
+        };
+        let signature = self.sign(&metadata, &extensions, &req.public_key_g1, message).await?;
+        Ok(Response::new(SignGenericResponse { signature }))
+    }
+
+    async fn sign_g1(&self, request: Request<SignG1Request>) -> Result<Response<SignG1Response>, Status> {
+        let (metadata, extensions, req) = request.into_parts();
+        let message = match req.data.len() {
+            32 => MessageInput {
+                message_compressed: Some(hex::encode(&req.data)),
+                ..Default::default()
+            },
+            64 => {
+                let point = g1_from_gnark_raw(&req.data).map_err(Status::invalid_argument)?;
+                MessageInput {
+                    message_point: Some(G1Point::from(&point)),
+                    ..Default::default()
+                }
+            }
+            _ => return Err(Status::invalid_argument("data must be a 64-byte or compressed 32-byte G1 point")),
+        };
+        let signature = self.sign(&metadata, &extensions, &req.public_key_g1, message).await?;
+        Ok(Response::new(SignG1Response { signature }))
+    }
+}
+
+#[tonic::async_trait]
+impl KeyManager for GrpcService {
+    async fn generate_key_pair(
+        &self,
+        request: Request<GenerateKeyPairRequest>,
+    ) -> Result<Response<GenerateKeyPairResponse>, Status> {
+        let public_key_g1 = self
+            .add_key(request.metadata(), request.extensions(), "key_generate", || {
+                Ok(KeyPair::generate("", &mut rand::thread_rng()))
+            })
+            .await?;
+        Ok(Response::new(GenerateKeyPairResponse {
+            public_key_g1,
+            ..Default::default()
+        }))
+    }
+
+    async fn import_key(&self, request: Request<ImportKeyRequest>) -> Result<Response<ImportKeyResponse>, Status> {
+        let (metadata, extensions, req) = request.into_parts();
+        if req.private_key.is_empty() {
+            let message = if req.mnemonic.is_empty() { "private_key is required" } else { "mnemonics are not supported" };
+            return Err(Status::invalid_argument(message));
+        }
+        let public_key_g1 = self
+            .add_key(&metadata, &extensions, "key_import", || {
+                KeyPair::from_private_key("", parse_private_key(&req.private_key)?)
+                    .map_err(|e| ApiError::bad_request("invalid_private_key", "private_key", e))
+            })
+            .await?;
+        Ok(Response::new(ImportKeyResponse { public_key_g1 }))
+    }
+
+    async fn list_keys(&self, request: Request<ListKeysRequest>) -> Result<Response<ListKeysResponse>, Status> {
+        if !self.endpoints.keys {
+            return Err(Status::unimplemented("listing keys is disabled"));
+        }
+        let principal = self.authenticate(request.metadata(), request.extensions()).await.map_err(status)?;
+        if !principal.can(Operation::ReadKeys) {
+            return Err(Status::permission_denied(format!("{} may not perform ReadKeys", principal.name)));
+        }
+        let key_pairs = self
+            .store
+            .list_key_pairs()
+            .await
+            .map_err(|e| status(ApiError::internal(format!("Failed to list key pairs: {}", e))))?;
+        let public_keys = key_pairs
+            .iter()
+            .filter(|key_pair| principal.can_access(&key_pair.eoa_address))
+            .map(|key_pair| public_key_hex(&key_pair.public_key_g1))
+            .collect::<Result<_, _>>()
+            .map_err(status)?;
+        Ok(Response::new(ListKeysResponse { public_keys }))
+    }
+}
+
+/// The EOA of a key created without one: the last 20 bytes of its key id, the
+/// way an Ethereum address is derived from a public key
+fn derived_eoa_address(key_pair: &KeyPair) -> Result<String, ApiError> {
+    let key_id = key_pair.key_id().map_err(ApiError::internal)?;
+    Ok(format!("0x{}", &key_id[key_id.len() - 40..]))
+}
+
+/// Parses a JSON metadata entry, if present
+fn json_metadata<T: serde::de::DeserializeOwned>(metadata: &MetadataMap, key: &str) -> Result<Option<T>, ApiError> {
+    let Some(value) = metadata.get(key) else {
+        return Ok(None);
+    };
+    value
+        .to_str()
+        .map_err(|e| e.to_string())
+        .and_then(|json| serde_json::from_str(json).map_err(|e| e.to_string()))
+        .map(Some)
+        .map_err(|e| ApiError::bad_request("invalid_metadata", key, format!("Malformed {} metadata: {}", key, e)))
+}
+
+/// Accepts TLS connections, completing each handshake in its own task so that a
+/// slow client does not hold up the others
+fn tls_incoming(mut incoming: TcpIncoming, tls: Arc<ServerConfig>) -> ReceiverStream<io::Result<TlsConnection>> {
+    let acceptor = TlsAcceptor::from(tls);
+    let (sender, receiver) = tokio::sync::mpsc::channel(64);
+    tokio::spawn(async move {
+        while let Some(stream) = incoming.next().await {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    log::warn!("Failed to accept a gRPC connection: {}", e);
+                    continue;
+                }
+            };
+            let (acceptor, sender) = (acceptor.clone(), sender.clone());
+            tokio::spawn(async move {
+                match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                    Ok(Ok(stream)) => {
+                        let identity = peer_identity(stream.get_ref().1);
+                        let _ = sender.send(Ok(TlsConnection { stream, identity })).await;
+                    }
+                    Ok(Err(e)) => log::debug!("gRPC TLS handshake failed: {}", e),
+                    Err(_) => log::debug!("gRPC TLS handshake timed out"),
+                }
+            });
+        }
+    });
+    ReceiverStream::new(receiver)
+}
+
+/// A TLS connection to the gRPC API, carrying the [`ClientIdentity`] of its
+/// client certificate into the request extensions
+struct TlsConnection {
+    stream: tokio_rustls::server::TlsStream<TcpStream>,
+    identity: Option<ClientIdentity>,
+}
+
+impl Connected for TlsConnection {
+    type ConnectInfo = Option<ClientIdentity>;
+
+    fn connect_info(&self) -> Self::ConnectInfo {
+        self.identity.clone()
+    }
+}
+
+impl AsyncRead for TlsConnection {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for TlsConnection {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
+
+/// The `0x` hex of a G1 public key in the gnark compressed format
+fn public_key_hex(public_key_g1: &G1Point) -> Result<String, ApiError> {
+    let point = public_key_g1
+        .to_checked_g1_point()
+        .map_err(|e| ApiError::internal(format!("Invalid stored public key: {}", e)))?;
+    Ok(format!("0x{}", hex::encode(g1_to_compressed(&point))))
+}
+
+/// A signature as the 64 bytes of an uncompressed G1 point
+fn signature_bytes(response: SignResponse) -> Result<Vec<u8>, ApiError> {
+    let signature = response
+        .product
+        .to_checked_g1_point()
+        .map_err(|e| ApiError::internal(format!("Invalid signature: {}", e)))?;
+    Ok(g1_to_bytes(&signature).to_vec())
+}
+
+/// The gRPC status of an API error
+fn status(e: ApiError) -> Status {
+    let message = e.to_string();
+    match e.status {
+        StatusCode::BAD_REQUEST => Status::invalid_argument(message),
+        StatusCode::UNAUTHORIZED => Status::unauthenticated(message),
+        StatusCode::FORBIDDEN => Status::permission_denied(message),
+        StatusCode::NOT_FOUND => Status::not_found(message),
+        StatusCode::CONFLICT => Status::already_exists(message),
+        StatusCode::TOO_MANY_REQUESTS => Status::resource_exhausted(message),
+        _ => Status::internal(message),
+    }
+}
+
@@ -0,0 +1,311 @@
+//! Protection against signing conflicting messages for the same AVS task.
+//!
+//! A signing request may carry a [`SigningContext`] naming the AVS and the task it
+//! responds to. The first message signed for a key and context is recorded, and
+//! from then on only that same message may be signed again for the context; any
+//! other message is refused with `409 equivocation`. The record is written before
+//! the signature is produced, so a crash in between can only make the service
+//! more conservative.
+//!
+//! Records can be moved between services in the interchange format, modelled on
+//! the EIP-3076 slashing protection format of Ethereum validators:
+//!
+//! ```json
+//! {
+//!   "metadata": { "interchange_format_version": "1", "generated_at": "1718582400" },
+//!   "data": [
+//!     {
+//!       "eoa_address": "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266",
+//!       "signed_tasks": [
+//!         { "avs": "0x…", "task_index": "12", "message_hash": "0x…", "signed_at": "1718582400" }
+//!       ]
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Numbers are decimal strings and `message_hash` is the `keccak256(x, y)` of the
+//! signed G1 point.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::http::StatusCode;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::hash::hash_g1_point;
+use crate::web::error::ApiError;
+use crate::web::models::SigningContext;
+
+/// Version written to and accepted from interchange files
+pub const INTERCHANGE_FORMAT_VERSION: &str = "1";
+
+/// The message signed by a key for one AVS task.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedTask {
+    /// Lowercase EOA address of the key
+    pub eoa_address: String,
+    /// Lowercase AVS identifier
+    pub avs: String,
+    pub task_index: u64,
+    /// Hex `keccak256(x, y)` of the signed message point
+    pub message_hash: String,
+    /// Seconds since the Unix epoch
+    pub signed_at: i64,
+}
+
+/// What recording a task did
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordOutcome {
+    /// The task had not been signed before
+    New,
+    /// The task was already signed with the same message
+    Repeat,
+    /// The task was already signed with a different message
+    Conflict {
+        /// The message hash signed before
+        message_hash: String,
+    },
+}
+
+/// Persists the message signed for each key and task
+#[async_trait]
+pub trait SignedTaskStore: Send + Sync {
+    /// Records a task unless it is already signed, atomically.
+    async fn record_task(&self, task: SignedTask) -> Result<RecordOutcome>;
+
+    /// Every recorded task, ordered by key, AVS and task index
+    async fn list_tasks(&self) -> Result<Vec<SignedTask>>;
+}
+
+/// Signed tasks kept in memory; they are forgotten on restart
+#[derive(Default)]
+pub struct MemorySignedTaskStore {
+    tasks: Mutex<HashMap<(String, String, u64), SignedTask>>,
+}
+
+impl MemorySignedTaskStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl SignedTaskStore for MemorySignedTaskStore {
+    async fn record_task(&self, task: SignedTask) -> Result<RecordOutcome> {
+        let mut tasks = self.tasks.lock().unwrap();
+        let key = (task.eoa_address.clone(), task.avs.clone(), task.task_index);
+        Ok(match tasks.get(&key) {
+            None => {
+                tasks.insert(key, task);
+                RecordOutcome::New
+            }
+            Some(existing) if existing.message_hash == task.message_hash => RecordOutcome::Repeat,
+            Some(existing) => RecordOutcome::Conflict {
+                message_hash: existing.message_hash.clone(),
+            },
+        })
+    }
+
+    async fn list_tasks(&self) -> Result<Vec<SignedTask>> {
+        let mut tasks: Vec<SignedTask> = self.tasks.lock().unwrap().values().cloned().collect();
+        tasks.sort_by(|a, b| (&a.eoa_address, &a.avs, a.task_index).cmp(&(&b.eoa_address, &b.avs, b.task_index)));
+        Ok(tasks)
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Refuses to sign conflicting messages for a task, registered as app data
+pub struct EquivocationGuard {
+    tasks: Arc<dyn SignedTaskStore>,
+}
+
+impl Default for EquivocationGuard {
+    /// A guard keeping signed tasks in memory
+    fn default() -> Self {
+        Self::new(Arc::new(MemorySignedTaskStore::new()))
+    }
+}
+
+impl EquivocationGuard {
+    pub fn new(tasks: Arc<dyn SignedTaskStore>) -> Self {
+        Self { tasks }
+    }
+
+    pub fn tasks(&self) -> &dyn SignedTaskStore {
+        self.tasks.as_ref()
+    }
+
+    /// Records that `message` is about to be signed for a task.
+    ///
+    /// Requests without a context are not restricted.
+    ///
+    /// # Returns
+    /// `Ok(())` for a new task or the same message again, or `409 equivocation`
+    /// if a different message was signed for the task
+    pub async fn check(
+        &self,
+        eoa_address: &str,
+        message: &crate::g1::G1Point,
+        context: Option<&SigningContext>,
+    ) -> Result<(), ApiError> {
+        let Some(context) = context else {
+            return Ok(());
+        };
+        let avs = context.avs.trim().to_lowercase();
+        if avs.is_empty() || avs.len() > 128 {
+            return Err(ApiError::bad_request("invalid_context", "context.avs", "avs must be 1 to 128 characters"));
+        }
+        let task = SignedTask {
+            eoa_address: eoa_address.to_lowercase(),
+            avs,
+            task_index: context.task_index,
+            message_hash: format!("0x{}", hex::encode(hash_g1_point(message))),
+            signed_at: now(),
+        };
+        let outcome = self
+            .tasks
+            .record_task(task)
+            .await
+            .map_err(|e| ApiError::internal(format!("Failed to record signed task: {:#}", e)))?;
+        match outcome {
+            RecordOutcome::New | RecordOutcome::Repeat => Ok(()),
+            RecordOutcome::Conflict { message_hash } => Err(ApiError::new(
+                StatusCode::CONFLICT,
+                "equivocation",
+                Some("context"),
+                format!(
+                    "task {} of {} was already signed with message {}",
+                    context.task_index, context.avs, message_hash
+                ),
+            )),
+        }
+    }
+}
+
+/// Metadata of an interchange file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InterchangeMetadata {
+    pub interchange_format_version: String,
+    pub generated_at: String,
+}
+
+/// A task in an interchange file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InterchangeTask {
+    pub avs: String,
+    pub task_index: String,
+    pub message_hash: String,
+    pub signed_at: String,
+}
+
+/// The tasks of one key in an interchange file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InterchangeKey {
+    pub eoa_address: String,
+    pub signed_tasks: Vec<InterchangeTask>,
+}
+
+/// Signed tasks in the interchange format
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Interchange {
+    pub metadata: InterchangeMetadata,
+    pub data: Vec<InterchangeKey>,
+}
+
+/// What an import did
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ImportSummary {
+    /// Tasks that were not recorded before
+    pub imported: usize,
+    /// Tasks already recorded with the same message
+    pub unchanged: usize,
+    /// Tasks already recorded with a different message; the existing record is kept
+    pub conflicts: usize,
+}
+
+/// Exports every signed task in the interchange format.
+pub async fn export_interchange(tasks: &dyn SignedTaskStore) -> Result<Interchange> {
+    let mut keys: BTreeMap<String, Vec<InterchangeTask>> = BTreeMap::new();
+    for task in tasks.list_tasks().await? {
+        keys.entry(task.eoa_address).or_default().push(InterchangeTask {
+            avs: task.avs,
+            task_index: task.task_index.to_string(),
+            message_hash: task.message_hash,
+            signed_at: task.signed_at.to_string(),
+        });
+    }
+    Ok(Interchange {
+        metadata: InterchangeMetadata {
+            interchange_format_version: INTERCHANGE_FORMAT_VERSION.to_string(),
+            generated_at: now().to_string(),
+        },
+        data: keys
+            .into_iter()
+            .map(|(eoa_address, signed_tasks)| InterchangeKey { eoa_address, signed_tasks })
+            .collect(),
+    })
+}
+
+fn parse_task(eoa_address: &str, task: &InterchangeTask) -> Result<SignedTask> {
+    let task_index = task
+        .task_index
+        .parse()
+        .map_err(|_| anyhow!("Invalid task_index {:?} for {}", task.task_index, eoa_address))?;
+    let signed_at = task
+        .signed_at
+        .parse()
+        .map_err(|_| anyhow!("Invalid signed_at {:?} for {}", task.signed_at, eoa_address))?;
+    let hash = hex::decode(task.message_hash.trim_start_matches("0x"))
+        .ok()
+        .filter(|hash| hash.len() == 32)
+        .ok_or_else(|| anyhow!("Invalid message_hash {:?} for {}", task.message_hash, eoa_address))?;
+    if task.avs.trim().is_empty() {
+        return Err(anyhow!("Empty avs for {}", eoa_address));
+    }
+    Ok(SignedTask {
+        eoa_address: eoa_address.to_lowercase(),
+        avs: task.avs.trim().to_lowercase(),
+        task_index,
+        message_hash: format!("0x{}", hex::encode(hash)),
+        signed_at,
+    })
+}
+
+/// Imports signed tasks from the interchange format.
+///
+/// The whole file is validated before anything is recorded. A task already
+/// recorded with a different message keeps its existing record, which still
+/// refuses every other message for the task.
+pub async fn import_interchange(tasks: &dyn SignedTaskStore, interchange: &Interchange) -> Result<ImportSummary> {
+    if interchange.metadata.interchange_format_version != INTERCHANGE_FORMAT_VERSION {
+        return Err(anyhow!(
+            "Unsupported interchange format version {}",
+            interchange.metadata.interchange_format_version
+        ));
+    }
+    let parsed = interchange
+        .data
+        .iter()
+        .flat_map(|key| key.signed_tasks.iter().map(move |task| parse_task(&key.eoa_address, task)))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut summary = ImportSummary::default();
+    for task in parsed {
+        match tasks.record_task(task).await? {
+            RecordOutcome::New => summary.imported += 1,
+            RecordOutcome::Repeat => summary.unchanged += 1,
+            RecordOutcome::Conflict { .. } => summary.conflicts += 1,
+        }
+    }
+    Ok(summary)
+}
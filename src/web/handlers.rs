@@ -1,134 +1,663 @@
-use actix_web::{web, HttpResponse, Responder};
-use crate::web::models::{ScalarMulRequest, ScalarMulResponse, SignRequest, SignResponse, G1Point};
-use crate::web::store::Store;
-use ark_bn254::{Fq, G1Projective};
-use ark_ec::CurveGroup;
-use ark_ff::One;
-use std::str::FromStr;
+use actix_web::dev::Payload;
+use actix_web::http::StatusCode;
+use actix_web::{web, FromRequest, HttpRequest, HttpResponse, Responder, ResponseError};
+use std::future::{ready, Ready};
+use crate::hash::hash_to_g1;
+use crate::pairing::verify_signature;
+use crate::web::audit::{append_record, AuditLog, AuditRecord, RequestId};
+use crate::web::auth::{Operation, Principal};
+use crate::web::eoa_auth::EoaVerifier;
+use crate::web::guard::EquivocationGuard;
+use crate::web::policy::{MessageSources, PolicyEngine};
+use crate::web::error::ApiError;
+use crate::encoding::{g1_from_compressed, g1_from_gnark_raw, g2_from_compressed, g2_from_gnark_raw, is_gnark_compressed};
+use crate::web::models::{BatchSignRequest, BatchSignResponse, BatchSignResult, EoaAuthorization, KeyPair, KeyStatus, MessageInput, MessageMode, PublicKeyView, ScalarMulRequest, ScalarMulResponse, SignRequest, SignResponse, SigningContext, G1Point, G2Point, VerifyRequest, VerifyResponse};
+use crate::web::signer::Signer;
+use crate::web::store::KeyStore;
+use ark_ff::Zero;
 use log::error;
 
-/// Get a key pair by EOA address
+/// Build the public view of a key pair with its metadata from the store
+pub(crate) async fn public_view(store: &dyn KeyStore, key_pair: &KeyPair) -> anyhow::Result<PublicKeyView> {
+    let metadata = store.key_metadata(&key_pair.eoa_address).await?;
+    key_pair.to_public_view(metadata).map_err(anyhow::Error::msg)
+}
+
+/// Get the public keys of an EOA
 pub async fn get_key_pair(
-    store: web::Data<Store>,
+    store: web::Data<dyn KeyStore>,
+    principal: Principal,
     eoa_address: web::Path<String>,
 ) -> impl Responder {
-    match store.get_key_pair(&eoa_address) {
-        Some(key_pair) => HttpResponse::Ok().json(key_pair),
-        None => HttpResponse::NotFound().finish(),
+    if let Err(e) = principal.authorize(Operation::ReadKeys, &eoa_address) {
+        return e.error_response();
+    }
+    let key_pair = match store.get_key_pair(&eoa_address).await {
+        Ok(Some(key_pair)) => key_pair,
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!("Failed to read key pair: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    match public_view(store.get_ref(), &key_pair).await {
+        Ok(view) => HttpResponse::Ok().json(view),
+        Err(e) => {
+            error!("Failed to build public key view: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
     }
 }
 
-/// List all key pairs
+/// List the public keys of all EOAs the caller may use
 pub async fn list_key_pairs(
-    store: web::Data<Store>,
+    store: web::Data<dyn KeyStore>,
+    principal: Principal,
 ) -> impl Responder {
-    let key_pairs = store.list_key_pairs();
-    HttpResponse::Ok().json(key_pairs)
+    let key_pairs = match store.list_key_pairs().await {
+        Ok(key_pairs) => key_pairs,
+        Err(e) => {
+            error!("Failed to list key pairs: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    let mut views = Vec::with_capacity(key_pairs.len());
+    for key_pair in key_pairs.iter().filter(|kp| principal.can_access(&kp.eoa_address)) {
+        match public_view(store.get_ref(), key_pair).await {
+            Ok(view) => views.push(view),
+            Err(e) => {
+                error!("Failed to build public key view: {}", e);
+                return HttpResponse::InternalServerError().finish();
+            }
+        }
+    }
+    HttpResponse::Ok().json(views)
 }
 
 /// Perform scalar multiplication
 pub async fn scalar_mul(
-    store: web::Data<Store>,
+    store: web::Data<dyn KeyStore>,
+    checks: SigningChecks,
+    audit: web::Data<dyn AuditLog>,
+    principal: Principal,
+    request_id: RequestId,
     req: web::Json<ScalarMulRequest>,
-) -> impl Responder {
-    // Get key pair from store first
-    let key_pair = match store.get_key_pair(&req.eoa_address) {
-        Some(kp) => kp,
-        None => {
-            error!("Key pair not found for address: {}", req.eoa_address);
-            return HttpResponse::NotFound().finish();
-        }
-    };
+) -> Result<HttpResponse, ApiError> {
+    let mut record = AuditRecord::new(&request_id, &principal.name, &req.eoa_address, "scalar_mul");
+    let response = scalar_mul_request(store.get_ref(), &checks, &principal, &req, &mut record).await;
+    record.finish(response.as_ref().map(|response| &response.signature));
+    append_record(audit.get_ref(), record).await?;
+    Ok(HttpResponse::Ok().json(response?))
+}
 
-    // Parse hash point
-    let hash_x = match Fq::from_str(&req.hash_x) {
-        Ok(x) => x,
-        Err(_) => {
-            error!("Failed to parse hash_x coordinate");
-            return HttpResponse::BadRequest().finish();
-        }
-    };
+async fn scalar_mul_request(
+    store: &dyn KeyStore,
+    checks: &SigningChecks,
+    principal: &Principal,
+    req: &ScalarMulRequest,
+    record: &mut AuditRecord,
+) -> Result<ScalarMulResponse, ApiError> {
+    principal.authorize(Operation::ScalarMul, &req.eoa_address)?;
 
-    let hash_y = match Fq::from_str(&req.hash_y) {
-        Ok(y) => y,
-        Err(_) => {
-            error!("Failed to parse hash_y coordinate");
-            return HttpResponse::BadRequest().finish();
-        }
+    // The point must be canonical, on the curve and not infinity before the key
+    // ever touches it, as a crafted point would leak the key through its product
+    let message = G1Point {
+        x: req.hash_x.clone(),
+        y: req.hash_y.clone(),
     };
+    let message = message.to_checked_g1_point().map_err(|e| {
+        let field = if e.starts_with("y coordinate") { "hash_y" } else { "hash_x" };
+        ApiError::bad_request("invalid_point", field, e)
+    })?;
+    let key_pair = signing_key_pair(store, &req.eoa_address).await?;
+    record.set_input(&message);
+    // A raw point cannot be attributed to a message source
+    checks
+        .run(principal, &req.eoa_address, &message, None, req.authorization.as_ref(), req.context.as_ref())
+        .await?;
 
-    let hash_point = G1Projective::new_unchecked(hash_x, hash_y, Fq::one());
+    // Perform scalar multiplication (hash_point * private_key)
+    let product = checks
+        .signer()
+        .scalar_mul(&key_pair, &message)
+        .map_err(|e| ApiError::internal(format!("Failed to sign with the key of {}: {:#}", req.eoa_address, e)))?;
 
-    // Get BLS private key
-    let private_key = match key_pair.to_private_key() {
-        Ok(k) => k,
-        Err(e) => {
-            error!("Failed to parse private key: {}", e);
-            return HttpResponse::InternalServerError().finish();
-        }
+    let message = G1Point {
+        x: req.hash_x.clone(),
+        y: req.hash_y.clone(),
     };
+    let signature = G1Point::from(&product);
+    store
+        .record_signature(&key_pair.eoa_address, "scalar_mul", &message, &signature)
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to record signature: {}", e)))?;
 
-    // Perform scalar multiplication (hash_point * private_key)
-    let result = hash_point * private_key;
-    let result_affine = result.into_affine();
-
-    // Create response
-    let response = ScalarMulResponse {
+    Ok(ScalarMulResponse {
         g1: key_pair.public_key_g1.clone(),
         g2: key_pair.public_key_g2.clone(),
-        signature: G1Point {
-            x: result_affine.x.to_string(),
-            y: result_affine.y.to_string(),
-        },
-    };
-
-    HttpResponse::Ok().json(response)
+        signature,
+    })
 }
 
-/// Sign a message
+/// Sign a message given as a G1 point, a 32-byte hash or a compressed point
 pub async fn sign(
-    store: web::Data<Store>,
+    store: web::Data<dyn KeyStore>,
+    checks: SigningChecks,
+    audit: web::Data<dyn AuditLog>,
+    principal: Principal,
+    request_id: RequestId,
     req: web::Json<SignRequest>,
-) -> impl Responder {
-    // Get key pair from store
-    let key_pair = match store.get_key_pair(&req.eoa_address) {
-        Some(kp) => kp,
-        None => {
-            error!("Key pair not found for address: {}", req.eoa_address);
-            return HttpResponse::NotFound().finish();
+) -> Result<HttpResponse, ApiError> {
+    let mut record = AuditRecord::new(&request_id, &principal.name, &req.eoa_address, "sign");
+    let response = sign_request(store.get_ref(), &checks, &principal, &req, &mut record).await;
+    record.finish(response.as_ref().map(|response| &response.product));
+    append_record(audit.get_ref(), record).await?;
+    Ok(HttpResponse::Ok().json(response?))
+}
+
+pub(crate) async fn sign_request(
+    store: &dyn KeyStore,
+    checks: &SigningChecks,
+    principal: &Principal,
+    req: &SignRequest,
+    record: &mut AuditRecord,
+) -> Result<SignResponse, ApiError> {
+    principal.authorize(Operation::Sign, &req.eoa_address)?;
+    // The key is looked up first, so that no check records a message that an
+    // unknown or disabled key could never sign
+    let key_pair = signing_key_pair(store, &req.eoa_address).await?;
+    let (mode, message) = check_sign_request(checks, principal, req, record).await?;
+    sign_with_key_pair(store, checks.signer(), key_pair, mode, &message).await
+}
+
+/// Resolves the message of an authorized sign request and runs the signing checks
+async fn check_sign_request(
+    checks: &SigningChecks,
+    principal: &Principal,
+    req: &SignRequest,
+    record: &mut AuditRecord,
+) -> Result<(MessageMode, crate::g1::G1Point), ApiError> {
+    let (mode, message) = resolve_message(&req.message, checks.policy.sources())?;
+    record.set_input(&message);
+    let source = req.message.message_source.as_ref().map(|sourced| sourced.source.as_str());
+    checks
+        .run(principal, &req.eoa_address, &message, source, req.authorization.as_ref(), req.context.as_ref())
+        .await?;
+    Ok((mode, message))
+}
+
+/// The most items a `POST /api/sign/batch` request may carry
+#[derive(Debug, Clone, Copy)]
+pub struct MaxBatchSize(pub usize);
+
+/// Sign several messages in one request. Each item is checked and signed like a
+/// `POST /api/sign` request and audited on its own; a failing item yields an
+/// error in its place without affecting the others. The key pairs of all items
+/// are read from the store at once and checked before any item is.
+pub async fn sign_batch(
+    store: web::Data<dyn KeyStore>,
+    checks: SigningChecks,
+    audit: web::Data<dyn AuditLog>,
+    principal: Principal,
+    request_id: RequestId,
+    max_batch_size: web::Data<MaxBatchSize>,
+    req: web::Json<BatchSignRequest>,
+) -> Result<HttpResponse, ApiError> {
+    if req.items.is_empty() {
+        return Err(ApiError::bad_request("empty_batch", "items", "at least one item is required"));
+    }
+    if req.items.len() > max_batch_size.0 {
+        return Err(ApiError::new(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "batch_too_large",
+            Some("items"),
+            format!("at most {} items may be signed in one batch", max_batch_size.0),
+        ));
+    }
+
+    let eoa_addresses: Vec<String> = req.items.iter().map(|item| item.eoa_address.clone()).collect();
+    let key_pairs = store
+        .get_key_pairs(&eoa_addresses)
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to read key pairs: {}", e)))?;
+
+    // Every key is checked before any item runs the signing checks, so that no
+    // item records a task context for a key that cannot sign
+    let mut usable_key_pairs = Vec::with_capacity(req.items.len());
+    for (item, key_pair) in req.items.iter().zip(key_pairs) {
+        let key_pair = async {
+            principal.authorize(Operation::Sign, &item.eoa_address)?;
+            usable_key_pair(store.get_ref(), &item.eoa_address, key_pair).await
         }
-    };
+        .await;
+        usable_key_pairs.push(key_pair);
+    }
 
-    // Parse point to sign
-    let point = match Fq::from_str(&req.point) {
-        Ok(x) => G1Projective::new_unchecked(x, Fq::one(), Fq::one()),
-        Err(_) => {
-            error!("Failed to parse point");
-            return HttpResponse::BadRequest().finish();
+    let mut results = Vec::with_capacity(req.items.len());
+    for (index, (item, key_pair)) in req.items.iter().zip(usable_key_pairs).enumerate() {
+        let mut record = AuditRecord::new(&request_id, &principal.name, &item.eoa_address, "sign_batch");
+        let response = async {
+            let key_pair = key_pair?;
+            let (mode, message) = check_sign_request(&checks, &principal, item, &mut record).await?;
+            sign_with_key_pair(store.get_ref(), checks.signer(), key_pair, mode, &message).await
         }
-    };
+        .await;
+        record.finish(response.as_ref().map(|response| &response.product));
+        append_record(audit.get_ref(), record).await?;
+        results.push(match response {
+            Ok(response) => BatchSignResult {
+                index,
+                status: StatusCode::OK.as_u16(),
+                result: Some(response),
+                error: None,
+            },
+            Err(e) => BatchSignResult {
+                index,
+                status: e.status.as_u16(),
+                result: None,
+                error: Some(e.body),
+            },
+        });
+    }
+    Ok(HttpResponse::Ok().json(BatchSignResponse { results }))
+}
 
-    // Get private key
-    let private_key = match key_pair.to_private_key() {
-        Ok(k) => k,
-        Err(e) => {
-            error!("Failed to parse private key: {}", e);
-            return HttpResponse::InternalServerError().finish();
+/// The checks every message passes before it is signed, and the signer that signs
+/// it, extracted from the app data
+#[derive(Clone)]
+pub struct SigningChecks {
+    eoa_verifier: web::Data<EoaVerifier>,
+    policy: web::Data<PolicyEngine>,
+    guard: web::Data<EquivocationGuard>,
+    signer: web::Data<dyn Signer>,
+}
+
+impl FromRequest for SigningChecks {
+    type Error = ApiError;
+    type Future = Ready<Result<Self, ApiError>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let checks = (|| {
+            Some(SigningChecks {
+                eoa_verifier: req.app_data::<web::Data<EoaVerifier>>()?.clone(),
+                policy: req.app_data::<web::Data<PolicyEngine>>()?.clone(),
+                guard: req.app_data::<web::Data<EquivocationGuard>>()?.clone(),
+                signer: req.app_data::<web::Data<dyn Signer>>()?.clone(),
+            })
+        })();
+        ready(checks.ok_or_else(|| ApiError::internal("The signing checks are not configured")))
+    }
+}
+
+impl SigningChecks {
+    pub fn new(
+        eoa_verifier: web::Data<EoaVerifier>,
+        policy: web::Data<PolicyEngine>,
+        guard: web::Data<EquivocationGuard>,
+        signer: web::Data<dyn Signer>,
+    ) -> Self {
+        Self {
+            eoa_verifier,
+            policy,
+            guard,
+            signer,
         }
+    }
+
+    /// The signer of the messages that pass the checks
+    pub fn signer(&self) -> &dyn Signer {
+        self.signer.get_ref()
+    }
+
+    /// Checks the EOA authorization, then the key policy, then records the task
+    /// context; the context comes last so that only messages about to be signed
+    /// are recorded.
+    pub async fn run(
+        &self,
+        principal: &Principal,
+        eoa_address: &str,
+        message: &crate::g1::G1Point,
+        source: Option<&str>,
+        authorization: Option<&EoaAuthorization>,
+        context: Option<&SigningContext>,
+    ) -> Result<(), ApiError> {
+        self.check_authorization(eoa_address, message, authorization).await?;
+        self.policy.check(principal, eoa_address, source)?;
+        self.guard.check(eoa_address, message, context).await
+    }
+
+    /// Checks the EOA authorization of a message
+    pub async fn check_authorization(
+        &self,
+        eoa_address: &str,
+        message: &crate::g1::G1Point,
+        authorization: Option<&EoaAuthorization>,
+    ) -> Result<(), ApiError> {
+        self.eoa_verifier.check(eoa_address, message, authorization).await
+    }
+
+    /// Checks a request released by its approvers against the key policy of the
+    /// requester, except `approval_required`, then records the task context.
+    pub async fn release(
+        &self,
+        requester: &Principal,
+        eoa_address: &str,
+        message: &crate::g1::G1Point,
+        source: Option<&str>,
+        context: Option<&SigningContext>,
+    ) -> Result<(), ApiError> {
+        self.policy.check_approved(requester, eoa_address, source)?;
+        self.guard.check(eoa_address, message, context).await
+    }
+
+    pub fn sources(&self) -> &MessageSources {
+        self.policy.sources()
+    }
+}
+
+/// Gets the key pair of an EOA to sign with, refusing disabled keys
+pub(crate) async fn signing_key_pair(store: &dyn KeyStore, eoa_address: &str) -> Result<KeyPair, ApiError> {
+    let key_pair = store
+        .get_key_pair(eoa_address)
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to read key pair: {}", e)))?;
+    usable_key_pair(store, eoa_address, key_pair).await
+}
+
+/// Checks that a key pair read from the store exists and is not disabled
+pub(crate) async fn usable_key_pair(
+    store: &dyn KeyStore,
+    eoa_address: &str,
+    key_pair: Option<KeyPair>,
+) -> Result<KeyPair, ApiError> {
+    let key_pair = key_pair
+        .ok_or_else(|| ApiError::not_found("unknown_eoa", "eoa_address", format!("no key pair for {}", eoa_address)))?;
+    let metadata = store
+        .key_metadata(eoa_address)
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to read key metadata: {}", e)))?;
+    if metadata.status == KeyStatus::Disabled {
+        return Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            "key_disabled",
+            Some("eoa_address"),
+            format!("the key of {} is disabled", eoa_address),
+        ));
+    }
+    Ok(key_pair)
+}
+
+/// Finds the key pair with a public key given in hex: a G1 key as 32 bytes
+/// compressed or 64 bytes uncompressed, or a G2 key as 64 bytes compressed or
+/// 128 bytes uncompressed. 64-byte keys are told apart by their gnark flags.
+pub(crate) async fn key_pair_by_public_key(
+    store: &dyn KeyStore,
+    public_key: &str,
+    field: &str,
+) -> Result<KeyPair, ApiError> {
+    let invalid = |message: String| ApiError::bad_request("invalid_public_key", field, message);
+    let bytes = hex::decode(public_key.trim_start_matches("0x")).map_err(|_| invalid("public key must be hex".to_string()))?;
+    let matches: Box<dyn Fn(&KeyPair) -> bool + Send> = match bytes.len() {
+        32 => {
+            let wanted = g1_from_compressed(&bytes).map_err(invalid)?;
+            Box::new(move |key_pair| key_pair.public_key_g1.to_checked_g1_point().is_ok_and(|key| key == wanted))
+        }
+        64 if !is_gnark_compressed(bytes[0]) => {
+            let wanted = g1_from_gnark_raw(&bytes).map_err(invalid)?;
+            Box::new(move |key_pair| key_pair.public_key_g1.to_checked_g1_point().is_ok_and(|key| key == wanted))
+        }
+        64 | 128 => {
+            let wanted = if bytes.len() == 64 { g2_from_compressed(&bytes) } else { g2_from_gnark_raw(&bytes) }
+                .map_err(invalid)?;
+            Box::new(move |key_pair| key_pair.public_key_g2.to_checked_g2_point().is_ok_and(|key| key == wanted))
+        }
+        _ => return Err(invalid("public key must be 32 or 64 bytes for G1, or 64 or 128 bytes for G2".to_string())),
     };
+    let key_pairs = store
+        .list_key_pairs()
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to list key pairs: {}", e)))?;
+    key_pairs
+        .into_iter()
+        .find(|key_pair| matches(key_pair))
+        .ok_or_else(|| unknown_key(public_key, field))
+}
+
+/// Finds the key pair of a public key for a principal, like [`key_pair_by_public_key`].
+/// A key the principal may not use is reported exactly like an unknown key, so that
+/// the error does not tell which keys the service holds. The record is moved to the
+/// EOA of the key once one matches.
+pub(crate) async fn accessible_key_pair_by_public_key(
+    store: &dyn KeyStore,
+    principal: &Principal,
+    public_key: &str,
+    field: &str,
+    record: &mut AuditRecord,
+) -> Result<KeyPair, ApiError> {
+    let key_pair = key_pair_by_public_key(store, public_key, field).await?;
+    record.eoa_address = key_pair.eoa_address.clone();
+    if !principal.can_access(&key_pair.eoa_address) {
+        return Err(unknown_key(public_key, field));
+    }
+    Ok(key_pair)
+}
 
-    // Perform signing (scalar multiplication)
-    let signature = point * private_key;
-    let signature_affine = signature.into_affine();
-
-    // Create response
-    let response = SignResponse {
-        product: G1Point {
-            x: signature_affine.x.to_string(),
-            y: signature_affine.y.to_string(),
-        },
-        signer_g1: key_pair.public_key_g1.clone(),
+fn unknown_key(public_key: &str, field: &str) -> ApiError {
+    ApiError::not_found("unknown_key", field, format!("no key pair for {}", public_key))
+}
+
+/// Signs a validated message point with a key pair and records the signature
+pub(crate) async fn sign_with_key_pair(
+    store: &dyn KeyStore,
+    signer: &dyn Signer,
+    key_pair: KeyPair,
+    mode: MessageMode,
+    message: &crate::g1::G1Point,
+) -> Result<SignResponse, ApiError> {
+    let product = signer
+        .scalar_mul(&key_pair, message)
+        .map_err(|e| ApiError::internal(format!("Failed to sign with the key of {}: {:#}", key_pair.eoa_address, e)))?;
+    let product = G1Point::from(&product);
+    let message = G1Point::from(message);
+    store
+        .record_signature(&key_pair.eoa_address, "sign", &message, &product)
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to record signature: {}", e)))?;
+
+    Ok(SignResponse {
+        mode,
+        message,
+        product,
+        signer_g1: key_pair.public_key_g1,
+        signer_g2: key_pair.public_key_g2,
+    })
+}
+
+/// Resolves a message given in exactly one of the supported forms to a G1 point.
+///
+/// Explicit and compressed points must be on the curve and not the point at
+/// infinity; hashes are mapped with `hashToG1`, which always yields a valid point.
+/// Data from a message source is hashed with the domain of the source first.
+pub(crate) fn resolve_message(
+    input: &MessageInput,
+    sources: &MessageSources,
+) -> Result<(MessageMode, crate::g1::G1Point), ApiError> {
+    let given = [
+        input.message_point.is_some(),
+        input.message_hash.is_some(),
+        input.message_compressed.is_some(),
+        input.message_source.is_some(),
+    ];
+    match given.iter().filter(|given| **given).count() {
+        0 => {
+            return Err(ApiError::bad_request(
+                "missing_field",
+                "message_point",
+                "one of message_point, message_hash, message_compressed or message_source is required",
+            ))
+        }
+        1 => {}
+        _ => {
+            return Err(ApiError::bad_request(
+                "conflicting_fields",
+                "message_point",
+                "give only one of message_point, message_hash, message_compressed or message_source",
+            ))
+        }
+    }
+
+    if let Some(point) = &input.message_point {
+        return Ok((MessageMode::Point, parse_g1(point, "message_point")?));
+    }
+    if let Some(hash) = &input.message_hash {
+        return Ok((MessageMode::Hash, hash_to_g1(&parse_message_hash(hash, "message_hash")?)));
+    }
+    if let Some(sourced) = &input.message_source {
+        let hash = sources.message_hash(&sourced.source, &sourced.data)?;
+        return Ok((MessageMode::Source, hash_to_g1(&hash)));
+    }
+    let field = "message_compressed";
+    let compressed = input.message_compressed.as_deref().unwrap_or_default();
+    let bytes = hex::decode(compressed.trim_start_matches("0x"))
+        .map_err(|_| ApiError::bad_request("invalid_point", field, "compressed point must be hex"))?;
+    if bytes.len() != 32 {
+        return Err(ApiError::bad_request("invalid_point", field, "compressed point must be 32 bytes"));
+    }
+    let point = g1_from_compressed(&bytes).map_err(|e| ApiError::bad_request("invalid_point", field, e))?;
+    if point.inner().is_zero() {
+        return Err(ApiError::bad_request("invalid_point", field, "point is the point at infinity"));
+    }
+    Ok((MessageMode::Compressed, point))
+}
+
+/// Parses a 32-byte hex message hash, with or without a `0x` prefix
+pub(crate) fn parse_message_hash(hash: &str, field: &str) -> Result<[u8; 32], ApiError> {
+    let bytes = hex::decode(hash.trim_start_matches("0x"))
+        .map_err(|_| ApiError::bad_request("invalid_hash", field, "message hash must be hex"))?;
+    bytes
+        .try_into()
+        .map_err(|_| ApiError::bad_request("invalid_hash", field, "message hash must be 32 bytes"))
+}
+
+fn parse_g1(point: &G1Point, field: &str) -> Result<crate::g1::G1Point, ApiError> {
+    point
+        .to_checked_g1_point()
+        .map_err(|e| ApiError::bad_request("invalid_point", field, e))
+}
+
+fn parse_g2(point: &G2Point, field: &str) -> Result<crate::g2::G2Point, ApiError> {
+    point
+        .to_checked_g2_point()
+        .map_err(|e| ApiError::bad_request("invalid_point", field, e))
+}
+
+/// Picks the single value or the non-empty list given for a field pair
+fn one_or_many<'a, T>(
+    single: &'a Option<T>,
+    many: &'a Option<Vec<T>>,
+    single_field: &str,
+    many_field: &str,
+) -> Result<Vec<(String, &'a T)>, ApiError> {
+    match (single, many) {
+        (Some(value), None) => Ok(vec![(single_field.to_string(), value)]),
+        (None, Some(values)) if values.is_empty() => Err(ApiError::bad_request(
+            "empty_list",
+            many_field,
+            format!("{} must not be empty", many_field),
+        )),
+        (None, Some(values)) => Ok(values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (format!("{}[{}]", many_field, i), v))
+            .collect()),
+        (Some(_), Some(_)) => Err(ApiError::bad_request(
+            "conflicting_fields",
+            single_field,
+            format!("give either {} or {}, not both", single_field, many_field),
+        )),
+        (None, None) => Err(ApiError::bad_request(
+            "missing_field",
+            single_field,
+            format!("{} or {} is required", single_field, many_field),
+        )),
+    }
+}
+
+/// Looks up the G2 public key of an EOA
+async fn public_key_of(store: &dyn KeyStore, eoa_address: &str, field: &str) -> Result<crate::g2::G2Point, ApiError> {
+    let key_pair = store
+        .get_key_pair(eoa_address)
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to read key pair: {}", e)))?
+        .ok_or_else(|| ApiError::not_found("unknown_eoa", field, format!("no key pair for {}", eoa_address)))?;
+    key_pair
+        .public_key_g2
+        .to_checked_g2_point()
+        .map_err(|e| ApiError::internal(format!("Stored G2 public key of {} is invalid: {}", eoa_address, e)))
+}
+
+/// Verify a single or aggregate BLS signature
+pub async fn verify(
+    store: web::Data<dyn KeyStore>,
+    policy: web::Data<PolicyEngine>,
+    principal: Principal,
+    req: web::Json<VerifyRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let (message_mode, message) = resolve_message(&req.message, policy.sources())?;
+
+    let signatures = one_or_many(&req.signature, &req.signatures, "signature", "signatures")?
+        .into_iter()
+        .map(|(field, point)| parse_g1(point, &field))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let public_keys = match (
+        req.public_key_g2.is_some() || req.public_keys_g2.is_some(),
+        req.eoa_address.is_some() || req.eoa_addresses.is_some(),
+    ) {
+        (true, false) => one_or_many(&req.public_key_g2, &req.public_keys_g2, "public_key_g2", "public_keys_g2")?
+            .into_iter()
+            .map(|(field, point)| parse_g2(point, &field))
+            .collect::<Result<Vec<_>, _>>()?,
+        (false, true) => {
+            let mut keys = Vec::new();
+            for (field, eoa_address) in one_or_many(&req.eoa_address, &req.eoa_addresses, "eoa_address", "eoa_addresses")? {
+                principal.authorize(Operation::Verify, eoa_address)?;
+                keys.push(public_key_of(store.get_ref(), eoa_address, &field).await?);
+            }
+            keys
+        }
+        (true, true) => {
+            return Err(ApiError::bad_request(
+                "conflicting_fields",
+                "public_key_g2",
+                "give either public keys or EOA addresses, not both",
+            ))
+        }
+        (false, false) => {
+            return Err(ApiError::bad_request(
+                "missing_field",
+                "public_key_g2",
+                "public_key_g2, public_keys_g2, eoa_address or eoa_addresses is required",
+            ))
+        }
     };
 
-    HttpResponse::Ok().json(response)
-} 
\ No newline at end of file
+    let signature = signatures.iter().fold(crate::g1::G1Point::zero(), |acc, s| acc.add(s));
+    let public_key = public_keys.iter().fold(crate::g2::G2Point::zero(), |acc, pk| acc.add(pk));
+    if signature.inner().is_zero() || public_key.inner().is_zero() {
+        return Err(ApiError::bad_request(
+            "degenerate_aggregate",
+            "signatures",
+            "aggregate signature or public key is the point at infinity",
+        ));
+    }
+
+    let aggregate = signatures.len() > 1 || public_keys.len() > 1;
+    Ok(HttpResponse::Ok().json(VerifyResponse {
+        valid: verify_signature(&message, &signature, &public_key),
+        mode: if aggregate { "aggregate" } else { "single" }.to_string(),
+        signers: public_keys.len(),
+        message_mode,
+        message: G1Point::from(&message),
+    }))
+}
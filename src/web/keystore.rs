@@ -0,0 +1,205 @@
+//! Decryption of EIP-2335 keystores.
+//!
+//! A keystore holds a 32-byte secret encrypted with `aes-128-ctr` under a key
+//! derived from a password with `scrypt` or `pbkdf2`. The password is normalized
+//! to NFKD and stripped of control characters before the key is derived, and the
+//! checksum `sha256(DK[16..32] || ciphertext)` tells a wrong password apart from
+//! a corrupt keystore. See <https://eips.ethereum.org/EIPS/eip-2335>.
+//!
+//! The secret is read as a big-endian BN254 scalar, which must be non-zero and
+//! below the group order.
+
+use aes::cipher::{KeyIvInit, StreamCipher};
+use anyhow::{anyhow, Result};
+use ark_bn254::Fr;
+use ark_ff::{BigInteger, PrimeField, Zero};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use unicode_normalization::UnicodeNormalization;
+use zeroize::Zeroizing;
+
+/// Version of the only supported keystore format
+const KEYSTORE_VERSION: u64 = 4;
+
+/// Largest accepted scrypt `n`, to bound the time and memory spent on an import
+const MAX_SCRYPT_N: u64 = 1 << 20;
+
+/// Largest accepted scrypt `r * p`
+const MAX_SCRYPT_RP: u64 = 64;
+
+/// Largest accepted pbkdf2 iteration count
+const MAX_PBKDF2_C: u64 = 1 << 22;
+
+type Aes128Ctr = ctr::Ctr128BE<aes::Aes128>;
+
+/// One step of the keystore's crypto section
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeystoreModule {
+    pub function: String,
+    #[serde(default)]
+    pub params: Value,
+    /// Hex
+    pub message: String,
+}
+
+/// The crypto section of a keystore
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeystoreCrypto {
+    pub kdf: KeystoreModule,
+    pub checksum: KeystoreModule,
+    pub cipher: KeystoreModule,
+}
+
+/// An EIP-2335 keystore. Fields other than `crypto` and `version` are ignored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Keystore {
+    pub crypto: KeystoreCrypto,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pubkey: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
+    pub version: u64,
+}
+
+/// Why a keystore could not be decrypted
+#[derive(Debug)]
+pub enum KeystoreError {
+    /// The keystore is malformed or uses an unsupported function
+    Invalid(anyhow::Error),
+    /// The checksum does not match, so the password is wrong
+    WrongPassword,
+}
+
+impl std::fmt::Display for KeystoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeystoreError::Invalid(e) => write!(f, "{}", e),
+            KeystoreError::WrongPassword => f.write_str("wrong password"),
+        }
+    }
+}
+
+impl From<anyhow::Error> for KeystoreError {
+    fn from(e: anyhow::Error) -> Self {
+        KeystoreError::Invalid(e)
+    }
+}
+
+/// Normalizes a password as EIP-2335 requires: NFKD, without C0, C1 and delete
+/// control characters
+pub fn normalize_password(password: &str) -> Zeroizing<String> {
+    Zeroizing::new(
+        password
+            .nfkd()
+            .filter(|c| !matches!(*c as u32, 0x00..=0x1f | 0x7f..=0x9f))
+            .collect(),
+    )
+}
+
+fn hex_param(params: &Value, name: &str) -> Result<Vec<u8>> {
+    let value = params[name].as_str().ok_or_else(|| anyhow!("missing kdf parameter {}", name))?;
+    hex::decode(value.trim_start_matches("0x")).map_err(|_| anyhow!("{} must be hex", name))
+}
+
+fn int_param(params: &Value, name: &str) -> Result<u64> {
+    params[name].as_u64().ok_or_else(|| anyhow!("missing kdf parameter {}", name))
+}
+
+/// Derives the decryption key from the normalized password
+fn derive_key(kdf: &KeystoreModule, password: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+    let params = &kdf.params;
+    let dklen = int_param(params, "dklen")?;
+    if dklen != 32 {
+        return Err(anyhow!("dklen must be 32"));
+    }
+    let salt = hex_param(params, "salt")?;
+    let mut key = Zeroizing::new(vec![0u8; 32]);
+    match kdf.function.as_str() {
+        "scrypt" => {
+            let (n, r, p) = (int_param(params, "n")?, int_param(params, "r")?, int_param(params, "p")?);
+            if !n.is_power_of_two() || !(2..=MAX_SCRYPT_N).contains(&n) {
+                return Err(anyhow!("scrypt n must be a power of two of at most {}", MAX_SCRYPT_N));
+            }
+            if r == 0 || p == 0 || r * p > MAX_SCRYPT_RP {
+                return Err(anyhow!("scrypt r * p must be between 1 and {}", MAX_SCRYPT_RP));
+            }
+            let params = scrypt::Params::new(n.trailing_zeros() as u8, r as u32, p as u32, 32)
+                .map_err(|e| anyhow!("invalid scrypt parameters: {}", e))?;
+            scrypt::scrypt(password, &salt, &params, &mut key).map_err(|e| anyhow!("scrypt failed: {}", e))?;
+        }
+        "pbkdf2" => {
+            if params["prf"].as_str() != Some("hmac-sha256") {
+                return Err(anyhow!("pbkdf2 prf must be hmac-sha256"));
+            }
+            let c = int_param(params, "c")?;
+            if c == 0 || c > MAX_PBKDF2_C {
+                return Err(anyhow!("pbkdf2 c must be between 1 and {}", MAX_PBKDF2_C));
+            }
+            pbkdf2::pbkdf2_hmac::<Sha256>(password, &salt, c as u32, &mut key);
+        }
+        other => return Err(anyhow!("unsupported kdf {}", other)),
+    }
+    Ok(key)
+}
+
+/// Decrypts the secret of a keystore as a BN254 scalar.
+///
+/// The key derivation is deliberately slow, so callers on an async runtime should
+/// run this on a blocking thread.
+///
+/// # Arguments
+/// * `keystore` - The EIP-2335 keystore
+/// * `password` - The password, before normalization
+///
+/// # Returns
+/// The secret scalar, or why it could not be decrypted
+pub fn decrypt_keystore(keystore: &Keystore, password: &str) -> Result<Fr, KeystoreError> {
+    if keystore.version != KEYSTORE_VERSION {
+        return Err(anyhow!("unsupported keystore version {}", keystore.version).into());
+    }
+    let crypto = &keystore.crypto;
+    if crypto.checksum.function != "sha256" {
+        return Err(anyhow!("unsupported checksum {}", crypto.checksum.function).into());
+    }
+    if crypto.cipher.function != "aes-128-ctr" {
+        return Err(anyhow!("unsupported cipher {}", crypto.cipher.function).into());
+    }
+    let ciphertext = hex::decode(&crypto.cipher.message).map_err(|_| anyhow!("cipher message must be hex"))?;
+    let checksum = hex::decode(&crypto.checksum.message).map_err(|_| anyhow!("checksum message must be hex"))?;
+    let iv: [u8; 16] = hex_param(&crypto.cipher.params, "iv")?
+        .try_into()
+        .map_err(|_| anyhow!("iv must be 16 bytes"))?;
+    if ciphertext.len() != 32 {
+        return Err(anyhow!("the secret must be 32 bytes").into());
+    }
+
+    let password = normalize_password(password);
+    let key = derive_key(&crypto.kdf, password.as_bytes())?;
+    let mut hasher = Sha256::new();
+    hasher.update(&key[16..32]);
+    hasher.update(&ciphertext);
+    if hasher.finalize().as_slice() != checksum.as_slice() {
+        return Err(KeystoreError::WrongPassword);
+    }
+
+    let mut secret = Zeroizing::new(ciphertext);
+    Aes128Ctr::new(key[..16].into(), &iv.into()).apply_keystream(&mut secret);
+    scalar_from_be_bytes(&secret).map_err(|e| anyhow!(e).into())
+}
+
+/// Reads a 32-byte big-endian scalar, rejecting zero and values not below the
+/// group order
+pub fn scalar_from_be_bytes(bytes: &[u8]) -> Result<Fr, String> {
+    if bytes.len() != 32 {
+        return Err("the secret must be 32 bytes".to_string());
+    }
+    let scalar = Fr::from_be_bytes_mod_order(bytes);
+    if scalar.into_bigint().to_bytes_be() != bytes {
+        return Err("the secret is not below the BN254 group order".to_string());
+    }
+    if scalar.is_zero() {
+        return Err("the secret must not be zero".to_string());
+    }
+    Ok(scalar)
+}
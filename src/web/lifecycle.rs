@@ -0,0 +1,296 @@
+//! Key lifecycle endpoints: generate, import, rotate, disable, enable and delete.
+//!
+//! Every endpoint needs the `manage_keys` operation, which is never granted when
+//! authentication is disabled. New key pairs are either generated with a random
+//! private key or imported from a raw scalar or an EIP-2335 keystore; their G1
+//! and G2 public keys are always computed from the private key.
+//!
+//! Rotating a key replaces its key pair and keeps the old public keys in the
+//! key's metadata, so they can still be read but nothing can be signed with them.
+//! Disabled keys refuse every signing request until they are enabled again.
+//! Deleting a key overwrites its secret where the store allows it, and private
+//! keys are zeroized in memory when dropped.
+//!
+//! Each operation is written to the audit log with the key id of the key pair it
+//! acted on as its `output_hash`.
+
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse};
+use ark_bn254::Fr;
+use zeroize::Zeroizing;
+
+use crate::utils::parse_decimal_field;
+use crate::web::audit::{append_record, AuditLog, AuditRecord, RequestId};
+use crate::web::auth::{Operation, Principal};
+use crate::web::error::ApiError;
+use crate::web::handlers::public_view;
+use crate::web::keystore::{decrypt_keystore, scalar_from_be_bytes, KeystoreError};
+use crate::web::models::{KeyImportRequest, KeyPair, KeyStatus, PublicKeyView};
+use crate::web::signer::Signer;
+use crate::web::store::KeyStore;
+
+/// Generate a key pair for an EOA that has none
+pub async fn generate_key(
+    store: web::Data<dyn KeyStore>,
+    signer: web::Data<dyn Signer>,
+    audit: web::Data<dyn AuditLog>,
+    principal: Principal,
+    request_id: RequestId,
+    eoa_address: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let record = AuditRecord::new(&request_id, &principal.name, &eoa_address, "key_generate");
+    let result = async {
+        principal.authorize(Operation::ManageKeys, &eoa_address)?;
+        check_eoa_address(&eoa_address)?;
+        let key_pair = KeyPair::generate(&eoa_address, &mut rand::thread_rng());
+        insert_key_pair(store.get_ref(), signer.get_ref(), key_pair).await
+    }
+    .await;
+    respond(audit.get_ref(), record, result, StatusCode::CREATED).await
+}
+
+/// Import a key pair for an EOA that has none
+pub async fn import_key(
+    store: web::Data<dyn KeyStore>,
+    signer: web::Data<dyn Signer>,
+    audit: web::Data<dyn AuditLog>,
+    principal: Principal,
+    request_id: RequestId,
+    eoa_address: web::Path<String>,
+    req: web::Json<KeyImportRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let record = AuditRecord::new(&request_id, &principal.name, &eoa_address, "key_import");
+    let result = async {
+        principal.authorize(Operation::ManageKeys, &eoa_address)?;
+        check_eoa_address(&eoa_address)?;
+        let key_pair = imported_key_pair(&eoa_address, req.into_inner()).await?;
+        insert_key_pair(store.get_ref(), signer.get_ref(), key_pair).await
+    }
+    .await;
+    respond(audit.get_ref(), record, result, StatusCode::CREATED).await
+}
+
+/// Replace the key pair of an EOA with a generated one or, given a body, with an
+/// imported one
+pub async fn rotate_key(
+    store: web::Data<dyn KeyStore>,
+    signer: web::Data<dyn Signer>,
+    audit: web::Data<dyn AuditLog>,
+    principal: Principal,
+    request_id: RequestId,
+    eoa_address: web::Path<String>,
+    body: web::Bytes,
+) -> Result<HttpResponse, ApiError> {
+    let record = AuditRecord::new(&request_id, &principal.name, &eoa_address, "key_rotate");
+    let result = async {
+        principal.authorize(Operation::ManageKeys, &eoa_address)?;
+        let key_pair = if body.is_empty() {
+            KeyPair::generate(&eoa_address, &mut rand::thread_rng())
+        } else {
+            let req: KeyImportRequest = serde_json::from_slice(&body)
+                .map_err(|e| ApiError::bad_request("invalid_body", "body", e.to_string()))?;
+            imported_key_pair(&eoa_address, req).await?
+        };
+        existing_key_pair(store.get_ref(), &eoa_address).await?;
+        let key_pair = seal(signer.get_ref(), key_pair)?;
+        // The replaced private key is zeroized as soon as it is dropped here
+        store
+            .rotate_key_pair(key_pair.clone())
+            .await
+            .map_err(|e| ApiError::internal(format!("Failed to rotate key pair of {}: {}", eoa_address, e)))?;
+        view(store.get_ref(), &key_pair).await
+    }
+    .await;
+    respond(audit.get_ref(), record, result, StatusCode::OK).await
+}
+
+/// Refuse every signing request for the key of an EOA
+pub async fn disable_key(
+    store: web::Data<dyn KeyStore>,
+    audit: web::Data<dyn AuditLog>,
+    principal: Principal,
+    request_id: RequestId,
+    eoa_address: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    set_status(store.get_ref(), audit.get_ref(), &principal, &request_id, &eoa_address, KeyStatus::Disabled).await
+}
+
+/// Allow a disabled key to sign again
+pub async fn enable_key(
+    store: web::Data<dyn KeyStore>,
+    audit: web::Data<dyn AuditLog>,
+    principal: Principal,
+    request_id: RequestId,
+    eoa_address: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    set_status(store.get_ref(), audit.get_ref(), &principal, &request_id, &eoa_address, KeyStatus::Active).await
+}
+
+/// Delete the key pair of an EOA, returning the public view it had
+pub async fn delete_key(
+    store: web::Data<dyn KeyStore>,
+    audit: web::Data<dyn AuditLog>,
+    principal: Principal,
+    request_id: RequestId,
+    eoa_address: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let record = AuditRecord::new(&request_id, &principal.name, &eoa_address, "key_delete");
+    let result = async {
+        principal.authorize(Operation::ManageKeys, &eoa_address)?;
+        let key_pair = existing_key_pair(store.get_ref(), &eoa_address).await?;
+        let deleted = view(store.get_ref(), &key_pair).await?;
+        store
+            .delete_key_pair(&eoa_address)
+            .await
+            .map_err(|e| ApiError::internal(format!("Failed to delete key pair of {}: {}", eoa_address, e)))?;
+        Ok(deleted)
+    }
+    .await;
+    respond(audit.get_ref(), record, result, StatusCode::OK).await
+}
+
+async fn set_status(
+    store: &dyn KeyStore,
+    audit: &dyn AuditLog,
+    principal: &Principal,
+    request_id: &RequestId,
+    eoa_address: &str,
+    status: KeyStatus,
+) -> Result<HttpResponse, ApiError> {
+    let operation = match status {
+        KeyStatus::Active => "key_enable",
+        KeyStatus::Disabled => "key_disable",
+    };
+    let record = AuditRecord::new(request_id, &principal.name, eoa_address, operation);
+    let result = async {
+        principal.authorize(Operation::ManageKeys, eoa_address)?;
+        let key_pair = existing_key_pair(store, eoa_address).await?;
+        store
+            .set_key_status(eoa_address, status)
+            .await
+            .map_err(|e| ApiError::internal(format!("Failed to set the status of {}: {}", eoa_address, e)))?;
+        view(store, &key_pair).await
+    }
+    .await;
+    respond(audit, record, result, StatusCode::OK).await
+}
+
+/// Audits a lifecycle operation, then returns the public view of its key pair
+async fn respond(
+    audit: &dyn AuditLog,
+    mut record: AuditRecord,
+    result: Result<PublicKeyView, ApiError>,
+    status: StatusCode,
+) -> Result<HttpResponse, ApiError> {
+    // The output hash of a G1 public key is its key id
+    record.finish(result.as_ref().map(|view| &view.public_key_g1));
+    append_record(audit, record).await?;
+    Ok(HttpResponse::build(status).json(result?))
+}
+
+async fn view(store: &dyn KeyStore, key_pair: &KeyPair) -> Result<PublicKeyView, ApiError> {
+    public_view(store, key_pair)
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to build public key view: {}", e)))
+}
+
+async fn existing_key_pair(store: &dyn KeyStore, eoa_address: &str) -> Result<KeyPair, ApiError> {
+    store
+        .get_key_pair(eoa_address)
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to read key pair: {}", e)))?
+        .ok_or_else(|| ApiError::not_found("unknown_eoa", "eoa_address", format!("no key pair for {}", eoa_address)))
+}
+
+pub(crate) async fn insert_key_pair(store: &dyn KeyStore, signer: &dyn Signer, key_pair: KeyPair) -> Result<PublicKeyView, ApiError> {
+    let eoa_address = key_pair.eoa_address.clone();
+    let exists = store
+        .get_key_pair(&eoa_address)
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to read key pair: {}", e)))?
+        .is_some();
+    if exists {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            "key_exists",
+            Some("eoa_address"),
+            format!("{} already has a key pair; rotate it instead", eoa_address),
+        ));
+    }
+    let key_pair = seal(signer, key_pair)?;
+    store
+        .insert_key_pair(key_pair.clone())
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to store key pair of {}: {}", eoa_address, e)))?;
+    view(store, &key_pair).await
+}
+
+/// Converts a new key pair to the form the signer keeps in the store
+fn seal(signer: &dyn Signer, key_pair: KeyPair) -> Result<KeyPair, ApiError> {
+    let eoa_address = key_pair.eoa_address.clone();
+    signer
+        .seal(key_pair)
+        .map_err(|e| ApiError::internal(format!("Failed to seal the key pair of {}: {:#}", eoa_address, e)))
+}
+
+/// Key pairs are only created for well-formed addresses, since some stores use
+/// the address as a file name
+pub(crate) fn check_eoa_address(eoa_address: &str) -> Result<(), ApiError> {
+    let valid = eoa_address
+        .strip_prefix("0x")
+        .is_some_and(|hex| hex.len() == 40 && hex.chars().all(|c| c.is_ascii_hexdigit()));
+    if !valid {
+        return Err(ApiError::bad_request(
+            "invalid_eoa",
+            "eoa_address",
+            "EOA address must be 0x followed by 40 hex digits",
+        ));
+    }
+    Ok(())
+}
+
+/// Builds the key pair of an import request
+async fn imported_key_pair(eoa_address: &str, req: KeyImportRequest) -> Result<KeyPair, ApiError> {
+    let private_key = match (req.private_key, req.keystore, req.password) {
+        (Some(private_key), None, None) => parse_private_key(&private_key)?,
+        (None, Some(keystore), Some(password)) => {
+            // The key derivation is slow, so keep it off the async workers
+            web::block(move || decrypt_keystore(&keystore, &password))
+                .await
+                .map_err(ApiError::internal)?
+                .map_err(|e| match e {
+                    KeystoreError::WrongPassword => {
+                        ApiError::bad_request("invalid_password", "password", "the keystore checksum does not match")
+                    }
+                    KeystoreError::Invalid(e) => ApiError::bad_request("invalid_keystore", "keystore", e.to_string()),
+                })?
+        }
+        (None, Some(_), None) => {
+            return Err(ApiError::bad_request("missing_field", "password", "password is required with keystore"))
+        }
+        (None, None, _) => {
+            return Err(ApiError::bad_request("missing_field", "private_key", "private_key or keystore is required"))
+        }
+        (Some(_), _, _) => {
+            return Err(ApiError::bad_request(
+                "conflicting_fields",
+                "private_key",
+                "give either private_key, or keystore with password",
+            ))
+        }
+    };
+    KeyPair::from_private_key(eoa_address, private_key)
+        .map_err(|e| ApiError::bad_request("invalid_private_key", "private_key", e))
+}
+
+/// Parses a decimal scalar, or 32-byte big-endian hex with a `0x` prefix
+pub(crate) fn parse_private_key(private_key: &str) -> Result<Fr, ApiError> {
+    let invalid = |message: String| ApiError::bad_request("invalid_private_key", "private_key", message);
+    match private_key.strip_prefix("0x") {
+        Some(hex) => {
+            let bytes = Zeroizing::new(hex::decode(hex).map_err(|_| invalid("private key must be hex".to_string()))?);
+            scalar_from_be_bytes(&bytes).map_err(invalid)
+        }
+        None => parse_decimal_field::<Fr>(private_key).map_err(invalid),
+    }
+}
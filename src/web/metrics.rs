@@ -0,0 +1,89 @@
+//! Prometheus metrics of the key service, served at `GET /metrics`.
+
+use actix_web::{web, HttpResponse};
+use prometheus_client::encoding::text::encode;
+use prometheus_client::encoding::EncodeLabelSet;
+use prometheus_client::metrics::counter::Counter;
+use prometheus_client::metrics::family::Family;
+use prometheus_client::registry::Registry;
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct KeyLabels {
+    eoa_address: String,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct DenialLabels {
+    eoa_address: String,
+    rule: String,
+}
+
+/// The metrics registry and the metrics recorded by the service.
+pub struct Metrics {
+    registry: Registry,
+    policy_denials: Family<DenialLabels, Counter>,
+    policy_allowed: Family<KeyLabels, Counter>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let mut registry = Registry::default();
+        let policy_denials = Family::<DenialLabels, Counter>::default();
+        registry.register(
+            "bn254_policy_denials",
+            "Signing requests denied by a key policy, by key and rule",
+            policy_denials.clone(),
+        );
+        let policy_allowed = Family::<KeyLabels, Counter>::default();
+        registry.register(
+            "bn254_policy_allowed",
+            "Signing requests allowed by the key policies, by key",
+            policy_allowed.clone(),
+        );
+        Self {
+            registry,
+            policy_denials,
+            policy_allowed,
+        }
+    }
+
+    /// Counts a signing request denied by a policy rule
+    pub fn policy_denied(&self, eoa_address: &str, rule: &str) {
+        self.policy_denials
+            .get_or_create(&DenialLabels {
+                eoa_address: eoa_address.to_lowercase(),
+                rule: rule.to_string(),
+            })
+            .inc();
+    }
+
+    /// Counts a signing request allowed by the policies
+    pub fn policy_allowed(&self, eoa_address: &str) {
+        self.policy_allowed
+            .get_or_create(&KeyLabels {
+                eoa_address: eoa_address.to_lowercase(),
+            })
+            .inc();
+    }
+
+    /// The metrics in the OpenMetrics text format
+    pub fn render(&self) -> String {
+        let mut buffer = String::new();
+        // Writing to a String cannot fail
+        encode(&mut buffer, &self.registry).expect("metrics encode to a string");
+        buffer
+    }
+}
+
+/// Serve the metrics in the OpenMetrics text format
+pub async fn metrics(metrics: web::Data<Metrics>) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("application/openmetrics-text; version=1.0.0; charset=utf-8")
+        .body(metrics.render())
+}
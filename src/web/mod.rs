@@ -1,35 +1,543 @@
+pub mod approvals;
+pub mod audit;
+pub mod auth;
+pub mod cipher;
+pub mod config;
+pub mod encrypted_dir;
+pub mod envelope;
+pub mod eoa_auth;
+pub mod grpc;
+pub mod error;
 pub mod models;
 pub mod store;
+pub mod guard;
+pub mod keystore;
 pub mod handlers;
+pub mod lifecycle;
+pub mod metrics;
+pub mod pkcs11;
+pub mod policy;
+pub mod signer;
+pub mod sqlite;
+pub mod tls;
+pub mod web3signer;
 
-use actix_web::{web, App, HttpServer};
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue, AUTHORIZATION};
+use actix_web::middleware::{from_fn, Next};
+use actix_web::{web, App, HttpMessage, HttpServer, Scope};
 use log::{info, error};
+use anyhow::anyhow;
+use std::io::Write;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use audit::{open_audit_log, verify_chain, AuditLog, RequestId, REQUEST_ID_HEADER};
+use auth::{forbidden, operation_for, unauthorized, ApiToken, Authenticator, Principal, TokenStore};
+use config::{AuditCommand, Config, EndpointsConfig, EoaAuthMode, GuardCommand, LogConfig, LogFormat, Pkcs11Command, SignerBackend, StoreBackend, TokenCommand};
+use eoa_auth::{AuthorizationDomain, EoaVerifier, MemoryNonceStore, NonceStore};
+use approvals::ApprovalQueue;
+use grpc::GrpcService;
+use guard::{export_interchange, import_interchange, EquivocationGuard, Interchange};
+use metrics::Metrics;
+use pkcs11::Pkcs11Kek;
+use policy::PolicyEngine;
+use signer::{LocalSigner, Signer, WrappingSigner};
+use tls::{ClientIdentity, ClientPrincipals, TlsReloader};
+use error::ApiError;
+use handlers::SigningChecks;
+use store::KeyStore;
 
-pub async fn start_server() -> std::io::Result<()> {
+/// Opens the signer selected by the configuration.
+pub fn open_signer(config: &Config) -> anyhow::Result<Arc<dyn Signer>> {
+    Ok(match config.signer.backend {
+        SignerBackend::Local => Arc::new(LocalSigner),
+        SignerBackend::Pkcs11 => Arc::new(WrappingSigner::new(Arc::new(config.pkcs11_kek()?))),
+    })
+}
+
+/// Opens the key store selected by the configuration.
+///
+/// If `store.import_json` names a JSON key file, its key pairs are sealed by
+/// `signer` and imported into the store on startup.
+pub async fn open_store(config: &Config, signer: &dyn Signer) -> anyhow::Result<Arc<dyn KeyStore>> {
+    let path = config.store_path();
+    let store: Arc<dyn KeyStore> = match config.store.backend {
+        StoreBackend::Json => match config.kek_provider()? {
+            Some(kek) => Arc::new(store::JsonStore::from_file_encrypted(path.unwrap_or_default(), kek)?),
+            None => Arc::new(store::JsonStore::from_file(path.unwrap_or_default())?),
+        },
+        StoreBackend::Memory => Arc::new(store::MemoryStore::new()),
+        StoreBackend::Dir => Arc::new(encrypted_dir::EncryptedDirStore::open(
+            path.ok_or_else(|| anyhow!("store.path must be set for the dir store"))?,
+            &config.store_key()?,
+        )?),
+        StoreBackend::Sqlite => Arc::new(
+            sqlite::SqliteStore::connect(&path.unwrap_or_default(), &config.store_key()?).await?,
+        ),
+    };
+
+    if let Some(path) = &config.store.import_json {
+        let imported = import_key_pairs(&store::JsonStore::from_file(path)?, store.as_ref(), signer).await?;
+        info!("Imported {} key pairs from {}", imported, path.display());
+    }
+    Ok(store)
+}
+
+/// Copies every key pair from one store into another, skipping EOAs that already
+/// have a key pair in the destination. Key pairs are sealed by `signer` on the way.
+pub async fn import_key_pairs(from: &dyn KeyStore, to: &dyn KeyStore, signer: &dyn Signer) -> anyhow::Result<usize> {
+    let mut imported = 0;
+    for key_pair in from.list_key_pairs().await? {
+        if to.get_key_pair(&key_pair.eoa_address).await?.is_none() {
+            to.insert_key_pair(signer.seal(key_pair)?).await?;
+            imported += 1;
+        }
+    }
+    Ok(imported)
+}
+
+/// Initializes the global logger from the logging configuration.
+pub fn init_logging(config: &LogConfig) {
+    let mut builder = env_logger::Builder::new();
+    builder.parse_filters(&config.level);
+    if config.format == LogFormat::Json {
+        builder.format(|buf, record| {
+            let line = serde_json::json!({
+                "timestamp": buf.timestamp().to_string(),
+                "level": record.level().to_string(),
+                "target": record.target(),
+                "message": record.args().to_string(),
+            });
+            writeln!(buf, "{}", line)
+        });
+    }
+    builder.init();
+}
+
+/// Opens the token database selected by the configuration.
+pub async fn open_token_store(config: &Config) -> anyhow::Result<Arc<dyn TokenStore>> {
+    Ok(Arc::new(sqlite::SqliteTokenStore::connect(&config.auth_database()).await?))
+}
+
+/// The authenticator for the `/api` scope.
+pub async fn open_authenticator(config: &Config) -> anyhow::Result<Authenticator> {
+    if !config.auth.enabled {
+        log::warn!("Authentication is disabled; anyone who can reach the service can use every key");
+        return Ok(Authenticator::Disabled);
+    }
+    Ok(Authenticator::Tokens(open_token_store(config).await?))
+}
+
+/// The verifier of EOA authorizations, keeping nonces in the token database.
+pub async fn open_eoa_verifier(config: &Config) -> anyhow::Result<EoaVerifier> {
+    let nonces: Arc<dyn NonceStore> = match config.eoa_auth.mode {
+        EoaAuthMode::Off => Arc::new(MemoryNonceStore::new()),
+        _ => Arc::new(sqlite::SqliteNonceStore::connect(&config.auth_database()).await?),
+    };
+    if config.eoa_auth.mode != EoaAuthMode::Off && config.eoa_auth.salt.is_none() {
+        log::warn!("eoa_auth.salt is not set; EOA authorizations can be replayed against other deployments without one");
+    }
+    let domain = AuthorizationDomain::new(config.eoa_auth.chain_id, config.eoa_auth.salt.as_deref())
+        .map_err(anyhow::Error::msg)?;
+    Ok(EoaVerifier::new(config.eoa_auth.mode, config.eoa_auth.max_validity_secs, domain, nonces))
+}
+
+/// The anti-equivocation guard, keeping signed tasks in the guard database.
+pub async fn open_guard(config: &Config) -> anyhow::Result<EquivocationGuard> {
+    Ok(EquivocationGuard::new(Arc::new(
+        sqlite::SqliteSignedTaskStore::connect(&config.guard_database()).await?,
+    )))
+}
+
+/// Runs a `guard` command, printing its output.
+pub async fn run_guard_command(config: &Config, command: GuardCommand) -> anyhow::Result<()> {
+    let guard = open_guard(config).await?;
+    match command {
+        GuardCommand::Export { output } => {
+            let interchange = serde_json::to_string_pretty(&export_interchange(guard.tasks()).await?)?;
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, interchange)?;
+                    eprintln!("Exported signed tasks to {}", path.display());
+                }
+                None => println!("{}", interchange),
+            }
+        }
+        GuardCommand::Import { file } => {
+            let interchange: Interchange = serde_json::from_str(&std::fs::read_to_string(&file)?)
+                .map_err(|e| anyhow!("{} is not an interchange file: {}", file.display(), e))?;
+            let summary = import_interchange(guard.tasks(), &interchange).await?;
+            println!(
+                "Imported {} signed tasks, {} already recorded, {} conflicting with existing records",
+                summary.imported, summary.unchanged, summary.conflicts
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Runs a `pkcs11` command, printing its output.
+pub fn run_pkcs11_command(config: &Config, command: Pkcs11Command) -> anyhow::Result<()> {
+    match command {
+        Pkcs11Command::GenerateKey => {
+            let (module, pin) = config.pkcs11_login()?;
+            Pkcs11Kek::generate_key(module, &config.pkcs11.token_label, &pin, &config.pkcs11.key_label)?;
+            println!(
+                "Generated AES key {} on token {}",
+                config.pkcs11.key_label, config.pkcs11.token_label
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Runs a `token` management command, printing its output.
+pub async fn run_token_command(config: &Config, command: TokenCommand) -> anyhow::Result<()> {
+    let tokens = open_token_store(config).await?;
+    match command {
+        TokenCommand::Create {
+            description,
+            eoa_addresses,
+            all_keys,
+            operations,
+            expires_in_days,
+        } => {
+            if eoa_addresses.is_empty() && !all_keys {
+                return Err(anyhow!("Give the keys the token may use with --eoa, or --all-keys"));
+            }
+            let expires_at = expires_in_days
+                .map(|days| SystemTime::now() + Duration::from_secs(days * 24 * 60 * 60))
+                .map(|t| t.duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64))
+                .transpose()?;
+            let eoa_addresses = (!all_keys).then_some(eoa_addresses);
+            let (bearer, token) = ApiToken::generate(&description, eoa_addresses, operations, expires_at);
+            let id = token.id.clone();
+            tokens.insert_token(token).await?;
+            eprintln!("Created token {}; store it now, it cannot be shown again", id);
+            println!("{}", bearer);
+        }
+        TokenCommand::List => {
+            println!("{}", serde_json::to_string_pretty(&tokens.list_tokens().await?)?);
+        }
+        TokenCommand::Revoke { id } => {
+            if !tokens.revoke_token(&id).await? {
+                return Err(anyhow!("No token with id {}", id));
+            }
+            println!("Revoked token {}", id);
+        }
+    }
+    Ok(())
+}
+
+/// Runs an `audit` command, printing its output.
+pub async fn run_audit_command(config: &Config, command: AuditCommand) -> anyhow::Result<()> {
+    let location = &config.audit.path;
+    if !location.starts_with("sqlite:") && !std::path::Path::new(location).is_file() {
+        return Err(anyhow!("Audit log {} does not exist", location));
+    }
+    let entries = open_audit_log(location).await?.entries().await?;
+    match command {
+        AuditCommand::Verify { head } => {
+            let summary = verify_chain(&entries, head.as_deref())
+                .map_err(|e| anyhow!("Audit log {} is corrupt: {}", location, e))?;
+            eprintln!("Audit log {} is intact with {} entries", location, summary.entries);
+            println!("{}", summary.head);
+        }
+        AuditCommand::List { eoa_address, since } => {
+            let matching = entries.iter().filter(|entry| {
+                eoa_address.as_ref().is_none_or(|eoa| entry.record.eoa_address.eq_ignore_ascii_case(eoa))
+                    && since.is_none_or(|since| entry.record.timestamp >= since)
+            });
+            for entry in matching {
+                println!("{}", serde_json::to_string(entry)?);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Middleware giving every request of the `/api` scope a [`RequestId`].
+///
+/// The id is taken from the `X-Request-Id` header if the client sent a usable one,
+/// generated otherwise, and echoed in the response.
+pub async fn assign_request_id(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| !id.is_empty() && id.len() <= 128 && id.bytes().all(|b| b.is_ascii_graphic()))
+        .map(|id| RequestId(id.to_string()))
+        .unwrap_or_else(RequestId::generate);
+    req.extensions_mut().insert(request_id.clone());
+    let mut resp = next.call(req).await?;
+    if let Ok(value) = HeaderValue::from_str(&request_id.0) {
+        resp.headers_mut().insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+    }
+    Ok(resp)
+}
+
+/// Middleware authenticating and authorizing every request of the `/api` scope.
+///
+/// The [`Authenticator`] is read from the app data; without one every request
+/// fails. A request is authenticated by its bearer token or, without one, by the
+/// client certificate of its TLS connection if [`ClientPrincipals`] maps it.
+/// Requests to routes missing from [`operation_for`] are denied, and the
+/// authenticated [`Principal`] is attached to the request for the handlers.
+pub async fn authenticate(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    match authorize_request(&req).await {
+        Ok(principal) => {
+            req.extensions_mut().insert(principal);
+            Ok(next.call(req).await?.map_into_left_body())
+        }
+        Err(e) => Ok(req.error_response(e).map_into_right_body()),
+    }
+}
+
+async fn authorize_request(req: &ServiceRequest) -> Result<Principal, ApiError> {
+    let authenticator = req
+        .app_data::<web::Data<Authenticator>>()
+        .ok_or_else(|| ApiError::internal("No authenticator configured"))?;
+
+    let principal = match authenticator.as_ref() {
+        Authenticator::Disabled => Principal::unrestricted("anonymous"),
+        Authenticator::Tokens(tokens) => {
+            let bearer = req
+                .headers()
+                .get(AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.strip_prefix("Bearer "));
+            let certificate_principal = || {
+                let identity = req.conn_data::<ClientIdentity>()?;
+                req.app_data::<web::Data<ClientPrincipals>>()?.principal_for(identity)
+            };
+            match (bearer, certificate_principal()) {
+                (Some(bearer), _) => auth::authenticate_token(tokens.as_ref(), bearer.trim()).await?,
+                (None, Some(principal)) => principal,
+                (None, None) => return Err(unauthorized("a bearer token or client certificate is required")),
+            }
+        }
+    };
+
+    // Unknown paths fall through to a 404, but every registered route must be mapped
+    if let Some(pattern) = req.match_pattern() {
+        match operation_for(req.method(), &pattern) {
+            Some(operation) if principal.can(operation) => {}
+            Some(operation) => return Err(forbidden(format!("{} may not perform {:?}", principal.name, operation))),
+            None => return Err(forbidden(format!("{} {} is not an authorized route", req.method(), pattern))),
+        }
+    }
+    Ok(principal)
+}
+
+/// The `/api` scope with the enabled endpoint groups behind [`authenticate`] and
+/// [`assign_request_id`].
+pub fn api_scope(
+    endpoints: &EndpointsConfig,
+) -> Scope<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = actix_web::Error,
+        InitError = (),
+    >,
+> {
+    let endpoints = endpoints.clone();
+    web::scope("/api")
+        .wrap(from_fn(authenticate))
+        .wrap(from_fn(assign_request_id))
+        .configure(move |cfg| configure_api(cfg, &endpoints))
+}
+
+/// Registers the `/api` routes of the enabled endpoint groups.
+pub fn configure_api(cfg: &mut web::ServiceConfig, endpoints: &EndpointsConfig) {
+    if endpoints.keys {
+        cfg.route("/keys/{eoa_address}", web::get().to(handlers::get_key_pair))
+            .route("/keys", web::get().to(handlers::list_key_pairs));
+    }
+    if endpoints.signing {
+        cfg.route("/scalar_mul", web::post().to(handlers::scalar_mul))
+            .route("/sign", web::post().to(handlers::sign))
+            .service(
+                web::resource("/sign/batch")
+                    .app_data(web::Data::new(handlers::MaxBatchSize(endpoints.max_batch_size)))
+                    .route(web::post().to(handlers::sign_batch)),
+            )
+            .route("/approvals", web::post().to(approvals::create_approval))
+            .route("/approvals", web::get().to(approvals::list_approvals))
+            .route("/approvals/{id}", web::get().to(approvals::get_approval))
+            .route("/approvals/{id}/approve", web::post().to(approvals::approve))
+            .route("/approvals/{id}/reject", web::post().to(approvals::reject));
+    }
+    if endpoints.verify {
+        cfg.route("/verify", web::post().to(handlers::verify));
+    }
+    if endpoints.lifecycle {
+        cfg.route("/keys/{eoa_address}/generate", web::post().to(lifecycle::generate_key))
+            .route("/keys/{eoa_address}/import", web::post().to(lifecycle::import_key))
+            .route("/keys/{eoa_address}/rotate", web::post().to(lifecycle::rotate_key))
+            .route("/keys/{eoa_address}/disable", web::post().to(lifecycle::disable_key))
+            .route("/keys/{eoa_address}/enable", web::post().to(lifecycle::enable_key))
+            .route("/keys/{eoa_address}", web::delete().to(lifecycle::delete_key));
+    }
+    if endpoints.web3signer {
+        cfg.route("/v1/eth2/publicKeys", web::get().to(web3signer::public_keys))
+            .route("/v1/eth2/sign/{identifier}", web::post().to(web3signer::sign));
+    }
+}
+
+/// Registers `GET /metrics` if the metrics endpoint is enabled.
+///
+/// The endpoint is outside the `/api` scope so that scrapers need no token.
+pub fn configure_metrics(cfg: &mut web::ServiceConfig, endpoints: &EndpointsConfig) {
+    if endpoints.metrics {
+        cfg.route("/metrics", web::get().to(metrics::metrics));
+    }
+}
+
+pub async fn start_server(config: Config) -> std::io::Result<()> {
+    let signer = match open_signer(&config) {
+        Ok(signer) => signer,
+        Err(e) => {
+            error!("Failed to initialize the signer: {:#}", e);
+            return Err(std::io::Error::other(e));
+        }
+    };
     // Initialize store
-    let store = match store::Store::new() {
+    let store = match open_store(&config, signer.as_ref()).await {
         Ok(store) => store,
         Err(e) => {
-            error!("Failed to initialize store: {}", e);
-            return Err(std::io::Error::new(std::io::ErrorKind::Other, e));
+            error!("Failed to initialize store: {:#}", e);
+            return Err(std::io::Error::other(e));
+        }
+    };
+    let store: web::Data<dyn KeyStore> = web::Data::from(store);
+    let signer: web::Data<dyn Signer> = web::Data::from(signer);
+    let authenticator = match open_authenticator(&config).await {
+        Ok(authenticator) => web::Data::new(authenticator),
+        Err(e) => {
+            error!("Failed to initialize authentication: {:#}", e);
+            return Err(std::io::Error::other(e));
+        }
+    };
+    let eoa_verifier = match open_eoa_verifier(&config).await {
+        Ok(verifier) => web::Data::new(verifier),
+        Err(e) => {
+            error!("Failed to initialize EOA authorization: {:#}", e);
+            return Err(std::io::Error::other(e));
+        }
+    };
+
+    let audit: web::Data<dyn AuditLog> = match open_audit_log(&config.audit.path).await {
+        Ok(audit) => web::Data::from(audit),
+        Err(e) => {
+            error!("Failed to open the audit log: {:#}", e);
+            return Err(std::io::Error::other(e));
         }
     };
-    let store = web::Data::new(store);
 
-    info!("Starting server at http://127.0.0.1:8080");
-    
-    HttpServer::new(move || {
+    let guard = match open_guard(&config).await {
+        Ok(guard) => web::Data::new(guard),
+        Err(e) => {
+            error!("Failed to open the signed task database: {:#}", e);
+            return Err(std::io::Error::other(e));
+        }
+    };
+
+    let approvals = web::Data::new(ApprovalQueue::from_config(&config.approvals));
+    let metrics = Arc::new(Metrics::new());
+    let policy = match PolicyEngine::from_config(&config, metrics.clone()) {
+        Ok(policy) => web::Data::new(policy),
+        Err(e) => {
+            error!("Failed to load the key policies: {:#}", e);
+            return Err(std::io::Error::other(e));
+        }
+    };
+    let metrics: web::Data<Metrics> = web::Data::from(metrics);
+
+    let client_principals = web::Data::new(ClientPrincipals(config.tls.clients.clone()));
+
+    let tls = if config.tls.enabled() {
+        match TlsReloader::new(&config.tls) {
+            Ok(tls) => Some(tls),
+            Err(e) => {
+                error!("Failed to load TLS material: {:#}", e);
+                return Err(std::io::Error::other(e));
+            }
+        }
+    } else {
+        None
+    };
+
+    if let Some(port) = config.grpc.port {
+        let checks = SigningChecks::new(eoa_verifier.clone(), policy.clone(), guard.clone(), signer.clone());
+        let service = GrpcService::new(
+            store.clone(),
+            checks,
+            audit.clone(),
+            authenticator.clone(),
+            client_principals.clone(),
+            config.endpoints.clone(),
+        );
+        let listener = tokio::net::TcpListener::bind((config.server.host.as_str(), port)).await?;
+        let grpc_tls = tls
+            .as_ref()
+            .map(|(_, reloader)| Arc::new(reloader.server_config(vec![b"h2".to_vec()])));
+        info!(
+            "Starting gRPC remote signer API at {}:{}{}",
+            config.server.host,
+            port,
+            if grpc_tls.is_some() { " with TLS" } else { "" }
+        );
+        tokio::spawn(async move {
+            if let Err(e) = service.serve(listener, grpc_tls).await {
+                error!("The gRPC server stopped: {:#}", e);
+            }
+        });
+    }
+
+    let address = (config.server.host.clone(), config.server.port);
+    let endpoints = config.endpoints.clone();
+    let mut server = HttpServer::new(move || {
         App::new()
             .app_data(store.clone())
-            .service(
-                web::scope("/api")
-                    .route("/keys/{eoa_address}", web::get().to(handlers::get_key_pair))
-                    .route("/keys", web::get().to(handlers::list_key_pairs))
-                    .route("/scalar_mul", web::post().to(handlers::scalar_mul))
-                    .route("/sign", web::post().to(handlers::sign))
-            )
+            .app_data(signer.clone())
+            .app_data(authenticator.clone())
+            .app_data(eoa_verifier.clone())
+            .app_data(audit.clone())
+            .app_data(policy.clone())
+            .app_data(guard.clone())
+            .app_data(approvals.clone())
+            .app_data(metrics.clone())
+            .app_data(client_principals.clone())
+            .service(api_scope(&endpoints))
+            .configure(|cfg| configure_metrics(cfg, &endpoints))
     })
-    .bind("127.0.0.1:8080")?
-    .run()
-    .await
-} 
\ No newline at end of file
+    .on_connect(tls::on_connect);
+    if let Some(workers) = config.server.workers {
+        server = server.workers(workers);
+    }
+
+    let Some((tls_config, reloader)) = tls else {
+        info!("Starting server at http://{}:{}", address.0, address.1);
+        return server.bind(address)?.run().await;
+    };
+    #[cfg(unix)]
+    tokio::spawn(tls::reload_on_sighup(Arc::new(reloader)));
+    #[cfg(not(unix))]
+    drop(reloader);
+    info!(
+        "Starting server at https://{}:{}{}",
+        address.0,
+        address.1,
+        if config.tls.client_ca.is_some() { " with client certificates" } else { "" }
+    );
+    server.bind_rustls_021(address, tls_config)?.run().await
+}
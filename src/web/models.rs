@@ -1,23 +1,175 @@
 use serde::{Deserialize, Serialize};
-use ark_bn254::{Fr, Fq};
-use ark_ff::One;
+use ark_bn254::{Fr, Fq, Fq2, G1Affine, G2Affine};
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::{One, Zero};
 use std::str::FromStr;
+use zeroize::{Zeroize, Zeroizing};
+use crate::hash::hash_g1_point_raw;
+use crate::web::envelope::{Envelope, EnvelopeCipher};
+use crate::web::keystore::Keystore;
+use crate::utils::parse_decimal_field;
+
+/// A BLS private key as a decimal string, either held in memory or still sealed
+/// in the [`Envelope`] it was stored in.
+///
+/// The type deliberately implements neither `Serialize` nor `Display`, and its
+/// `Debug` output is redacted, so the secret cannot end up in a response or a log
+/// line by accident. Code that needs the value calls [`SecretKey::expose_secret`],
+/// which opens a sealed key for that call only. The string is zeroized when the
+/// key is dropped.
+#[derive(Clone)]
+pub struct SecretKey(Secret);
+
+#[derive(Clone)]
+enum Secret {
+    Plain(String),
+    Sealed {
+        envelope: Envelope,
+        aad: Vec<u8>,
+        cipher: EnvelopeCipher,
+    },
+}
+
+impl SecretKey {
+    pub fn new(secret: String) -> Self {
+        Self(Secret::Plain(secret))
+    }
+
+    /// A key that stays sealed until it is exposed
+    ///
+    /// # Arguments
+    /// * `envelope` - The sealed decimal string
+    /// * `aad` - The associated data the envelope was sealed with
+    /// * `cipher` - The cipher holding the KEK that opens the envelope
+    pub fn sealed(envelope: Envelope, aad: Vec<u8>, cipher: EnvelopeCipher) -> Self {
+        Self(Secret::Sealed { envelope, aad, cipher })
+    }
+
+    /// Whether the key is still sealed in its envelope
+    pub fn is_sealed(&self) -> bool {
+        matches!(self.0, Secret::Sealed { .. })
+    }
+
+    /// Returns the secret as a decimal string, opening a sealed key first
+    pub fn expose_secret(&self) -> Result<Zeroizing<String>, String> {
+        match &self.0 {
+            Secret::Plain(secret) => Ok(Zeroizing::new(secret.clone())),
+            Secret::Sealed { envelope, aad, cipher } => {
+                let plaintext = cipher
+                    .open(aad, envelope)
+                    .map_err(|e| format!("Failed to decrypt the private key: {:#}", e))?;
+                let secret = std::str::from_utf8(&plaintext).map_err(|_| "Malformed private key".to_string())?;
+                Ok(Zeroizing::new(secret.to_string()))
+            }
+        }
+    }
+}
+
+/// Keys compare by their secret; a sealed key that cannot be opened equals no key
+impl PartialEq for SecretKey {
+    fn eq(&self, other: &Self) -> bool {
+        match (self.expose_secret(), other.expose_secret()) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SecretKey(<redacted>)")
+    }
+}
+
+impl Drop for SecretKey {
+    fn drop(&mut self) {
+        if let Secret::Plain(secret) = &mut self.0 {
+            secret.zeroize();
+        }
+    }
+}
 
 /// Represents a key pair in the database
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone)]
 pub struct KeyPair {
     pub eoa_address: String,
-    pub private_key: String,
+    pub private_key: SecretKey,
     pub public_key_g1: G1Point,
     pub public_key_g2: G2Point,
 }
 
+/// Whether a key pair may be used to sign
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyStatus {
+    #[default]
+    Active,
+    /// The key pair is kept but every signing request is refused
+    Disabled,
+}
+
+/// The public keys of a key pair replaced by a rotation. Its private key is not
+/// kept, so it can no longer sign.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetiredKey {
+    pub key_id: String,
+    pub public_key_g1: G1Point,
+    pub public_key_g2: G2Point,
+    /// Seconds since the Unix epoch
+    pub retired_at: i64,
+}
+
+/// Metadata kept by the key store alongside a key pair
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyMetadata {
+    pub labels: Vec<String>,
+    /// Seconds since the Unix epoch, if the store records it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<i64>,
+    #[serde(default)]
+    pub status: KeyStatus,
+    /// Keys replaced by rotations, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub retired: Vec<RetiredKey>,
+}
+
+/// The public view of a key pair returned by the API; it holds no secret material
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublicKeyView {
+    /// `keccak256(G1.x, G1.y)`, the operator id used by the EigenLayer registries
+    pub key_id: String,
+    pub eoa_address: String,
+    pub public_key_g1: G1Point,
+    pub public_key_g2: G2Point,
+    pub metadata: KeyMetadata,
+}
+
+/// A private key to import, as a raw scalar or an EIP-2335 keystore.
+///
+/// Debug is deliberately not derived, and the secret fields are zeroized when the
+/// request is dropped.
+#[derive(Default, Serialize, Deserialize)]
+pub struct KeyImportRequest {
+    /// Decimal scalar, or 32-byte big-endian hex with a `0x` prefix
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub private_key: Option<Zeroizing<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keystore: Option<Keystore>,
+    /// Password of the keystore
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<Zeroizing<String>>,
+}
+
 /// Request for scalar multiplication
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ScalarMulRequest {
     pub eoa_address: String,
     pub hash_x: String,
     pub hash_y: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authorization: Option<EoaAuthorization>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<SigningContext>,
 }
 
 /// Response for scalar multiplication
@@ -28,22 +180,92 @@ pub struct ScalarMulResponse {
     pub signature: G1Point,
 }
 
-/// Request for signing a G1 point with an EOA's private key
+/// A message given in exactly one of the supported forms
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MessageInput {
+    /// A G1 point `{ "x", "y" }`, which must be on the curve
+    pub message_point: Option<G1Point>,
+    /// 32-byte hex hash, mapped to G1 with the contract's `hashToG1`
+    pub message_hash: Option<String>,
+    /// 32-byte hex G1 point in the gnark compressed format
+    pub message_compressed: Option<String>,
+    /// Data from a registered message source, hashed and mapped with `hashToG1`
+    pub message_source: Option<SourcedMessage>,
+}
+
+/// Data from a registered message source, hashed as `keccak256(domain || data)`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourcedMessage {
+    /// Name of the source
+    pub source: String,
+    /// Hex data
+    pub data: String,
+}
+
+/// How the signed message was given
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageMode {
+    Point,
+    Hash,
+    Compressed,
+    Source,
+}
+
+/// Request for signing a message with an EOA's private key
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SignRequest {
     pub eoa_address: String,
-    pub point: String,
+    #[serde(flatten)]
+    pub message: MessageInput,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authorization: Option<EoaAuthorization>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<SigningContext>,
 }
 
-/// G1 point coordinates
+/// The AVS task a signature responds to; only one message is signed per key and task
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SigningContext {
+    /// Identifier of the AVS, usually its service manager address
+    pub avs: String,
+    pub task_index: u64,
+}
+
+/// How an EOA authorization is signed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthScheme {
+    /// `personal_sign` over the 32-byte authorization digest
+    Eip191,
+    /// `eth_signTypedData_v4` over a `SignRequest` struct
+    Eip712,
+}
+
+/// An ECDSA signature by the operator's EOA authorizing a single signing request
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EoaAuthorization {
+    pub scheme: AuthScheme,
+    /// Single-use number chosen by the operator
+    pub nonce: u64,
+    /// Seconds since the Unix epoch after which the authorization is rejected
+    pub expiry: u64,
+    /// 65-byte hex `r || s || v` signature
+    pub signature: String,
+}
+
+/// G1 point coordinates
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct G1Point {
     pub x: String,
     pub y: String,
 }
 
-/// G2 point coordinates
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// G2 point coordinates.
+///
+/// Each Fq2 coordinate follows the Solidity convention used by `players.json` and
+/// the contracts: `x_a`/`y_a` hold the imaginary part and `x_b`/`y_b` the real part.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct G2Point {
     pub x_a: String,
     pub x_b: String,
@@ -51,11 +273,186 @@ pub struct G2Point {
     pub y_b: String,
 }
 
-/// Response for signing a G1 point
-#[derive(Debug, Serialize, Deserialize)]
+/// Response for signing a message
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SignResponse {
+    /// How the message was given in the request
+    pub mode: MessageMode,
+    /// The G1 point that was signed
+    pub message: G1Point,
+    /// The signature, `sk * message`
     pub product: G1Point,
     pub signer_g1: G1Point,
+    pub signer_g2: G2Point,
+}
+
+/// Request for signing several messages at once
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchSignRequest {
+    /// Each item is validated like a `POST /api/sign` request
+    pub items: Vec<SignRequest>,
+}
+
+/// Response for signing several messages, with one result per item in request order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchSignResponse {
+    pub results: Vec<BatchSignResult>,
+}
+
+/// The outcome of one item of a batch: either its signature or its error
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchSignResult {
+    /// Position of the item in the request
+    pub index: usize,
+    /// The HTTP status the item would have had as a single `POST /api/sign`
+    pub status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<SignResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorResponse>,
+}
+
+/// Kind of message signed with `POST /api/v1/eth2/sign/{identifier}`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Web3SignerType {
+    /// A 32-byte `signingRoot`, mapped to G1 with `hashToG1`
+    Hash,
+    /// A G1 point `message`
+    G1Point,
+    /// Data from a registered message source
+    Source,
+}
+
+/// Body of `POST /api/v1/eth2/sign/{identifier}`, shaped like a Web3Signer
+/// signing request; the field the `type` needs must be given, and no other
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Web3SignerRequest {
+    #[serde(rename = "type")]
+    pub kind: Web3SignerType,
+    /// 32-byte hex hash
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signing_root: Option<String>,
+    /// Hex G1 point, 64 bytes uncompressed or 32 bytes in the gnark compressed format
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_source: Option<SourcedMessage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authorization: Option<EoaAuthorization>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<SigningContext>,
+}
+
+/// JSON response of `POST /api/v1/eth2/sign/{identifier}`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Web3SignerResponse {
+    /// Hex of the signature as a 64-byte uncompressed G1 point
+    pub signature: String,
+}
+
+/// Request for a signature that is only released once enough approvers approve it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApprovalRequest {
+    #[serde(flatten)]
+    pub request: SignRequest,
+    /// Why the signature is needed, shown to the approvers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// Where an approval request stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApprovalStatus {
+    /// Waiting for approvals
+    Pending,
+    /// Approved and signed
+    Approved,
+    /// Rejected by an approver
+    Rejected,
+    /// Not decided in time
+    Expired,
+    /// Approved, but refused when it was signed
+    Failed,
+}
+
+/// An approval given to a request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApprovalVote {
+    pub approver: String,
+    /// Seconds since the Unix epoch
+    pub approved_at: i64,
+}
+
+/// An approval request as shown to its requester and approvers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalView {
+    pub id: String,
+    pub eoa_address: String,
+    /// The principal that requested the signature
+    pub requester: String,
+    pub description: Option<String>,
+    pub mode: MessageMode,
+    /// The G1 point to be signed
+    pub message: G1Point,
+    pub context: Option<SigningContext>,
+    pub status: ApprovalStatus,
+    /// Number of approvals needed
+    pub threshold: usize,
+    pub approvals: Vec<ApprovalVote>,
+    pub rejected_by: Option<String>,
+    /// Seconds since the Unix epoch
+    pub created_at: i64,
+    /// Seconds since the Unix epoch at which a pending request expires
+    pub expires_at: i64,
+    /// Why an approved request could not be signed
+    pub error: Option<ErrorResponse>,
+    /// The signature, shown to the requester once approved
+    pub signature: Option<SignResponse>,
+}
+
+/// Structured error returned for rejected requests
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    /// A stable, machine readable error code such as `invalid_point`
+    pub error: String,
+    /// The request field the error refers to, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    pub message: String,
+}
+
+/// Request for verifying a single or aggregate BLS signature.
+///
+/// The message is given as a [`MessageInput`]. For an aggregate check, every signature and public key is summed
+/// and all signers are expected to have signed the same message.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct VerifyRequest {
+    #[serde(flatten)]
+    pub message: MessageInput,
+    pub signature: Option<G1Point>,
+    pub signatures: Option<Vec<G1Point>>,
+    pub public_key_g2: Option<G2Point>,
+    pub public_keys_g2: Option<Vec<G2Point>>,
+    /// Look up the G2 public key of an EOA in the store
+    pub eoa_address: Option<String>,
+    pub eoa_addresses: Option<Vec<String>>,
+}
+
+/// Response for a signature verification
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VerifyResponse {
+    pub valid: bool,
+    /// `single` or `aggregate`
+    pub mode: String,
+    /// Number of public keys the signature was checked against
+    pub signers: usize,
+    /// How the message was given in the request
+    pub message_mode: MessageMode,
+    /// The message point the signature was checked against
+    pub message: G1Point,
 }
 
 impl G1Point {
@@ -66,22 +463,140 @@ impl G1Point {
     }
 }
 
+impl From<&crate::g1::G1Point> for G1Point {
+    fn from(p: &crate::g1::G1Point) -> Self {
+        let p = p.inner().into_affine();
+        Self {
+            x: p.x.to_string(),
+            y: p.y.to_string(),
+        }
+    }
+}
+
+impl From<&crate::g2::G2Point> for G2Point {
+    fn from(p: &crate::g2::G2Point) -> Self {
+        let p = p.inner().into_affine();
+        Self {
+            x_a: p.x.c1.to_string(),
+            x_b: p.x.c0.to_string(),
+            y_a: p.y.c1.to_string(),
+            y_b: p.y.c0.to_string(),
+        }
+    }
+}
+
+impl G1Point {
+    /// Parses the coordinates into a G1 point, rejecting non-canonical coordinates,
+    /// points that are not on the curve and the point at infinity.
+    pub fn to_checked_g1_point(&self) -> Result<crate::g1::G1Point, String> {
+        let x = parse_decimal_field::<Fq>(&self.x).map_err(|e| format!("x coordinate: {}", e))?;
+        let y = parse_decimal_field::<Fq>(&self.y).map_err(|e| format!("y coordinate: {}", e))?;
+        let p = G1Affine::new_unchecked(x, y);
+        if !p.is_on_curve() {
+            return Err("point is not on the curve".to_string());
+        }
+        if p.is_zero() {
+            return Err("point is the point at infinity".to_string());
+        }
+        Ok(crate::g1::G1Point(p.into()))
+    }
+}
+
 impl G2Point {
+    /// Parses the coordinates into a G2 point, rejecting non-canonical coordinates,
+    /// points that are not on the curve or not in the prime order subgroup, and the
+    /// point at infinity.
+    pub fn to_checked_g2_point(&self) -> Result<crate::g2::G2Point, String> {
+        let coordinate = |s: &str, name: &str| {
+            parse_decimal_field::<Fq>(s).map_err(|e| format!("{} coordinate: {}", name, e))
+        };
+        let p = G2Affine::new_unchecked(
+            Fq2::new(coordinate(&self.x_b, "x_b")?, coordinate(&self.x_a, "x_a")?),
+            Fq2::new(coordinate(&self.y_b, "y_b")?, coordinate(&self.y_a, "y_a")?),
+        );
+        if !p.is_on_curve() {
+            return Err("point is not on the curve".to_string());
+        }
+        if !p.is_in_correct_subgroup_assuming_on_curve() {
+            return Err("point is not in the prime order subgroup".to_string());
+        }
+        if p.is_zero() {
+            return Err("point is the point at infinity".to_string());
+        }
+        Ok(crate::g2::G2Point(p.into()))
+    }
+
     pub fn to_g2_point(&self) -> Result<ark_bn254::G2Projective, String> {
         let x_a = Fq::from_str(&self.x_a).map_err(|_| "Failed to parse x_a coordinate".to_string())?;
         let x_b = Fq::from_str(&self.x_b).map_err(|_| "Failed to parse x_b coordinate".to_string())?;
         let y_a = Fq::from_str(&self.y_a).map_err(|_| "Failed to parse y_a coordinate".to_string())?;
         let y_b = Fq::from_str(&self.y_b).map_err(|_| "Failed to parse y_b coordinate".to_string())?;
+        // Solidity gives [imaginary, real], Arkworks expects (real, imaginary)
         Ok(ark_bn254::G2Projective::new_unchecked(
-            ark_bn254::Fq2::new(x_a, x_b),
-            ark_bn254::Fq2::new(y_a, y_b),
+            ark_bn254::Fq2::new(x_b, x_a),
+            ark_bn254::Fq2::new(y_b, y_a),
             ark_bn254::Fq2::one(),
         ))
     }
 }
 
 impl KeyPair {
+    /// Builds the key pair of a private key, computing its G1 and G2 public keys.
+    ///
+    /// # Arguments
+    /// * `eoa_address` - The EOA the key pair belongs to
+    /// * `private_key` - The BLS private key, which must not be zero
+    pub fn from_private_key(eoa_address: &str, private_key: Fr) -> Result<Self, String> {
+        if private_key.is_zero() {
+            return Err("private key must not be zero".to_string());
+        }
+        Ok(Self {
+            eoa_address: eoa_address.to_string(),
+            private_key: SecretKey::new(private_key.to_string()),
+            public_key_g1: G1Point::from(&crate::g1::G1Point::generator().scalar_mul(private_key)),
+            public_key_g2: G2Point::from(&crate::g2::G2Point::generator().scalar_mul(private_key)),
+        })
+    }
+
+    /// Generates a key pair with a random private key
+    pub fn generate<R: rand::Rng>(eoa_address: &str, rng: &mut R) -> Self {
+        loop {
+            let private_key = <Fr as ark_ff::UniformRand>::rand(rng);
+            if let Ok(key_pair) = Self::from_private_key(eoa_address, private_key) {
+                return key_pair;
+            }
+        }
+    }
+
+    /// The public keys of this key pair, as kept after a rotation replaces it
+    pub fn to_retired(&self, retired_at: i64) -> Result<RetiredKey, String> {
+        Ok(RetiredKey {
+            key_id: self.key_id()?,
+            public_key_g1: self.public_key_g1.clone(),
+            public_key_g2: self.public_key_g2.clone(),
+            retired_at,
+        })
+    }
+
     pub fn to_private_key(&self) -> Result<Fr, String> {
-        Fr::from_str(&self.private_key).map_err(|_| "Failed to parse private key".to_string())
+        let secret = self.private_key.expose_secret()?;
+        Fr::from_str(&secret).map_err(|_| "Failed to parse private key".to_string())
+    }
+
+    /// The key id, `keccak256(G1.x, G1.y)` as 0x-prefixed hex
+    pub fn key_id(&self) -> Result<String, String> {
+        let g1 = self.public_key_g1.to_g1_point()?;
+        Ok(format!("0x{}", hex::encode(hash_g1_point_raw(&g1))))
+    }
+
+    /// The public view of this key pair
+    pub fn to_public_view(&self, metadata: KeyMetadata) -> Result<PublicKeyView, String> {
+        Ok(PublicKeyView {
+            key_id: self.key_id()?,
+            eoa_address: self.eoa_address.clone(),
+            public_key_g1: self.public_key_g1.clone(),
+            public_key_g2: self.public_key_g2.clone(),
+            metadata,
+        })
     }
 } 
\ No newline at end of file
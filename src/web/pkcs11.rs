@@ -0,0 +1,173 @@
+//! A PKCS#11 token holding the AES key that wraps BN254 private keys.
+//!
+//! Tokens cannot compute on BN254, so the token never sees a private key. Each
+//! private key is sealed in an [`Envelope`](crate::web::envelope::Envelope) whose
+//! data key is encrypted on the token with AES-256-GCM under a sensitive,
+//! non-extractable key. A [`WrappingSigner`](crate::web::signer::WrappingSigner)
+//! unwraps a private key into zeroized memory for a single operation.
+//!
+//! Any PKCS#11 module works; SoftHSM is enough on a plain Linux box:
+//!
+//! ```bash
+//! softhsm2-util --init-token --free --label bn254 --pin 1234 --so-pin 123456
+//! BN254_PKCS11_PIN=1234 bn254-key-service pkcs11 generate-key
+//! ```
+
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Context, Result};
+use cryptoki::context::{CInitializeArgs, CInitializeFlags, Pkcs11};
+use cryptoki::error::{Error, RvError};
+use cryptoki::mechanism::aead::GcmParams;
+use cryptoki::mechanism::Mechanism;
+use cryptoki::object::{Attribute, KeyType, ObjectClass, ObjectHandle};
+use cryptoki::session::{Session, UserType};
+use cryptoki::types::AuthPin;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use zeroize::Zeroizing;
+
+use crate::web::envelope::KekProvider;
+
+/// Length in bytes of the AES-GCM IV prepended to each wrapped key
+const IV_LEN: usize = 12;
+
+/// A KEK kept on a PKCS#11 token. Data keys are wrapped and unwrapped by the
+/// token, so the KEK itself never enters process memory.
+pub struct Pkcs11Kek {
+    session: Mutex<Session>,
+    key: ObjectHandle,
+}
+
+impl Pkcs11Kek {
+    /// Logs in to a token and finds its AES key
+    ///
+    /// # Arguments
+    /// * `module` - The PKCS#11 module, e.g. `/usr/lib/softhsm/libsofthsm2.so`
+    /// * `token_label` - The label of the token
+    /// * `pin` - The user PIN of the token
+    /// * `key_label` - The label of the AES key
+    pub fn open<P: AsRef<Path>>(module: P, token_label: &str, pin: &str, key_label: &str) -> Result<Self> {
+        let session = login(module.as_ref(), token_label, pin)?;
+        let key = find_key(&session, key_label)?
+            .ok_or_else(|| anyhow!("Token {} has no AES key labelled {}", token_label, key_label))?;
+        Ok(Self {
+            session: Mutex::new(session),
+            key,
+        })
+    }
+
+    /// Generates the AES-256 key of a token, which must not have one yet.
+    /// The key is a sensitive, non-extractable token object that may only
+    /// encrypt and decrypt.
+    ///
+    /// # Arguments
+    /// * `module` - The PKCS#11 module
+    /// * `token_label` - The label of the token
+    /// * `pin` - The user PIN of the token
+    /// * `key_label` - The label of the new key
+    pub fn generate_key<P: AsRef<Path>>(module: P, token_label: &str, pin: &str, key_label: &str) -> Result<Self> {
+        let session = login(module.as_ref(), token_label, pin)?;
+        if find_key(&session, key_label)?.is_some() {
+            return Err(anyhow!("Token {} already has a key labelled {}", token_label, key_label));
+        }
+        let template = [
+            Attribute::Token(true),
+            Attribute::Private(true),
+            Attribute::Sensitive(true),
+            Attribute::Extractable(false),
+            Attribute::Encrypt(true),
+            Attribute::Decrypt(true),
+            Attribute::ValueLen(32.into()),
+            Attribute::Label(key_label.as_bytes().to_vec()),
+        ];
+        let key = session
+            .generate_key(&Mechanism::AesKeyGen, &template)
+            .context("Failed to generate the AES key on the token")?;
+        Ok(Self {
+            session: Mutex::new(session),
+            key,
+        })
+    }
+}
+
+impl KekProvider for Pkcs11Kek {
+    fn name(&self) -> &str {
+        "pkcs11"
+    }
+
+    fn wrap_key(&self, aad: &[u8], data_key: &[u8; 32]) -> Result<Vec<u8>> {
+        let mut iv = [0u8; IV_LEN];
+        OsRng.fill_bytes(&mut iv);
+        let mut blob = iv.to_vec();
+        let params = GcmParams::new(&mut iv, aad, 128.into())?;
+        let wrapped = self
+            .session
+            .lock()
+            .unwrap()
+            .encrypt(&Mechanism::AesGcm(params), self.key, data_key)
+            .context("Failed to wrap data key on the token")?;
+        blob.extend(wrapped);
+        Ok(blob)
+    }
+
+    fn unwrap_key(&self, aad: &[u8], wrapped: &[u8]) -> Result<Zeroizing<[u8; 32]>> {
+        if wrapped.len() < IV_LEN {
+            return Err(anyhow!("Wrapped data key is truncated"));
+        }
+        let (iv, wrapped) = wrapped.split_at(IV_LEN);
+        let mut iv: [u8; IV_LEN] = iv.try_into()?;
+        let params = GcmParams::new(&mut iv, aad, 128.into())?;
+        let data_key = Zeroizing::new(
+            self.session
+                .lock()
+                .unwrap()
+                .decrypt(&Mechanism::AesGcm(params), self.key, wrapped)
+                .map_err(|_| anyhow!("Failed to unwrap data key; was it wrapped by another KEK?"))?,
+        );
+        let mut key = Zeroizing::new([0u8; 32]);
+        if data_key.len() != key.len() {
+            return Err(anyhow!("Wrapped data key must be 32 bytes"));
+        }
+        key.copy_from_slice(&data_key);
+        Ok(key)
+    }
+}
+
+/// Opens a read-write session on a token and logs in as its user
+fn login(module: &Path, token_label: &str, pin: &str) -> Result<Session> {
+    let pkcs11 = Pkcs11::new(module).with_context(|| format!("Failed to load PKCS#11 module {}", module.display()))?;
+    // Every context of a process shares the module, which may only be initialized once
+    match pkcs11.initialize(CInitializeArgs::new(CInitializeFlags::OS_LOCKING_OK)) {
+        Ok(()) | Err(Error::Pkcs11(RvError::CryptokiAlreadyInitialized, _)) => {}
+        Err(e) => return Err(e).context("Failed to initialize the PKCS#11 module"),
+    }
+    let mut slot = None;
+    for candidate in pkcs11.get_slots_with_token()? {
+        if pkcs11.get_token_info(candidate)?.label() == token_label {
+            slot = Some(candidate);
+            break;
+        }
+    }
+    let slot = slot.ok_or_else(|| anyhow!("No PKCS#11 token labelled {}", token_label))?;
+    let session = pkcs11.open_rw_session(slot)?;
+    match session.login(UserType::User, Some(&AuthPin::from(pin))) {
+        Ok(()) | Err(Error::Pkcs11(RvError::UserAlreadyLoggedIn, _)) => {}
+        Err(e) => return Err(e).with_context(|| format!("Failed to log in to token {}", token_label)),
+    }
+    Ok(session)
+}
+
+fn find_key(session: &Session, key_label: &str) -> Result<Option<ObjectHandle>> {
+    let keys = session.find_objects(&[
+        Attribute::Class(ObjectClass::SECRET_KEY),
+        Attribute::KeyType(KeyType::AES),
+        Attribute::Label(key_label.as_bytes().to_vec()),
+    ])?;
+    match keys.as_slice() {
+        [] => Ok(None),
+        [key] => Ok(Some(*key)),
+        _ => Err(anyhow!("Several AES keys are labelled {}", key_label)),
+    }
+}
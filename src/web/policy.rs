@@ -0,0 +1,254 @@
+//! Per-key signing policies.
+//!
+//! A [`KeyPolicy`] is evaluated before every scalar multiplication with the key
+//! it names. The `*` policy applies to keys without a policy of their own, and
+//! keys without either are unrestricted. The rules are checked in this order:
+//!
+//! 1. `expires_at`: the key may no longer sign
+//! 2. `principals`: the caller may not use the key
+//! 3. `time_windows`: the current UTC time is outside every window
+//! 4. `message_sources`: the message was not derived through a listed source
+//! 5. `approval_required`: the request was not approved through `/api/approvals`
+//! 6. `max_per_hour` and `max_per_day`: the key has signed too often
+//!
+//! A request that passes takes one slot of the rate limits, which are counted in
+//! memory and so start afresh when the service restarts. Every denial is counted
+//! in the `bn254_policy_denials_total` metric.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::http::StatusCode;
+use anyhow::{anyhow, Result};
+use sha3::{Digest, Keccak256};
+
+use crate::web::auth::Principal;
+use crate::web::config::{Config, KeyPolicy, MessageSource};
+use crate::web::error::ApiError;
+use crate::web::metrics::Metrics;
+
+const HOUR: i64 = 60 * 60;
+const DAY: i64 = 24 * HOUR;
+
+/// The registered message sources, by name
+#[derive(Debug, Clone, Default)]
+pub struct MessageSources(HashMap<String, [u8; 32]>);
+
+impl MessageSources {
+    /// Parses the domain tags of the configured sources.
+    pub fn new(sources: &[MessageSource]) -> Result<Self> {
+        let mut domains = HashMap::new();
+        for source in sources {
+            let domain = hex::decode(source.domain.trim_start_matches("0x"))
+                .ok()
+                .and_then(|domain| <[u8; 32]>::try_from(domain).ok())
+                .ok_or_else(|| anyhow!("The domain of message source {} must be 32 bytes of hex", source.name))?;
+            domains.insert(source.name.clone(), domain);
+        }
+        Ok(Self(domains))
+    }
+
+    /// Computes `keccak256(domain || data)` for data from a registered source.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the source
+    /// * `data` - Hex data, with or without a `0x` prefix
+    ///
+    /// # Returns
+    /// The 32-byte message hash, or a 400 error for an unknown source or malformed data
+    pub fn message_hash(&self, name: &str, data: &str) -> Result<[u8; 32], ApiError> {
+        let domain = self.0.get(name).ok_or_else(|| {
+            ApiError::bad_request("unknown_message_source", "message_source.source", format!("no message source named {}", name))
+        })?;
+        let data = hex::decode(data.trim_start_matches("0x"))
+            .map_err(|_| ApiError::bad_request("invalid_data", "message_source.data", "data must be hex"))?;
+        let mut hasher = Keccak256::new();
+        hasher.update(domain);
+        hasher.update(&data);
+        Ok(hasher.finalize().into())
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Evaluates the key policies and counts the signatures of each key.
+pub struct PolicyEngine {
+    /// Policies by lowercase EOA address, or `*`
+    policies: HashMap<String, KeyPolicy>,
+    sources: MessageSources,
+    /// Times of the signatures of each key within the last day
+    usage: Mutex<HashMap<String, VecDeque<i64>>>,
+    metrics: Arc<Metrics>,
+}
+
+impl Default for PolicyEngine {
+    /// An engine without policies or message sources
+    fn default() -> Self {
+        Self {
+            policies: HashMap::new(),
+            sources: MessageSources::default(),
+            usage: Mutex::new(HashMap::new()),
+            metrics: Arc::new(Metrics::new()),
+        }
+    }
+}
+
+impl PolicyEngine {
+    pub fn new(policies: &[KeyPolicy], sources: &[MessageSource], metrics: Arc<Metrics>) -> Result<Self> {
+        Ok(Self {
+            policies: policies
+                .iter()
+                .map(|policy| (policy.eoa_address.to_lowercase(), policy.clone()))
+                .collect(),
+            sources: MessageSources::new(sources)?,
+            usage: Mutex::new(HashMap::new()),
+            metrics,
+        })
+    }
+
+    /// The engine for the policies and message sources of the configuration
+    pub fn from_config(config: &Config, metrics: Arc<Metrics>) -> Result<Self> {
+        Self::new(&config.policies, &config.message_sources, metrics)
+    }
+
+    pub fn sources(&self) -> &MessageSources {
+        &self.sources
+    }
+
+    /// The policy that applies to a key, if any
+    pub fn policy_for(&self, eoa_address: &str) -> Option<&KeyPolicy> {
+        self.policies
+            .get(&eoa_address.to_lowercase())
+            .or_else(|| self.policies.get("*"))
+    }
+
+    /// Checks a signing request against the policy of its key, see [`PolicyEngine::check_at`].
+    pub fn check(&self, principal: &Principal, eoa_address: &str, source: Option<&str>) -> Result<(), ApiError> {
+        self.check_at(principal, eoa_address, source, now())
+    }
+
+    /// Checks a request released by its approvers, which satisfies `approval_required`
+    /// but every other rule still applies to.
+    pub fn check_approved(&self, principal: &Principal, eoa_address: &str, source: Option<&str>) -> Result<(), ApiError> {
+        self.evaluate(principal, eoa_address, source, now(), true)
+    }
+
+    /// Checks a signing request against the policy of its key and, if it passes,
+    /// counts it against the key's rate limits.
+    ///
+    /// # Arguments
+    /// * `principal` - The caller
+    /// * `eoa_address` - The key to sign with
+    /// * `source` - The message source the message was derived through, if any
+    /// * `now` - The current Unix time
+    ///
+    /// # Returns
+    /// `Ok(())`, or a 403 error (429 for rate limits) naming the violated rule
+    pub fn check_at(
+        &self,
+        principal: &Principal,
+        eoa_address: &str,
+        source: Option<&str>,
+        now: i64,
+    ) -> Result<(), ApiError> {
+        self.evaluate(principal, eoa_address, source, now, false)
+    }
+
+    fn evaluate(
+        &self,
+        principal: &Principal,
+        eoa_address: &str,
+        source: Option<&str>,
+        now: i64,
+        approved: bool,
+    ) -> Result<(), ApiError> {
+        let Some(policy) = self.policy_for(eoa_address) else {
+            self.metrics.policy_allowed(eoa_address);
+            return Ok(());
+        };
+        let deny = |rule: &str, status: StatusCode, error: &str, field: &str, message: String| {
+            self.metrics.policy_denied(eoa_address, rule);
+            Err(ApiError::new(status, error, Some(field), message))
+        };
+
+        if policy.expires_at.is_some_and(|expires_at| now >= expires_at) {
+            return deny(
+                "expires_at",
+                StatusCode::FORBIDDEN,
+                "policy_key_expired",
+                "eoa_address",
+                format!("the key of {} may no longer sign", eoa_address),
+            );
+        }
+        if let Some(principals) = &policy.principals {
+            if !principals.contains(&principal.name) {
+                return deny(
+                    "principals",
+                    StatusCode::FORBIDDEN,
+                    "policy_principal_denied",
+                    "eoa_address",
+                    format!("{} may not use the key of {}", principal.name, eoa_address),
+                );
+            }
+        }
+        if !policy.time_windows.is_empty() && !policy.time_windows.iter().any(|window| window.contains(now)) {
+            return deny(
+                "time_windows",
+                StatusCode::FORBIDDEN,
+                "policy_outside_time_window",
+                "eoa_address",
+                format!("the key of {} may not sign at this time", eoa_address),
+            );
+        }
+        if let Some(sources) = &policy.message_sources {
+            if !source.is_some_and(|source| sources.iter().any(|s| s == source)) {
+                return deny(
+                    "message_sources",
+                    StatusCode::FORBIDDEN,
+                    "policy_message_source_required",
+                    "message_source",
+                    format!("the key of {} only signs messages from {}", eoa_address, sources.join(", ")),
+                );
+            }
+        }
+        if policy.approval_required && !approved {
+            return deny(
+                "approval_required",
+                StatusCode::FORBIDDEN,
+                "policy_approval_required",
+                "eoa_address",
+                format!("signatures with the key of {} must be approved through /api/approvals", eoa_address),
+            );
+        }
+
+        let mut usage = self.usage.lock().unwrap();
+        let times = usage.entry(eoa_address.to_lowercase()).or_default();
+        while times.front().is_some_and(|t| *t <= now - DAY) {
+            times.pop_front();
+        }
+        let last_hour = times.iter().filter(|t| **t > now - HOUR).count();
+        for (rule, limit, used, period) in [
+            ("max_per_hour", policy.max_per_hour, last_hour, "hour"),
+            ("max_per_day", policy.max_per_day, times.len(), "day"),
+        ] {
+            if limit.is_some_and(|limit| used >= limit as usize) {
+                return deny(
+                    rule,
+                    StatusCode::TOO_MANY_REQUESTS,
+                    "policy_rate_limited",
+                    "eoa_address",
+                    format!("the key of {} has reached its limit of {} signatures per {}", eoa_address, limit.unwrap_or_default(), period),
+                );
+            }
+        }
+        times.push_back(now);
+        self.metrics.policy_allowed(eoa_address);
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+//! Private key operations of the signing endpoints.
+//!
+//! The key store keeps each private key in the form its [`Signer`] expects: a
+//! decimal scalar for the [`LocalSigner`], or an [`Envelope`] sealed under a key
+//! encryption key for the [`WrappingSigner`]. Key pairs are passed through
+//! [`Signer::seal`] before they are stored, so the two forms are never mixed.
+
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use ark_bn254::Fr;
+use zeroize::Zeroize;
+
+use crate::web::envelope::{Envelope, EnvelopeCipher, KekProvider};
+use crate::web::models::{KeyPair, SecretKey};
+
+/// Performs the operations that need a private key
+pub trait Signer: Send + Sync {
+    /// Converts a key pair with a plaintext private key to the form kept in the
+    /// key store
+    fn seal(&self, key_pair: KeyPair) -> Result<KeyPair>;
+
+    /// Multiplies `point` by the private key of a key pair read from the key store
+    fn scalar_mul(&self, key_pair: &KeyPair, point: &crate::g1::G1Point) -> Result<crate::g1::G1Point>;
+}
+
+/// Uses the plaintext private keys of the key store
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LocalSigner;
+
+impl Signer for LocalSigner {
+    fn seal(&self, key_pair: KeyPair) -> Result<KeyPair> {
+        Ok(key_pair)
+    }
+
+    fn scalar_mul(&self, key_pair: &KeyPair, point: &crate::g1::G1Point) -> Result<crate::g1::G1Point> {
+        let secret = key_pair.private_key.expose_secret().map_err(anyhow::Error::msg)?;
+        if is_sealed(&secret) {
+            return Err(anyhow!(
+                "The private key of {} is wrapped; it needs the pkcs11 signer",
+                key_pair.eoa_address
+            ));
+        }
+        let mut private_key = Fr::from_str(&secret).map_err(|_| anyhow!("Failed to parse private key"))?;
+        let product = point.scalar_mul(private_key);
+        private_key.zeroize();
+        Ok(product)
+    }
+}
+
+/// Keeps private keys sealed in [`Envelope`]s whose data keys are wrapped by a
+/// [`KekProvider`], typically a [`Pkcs11Kek`](crate::web::pkcs11::Pkcs11Kek).
+///
+/// A private key is only unwrapped for the operation that needs it, and every
+/// copy of it is zeroized before the operation returns.
+pub struct WrappingSigner {
+    envelope: EnvelopeCipher,
+}
+
+impl WrappingSigner {
+    pub fn new(kek: Arc<dyn KekProvider>) -> Self {
+        Self {
+            envelope: EnvelopeCipher::new(kek),
+        }
+    }
+}
+
+impl Signer for WrappingSigner {
+    fn seal(&self, key_pair: KeyPair) -> Result<KeyPair> {
+        let secret = key_pair.private_key.expose_secret().map_err(anyhow::Error::msg)?;
+        if is_sealed(&secret) {
+            return Ok(key_pair);
+        }
+        let sealed = self.envelope.seal(key_pair.eoa_address.as_bytes(), secret.as_bytes())?;
+        Ok(KeyPair {
+            private_key: SecretKey::new(serde_json::to_string(&sealed)?),
+            ..key_pair
+        })
+    }
+
+    fn scalar_mul(&self, key_pair: &KeyPair, point: &crate::g1::G1Point) -> Result<crate::g1::G1Point> {
+        let secret = key_pair.private_key.expose_secret().map_err(anyhow::Error::msg)?;
+        if !is_sealed(&secret) {
+            return Err(anyhow!(
+                "The private key of {} is not wrapped; import it again through the pkcs11 signer",
+                key_pair.eoa_address
+            ));
+        }
+        let sealed: Envelope = serde_json::from_str(&secret)
+            .with_context(|| format!("Malformed wrapped private key of {}", key_pair.eoa_address))?;
+        let plaintext = self
+            .envelope
+            .open(key_pair.eoa_address.as_bytes(), &sealed)
+            .with_context(|| format!("Failed to unwrap the private key of {}", key_pair.eoa_address))?;
+        let mut private_key = std::str::from_utf8(&plaintext)
+            .ok()
+            .and_then(|decimal| Fr::from_str(decimal).ok())
+            .ok_or_else(|| anyhow!("Failed to parse private key"))?;
+        let product = point.scalar_mul(private_key);
+        private_key.zeroize();
+        Ok(product)
+    }
+}
+
+/// Whether a stored private key is a sealed envelope rather than a decimal scalar
+fn is_sealed(private_key: &str) -> bool {
+    private_key.starts_with('{')
+}
@@ -0,0 +1,566 @@
+//! SQLite-backed persistent key store.
+//!
+//! Key pairs and the signing history live in a SQLite database whose schema is
+//! managed by the migrations in `migrations/`. Private keys are never written in
+//! plaintext: each one is encrypted with AES-256-GCM under the store key, using
+//! the EOA address as associated data so that a ciphertext cannot be moved to a
+//! different row. The database runs with `secure_delete`, and a deleted key's
+//! ciphertext is overwritten before its row is removed.
+//!
+//! The same database can also hold the API tokens of the service, the used EOA
+//! authorization nonces, the audit log and the signed AVS tasks, see
+//! [`SqliteTokenStore`], [`SqliteNonceStore`], [`SqliteAuditLog`] and
+//! [`SqliteSignedTaskStore`].
+
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions, SqliteRow};
+use sqlx::Row;
+
+use crate::web::audit::{AuditEntry, AuditLog, AuditRecord};
+use crate::web::auth::{ApiToken, TokenStore};
+use crate::web::cipher::SecretCipher;
+use crate::web::eoa_auth::NonceStore;
+use crate::web::guard::{RecordOutcome, SignedTask, SignedTaskStore};
+use crate::web::models::{G1Point, G2Point, KeyMetadata, KeyPair, KeyStatus, SecretKey};
+use crate::web::store::{retire, KeyStore};
+
+/// A signature recorded in the signing history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SigningRecord {
+    pub eoa_address: String,
+    pub operation: String,
+    pub message: G1Point,
+    pub signature: G1Point,
+    /// Seconds since the Unix epoch
+    pub created_at: i64,
+}
+
+/// A key store persisted in SQLite.
+pub struct SqliteStore {
+    pool: SqlitePool,
+    cipher: SecretCipher,
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Opens the database at `url`, creating it if needed, and runs pending migrations.
+async fn open_pool(url: &str) -> Result<SqlitePool> {
+    let options = SqliteConnectOptions::from_str(url)?
+        .create_if_missing(true)
+        .foreign_keys(true)
+        .pragma("secure_delete", "ON");
+    // An in-memory database only lives as long as its connection
+    let max_connections = if url.contains(":memory:") { 1 } else { 5 };
+    let pool = SqlitePoolOptions::new()
+        .max_connections(max_connections)
+        .connect_with(options)
+        .await
+        .with_context(|| format!("Failed to open database {}", url))?;
+    sqlx::migrate!("./migrations").run(&pool).await?;
+    Ok(pool)
+}
+
+impl SqliteStore {
+    /// Opens the database at `url`, creating it if needed, and runs pending migrations.
+    ///
+    /// # Arguments
+    /// * `url` - A SQLite URL such as `sqlite://keys.db` or `sqlite::memory:`
+    /// * `store_key` - The 32-byte key used to encrypt private keys at rest
+    pub async fn connect(url: &str, store_key: &[u8; 32]) -> Result<Self> {
+        Ok(Self {
+            pool: open_pool(url).await?,
+            cipher: SecretCipher::new(store_key),
+        })
+    }
+
+    /// A token store sharing the database of this key store
+    pub fn token_store(&self) -> SqliteTokenStore {
+        SqliteTokenStore {
+            pool: self.pool.clone(),
+        }
+    }
+
+    fn decrypt(&self, eoa_address: &str, blob: &[u8]) -> Result<String> {
+        let plaintext = self
+            .cipher
+            .decrypt(eoa_address.as_bytes(), blob)
+            .with_context(|| format!("Failed to decrypt private key for {}", eoa_address))?;
+        Ok(String::from_utf8(plaintext)?)
+    }
+
+    fn key_pair_from_row(&self, row: &SqliteRow) -> Result<KeyPair> {
+        let eoa_address: String = row.try_get("eoa_address")?;
+        let blob: Vec<u8> = row.try_get("private_key_encrypted")?;
+        Ok(KeyPair {
+            private_key: SecretKey::new(self.decrypt(&eoa_address, &blob)?),
+            eoa_address,
+            public_key_g1: G1Point {
+                x: row.try_get("g1_x")?,
+                y: row.try_get("g1_y")?,
+            },
+            public_key_g2: G2Point {
+                x_a: row.try_get("g2_x_a")?,
+                x_b: row.try_get("g2_x_b")?,
+                y_a: row.try_get("g2_y_a")?,
+                y_b: row.try_get("g2_y_b")?,
+            },
+        })
+    }
+
+    /// Inserts a key pair with labels, replacing any existing key pair for the same
+    /// EOA while keeping its signing history.
+    pub async fn upsert_key_pair(&self, key_pair: &KeyPair, labels: &[String]) -> Result<()> {
+        let encrypted = self
+            .cipher
+            .encrypt(key_pair.eoa_address.as_bytes(), key_pair.private_key.expose_secret().map_err(anyhow::Error::msg)?.as_bytes())?;
+        sqlx::query(
+            "INSERT INTO key_pairs \
+             (eoa_address, private_key_encrypted, g1_x, g1_y, g2_x_a, g2_x_b, g2_y_a, g2_y_b, labels, created_at) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?) \
+             ON CONFLICT (eoa_address) DO UPDATE SET \
+             private_key_encrypted = excluded.private_key_encrypted, \
+             g1_x = excluded.g1_x, g1_y = excluded.g1_y, \
+             g2_x_a = excluded.g2_x_a, g2_x_b = excluded.g2_x_b, \
+             g2_y_a = excluded.g2_y_a, g2_y_b = excluded.g2_y_b, \
+             labels = excluded.labels",
+        )
+        .bind(&key_pair.eoa_address)
+        .bind(encrypted)
+        .bind(&key_pair.public_key_g1.x)
+        .bind(&key_pair.public_key_g1.y)
+        .bind(&key_pair.public_key_g2.x_a)
+        .bind(&key_pair.public_key_g2.x_b)
+        .bind(&key_pair.public_key_g2.y_a)
+        .bind(&key_pair.public_key_g2.y_b)
+        .bind(serde_json::to_string(labels)?)
+        .bind(now())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Returns the labels attached to a key pair.
+    pub async fn labels(&self, eoa_address: &str) -> Result<Option<Vec<String>>> {
+        let labels: Option<String> =
+            sqlx::query_scalar("SELECT labels FROM key_pairs WHERE eoa_address = ?")
+                .bind(eoa_address)
+                .fetch_optional(&self.pool)
+                .await?;
+        labels.map(|l| Ok(serde_json::from_str(&l)?)).transpose()
+    }
+
+    /// Returns the signing history of a key, oldest first.
+    pub async fn signing_history(&self, eoa_address: &str) -> Result<Vec<SigningRecord>> {
+        let rows = sqlx::query(
+            "SELECT * FROM signing_history WHERE eoa_address = ? ORDER BY created_at, id",
+        )
+        .bind(eoa_address)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter()
+            .map(|row| {
+                Ok(SigningRecord {
+                    eoa_address: row.try_get("eoa_address")?,
+                    operation: row.try_get("operation")?,
+                    message: G1Point {
+                        x: row.try_get("message_x")?,
+                        y: row.try_get("message_y")?,
+                    },
+                    signature: G1Point {
+                        x: row.try_get("signature_x")?,
+                        y: row.try_get("signature_y")?,
+                    },
+                    created_at: row.try_get("created_at")?,
+                })
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl KeyStore for SqliteStore {
+    /// Get a key pair by EOA address
+    async fn get_key_pair(&self, eoa_address: &str) -> Result<Option<KeyPair>> {
+        let row = sqlx::query("SELECT * FROM key_pairs WHERE eoa_address = ?")
+            .bind(eoa_address)
+            .fetch_optional(&self.pool)
+            .await?;
+        row.map(|row| self.key_pair_from_row(&row)).transpose()
+    }
+
+    /// List all key pairs
+    async fn list_key_pairs(&self) -> Result<Vec<KeyPair>> {
+        let rows = sqlx::query("SELECT * FROM key_pairs ORDER BY created_at, eoa_address")
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter().map(|row| self.key_pair_from_row(row)).collect()
+    }
+
+    async fn insert_key_pair(&self, key_pair: KeyPair) -> Result<()> {
+        if self.get_key_pair(&key_pair.eoa_address).await?.is_some() {
+            return Err(anyhow!("A key pair already exists for {}", key_pair.eoa_address));
+        }
+        self.upsert_key_pair(&key_pair, &[]).await
+    }
+
+    async fn key_metadata(&self, eoa_address: &str) -> Result<KeyMetadata> {
+        let row = sqlx::query("SELECT labels, created_at, status, retired FROM key_pairs WHERE eoa_address = ?")
+            .bind(eoa_address)
+            .fetch_optional(&self.pool)
+            .await?;
+        match row {
+            Some(row) => Ok(KeyMetadata {
+                labels: serde_json::from_str(&row.try_get::<String, _>("labels")?)?,
+                created_at: Some(row.try_get("created_at")?),
+                status: serde_json::from_value(serde_json::Value::String(row.try_get("status")?))?,
+                retired: serde_json::from_str(&row.try_get::<String, _>("retired")?)?,
+            }),
+            None => Ok(KeyMetadata::default()),
+        }
+    }
+
+    async fn set_key_status(&self, eoa_address: &str, status: KeyStatus) -> Result<bool> {
+        let updated = sqlx::query("UPDATE key_pairs SET status = ? WHERE eoa_address = ?")
+            .bind(serde_json::to_value(status)?.as_str())
+            .bind(eoa_address)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+        Ok(updated > 0)
+    }
+
+    async fn delete_key_pair(&self, eoa_address: &str) -> Result<bool> {
+        // The signing history references the key, so it goes with it
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            "UPDATE key_pairs SET private_key_encrypted = zeroblob(length(private_key_encrypted)) \
+             WHERE eoa_address = ?",
+        )
+        .bind(eoa_address)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("DELETE FROM signing_history WHERE eoa_address = ?")
+            .bind(eoa_address)
+            .execute(&mut *tx)
+            .await?;
+        let deleted = sqlx::query("DELETE FROM key_pairs WHERE eoa_address = ?")
+            .bind(eoa_address)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        tx.commit().await?;
+        Ok(deleted > 0)
+    }
+
+    async fn rotate_key_pair(&self, key_pair: KeyPair) -> Result<KeyPair> {
+        let previous = self
+            .get_key_pair(&key_pair.eoa_address)
+            .await?
+            .ok_or_else(|| anyhow!("No key pair exists for {}", key_pair.eoa_address))?;
+        let labels = self.labels(&key_pair.eoa_address).await?.unwrap_or_default();
+        self.upsert_key_pair(&key_pair, &labels).await?;
+        sqlx::query("UPDATE key_pairs SET retired = json_insert(retired, '$[#]', json(?)) WHERE eoa_address = ?")
+            .bind(serde_json::to_string(&retire(&previous)?)?)
+            .bind(&key_pair.eoa_address)
+            .execute(&self.pool)
+            .await?;
+        Ok(previous)
+    }
+
+    async fn record_signature(
+        &self,
+        eoa_address: &str,
+        operation: &str,
+        message: &G1Point,
+        signature: &G1Point,
+    ) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO signing_history \
+             (eoa_address, operation, message_x, message_y, signature_x, signature_y, created_at) \
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(eoa_address)
+        .bind(operation)
+        .bind(&message.x)
+        .bind(&message.y)
+        .bind(&signature.x)
+        .bind(&signature.y)
+        .bind(now())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+/// API tokens persisted in SQLite.
+pub struct SqliteTokenStore {
+    pool: SqlitePool,
+}
+
+impl SqliteTokenStore {
+    /// Opens the database at `url`, creating it if needed, and runs pending migrations.
+    pub async fn connect(url: &str) -> Result<Self> {
+        Ok(Self {
+            pool: open_pool(url).await?,
+        })
+    }
+
+    fn token_from_row(row: &SqliteRow) -> Result<ApiToken> {
+        let eoa_addresses: Option<String> = row.try_get("eoa_addresses")?;
+        Ok(ApiToken {
+            id: row.try_get("id")?,
+            description: row.try_get("description")?,
+            secret_hash: row.try_get("secret_hash")?,
+            eoa_addresses: eoa_addresses.map(|e| serde_json::from_str(&e)).transpose()?,
+            operations: serde_json::from_str(&row.try_get::<String, _>("operations")?)?,
+            created_at: row.try_get("created_at")?,
+            expires_at: row.try_get("expires_at")?,
+            revoked: row.try_get("revoked")?,
+        })
+    }
+}
+
+#[async_trait]
+impl TokenStore for SqliteTokenStore {
+    async fn get_token(&self, id: &str) -> Result<Option<ApiToken>> {
+        let row = sqlx::query("SELECT * FROM api_tokens WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+        row.map(|row| Self::token_from_row(&row)).transpose()
+    }
+
+    async fn list_tokens(&self) -> Result<Vec<ApiToken>> {
+        let rows = sqlx::query("SELECT * FROM api_tokens ORDER BY created_at, id")
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter().map(Self::token_from_row).collect()
+    }
+
+    async fn insert_token(&self, token: ApiToken) -> Result<()> {
+        let eoa_addresses = token.eoa_addresses.as_ref().map(serde_json::to_string).transpose()?;
+        sqlx::query(
+            "INSERT INTO api_tokens \
+             (id, description, secret_hash, eoa_addresses, operations, created_at, expires_at, revoked) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&token.id)
+        .bind(&token.description)
+        .bind(&token.secret_hash)
+        .bind(eoa_addresses)
+        .bind(serde_json::to_string(&token.operations)?)
+        .bind(token.created_at)
+        .bind(token.expires_at)
+        .bind(token.revoked)
+        .execute(&self.pool)
+        .await
+        .with_context(|| format!("Failed to insert token {}", token.id))?;
+        Ok(())
+    }
+
+    async fn revoke_token(&self, id: &str) -> Result<bool> {
+        let revoked = sqlx::query("UPDATE api_tokens SET revoked = 1 WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+        Ok(revoked > 0)
+    }
+}
+
+/// Used EOA authorization nonces persisted in SQLite.
+pub struct SqliteNonceStore {
+    pool: SqlitePool,
+}
+
+impl SqliteNonceStore {
+    /// Opens the database at `url`, creating it if needed, and runs pending migrations.
+    pub async fn connect(url: &str) -> Result<Self> {
+        Ok(Self {
+            pool: open_pool(url).await?,
+        })
+    }
+}
+
+#[async_trait]
+impl NonceStore for SqliteNonceStore {
+    async fn use_nonce(&self, eoa_address: &str, nonce: u64, expiry: u64) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        // An expired authorization is rejected anyway, so its nonce can be dropped
+        sqlx::query("DELETE FROM eoa_nonces WHERE expires_at <= ?")
+            .bind(now())
+            .execute(&mut *tx)
+            .await?;
+        let inserted = sqlx::query(
+            "INSERT OR IGNORE INTO eoa_nonces (eoa_address, nonce, expires_at) VALUES (?, ?, ?)",
+        )
+        .bind(eoa_address.to_lowercase())
+        .bind(nonce.to_string())
+        .bind(i64::try_from(expiry)?)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        tx.commit().await?;
+        Ok(inserted > 0)
+    }
+}
+
+/// The audit log persisted in the `audit_log` table of a SQLite database.
+pub struct SqliteAuditLog {
+    pool: SqlitePool,
+    /// Serializes appends so that each one chains to the entry before it
+    append_lock: tokio::sync::Mutex<()>,
+}
+
+impl SqliteAuditLog {
+    /// Opens the database at `url`, creating it if needed, and runs pending migrations.
+    pub async fn connect(url: &str) -> Result<Self> {
+        Ok(Self {
+            pool: open_pool(url).await?,
+            append_lock: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    fn entry_from_row(row: &SqliteRow) -> Result<AuditEntry> {
+        Ok(AuditEntry {
+            seq: u64::try_from(row.try_get::<i64, _>("seq")?)?,
+            record: AuditRecord {
+                request_id: row.try_get("request_id")?,
+                timestamp: row.try_get("timestamp")?,
+                principal: row.try_get("principal")?,
+                eoa_address: row.try_get("eoa_address")?,
+                operation: row.try_get("operation")?,
+                input_hash: row.try_get("input_hash")?,
+                output_hash: row.try_get("output_hash")?,
+                result: row.try_get("result")?,
+                approval_id: row.try_get("approval_id")?,
+            },
+            prev_hash: row.try_get("prev_hash")?,
+            hash: row.try_get("hash")?,
+        })
+    }
+}
+
+#[async_trait]
+impl AuditLog for SqliteAuditLog {
+    async fn append(&self, record: AuditRecord) -> Result<AuditEntry> {
+        let _guard = self.append_lock.lock().await;
+        let mut tx = self.pool.begin().await?;
+        let last = sqlx::query("SELECT * FROM audit_log ORDER BY seq DESC LIMIT 1")
+            .fetch_optional(&mut *tx)
+            .await?
+            .map(|row| Self::entry_from_row(&row))
+            .transpose()?;
+        let entry = AuditEntry::chain(last.as_ref(), record)?;
+        sqlx::query(
+            "INSERT INTO audit_log \
+             (seq, request_id, timestamp, principal, eoa_address, operation, input_hash, output_hash, result, approval_id, prev_hash, hash) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(i64::try_from(entry.seq)?)
+        .bind(&entry.record.request_id)
+        .bind(entry.record.timestamp)
+        .bind(&entry.record.principal)
+        .bind(&entry.record.eoa_address)
+        .bind(&entry.record.operation)
+        .bind(&entry.record.input_hash)
+        .bind(&entry.record.output_hash)
+        .bind(&entry.record.result)
+        .bind(&entry.record.approval_id)
+        .bind(&entry.prev_hash)
+        .bind(&entry.hash)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to append to the audit log")?;
+        tx.commit().await?;
+        Ok(entry)
+    }
+
+    async fn entries(&self) -> Result<Vec<AuditEntry>> {
+        let rows = sqlx::query("SELECT * FROM audit_log ORDER BY seq")
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter().map(Self::entry_from_row).collect()
+    }
+}
+
+/// Signed AVS tasks persisted in SQLite.
+pub struct SqliteSignedTaskStore {
+    pool: SqlitePool,
+}
+
+impl SqliteSignedTaskStore {
+    /// Opens the database at `url`, creating it if needed, and runs pending migrations.
+    pub async fn connect(url: &str) -> Result<Self> {
+        Ok(Self {
+            pool: open_pool(url).await?,
+        })
+    }
+}
+
+#[async_trait]
+impl SignedTaskStore for SqliteSignedTaskStore {
+    async fn record_task(&self, task: SignedTask) -> Result<RecordOutcome> {
+        let mut tx = self.pool.begin().await?;
+        let inserted = sqlx::query(
+            "INSERT OR IGNORE INTO signed_tasks (eoa_address, avs, task_index, message_hash, signed_at) \
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(&task.eoa_address)
+        .bind(&task.avs)
+        .bind(task.task_index.to_string())
+        .bind(&task.message_hash)
+        .bind(task.signed_at)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        let outcome = if inserted > 0 {
+            RecordOutcome::New
+        } else {
+            let existing: String = sqlx::query_scalar(
+                "SELECT message_hash FROM signed_tasks WHERE eoa_address = ? AND avs = ? AND task_index = ?",
+            )
+            .bind(&task.eoa_address)
+            .bind(&task.avs)
+            .bind(task.task_index.to_string())
+            .fetch_one(&mut *tx)
+            .await?;
+            if existing == task.message_hash {
+                RecordOutcome::Repeat
+            } else {
+                RecordOutcome::Conflict { message_hash: existing }
+            }
+        };
+        tx.commit().await?;
+        Ok(outcome)
+    }
+
+    async fn list_tasks(&self) -> Result<Vec<SignedTask>> {
+        let rows = sqlx::query("SELECT * FROM signed_tasks").fetch_all(&self.pool).await?;
+        let mut tasks = rows
+            .iter()
+            .map(|row| {
+                Ok(SignedTask {
+                    eoa_address: row.try_get("eoa_address")?,
+                    avs: row.try_get("avs")?,
+                    task_index: row.try_get::<String, _>("task_index")?.parse()?,
+                    message_hash: row.try_get("message_hash")?,
+                    signed_at: row.try_get("signed_at")?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        // Task indices are stored as text, so they are ordered here
+        tasks.sort_by(|a, b| (&a.eoa_address, &a.avs, a.task_index).cmp(&(&b.eoa_address, &b.avs, b.task_index)));
+        Ok(tasks)
+    }
+}
@@ -1,58 +1,461 @@
 use std::collections::HashMap;
-use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde_json::{json, Map, Value};
 use std::fs;
-use crate::web::models::{KeyPair, G1Point, G2Point};
-use anyhow::Result;
-
-/// A simple in-memory store for key pairs
-pub struct Store {
-    players: HashMap<String, KeyPair>,
-}
-
-impl Store {
-    /// Create a new store
-    pub fn new() -> Result<Self> {
-        let file_content = fs::read_to_string("src/web/players.json")?;
-        let json: Value = serde_json::from_str(&file_content)?;
-        
-        let mut players = HashMap::new();
-        
-        if let Value::Object(obj) = json {
-            for (_name, player) in obj {
-                if let Value::Object(player_obj) = player {
-                    let eoa_address = player_obj["pub"].as_str().unwrap_or_default().to_string();
-                    let private_key = player_obj["bls"]["priv_key"].as_str().unwrap_or_default().to_string();
-                    
-                    let key_pair = KeyPair {
-                        eoa_address: eoa_address.clone(),
-                        private_key,
-                        public_key_g1: G1Point {
-                            x: player_obj["bls"]["g1_x"].as_str().unwrap_or_default().to_string(),
-                            y: player_obj["bls"]["g1_y"].as_str().unwrap_or_default().to_string(),
-                        },
-                        public_key_g2: G2Point {
-                            x_a: player_obj["bls"]["g2_x_0"].as_str().unwrap_or_default().to_string(),
-                            x_b: player_obj["bls"]["g2_x_1"].as_str().unwrap_or_default().to_string(),
-                            y_a: player_obj["bls"]["g2_y_0"].as_str().unwrap_or_default().to_string(),
-                            y_b: player_obj["bls"]["g2_y_1"].as_str().unwrap_or_default().to_string(),
-                        },
-                    };
-                    
-                    players.insert(eoa_address, key_pair);
-                }
+use std::io::Write;
+use crate::web::envelope::{Envelope, EnvelopeCipher, KekProvider};
+use crate::web::models::{KeyMetadata, KeyPair, KeyStatus, G1Point, G2Point, RetiredKey, SecretKey};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+
+/// Default location of the JSON key file
+pub const DEFAULT_JSON_PATH: &str = "src/web/players.json";
+
+/// Storage for the BLS key pairs served by the web service, indexed by EOA address.
+///
+/// Handlers only depend on this trait, so the backend can be chosen at startup:
+/// - [`JsonStore`]: the `players.json` format
+/// - [`EncryptedDirStore`](crate::web::encrypted_dir::EncryptedDirStore): one
+///   encrypted file per key in a directory
+/// - [`SqliteStore`](crate::web::sqlite::SqliteStore): a SQLite database with
+///   signing history
+/// - [`MemoryStore`]: an in-memory store for tests
+#[async_trait]
+pub trait KeyStore: Send + Sync {
+    /// Get a key pair by EOA address
+    async fn get_key_pair(&self, eoa_address: &str) -> Result<Option<KeyPair>>;
+
+    /// Get the key pairs of several EOAs; backends guarded by a lock read them all
+    /// under one acquisition
+    ///
+    /// # Returns
+    /// The key pair of each address, in the order given
+    async fn get_key_pairs(&self, eoa_addresses: &[String]) -> Result<Vec<Option<KeyPair>>> {
+        let mut key_pairs = Vec::with_capacity(eoa_addresses.len());
+        for eoa_address in eoa_addresses {
+            key_pairs.push(self.get_key_pair(eoa_address).await?);
+        }
+        Ok(key_pairs)
+    }
+
+    /// List all key pairs
+    async fn list_key_pairs(&self) -> Result<Vec<KeyPair>>;
+
+    /// Insert a key pair, failing if the EOA already has one
+    async fn insert_key_pair(&self, key_pair: KeyPair) -> Result<()>;
+
+    /// Delete the key pair of an EOA with its metadata, overwriting the stored
+    /// secret first where the backend allows it
+    ///
+    /// # Returns
+    /// Whether a key pair was deleted
+    async fn delete_key_pair(&self, eoa_address: &str) -> Result<bool>;
+
+    /// Replace the key pair of an EOA that already has one. The public keys of the
+    /// replaced key pair are kept in the metadata as a [`RetiredKey`].
+    ///
+    /// # Returns
+    /// The key pair that was replaced
+    async fn rotate_key_pair(&self, key_pair: KeyPair) -> Result<KeyPair>;
+
+    /// Metadata of a key pair; the default metadata for unknown EOAs
+    async fn key_metadata(&self, eoa_address: &str) -> Result<KeyMetadata>;
+
+    /// Enable or disable the key pair of an EOA
+    ///
+    /// # Returns
+    /// Whether the EOA has a key pair
+    async fn set_key_status(&self, eoa_address: &str, status: KeyStatus) -> Result<bool>;
+
+    /// Record a signature in the signing history, if the backend keeps one
+    async fn record_signature(
+        &self,
+        _eoa_address: &str,
+        _operation: &str,
+        _message: &G1Point,
+        _signature: &G1Point,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// The retired key kept when `previous` is replaced by a rotation
+pub(crate) fn retire(previous: &KeyPair) -> Result<RetiredKey> {
+    previous.to_retired(now()).map_err(anyhow::Error::msg)
+}
+
+/// Inserts into a map of key pairs, rejecting duplicates
+fn insert_new(players: &mut HashMap<String, KeyPair>, key_pair: KeyPair) -> Result<()> {
+    if players.contains_key(&key_pair.eoa_address) {
+        return Err(anyhow!("A key pair already exists for {}", key_pair.eoa_address));
+    }
+    players.insert(key_pair.eoa_address.clone(), key_pair);
+    Ok(())
+}
+
+/// Replaces an entry in a map of key pairs, which must already exist
+fn replace_existing(players: &mut HashMap<String, KeyPair>, key_pair: KeyPair) -> Result<KeyPair> {
+    match players.get_mut(&key_pair.eoa_address) {
+        Some(existing) => Ok(std::mem::replace(existing, key_pair)),
+        None => Err(anyhow!("No key pair exists for {}", key_pair.eoa_address)),
+    }
+}
+
+/// An in-memory store for key pairs, mostly useful in tests
+#[derive(Default)]
+pub struct MemoryStore {
+    players: RwLock<HashMap<String, KeyPair>>,
+    metadata: RwLock<HashMap<String, KeyMetadata>>,
+}
+
+impl MemoryStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a store holding the given key pairs
+    pub fn with_key_pairs<I: IntoIterator<Item = KeyPair>>(key_pairs: I) -> Self {
+        let players = key_pairs
+            .into_iter()
+            .map(|kp| (kp.eoa_address.clone(), kp))
+            .collect();
+        Self {
+            players: RwLock::new(players),
+            metadata: RwLock::default(),
+        }
+    }
+}
+
+#[async_trait]
+impl KeyStore for MemoryStore {
+    async fn get_key_pair(&self, eoa_address: &str) -> Result<Option<KeyPair>> {
+        Ok(self.players.read().unwrap().get(eoa_address).cloned())
+    }
+
+    async fn get_key_pairs(&self, eoa_addresses: &[String]) -> Result<Vec<Option<KeyPair>>> {
+        let players = self.players.read().unwrap();
+        Ok(eoa_addresses.iter().map(|eoa_address| players.get(eoa_address).cloned()).collect())
+    }
+
+    async fn list_key_pairs(&self) -> Result<Vec<KeyPair>> {
+        Ok(self.players.read().unwrap().values().cloned().collect())
+    }
+
+    async fn insert_key_pair(&self, key_pair: KeyPair) -> Result<()> {
+        insert_new(&mut self.players.write().unwrap(), key_pair)
+    }
+
+    async fn delete_key_pair(&self, eoa_address: &str) -> Result<bool> {
+        self.metadata.write().unwrap().remove(eoa_address);
+        // The private key is zeroized when the removed key pair is dropped
+        Ok(self.players.write().unwrap().remove(eoa_address).is_some())
+    }
+
+    async fn rotate_key_pair(&self, key_pair: KeyPair) -> Result<KeyPair> {
+        let eoa_address = key_pair.eoa_address.clone();
+        let previous = replace_existing(&mut self.players.write().unwrap(), key_pair)?;
+        self.metadata
+            .write()
+            .unwrap()
+            .entry(eoa_address)
+            .or_default()
+            .retired
+            .push(retire(&previous)?);
+        Ok(previous)
+    }
+
+    async fn key_metadata(&self, eoa_address: &str) -> Result<KeyMetadata> {
+        Ok(self.metadata.read().unwrap().get(eoa_address).cloned().unwrap_or_default())
+    }
+
+    async fn set_key_status(&self, eoa_address: &str, status: KeyStatus) -> Result<bool> {
+        if !self.players.read().unwrap().contains_key(eoa_address) {
+            return Ok(false);
+        }
+        self.metadata.write().unwrap().entry(eoa_address.to_string()).or_default().status = status;
+        Ok(true)
+    }
+}
+
+/// Parses one entry of a `players.json` file. An encrypted private key is kept
+/// sealed, to be opened with `envelope` when a signer uses it.
+fn key_pair_from_player(player: &Value, envelope: Option<&EnvelopeCipher>) -> Result<Option<KeyPair>> {
+    let Some(player_obj) = player.as_object() else {
+        return Ok(None);
+    };
+    let field = |v: &Value| v.as_str().unwrap_or_default().to_string();
+    let eoa_address = field(&player_obj["pub"]);
+    let bls = &player_obj["bls"];
+    let private_key = match bls.get("encrypted_priv_key") {
+        Some(sealed) => {
+            let envelope = envelope
+                .ok_or_else(|| anyhow!("The private key of {} is encrypted, but no KEK is configured", eoa_address))?;
+            let sealed: Envelope = serde_json::from_value(sealed.clone())
+                .with_context(|| format!("Malformed encrypted private key of {}", eoa_address))?;
+            SecretKey::sealed(sealed, eoa_address.as_bytes().to_vec(), envelope.clone())
+        }
+        None => SecretKey::new(field(&bls["priv_key"])),
+    };
+    Ok(Some(KeyPair {
+        eoa_address,
+        private_key,
+        public_key_g1: G1Point {
+            x: field(&bls["g1_x"]),
+            y: field(&bls["g1_y"]),
+        },
+        public_key_g2: G2Point {
+            x_a: field(&bls["g2_x_0"]),
+            x_b: field(&bls["g2_x_1"]),
+            y_a: field(&bls["g2_y_0"]),
+            y_b: field(&bls["g2_y_1"]),
+        },
+    }))
+}
+
+/// Reads the lifecycle metadata of a `players.json` entry
+fn metadata_from_player(player: &Value) -> Result<KeyMetadata> {
+    let mut metadata = KeyMetadata::default();
+    if let Some(status) = player.get("status") {
+        metadata.status = serde_json::from_value(status.clone())?;
+    }
+    if let Some(retired) = player.get("retired") {
+        metadata.retired = serde_json::from_value(retired.clone())?;
+    }
+    Ok(metadata)
+}
+
+/// Writes the BLS fields of a key pair into a `players.json` entry, keeping any
+/// other fields of the entry and of its `bls` object untouched. With an
+/// `envelope` the private key is sealed into `encrypted_priv_key` instead of
+/// written to `priv_key`, and the other form is removed.
+fn write_player(player: &mut Value, key_pair: &KeyPair, envelope: Option<&EnvelopeCipher>) -> Result<()> {
+    player["pub"] = json!(key_pair.eoa_address);
+    if !player["bls"].is_object() {
+        player["bls"] = json!({});
+    }
+    let bls = &mut player["bls"];
+    bls["g1_x"] = json!(key_pair.public_key_g1.x);
+    bls["g1_y"] = json!(key_pair.public_key_g1.y);
+    bls["g2_x_0"] = json!(key_pair.public_key_g2.x_a);
+    bls["g2_x_1"] = json!(key_pair.public_key_g2.x_b);
+    bls["g2_y_0"] = json!(key_pair.public_key_g2.y_a);
+    bls["g2_y_1"] = json!(key_pair.public_key_g2.y_b);
+    let private_key = key_pair.private_key.expose_secret().map_err(anyhow::Error::msg)?;
+    let stale = match envelope {
+        Some(envelope) => {
+            let sealed = envelope.seal(key_pair.eoa_address.as_bytes(), private_key.as_bytes())?;
+            bls["encrypted_priv_key"] = serde_json::to_value(sealed)?;
+            "priv_key"
+        }
+        None => {
+            bls["priv_key"] = json!(private_key.as_str());
+            "encrypted_priv_key"
+        }
+    };
+    if let Some(bls) = bls.as_object_mut() {
+        bls.remove(stale);
+    }
+    Ok(())
+}
+
+/// A store backed by a JSON file in the `players.json` format.
+///
+/// Entries are keyed by player name and hold the EOA address in `pub` and the BLS
+/// keys in `bls`. A disabled key has `"status": "disabled"` and rotated keys are
+/// listed in `retired`. Changes are written back to the file, keeping unrelated
+/// fields.
+///
+/// Opened with [`JsonStore::from_file_encrypted`], private keys are kept as
+/// [`Envelope`]s in `encrypted_priv_key`. Key pairs are read with their private
+/// keys still sealed, and a key is only decrypted when a [`Signer`](crate::web::signer::Signer)
+/// uses it, so plaintext keys live no longer than the operation that needs them.
+pub struct JsonStore {
+    path: PathBuf,
+    players: RwLock<Map<String, Value>>,
+    envelope: Option<EnvelopeCipher>,
+}
+
+impl JsonStore {
+    /// Load the key pairs from a JSON file in the `players.json` format
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file_content = fs::read_to_string(path.as_ref())?;
+        let players = match serde_json::from_str(&file_content)? {
+            Value::Object(obj) => obj,
+            _ => return Err(anyhow!("Expected a JSON object of players")),
+        };
+        Ok(Self {
+            path: path.as_ref().to_path_buf(),
+            players: RwLock::new(players),
+            envelope: None,
+        })
+    }
+
+    /// Load the key pairs from a JSON file whose private keys are encrypted with
+    /// data keys wrapped by `kek`.
+    ///
+    /// Plaintext private keys found in the file are encrypted and the file is
+    /// rewritten before the store is returned.
+    pub fn from_file_encrypted<P: AsRef<Path>>(path: P, kek: Arc<dyn KekProvider>) -> Result<Self> {
+        let store = Self::from_file(path)?;
+        let envelope = EnvelopeCipher::new(kek);
+        let mut players = store.players.into_inner().unwrap();
+        let mut sealed = 0;
+        for player in players.values_mut() {
+            if player["bls"].get("priv_key").is_none() {
+                continue;
             }
+            if let Some(key_pair) = key_pair_from_player(player, None)? {
+                write_player(player, &key_pair, Some(&envelope))?;
+                sealed += 1;
+            }
+        }
+        let store = Self {
+            path: store.path,
+            players: RwLock::new(players),
+            envelope: Some(envelope),
+        };
+        if sealed > 0 {
+            store.save(&store.players.read().unwrap())?;
         }
-        
-        Ok(Self { players })
+        Ok(store)
     }
 
-    /// Get a key pair by EOA address
-    pub fn get_key_pair(&self, eoa_address: &str) -> Option<&KeyPair> {
-        self.players.get(eoa_address)
+    fn find<'a>(players: &'a Map<String, Value>, eoa_address: &str) -> Option<&'a String> {
+        players
+            .iter()
+            .find(|(_, player)| player["pub"].as_str() == Some(eoa_address))
+            .map(|(name, _)| name)
     }
 
-    /// List all key pairs
-    pub fn list_key_pairs(&self) -> Vec<&KeyPair> {
-        self.players.values().collect()
+    /// Writes the players back to disk through a temporary file, so that a crash
+    /// never leaves a partially written key file.
+    ///
+    /// The previous file is kept open across the rename and overwritten with zeros
+    /// afterwards, so that deleted and rotated secrets do not linger in its freed
+    /// blocks. Zeroing it before the rename would leave no key file if the process
+    /// died in between.
+    fn save(&self, players: &Map<String, Value>) -> Result<()> {
+        let tmp = self.path.with_extension("json.tmp");
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(serde_json::to_string_pretty(players)?.as_bytes())?;
+        file.sync_all()?;
+        drop(file);
+
+        let previous = fs::OpenOptions::new().write(true).open(&self.path).ok();
+        fs::rename(&tmp, &self.path)?;
+        if let Some(mut previous) = previous {
+            let len = previous.metadata()?.len() as usize;
+            previous.write_all(&vec![0u8; len])?;
+            previous.sync_all()?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl KeyStore for JsonStore {
+    async fn get_key_pair(&self, eoa_address: &str) -> Result<Option<KeyPair>> {
+        let players = self.players.read().unwrap();
+        match Self::find(&players, eoa_address) {
+            Some(name) => key_pair_from_player(&players[name], self.envelope.as_ref()),
+            None => Ok(None),
+        }
+    }
+
+    async fn get_key_pairs(&self, eoa_addresses: &[String]) -> Result<Vec<Option<KeyPair>>> {
+        let players = self.players.read().unwrap();
+        eoa_addresses
+            .iter()
+            .map(|eoa_address| match Self::find(&players, eoa_address) {
+                Some(name) => key_pair_from_player(&players[name], self.envelope.as_ref()),
+                None => Ok(None),
+            })
+            .collect()
+    }
+
+    async fn list_key_pairs(&self) -> Result<Vec<KeyPair>> {
+        let players = self.players.read().unwrap();
+        let key_pairs = players
+            .values()
+            .map(|player| key_pair_from_player(player, self.envelope.as_ref()))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(key_pairs.into_iter().flatten().collect())
+    }
+
+    async fn insert_key_pair(&self, key_pair: KeyPair) -> Result<()> {
+        let mut players = self.players.write().unwrap();
+        if Self::find(&players, &key_pair.eoa_address).is_some() {
+            return Err(anyhow!("A key pair already exists for {}", key_pair.eoa_address));
+        }
+        let mut player = json!({});
+        write_player(&mut player, &key_pair, self.envelope.as_ref())?;
+        let mut updated = players.clone();
+        updated.insert(key_pair.eoa_address.clone(), player);
+        self.save(&updated)?;
+        *players = updated;
+        Ok(())
+    }
+
+    async fn delete_key_pair(&self, eoa_address: &str) -> Result<bool> {
+        let mut players = self.players.write().unwrap();
+        let Some(name) = Self::find(&players, eoa_address).cloned() else {
+            return Ok(false);
+        };
+        let mut updated = players.clone();
+        updated.remove(&name);
+        self.save(&updated)?;
+        *players = updated;
+        Ok(true)
+    }
+
+    async fn rotate_key_pair(&self, key_pair: KeyPair) -> Result<KeyPair> {
+        let mut players = self.players.write().unwrap();
+        let Some(name) = Self::find(&players, &key_pair.eoa_address).cloned() else {
+            return Err(anyhow!("No key pair exists for {}", key_pair.eoa_address));
+        };
+        let previous = key_pair_from_player(&players[&name], self.envelope.as_ref())?
+            .ok_or_else(|| anyhow!("Malformed entry for {}", key_pair.eoa_address))?;
+        let mut retired = metadata_from_player(&players[&name])?.retired;
+        retired.push(retire(&previous)?);
+        let mut updated = players.clone();
+        write_player(&mut updated[&name], &key_pair, self.envelope.as_ref())?;
+        updated[&name]["retired"] = serde_json::to_value(retired)?;
+        self.save(&updated)?;
+        *players = updated;
+        Ok(previous)
     }
-} 
\ No newline at end of file
+
+    async fn key_metadata(&self, eoa_address: &str) -> Result<KeyMetadata> {
+        let players = self.players.read().unwrap();
+        match Self::find(&players, eoa_address) {
+            Some(name) => metadata_from_player(&players[name]),
+            None => Ok(KeyMetadata::default()),
+        }
+    }
+
+    async fn set_key_status(&self, eoa_address: &str, status: KeyStatus) -> Result<bool> {
+        let mut players = self.players.write().unwrap();
+        let Some(name) = Self::find(&players, eoa_address).cloned() else {
+            return Ok(false);
+        };
+        let mut updated = players.clone();
+        let player = updated[&name].as_object_mut().ok_or_else(|| anyhow!("Malformed entry for {}", eoa_address))?;
+        match status {
+            KeyStatus::Active => player.remove("status"),
+            KeyStatus::Disabled => player.insert("status".to_string(), serde_json::to_value(status)?),
+        };
+        self.save(&updated)?;
+        *players = updated;
+        Ok(true)
+    }
+}
@@ -0,0 +1,277 @@
+//! TLS listener with client certificate authentication.
+//!
+//! The server certificate and the client CA bundle are held behind reloadable
+//! handles, so that [`TlsReloader::reload`] (triggered by `SIGHUP`) swaps them for
+//! new handshakes while established connections keep their session. The gRPC API
+//! listens with the same material through [`TlsReloader::server_config`].
+//!
+//! When a client CA is configured every client must present a certificate issued
+//! by it. The common name and the DNS, URI and email subject alternative names of
+//! that certificate are attached to the connection as a [`ClientIdentity`], which
+//! the authentication middleware maps to a principal through `[[tls.clients]]`.
+
+use std::any::Any;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+
+use actix_tls::accept::rustls_0_21::TlsStream;
+use actix_web::dev::Extensions;
+use actix_web::rt::net::TcpStream;
+use anyhow::{anyhow, Context, Result};
+use log::{error, info};
+use rustls::server::{AllowAnyAuthenticatedClient, ClientCertVerified, ClientCertVerifier, ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+use rustls::{Certificate, DistinguishedName, PrivateKey, RootCertStore, ServerConfig, ServerConnection};
+use x509_parser::extensions::GeneralName;
+
+use crate::web::auth::Principal;
+use crate::web::config::{ClientPrincipal, TlsConfig};
+
+/// Reads every certificate of a PEM file.
+pub fn load_certs<P: AsRef<Path>>(path: P) -> Result<Vec<Certificate>> {
+    let file = File::open(path.as_ref()).with_context(|| format!("Failed to open {}", path.as_ref().display()))?;
+    let certs = rustls_pemfile::certs(&mut BufReader::new(file))
+        .with_context(|| format!("Malformed PEM in {}", path.as_ref().display()))?;
+    if certs.is_empty() {
+        return Err(anyhow!("No certificates in {}", path.as_ref().display()));
+    }
+    Ok(certs.into_iter().map(Certificate).collect())
+}
+
+/// Reads the first PKCS#8, PKCS#1 or SEC1 private key of a PEM file.
+pub fn load_key<P: AsRef<Path>>(path: P) -> Result<PrivateKey> {
+    let file = File::open(path.as_ref()).with_context(|| format!("Failed to open {}", path.as_ref().display()))?;
+    let items = rustls_pemfile::read_all(&mut BufReader::new(file))
+        .with_context(|| format!("Malformed PEM in {}", path.as_ref().display()))?;
+    items
+        .into_iter()
+        .find_map(|item| match item {
+            rustls_pemfile::Item::PKCS8Key(der)
+            | rustls_pemfile::Item::RSAKey(der)
+            | rustls_pemfile::Item::ECKey(der) => Some(PrivateKey(der)),
+            _ => None,
+        })
+        .ok_or_else(|| anyhow!("No private key in {}", path.as_ref().display()))
+}
+
+fn load_certified_key(tls: &TlsConfig) -> Result<CertifiedKey> {
+    let cert = tls.cert.as_ref().ok_or_else(|| anyhow!("tls.cert is not set"))?;
+    let key = tls.key.as_ref().ok_or_else(|| anyhow!("tls.key is not set"))?;
+    let signing_key = rustls::sign::any_supported_type(&load_key(key)?)
+        .map_err(|e| anyhow!("Unsupported private key in {}: {}", key.display(), e))?;
+    Ok(CertifiedKey::new(load_certs(cert)?, signing_key))
+}
+
+fn load_client_verifier(client_ca: &Path) -> Result<Arc<dyn ClientCertVerifier>> {
+    let mut roots = RootCertStore::empty();
+    for cert in load_certs(client_ca)? {
+        roots
+            .add(&cert)
+            .with_context(|| format!("Invalid CA certificate in {}", client_ca.display()))?;
+    }
+    Ok(AllowAnyAuthenticatedClient::new(roots).boxed())
+}
+
+/// A server certificate that can be replaced while the server runs
+struct ReloadableCert {
+    current: RwLock<Arc<CertifiedKey>>,
+}
+
+impl ResolvesServerCert for ReloadableCert {
+    fn resolve(&self, _: ClientHello) -> Option<Arc<CertifiedKey>> {
+        Some(self.current.read().unwrap().clone())
+    }
+}
+
+/// A client certificate verifier whose trust anchors can be replaced while the server runs
+struct ReloadableClientVerifier {
+    current: RwLock<Arc<dyn ClientCertVerifier>>,
+}
+
+impl ClientCertVerifier for ReloadableClientVerifier {
+    fn client_auth_mandatory(&self) -> bool {
+        true
+    }
+
+    /// No CA names are advertised since they can change on reload; clients then
+    /// send the certificate they have
+    fn client_auth_root_subjects(&self) -> &[DistinguishedName] {
+        &[]
+    }
+
+    fn verify_client_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        now: SystemTime,
+    ) -> Result<ClientCertVerified, rustls::Error> {
+        let current = self.current.read().unwrap().clone();
+        current.verify_client_cert(end_entity, intermediates, now)
+    }
+}
+
+/// Reloads the TLS material of a running server.
+pub struct TlsReloader {
+    tls: TlsConfig,
+    cert: Arc<ReloadableCert>,
+    client_verifier: Option<Arc<ReloadableClientVerifier>>,
+}
+
+impl TlsReloader {
+    /// Loads the TLS material and builds the server configuration.
+    ///
+    /// # Returns
+    /// The rustls configuration to listen with, and the handle that reloads its
+    /// certificates
+    pub fn new(tls: &TlsConfig) -> Result<(ServerConfig, Self)> {
+        let cert = Arc::new(ReloadableCert {
+            current: RwLock::new(Arc::new(load_certified_key(tls)?)),
+        });
+        let client_verifier = tls
+            .client_ca
+            .as_ref()
+            .map(|ca| {
+                Ok::<_, anyhow::Error>(Arc::new(ReloadableClientVerifier {
+                    current: RwLock::new(load_client_verifier(ca)?),
+                }))
+            })
+            .transpose()?;
+
+        let reloader = Self {
+            tls: tls.clone(),
+            cert,
+            client_verifier,
+        };
+        Ok((reloader.server_config(vec![b"http/1.1".to_vec()]), reloader))
+    }
+
+    /// A server configuration sharing the reloadable certificate and client CA,
+    /// for another listener such as the gRPC API.
+    ///
+    /// # Arguments
+    /// * `alpn_protocols` - The protocols the listener negotiates
+    pub fn server_config(&self, alpn_protocols: Vec<Vec<u8>>) -> ServerConfig {
+        let builder = ServerConfig::builder().with_safe_defaults();
+        let mut config = match &self.client_verifier {
+            Some(verifier) => builder
+                .with_client_cert_verifier(verifier.clone() as Arc<dyn ClientCertVerifier>)
+                .with_cert_resolver(self.cert.clone()),
+            None => builder.with_no_client_auth().with_cert_resolver(self.cert.clone()),
+        };
+        config.alpn_protocols = alpn_protocols;
+        config
+    }
+
+    /// Reloads the server certificate, key and client CA from their files.
+    ///
+    /// Nothing is replaced unless all of them load, so a bad file leaves the
+    /// server running with its previous material.
+    pub fn reload(&self) -> Result<()> {
+        let cert = load_certified_key(&self.tls)?;
+        let verifier = match (&self.client_verifier, &self.tls.client_ca) {
+            (Some(_), Some(ca)) => Some(load_client_verifier(ca)?),
+            _ => None,
+        };
+        *self.cert.current.write().unwrap() = Arc::new(cert);
+        if let (Some(current), Some(verifier)) = (&self.client_verifier, verifier) {
+            *current.current.write().unwrap() = verifier;
+        }
+        Ok(())
+    }
+}
+
+/// Reloads the TLS material every time the process receives `SIGHUP`.
+#[cfg(unix)]
+pub async fn reload_on_sighup(reloader: Arc<TlsReloader>) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(e) => {
+            error!("Failed to listen for SIGHUP, TLS reload is disabled: {}", e);
+            return;
+        }
+    };
+    while hangups.recv().await.is_some() {
+        match reloader.reload() {
+            Ok(()) => info!("Reloaded TLS certificates"),
+            Err(e) => error!("Failed to reload TLS certificates, keeping the previous ones: {:#}", e),
+        }
+    }
+}
+
+/// The names of the verified certificate a client connected with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientIdentity {
+    pub common_name: Option<String>,
+    /// DNS, URI and email subject alternative names
+    pub alt_names: Vec<String>,
+}
+
+impl ClientIdentity {
+    /// Reads the names of a DER certificate.
+    pub fn from_der(der: &[u8]) -> Result<Self> {
+        let (_, cert) = x509_parser::parse_x509_certificate(der).map_err(|e| anyhow!("Malformed certificate: {}", e))?;
+        let common_name = cert
+            .subject()
+            .iter_common_name()
+            .next()
+            .and_then(|cn| cn.as_str().ok())
+            .map(str::to_string);
+        let alt_names = match cert.subject_alternative_name() {
+            Ok(Some(san)) => san
+                .value
+                .general_names
+                .iter()
+                .filter_map(|name| match name {
+                    GeneralName::DNSName(name) | GeneralName::URI(name) | GeneralName::RFC822Name(name) => {
+                        Some(name.to_string())
+                    }
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        Ok(Self { common_name, alt_names })
+    }
+
+    /// Whether the certificate carries a name as its common name or an alternative name
+    pub fn has_name(&self, name: &str) -> bool {
+        self.common_name.as_deref() == Some(name) || self.alt_names.iter().any(|n| n == name)
+    }
+}
+
+/// Connection hook attaching the [`ClientIdentity`] of TLS clients to the connection data.
+pub fn on_connect(connection: &dyn Any, data: &mut Extensions) {
+    let Some(stream) = connection.downcast_ref::<TlsStream<TcpStream>>() else {
+        return;
+    };
+    if let Some(identity) = peer_identity(stream.get_ref().1) {
+        data.insert(identity);
+    }
+}
+
+/// The [`ClientIdentity`] of the certificate a TLS client connected with, if any
+pub fn peer_identity(session: &ServerConnection) -> Option<ClientIdentity> {
+    let cert = session.peer_certificates()?.first()?;
+    ClientIdentity::from_der(&cert.0)
+        .map_err(|e| error!("Failed to read client certificate: {}", e))
+        .ok()
+}
+
+/// Principals for client certificates, registered as app data
+#[derive(Debug, Clone, Default)]
+pub struct ClientPrincipals(pub Vec<ClientPrincipal>);
+
+impl ClientPrincipals {
+    /// The principal of the first configured client the certificate matches
+    pub fn principal_for(&self, identity: &ClientIdentity) -> Option<Principal> {
+        self.0
+            .iter()
+            .find(|client| identity.has_name(&client.name))
+            .map(ClientPrincipal::principal)
+    }
+}
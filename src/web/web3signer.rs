@@ -0,0 +1,147 @@
+//! Web3Signer-style endpoints, so that tooling written for Ethereum validator
+//! signers can be adapted to BN254 keys with little glue:
+//!
+//! - `GET /api/v1/eth2/publicKeys` lists the G1 public keys of the caller's keys
+//! - `POST /api/v1/eth2/sign/{identifier}` signs with the key of a public key
+//!
+//! Keys are addressed by their G1 or G2 public key in hex rather than by EOA
+//! address, and the kind of message is carried in the `type` of the body.
+//! Signing goes through the same checks, history and audit log as
+//! `POST /api/sign`, and a key the caller may not use is reported as unknown. As
+//! with Web3Signer, the signature is returned as hex text unless the client
+//! accepts `application/json`, and a refusal of the anti-equivocation guard is a
+//! `412 Precondition Failed`.
+
+use actix_web::http::header::Accept;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse};
+
+use crate::encoding::{g1_from_gnark_raw, g1_to_bytes, g1_to_compressed};
+use crate::web::audit::{append_record, AuditLog, AuditRecord, RequestId};
+use crate::web::auth::Principal;
+use crate::web::error::ApiError;
+use crate::web::handlers::{accessible_key_pair_by_public_key, sign_request, SigningChecks};
+use crate::web::models::{G1Point, MessageInput, SignRequest, Web3SignerRequest, Web3SignerResponse, Web3SignerType};
+use crate::web::store::KeyStore;
+
+/// List the G1 public keys the caller may read, as compressed hex
+pub async fn public_keys(store: web::Data<dyn KeyStore>, principal: Principal) -> Result<HttpResponse, ApiError> {
+    let key_pairs = store
+        .list_key_pairs()
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to list key pairs: {}", e)))?;
+    let mut public_keys = Vec::with_capacity(key_pairs.len());
+    for key_pair in key_pairs.iter().filter(|kp| principal.can_access(&kp.eoa_address)) {
+        let point = key_pair
+            .public_key_g1
+            .to_checked_g1_point()
+            .map_err(|e| ApiError::internal(format!("Invalid stored public key: {}", e)))?;
+        public_keys.push(format!("0x{}", hex::encode(g1_to_compressed(&point))));
+    }
+    Ok(HttpResponse::Ok().json(public_keys))
+}
+
+/// Sign a message with the key of a G1 or G2 public key
+pub async fn sign(
+    store: web::Data<dyn KeyStore>,
+    checks: SigningChecks,
+    audit: web::Data<dyn AuditLog>,
+    principal: Principal,
+    request_id: RequestId,
+    http: HttpRequest,
+    req: web::Json<Web3SignerRequest>,
+) -> Result<HttpResponse, ApiError> {
+    // Until a key matches, the request is audited under the identifier it gave
+    let identifier = http.match_info().query("identifier");
+    let mut record = AuditRecord::new(&request_id, &principal.name, identifier, "sign");
+    let response = async {
+        let key_pair =
+            accessible_key_pair_by_public_key(store.get_ref(), &principal, identifier, "identifier", &mut record).await?;
+        let req = req.into_inner();
+        let sign = SignRequest {
+            eoa_address: key_pair.eoa_address,
+            message: message_input(&req)?,
+            authorization: req.authorization,
+            context: req.context,
+        };
+        sign_request(store.get_ref(), &checks, &principal, &sign, &mut record).await
+    }
+    .await;
+    record.finish(response.as_ref().map(|response| &response.product));
+    append_record(audit.get_ref(), record).await?;
+
+    let product = response.map_err(|e| match e.body.error.as_str() {
+        "equivocation" => ApiError { status: StatusCode::PRECONDITION_FAILED, ..e },
+        _ => e,
+    })?;
+    let signature = product
+        .product
+        .to_checked_g1_point()
+        .map_err(|e| ApiError::internal(format!("Invalid signature: {}", e)))?;
+    let signature = format!("0x{}", hex::encode(g1_to_bytes(&signature)));
+    if accepts_json(&http) {
+        Ok(HttpResponse::Ok().json(Web3SignerResponse { signature }))
+    } else {
+        Ok(HttpResponse::Ok().content_type("text/plain").body(signature))
+    }
+}
+
+/// Converts the message of a request to the form `POST /api/sign` takes
+fn message_input(req: &Web3SignerRequest) -> Result<MessageInput, ApiError> {
+    let (field, given) = match req.kind {
+        Web3SignerType::Hash => ("signingRoot", req.signing_root.is_some()),
+        Web3SignerType::G1Point => ("message", req.message.is_some()),
+        Web3SignerType::Source => ("messageSource", req.message_source.is_some()),
+    };
+    if !given {
+        return Err(ApiError::bad_request(
+            "missing_field",
+            field,
+            format!("{} is required for type {:?}", field, req.kind),
+        ));
+    }
+    let others = [req.signing_root.is_some(), req.message.is_some(), req.message_source.is_some()];
+    if others.iter().filter(|given| **given).count() > 1 {
+        return Err(ApiError::bad_request(
+            "conflicting_fields",
+            field,
+            format!("give only {} for type {:?}", field, req.kind),
+        ));
+    }
+
+    Ok(match req.kind {
+        Web3SignerType::Hash => MessageInput {
+            message_hash: req.signing_root.clone(),
+            ..Default::default()
+        },
+        Web3SignerType::Source => MessageInput {
+            message_source: req.message_source.clone(),
+            ..Default::default()
+        },
+        Web3SignerType::G1Point => {
+            let message = req.message.as_deref().unwrap_or_default();
+            let invalid = |message: &str| ApiError::bad_request("invalid_point", field, message);
+            let bytes = hex::decode(message.trim_start_matches("0x")).map_err(|_| invalid("message must be hex"))?;
+            match bytes.len() {
+                32 => MessageInput {
+                    message_compressed: Some(message.to_string()),
+                    ..Default::default()
+                },
+                64 => {
+                    let point = g1_from_gnark_raw(&bytes).map_err(|e| invalid(&e))?;
+                    MessageInput {
+                        message_point: Some(G1Point::from(&point)),
+                        ..Default::default()
+                    }
+                }
+                _ => return Err(invalid("message must be a 64-byte or compressed 32-byte G1 point")),
+            }
+        }
+    })
+}
+
+/// Whether the client asked for a JSON response rather than hex text
+fn accepts_json(req: &HttpRequest) -> bool {
+    req.get_header::<Accept>()
+        .is_some_and(|accept| accept.iter().any(|item| item.item.essence_str() == "application/json"))
+}
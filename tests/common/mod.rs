@@ -0,0 +1,76 @@
+//! App setup shared by the web integration tests.
+
+use actix_web::dev::{ServiceFactory, ServiceRequest};
+use actix_web::{web, App};
+use bn254_rs::web::approvals::ApprovalQueue;
+use bn254_rs::web::audit::{AuditLog, MemoryAuditLog};
+use bn254_rs::web::auth::Authenticator;
+use bn254_rs::web::config::{ApprovalsConfig, EndpointsConfig};
+use bn254_rs::web::eoa_auth::EoaVerifier;
+use bn254_rs::web::guard::EquivocationGuard;
+use bn254_rs::web::metrics::Metrics;
+use bn254_rs::web::policy::PolicyEngine;
+use bn254_rs::web::signer::{LocalSigner, Signer};
+use bn254_rs::web::store::KeyStore;
+use bn254_rs::web::tls::ClientPrincipals;
+use bn254_rs::web::{api_scope, configure_metrics};
+use std::sync::Arc;
+
+/// The app data of a key service, as `start_server` registers it.
+///
+/// [`TestApp::new`] gives an open service with default checks; tests override
+/// the fields they exercise with struct update syntax.
+#[derive(Clone)]
+pub struct TestApp {
+    pub store: web::Data<dyn KeyStore>,
+    pub signer: web::Data<dyn Signer>,
+    pub authenticator: web::Data<Authenticator>,
+    pub eoa_verifier: web::Data<EoaVerifier>,
+    pub audit: web::Data<dyn AuditLog>,
+    pub policy: web::Data<PolicyEngine>,
+    pub guard: web::Data<EquivocationGuard>,
+    pub approvals: web::Data<ApprovalQueue>,
+    pub metrics: web::Data<Metrics>,
+    pub client_principals: web::Data<ClientPrincipals>,
+    pub endpoints: EndpointsConfig,
+}
+
+impl TestApp {
+    /// A service over `store` with the local signer, no authentication and
+    /// default policies, guard and endpoints
+    pub fn new(store: Arc<dyn KeyStore>) -> Self {
+        Self {
+            store: web::Data::from(store),
+            signer: web::Data::from(Arc::new(LocalSigner) as Arc<dyn Signer>),
+            authenticator: web::Data::new(Authenticator::Disabled),
+            eoa_verifier: web::Data::new(EoaVerifier::default()),
+            audit: web::Data::from(Arc::new(MemoryAuditLog::new()) as Arc<dyn AuditLog>),
+            policy: web::Data::new(PolicyEngine::default()),
+            guard: web::Data::new(EquivocationGuard::default()),
+            approvals: web::Data::new(ApprovalQueue::from_config(&ApprovalsConfig::default())),
+            metrics: web::Data::new(Metrics::new()),
+            client_principals: web::Data::new(ClientPrincipals::default()),
+            endpoints: EndpointsConfig::default(),
+        }
+    }
+
+    /// Registers the app data and the API routes on `app`
+    pub fn register<T>(&self, app: App<T>) -> App<T>
+    where
+        T: ServiceFactory<ServiceRequest, Config = (), Error = actix_web::Error, InitError = ()>,
+    {
+        let endpoints = self.endpoints.clone();
+        app.app_data(self.store.clone())
+            .app_data(self.signer.clone())
+            .app_data(self.authenticator.clone())
+            .app_data(self.eoa_verifier.clone())
+            .app_data(self.audit.clone())
+            .app_data(self.policy.clone())
+            .app_data(self.guard.clone())
+            .app_data(self.approvals.clone())
+            .app_data(self.metrics.clone())
+            .app_data(self.client_principals.clone())
+            .service(api_scope(&endpoints))
+            .configure(|cfg| configure_metrics(cfg, &endpoints))
+    }
+}
@@ -0,0 +1,167 @@
+use ark_bn254::Fr;
+use bn254_rs::dkg::*;
+use bn254_rs::threshold::{combine_signatures, recover_secret, sign_share, verify_partial_signature};
+use bn254_rs::*;
+
+/// Hooks for simulating a misbehaving dealer.
+#[derive(Default)]
+struct Faults {
+    /// Dealer whose share to a given recipient is corrupted: (dealer, recipient)
+    corrupt_share: Option<(u64, u64)>,
+    /// Dealers that answer complaints with a corrupted share
+    bad_justification: Vec<u64>,
+    /// Dealers that never answer complaints
+    silent: Vec<u64>,
+}
+
+/// Runs a DKG in-process, delivering every message to every participant.
+fn run_dkg(params: DkgParams, faults: Faults) -> (Vec<DkgOutput>, Vec<Complaint>) {
+    let mut rng = rand::thread_rng();
+    let mut participants: Vec<Participant> = (1..=params.participants as u64)
+        .map(|i| Participant::new(i, params, &mut rng).unwrap())
+        .collect();
+
+    // Phase 1: dealing
+    let deals: Vec<_> = participants.iter().map(|p| p.deal().unwrap()).collect();
+    for (commitment, shares) in deals {
+        for p in participants.iter_mut() {
+            p.receive_commitment(commitment.clone()).unwrap();
+        }
+        for mut share in shares {
+            if faults.corrupt_share == Some((share.dealer, share.recipient)) {
+                share.value += Fr::from(1u64);
+            }
+            participants[share.recipient as usize - 1]
+                .receive_share(share)
+                .unwrap();
+        }
+    }
+
+    // Phase 2: complaints
+    let complaints: Vec<Complaint> = participants
+        .iter_mut()
+        .flat_map(|p| p.complaints().unwrap())
+        .collect();
+    for p in participants.iter_mut() {
+        for complaint in &complaints {
+            if complaint.accuser != p.index() {
+                p.receive_complaint(*complaint).unwrap();
+            }
+        }
+    }
+
+    // Phase 3: justifications
+    let mut justifications = Vec::new();
+    for p in participants.iter_mut() {
+        let mut answers = p.justifications().unwrap();
+        if faults.silent.contains(&p.index()) {
+            continue;
+        }
+        if faults.bad_justification.contains(&p.index()) {
+            for j in answers.iter_mut() {
+                j.share.value += Fr::from(1u64);
+            }
+        }
+        justifications.extend(answers);
+    }
+    for p in participants.iter_mut() {
+        for j in &justifications {
+            p.receive_justification(*j).unwrap();
+        }
+    }
+
+    // Phase 4: finalize
+    let outputs = participants.iter_mut().map(|p| p.finalize().unwrap()).collect();
+    (outputs, complaints)
+}
+
+fn assert_consistent(outputs: &[DkgOutput]) {
+    for o in outputs {
+        assert_eq!(o.group_public_key_g1, outputs[0].group_public_key_g1);
+        assert_eq!(o.group_public_key_g2, outputs[0].group_public_key_g2);
+        assert_eq!(o.qualified, outputs[0].qualified);
+        assert!(verify_pubkey_pair(&o.group_public_key_g1, &o.group_public_key_g2));
+        assert_eq!(o.public_key_shares[&o.share.index], o.share.public_key_g2());
+    }
+}
+
+#[test]
+fn test_honest_dkg_produces_usable_threshold_key() {
+    let params = DkgParams::new(3, 5).unwrap();
+    let (outputs, complaints) = run_dkg(params, Faults::default());
+    assert!(complaints.is_empty());
+    assert_consistent(&outputs);
+    assert_eq!(outputs[0].qualified, vec![1, 2, 3, 4, 5]);
+
+    let message = hash_to_g1(&[0xab; 32]);
+    let partials: Vec<_> = outputs[1..4]
+        .iter()
+        .map(|o| sign_share(&o.share, &message))
+        .collect();
+    for (partial, output) in partials.iter().zip(&outputs[1..4]) {
+        assert!(verify_partial_signature(
+            partial,
+            &message,
+            &outputs[0].public_key_shares[&output.share.index]
+        ));
+    }
+    let signature = combine_signatures(&partials, 3).unwrap();
+    assert!(verify_signature(&message, &signature, &outputs[0].group_public_key_g2));
+
+    // The reconstructed group secret matches the group public key
+    let shares: Vec<_> = outputs.iter().map(|o| o.share).collect();
+    let secret = recover_secret(&shares[..3], 3).unwrap();
+    assert_eq!(G1Point::generator().scalar_mul(secret), outputs[0].group_public_key_g1);
+}
+
+#[test]
+fn test_justified_complaint_keeps_dealer_qualified() {
+    let params = DkgParams::new(2, 4).unwrap();
+    let faults = Faults {
+        corrupt_share: Some((2, 3)),
+        ..Default::default()
+    };
+    let (outputs, complaints) = run_dkg(params, faults);
+    assert_eq!(complaints, vec![Complaint { accuser: 3, dealer: 2 }]);
+    assert_consistent(&outputs);
+    assert_eq!(outputs[0].qualified, vec![1, 2, 3, 4]);
+}
+
+#[test]
+fn test_bad_justification_disqualifies_dealer() {
+    let params = DkgParams::new(2, 4).unwrap();
+    let faults = Faults {
+        corrupt_share: Some((2, 3)),
+        bad_justification: vec![2],
+        ..Default::default()
+    };
+    let (outputs, _) = run_dkg(params, faults);
+    assert_consistent(&outputs);
+    assert_eq!(outputs[0].qualified, vec![1, 3, 4]);
+}
+
+#[test]
+fn test_unanswered_complaint_disqualifies_dealer() {
+    let params = DkgParams::new(2, 4).unwrap();
+    let faults = Faults {
+        corrupt_share: Some((4, 1)),
+        silent: vec![4],
+        ..Default::default()
+    };
+    let (outputs, _) = run_dkg(params, faults);
+    assert_consistent(&outputs);
+    assert_eq!(outputs[0].qualified, vec![1, 2, 3]);
+}
+
+#[test]
+fn test_phase_misuse_is_rejected() {
+    let params = DkgParams::new(2, 3).unwrap();
+    let mut p = Participant::new(1, params, &mut rand::thread_rng()).unwrap();
+    assert!(p.justifications().is_err());
+    assert!(p.finalize().is_err());
+    assert!(p
+        .receive_complaint(Complaint { accuser: 2, dealer: 1 })
+        .is_err());
+    assert!(Participant::new(4, params, &mut rand::thread_rng()).is_err());
+    assert!(DkgParams::new(4, 3).is_err());
+}
@@ -0,0 +1,56 @@
+use ark_bn254::Fr;
+use bn254_rs::encoding::*;
+use bn254_rs::*;
+
+#[test]
+fn test_g1_generator_compressed_matches_gnark() {
+    // gnark encodes the generator (1, 2) as x = 1 with the "smallest" flag
+    let mut expected = [0u8; 32];
+    expected[0] = 0x80;
+    expected[31] = 1;
+    assert_eq!(g1_to_compressed(&G1Point::generator()), expected);
+    assert_eq!(g1_from_compressed(&expected).unwrap(), G1Point::generator());
+}
+
+#[test]
+fn test_roundtrips() {
+    for k in [1u64, 2, 3, 1000, 123456789] {
+        let s = Fr::from(k);
+        let g1 = G1Point::generator().scalar_mul(s);
+        let g2 = G2Point::generator().scalar_mul(s);
+        assert_eq!(g1_from_bytes(&g1_to_bytes(&g1)).unwrap(), g1);
+        assert_eq!(g1_from_compressed(&g1_to_compressed(&g1)).unwrap(), g1);
+        assert_eq!(g2_from_bytes(&g2_to_bytes(&g2)).unwrap(), g2);
+        assert_eq!(g2_from_compressed(&g2_to_compressed(&g2)).unwrap(), g2);
+
+        let neg = g1.negate();
+        assert_eq!(g1_from_compressed(&g1_to_compressed(&neg)).unwrap(), neg);
+        let neg2 = g2.negate();
+        assert_eq!(g2_from_compressed(&g2_to_compressed(&neg2)).unwrap(), neg2);
+    }
+}
+
+#[test]
+fn test_infinity() {
+    assert_eq!(g1_to_bytes(&G1Point::zero()), [0u8; 64]);
+    assert_eq!(g1_from_bytes(&[0u8; 64]).unwrap(), G1Point::zero());
+    assert_eq!(g1_from_compressed(&g1_to_compressed(&G1Point::zero())).unwrap(), G1Point::zero());
+    assert_eq!(g2_from_compressed(&g2_to_compressed(&G2Point::zero())).unwrap(), G2Point::zero());
+}
+
+#[test]
+fn test_invalid_points_rejected() {
+    let mut bytes = g1_to_bytes(&G1Point::generator());
+    bytes[63] = 3;
+    assert!(g1_from_bytes(&bytes).is_err());
+    assert!(g1_from_bytes(&bytes[..32]).is_err());
+
+    // Only compressed flags are accepted by the compressed decoder
+    let uncompressed = [0u8; 32];
+    assert!(g1_from_compressed(&uncompressed).is_err());
+
+    // Coordinates must be reduced modulo p
+    let mut unreduced = [0xffu8; 64];
+    unreduced[0] = 0x3f;
+    assert!(g1_from_bytes(&unreduced).is_err());
+}
@@ -0,0 +1,192 @@
+use ark_bn254::{Fr, G1Affine, G2Affine};
+use ark_ec::CurveGroup;
+use ark_ff::{Field, UniformRand};
+use bn254_rs::encoding::*;
+use bn254_rs::groth16::*;
+use bn254_rs::*;
+use serde_json::json;
+
+/// A verification key and a valid proof built from known trapdoor values.
+///
+/// Without a circuit we cannot run a prover, but knowing alpha, beta, gamma,
+/// delta and the discrete logs of the IC points lets us solve the verification
+/// equation for C directly.
+struct Fixture {
+    vk: VerifyingKey,
+    proof: Proof,
+    inputs: Vec<Fr>,
+}
+
+fn fixture(n_public: usize) -> Fixture {
+    let mut rng = rand::thread_rng();
+    let [alpha, beta, gamma, delta, a, b] = [(); 6].map(|_| Fr::rand(&mut rng));
+    let ic_logs: Vec<Fr> = (0..=n_public).map(|_| Fr::rand(&mut rng)).collect();
+    let inputs: Vec<Fr> = (0..n_public).map(|_| Fr::rand(&mut rng)).collect();
+
+    // a * b = alpha * beta + x * gamma + c * delta
+    let x = ic_logs[0]
+        + inputs
+            .iter()
+            .zip(&ic_logs[1..])
+            .map(|(i, l)| *i * l)
+            .sum::<Fr>();
+    let c = (a * b - alpha * beta - x * gamma) * delta.inverse().unwrap();
+
+    let g1 = G1Point::generator();
+    let g2 = G2Point::generator();
+    Fixture {
+        vk: VerifyingKey {
+            alpha_g1: g1.scalar_mul(alpha),
+            beta_g2: g2.scalar_mul(beta),
+            gamma_g2: g2.scalar_mul(gamma),
+            delta_g2: g2.scalar_mul(delta),
+            ic: ic_logs.iter().map(|l| g1.scalar_mul(*l)).collect(),
+        },
+        proof: Proof {
+            a: g1.scalar_mul(a),
+            b: g2.scalar_mul(b),
+            c: g1.scalar_mul(c),
+        },
+        inputs,
+    }
+}
+
+fn g1_json(p: &G1Point) -> serde_json::Value {
+    let p: G1Affine = p.inner().into_affine();
+    json!([p.x.to_string(), p.y.to_string(), "1"])
+}
+
+fn g2_json(p: &G2Point) -> serde_json::Value {
+    let p: G2Affine = p.inner().into_affine();
+    json!([
+        [p.x.c0.to_string(), p.x.c1.to_string()],
+        [p.y.c0.to_string(), p.y.c1.to_string()],
+        ["1", "0"]
+    ])
+}
+
+fn snarkjs_vk(vk: &VerifyingKey) -> String {
+    json!({
+        "protocol": "groth16",
+        "curve": "bn128",
+        "nPublic": vk.ic.len() - 1,
+        "vk_alpha_1": g1_json(&vk.alpha_g1),
+        "vk_beta_2": g2_json(&vk.beta_g2),
+        "vk_gamma_2": g2_json(&vk.gamma_g2),
+        "vk_delta_2": g2_json(&vk.delta_g2),
+        "IC": vk.ic.iter().map(g1_json).collect::<Vec<_>>(),
+    })
+    .to_string()
+}
+
+fn snarkjs_proof(proof: &Proof) -> String {
+    json!({
+        "pi_a": g1_json(&proof.a),
+        "pi_b": g2_json(&proof.b),
+        "pi_c": g1_json(&proof.c),
+        "protocol": "groth16",
+        "curve": "bn128",
+    })
+    .to_string()
+}
+
+fn gnark_vk(vk: &VerifyingKey, compressed: bool) -> Vec<u8> {
+    let g1 = |p: &G1Point| -> Vec<u8> {
+        if compressed {
+            g1_to_compressed(p).to_vec()
+        } else {
+            g1_to_bytes(p).to_vec()
+        }
+    };
+    let g2 = |p: &G2Point| -> Vec<u8> {
+        if compressed {
+            g2_to_compressed(p).to_vec()
+        } else {
+            g2_to_bytes(p).to_vec()
+        }
+    };
+    let mut out = Vec::new();
+    out.extend(g1(&vk.alpha_g1));
+    out.extend(g1(&G1Point::generator())); // [beta]_1 is not used by the verifier
+    out.extend(g2(&vk.beta_g2));
+    out.extend(g2(&vk.gamma_g2));
+    out.extend(g1(&G1Point::generator())); // [delta]_1 is not used by the verifier
+    out.extend(g2(&vk.delta_g2));
+    out.extend((vk.ic.len() as u32).to_be_bytes());
+    for p in &vk.ic {
+        out.extend(g1(p));
+    }
+    out.extend(0u32.to_be_bytes());
+    out.extend(0u32.to_be_bytes());
+    out
+}
+
+#[test]
+fn test_verify_valid_proof() {
+    let f = fixture(3);
+    assert!(verify(&f.vk, &f.proof, &f.inputs).unwrap());
+}
+
+#[test]
+fn test_reject_wrong_inputs() {
+    let f = fixture(2);
+    let mut inputs = f.inputs.clone();
+    inputs[1] += Fr::from(1u64);
+    assert!(!verify(&f.vk, &f.proof, &inputs).unwrap());
+    assert!(verify(&f.vk, &f.proof, &inputs[..1]).is_err());
+}
+
+#[test]
+fn test_reject_tampered_proof() {
+    let f = fixture(1);
+    let mut proof = f.proof;
+    proof.c = proof.c.add(&G1Point::generator());
+    assert!(!verify(&f.vk, &proof, &f.inputs).unwrap());
+}
+
+#[test]
+fn test_snarkjs_roundtrip() {
+    let f = fixture(2);
+    let vk = VerifyingKey::from_snarkjs_json(&snarkjs_vk(&f.vk)).unwrap();
+    let proof = Proof::from_snarkjs_json(&snarkjs_proof(&f.proof)).unwrap();
+    let public = json!(f.inputs.iter().map(|i| i.to_string()).collect::<Vec<_>>()).to_string();
+    let inputs = parse_snarkjs_public_inputs(&public).unwrap();
+
+    assert_eq!(vk, f.vk);
+    assert_eq!(proof, f.proof);
+    assert!(verify(&vk, &proof, &inputs).unwrap());
+}
+
+#[test]
+fn test_snarkjs_rejects_bad_files() {
+    let f = fixture(1);
+    let vk = snarkjs_vk(&f.vk).replace("\"nPublic\":1", "\"nPublic\":2");
+    assert!(VerifyingKey::from_snarkjs_json(&vk).is_err());
+
+    let plonk = snarkjs_proof(&f.proof).replace("groth16", "plonk");
+    assert!(Proof::from_snarkjs_json(&plonk).is_err());
+
+    // Public inputs must be reduced modulo r, as in the Solidity verifier
+    let r = "21888242871839275222246405745257275088548364400416034343698204186575808495617";
+    assert!(parse_snarkjs_public_inputs(&format!("[\"{}\"]", r)).is_err());
+}
+
+#[test]
+fn test_gnark_binary_formats() {
+    let f = fixture(2);
+    for compressed in [true, false] {
+        let vk = VerifyingKey::from_gnark_bytes(&gnark_vk(&f.vk, compressed)).unwrap();
+        assert_eq!(vk, f.vk);
+        assert!(verify(&vk, &f.proof, &f.inputs).unwrap());
+    }
+}
+
+#[test]
+fn test_gnark_rejects_commitments_and_truncation() {
+    let f = fixture(1);
+    let mut bytes = gnark_vk(&f.vk, true);
+    let len = bytes.len();
+    bytes[len - 1] = 1;
+    assert!(VerifyingKey::from_gnark_bytes(&bytes).is_err());
+    assert!(VerifyingKey::from_gnark_bytes(&bytes[..len - 20]).is_err());
+}
@@ -0,0 +1,139 @@
+use ark_bn254::Fr;
+use ark_ff::UniformRand;
+use bn254_rs::encoding::{g1_to_compressed, g2_to_compressed};
+use bn254_rs::kzg::*;
+use bn254_rs::*;
+
+fn setup(size: usize) -> Srs {
+    Srs::insecure_from_secret(Fr::rand(&mut rand::thread_rng()), size)
+}
+
+fn random_poly(len: usize) -> Vec<Fr> {
+    let mut rng = rand::thread_rng();
+    (0..len).map(|_| Fr::rand(&mut rng)).collect()
+}
+
+#[test]
+fn test_open_and_verify() {
+    let srs = setup(16);
+    let poly = random_poly(16);
+    let commitment = commit(&srs, &poly).unwrap();
+    let opening = open(&srs, &poly, Fr::from(17u64)).unwrap();
+    assert!(verify(&srs, &commitment, &opening));
+
+    let mut wrong = opening;
+    wrong.value += Fr::from(1u64);
+    assert!(!verify(&srs, &commitment, &wrong));
+}
+
+#[test]
+fn test_constant_polynomial() {
+    let srs = setup(4);
+    let poly = [Fr::from(9u64)];
+    let commitment = commit(&srs, &poly).unwrap();
+    let opening = open(&srs, &poly, Fr::from(3u64)).unwrap();
+    assert_eq!(opening.proof, G1Point::zero());
+    assert!(verify(&srs, &commitment, &opening));
+}
+
+#[test]
+fn test_degree_exceeds_srs() {
+    let srs = setup(4);
+    assert!(commit(&srs, &random_poly(5)).is_err());
+    assert_eq!(srs.max_degree(), 3);
+}
+
+#[test]
+fn test_multi_point_openings() {
+    let srs = setup(8);
+    let poly = random_poly(8);
+    let commitment = commit(&srs, &poly).unwrap();
+    let points: Vec<Fr> = (1..=5u64).map(Fr::from).collect();
+    let openings = open_multi(&srs, &poly, &points).unwrap();
+
+    let items: Vec<_> = openings.iter().map(|o| (commitment, *o)).collect();
+    assert!(batch_verify(&srs, &items));
+
+    let mut tampered = items.clone();
+    tampered[3].1.value += Fr::from(1u64);
+    assert!(!batch_verify(&srs, &tampered));
+}
+
+#[test]
+fn test_batch_verify_across_commitments() {
+    let srs = setup(8);
+    let items: Vec<_> = (0..4u64)
+        .map(|i| {
+            let poly = random_poly(8);
+            let commitment = commit(&srs, &poly).unwrap();
+            (commitment, open(&srs, &poly, Fr::from(100 + i)).unwrap())
+        })
+        .collect();
+    assert!(batch_verify(&srs, &items));
+
+    let mut swapped = items.clone();
+    swapped[0].0 = items[1].0;
+    assert!(!batch_verify(&srs, &swapped));
+}
+
+#[test]
+fn test_batch_open_same_point() {
+    let srs = setup(8);
+    let polys: Vec<Vec<Fr>> = vec![random_poly(8), random_poly(3), random_poly(6)];
+    let commitments: Vec<_> = polys.iter().map(|p| commit(&srs, p).unwrap()).collect();
+    let point = Fr::from(42u64);
+
+    let opening = batch_open(&srs, &polys, &commitments, point).unwrap();
+    assert!(verify_batch_opening(&srs, &commitments, &opening));
+
+    let mut wrong = opening.clone();
+    wrong.values[1] += Fr::from(1u64);
+    assert!(!verify_batch_opening(&srs, &commitments, &wrong));
+    assert!(!verify_batch_opening(&srs, &commitments[..2], &opening));
+}
+
+#[test]
+fn test_srs_from_compressed() {
+    let srs = setup(4);
+    let g1_bytes: Vec<u8> = srs.g1_powers().iter().flat_map(g1_to_compressed).collect();
+    let g2_bytes = g2_to_compressed(srs.g2_tau());
+    assert_eq!(Srs::from_compressed(&g1_bytes, &g2_bytes).unwrap(), srs);
+
+    // A G2 power for a different tau is rejected
+    let other = setup(2);
+    assert!(Srs::from_compressed(&g1_bytes, &g2_to_compressed(other.g2_tau())).is_err());
+}
+
+#[test]
+fn test_srs_checks_every_power() {
+    let srs = setup(6);
+    let mut powers = srs.g1_powers().to_vec();
+    // Only the fourth power is off, so a check of tau * G1 alone would pass
+    powers[4] = powers[4].add(&G1Point::generator());
+    assert!(Srs::new(powers, *srs.g2_tau()).is_err());
+    assert_eq!(Srs::new(srs.g1_powers().to_vec(), *srs.g2_tau()).unwrap(), srs);
+}
+
+#[test]
+fn test_srs_load_eigenda_layout() {
+    let tau = Fr::rand(&mut rand::thread_rng());
+    let srs = Srs::insecure_from_secret(tau, 4);
+    // g2.point holds the G2 powers from the generator up, so tau * G2 is the second point
+    let g1_bytes: Vec<u8> = srs.g1_powers().iter().flat_map(g1_to_compressed).collect();
+    let g2_bytes: Vec<u8> = [G2Point::generator(), *srs.g2_tau(), srs.g2_tau().scalar_mul(tau)]
+        .iter()
+        .flat_map(g2_to_compressed)
+        .collect();
+    let dir = std::env::temp_dir().join(format!("bn254-rs-srs-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (g1_path, g2_path) = (dir.join("g1.point"), dir.join("g2.point"));
+    std::fs::write(&g1_path, &g1_bytes).unwrap();
+    std::fs::write(&g2_path, &g2_bytes).unwrap();
+    assert_eq!(Srs::load(&g1_path, &g2_path, 3).unwrap(), Srs::insecure_from_secret(tau, 3));
+    assert!(Srs::load(&g1_path, &g2_path, 5).is_err());
+
+    // A file that starts with tau * G2 is not in the EigenDA layout
+    std::fs::write(&g2_path, &g2_bytes[64..]).unwrap();
+    assert!(Srs::load(&g1_path, &g2_path, 3).is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -150,3 +150,54 @@ fn test_operator_points() {
         assert!(result, "Pairing check failed at {}", i);
     }
 }
+
+/// Test function that checks every operator's G1 and G2 keys share a secret,
+/// using the library's batch validator.
+#[test]
+fn test_validate_operator_file() {
+    let operators = bn254_rs::operators::load_operators("testdata/operators.json").unwrap();
+    let errors = bn254_rs::operators::validate_operators(&operators);
+    assert!(errors.is_empty(), "unexpected errors: {:?}", errors);
+}
+
+/// Test function that swaps G2 keys between two operators and expects the
+/// batch validator to flag both entries.
+#[test]
+fn test_validate_operator_file_detects_mismatched_keys() {
+    let mut operators = bn254_rs::operators::load_operators("testdata/operators.json").unwrap();
+    let g2_0 = operators[0].bls_wallet.public_key_g2.clone();
+    operators[0].bls_wallet.public_key_g2 = operators[1].bls_wallet.public_key_g2.clone();
+    operators[1].bls_wallet.public_key_g2 = g2_0;
+
+    let errors = bn254_rs::operators::validate_operators(&operators);
+    let indices: Vec<usize> = errors.iter().map(|e| e.index).collect();
+    assert_eq!(indices, vec![0, 1]);
+    assert_eq!(errors[0].address, operators[0].wallet.address);
+}
+
+/// Test function that corrupts a G1 coordinate and expects the entry to be
+/// rejected before the pairing check.
+#[test]
+fn test_validate_operator_file_detects_off_curve_point() {
+    let mut operators = bn254_rs::operators::load_operators("testdata/operators.json").unwrap();
+    operators[2].bls_wallet.public_key_g1.y = "1".to_string();
+
+    let errors = bn254_rs::operators::validate_operators(&operators);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].index, 2);
+    assert!(errors[0].reason.contains("not on the curve"));
+}
+
+/// Test function for the single-pair consistency check.
+#[test]
+fn test_verify_pubkey_pair() {
+    let json = fs::read_to_string("testdata/operators.json").unwrap();
+    let operators: Vec<Operator> = serde_json::from_str(&json).unwrap();
+
+    let g1 = g1_from_json(&operators[0].bls_wallet.public_key_g1);
+    let g2 = g2_from_json(&operators[0].bls_wallet.public_key_g2);
+    let other_g2 = g2_from_json(&operators[1].bls_wallet.public_key_g2);
+
+    assert!(bn254_rs::verify_pubkey_pair(&g1, &g2));
+    assert!(!bn254_rs::verify_pubkey_pair(&g1, &other_g2));
+}
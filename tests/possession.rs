@@ -0,0 +1,71 @@
+use ark_bn254::Fr;
+use ark_ec::CurveGroup;
+use bn254_rs::*;
+
+fn key_pair(sk: u64) -> (Fr, G1Point, G2Point) {
+    let sk = Fr::from(sk);
+    (
+        sk,
+        G1Point::generator().scalar_mul(sk),
+        G2Point::generator().scalar_mul(sk),
+    )
+}
+
+#[test]
+fn test_hash_to_g1_is_on_curve() {
+    for seed in 0u8..16 {
+        let p = hash_to_g1(&[seed; 32]);
+        assert!(p.inner().into_affine().is_on_curve(), "seed {} not on curve", seed);
+    }
+}
+
+#[test]
+fn test_pop_roundtrip() {
+    let (sk, pk_g1, pk_g2) = key_pair(123456789);
+    let pop = prove_possession(sk);
+    assert!(verify_possession(&pk_g1, &pk_g2, &pop));
+}
+
+#[test]
+fn test_pop_rejects_wrong_secret() {
+    let (_, pk_g1, pk_g2) = key_pair(1000);
+    let pop = prove_possession(Fr::from(1001u64));
+    assert!(!verify_possession(&pk_g1, &pk_g2, &pop));
+}
+
+#[test]
+fn test_pop_rejects_mismatched_g1_key() {
+    let (sk, _, pk_g2) = key_pair(55);
+    let (_, other_g1, _) = key_pair(56);
+    let pop = prove_possession(sk);
+    assert!(!verify_possession(&other_g1, &pk_g2, &pop));
+}
+
+#[test]
+fn test_pop_rejects_rogue_key() {
+    // A rogue key pkG2' = G2 * x - pkG2 cannot be accompanied by a valid proof
+    // without knowing the honest secret, even if the attacker controls x.
+    let (_, honest_g1, honest_g2) = key_pair(77);
+    let (x, x_g1, x_g2) = key_pair(99);
+    let rogue_g1 = x_g1.add(&honest_g1.negate());
+    let rogue_g2 = x_g2.add(&honest_g2.negate());
+    let forged = prove_possession(x);
+    assert!(!verify_possession(&rogue_g1, &rogue_g2, &forged));
+}
+
+#[test]
+fn test_pop_rejects_infinity() {
+    let zero = Fr::from(0u64);
+    let pop = prove_possession(zero);
+    let g1 = G1Point::generator().scalar_mul(zero);
+    let g2 = G2Point::generator().scalar_mul(zero);
+    assert!(!verify_possession(&g1, &g2, &pop));
+}
+
+#[test]
+fn test_pop_is_not_a_plain_signature() {
+    // The PoP message is domain separated from the raw public key hash
+    let (sk, pk_g1, pk_g2) = key_pair(31337);
+    let plain = hash_to_g1(&hash_g1_point(&pk_g1)).scalar_mul(sk);
+    assert!(!verify_possession(&pk_g1, &pk_g2, &plain));
+}
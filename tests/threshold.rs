@@ -0,0 +1,63 @@
+use ark_bn254::Fr;
+use bn254_rs::threshold::*;
+use bn254_rs::*;
+
+fn message() -> G1Point {
+    hash_to_g1(&[7u8; 32])
+}
+
+#[test]
+fn test_any_threshold_subset_produces_valid_signature() {
+    let sk = Fr::from(987654321u64);
+    let pk_g2 = G2Point::generator().scalar_mul(sk);
+    let shares = split_secret(sk, 3, 5, &mut rand::thread_rng()).unwrap();
+    let msg = message();
+
+    let subsets: [&[usize]; 4] = [&[0, 1, 2], &[2, 3, 4], &[0, 2, 4], &[1, 3, 4]];
+    for subset in subsets {
+        let partials: Vec<_> = subset.iter().map(|&i| sign_share(&shares[i], &msg)).collect();
+        let signature = combine_signatures(&partials, 3).unwrap();
+        assert_eq!(signature, msg.scalar_mul(sk), "subset {:?}", subset);
+        assert!(verify_signature(&msg, &signature, &pk_g2));
+    }
+}
+
+#[test]
+fn test_too_few_partials_rejected() {
+    let shares = split_secret(Fr::from(5u64), 3, 5, &mut rand::thread_rng()).unwrap();
+    let msg = message();
+    let partials: Vec<_> = shares[..2].iter().map(|s| sign_share(s, &msg)).collect();
+    assert!(combine_signatures(&partials, 3).is_err());
+}
+
+#[test]
+fn test_duplicate_partials_rejected() {
+    let shares = split_secret(Fr::from(5u64), 2, 3, &mut rand::thread_rng()).unwrap();
+    let msg = message();
+    let partial = sign_share(&shares[0], &msg);
+    assert!(combine_signatures(&[partial, partial], 2).is_err());
+}
+
+#[test]
+fn test_partial_signature_verifies_under_share_key() {
+    let shares = split_secret(Fr::from(11u64), 2, 3, &mut rand::thread_rng()).unwrap();
+    let msg = message();
+    let partial = sign_share(&shares[1], &msg);
+    assert!(verify_partial_signature(&partial, &msg, &shares[1].public_key_g2()));
+    assert!(!verify_partial_signature(&partial, &msg, &shares[0].public_key_g2()));
+}
+
+#[test]
+fn test_recover_secret() {
+    let sk = Fr::from(424242u64);
+    let shares = split_secret(sk, 4, 7, &mut rand::thread_rng()).unwrap();
+    assert_eq!(recover_secret(&shares[3..], 4).unwrap(), sk);
+    assert!(recover_secret(&shares[..3], 4).is_err());
+}
+
+#[test]
+fn test_invalid_parameters() {
+    let mut rng = rand::thread_rng();
+    assert!(split_secret(Fr::from(1u64), 0, 3, &mut rng).is_err());
+    assert!(split_secret(Fr::from(1u64), 4, 3, &mut rng).is_err());
+}
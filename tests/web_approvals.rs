@@ -0,0 +1,300 @@
+mod common;
+
+use common::TestApp;
+use actix_web::{test as actix_test, web, App};
+use bn254_rs::web::approvals::{ApprovalQueue, PendingMessage};
+use bn254_rs::web::audit::{AuditLog, AuditRecord, MemoryAuditLog, RequestId};
+use bn254_rs::web::auth::{ApiToken, Authenticator, MemoryTokenStore, Operation, Principal, TokenStore};
+use bn254_rs::web::config::{ApprovalsConfig, Config, KeyPolicy, StoreBackend};
+use bn254_rs::web::guard::{EquivocationGuard, MemorySignedTaskStore, SignedTaskStore};
+use bn254_rs::web::models::{ApprovalStatus, ApprovalView, KeyStatus, MessageMode};
+use bn254_rs::web::policy::PolicyEngine;
+use bn254_rs::web::sqlite::SqliteAuditLog;
+use bn254_rs::web::store::{JsonStore, KeyStore, MemoryStore, DEFAULT_JSON_PATH};
+use bn254_rs::{hash_to_g1, verify_signature};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+const ALICE: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+
+fn approval_policy() -> KeyPolicy {
+    KeyPolicy {
+        eoa_address: ALICE.to_string(),
+        max_per_hour: None,
+        max_per_day: None,
+        principals: None,
+        time_windows: Vec::new(),
+        expires_at: None,
+        message_sources: None,
+        approval_required: true,
+    }
+}
+
+fn pending() -> PendingMessage {
+    PendingMessage {
+        eoa_address: ALICE.to_string(),
+        mode: MessageMode::Hash,
+        message: hash_to_g1(&[1; 32]),
+        source: None,
+        context: None,
+    }
+}
+
+#[actix_web::test]
+async fn test_signature_is_released_at_the_threshold() {
+    let tokens = Arc::new(MemoryTokenStore::new());
+    let mut bearers = Vec::new();
+    let mut names = Vec::new();
+    // The requester, then three approvers; the requester may also approve
+    for operations in [
+        vec![Operation::Sign, Operation::Approve],
+        vec![Operation::Approve],
+        vec![Operation::Approve],
+        vec![Operation::Approve],
+    ] {
+        let (bearer, token) = ApiToken::generate("test", None, operations, None);
+        tokens.insert_token(token.clone()).await.unwrap();
+        bearers.push(bearer);
+        names.push(token.principal().name);
+    }
+    let store: Arc<dyn KeyStore> = Arc::new(MemoryStore::with_key_pairs(
+        JsonStore::from_file(DEFAULT_JSON_PATH).unwrap().list_key_pairs().await.unwrap(),
+    ));
+    let audit = Arc::new(MemoryAuditLog::new());
+    let policy = PolicyEngine::new(&[approval_policy()], &[], Arc::new(Default::default())).unwrap();
+    let app = TestApp {
+        authenticator: web::Data::new(Authenticator::Tokens(tokens)),
+        audit: web::Data::from(audit.clone() as Arc<dyn AuditLog>),
+        policy: web::Data::new(policy),
+        approvals: web::Data::new(ApprovalQueue::new(names.clone(), 2, 3600)),
+        ..TestApp::new(store)
+    };
+    let app = actix_test::init_service(app.register(App::new())).await;
+    let call = |who: usize, req: actix_test::TestRequest| {
+        let req = req
+            .insert_header(("Authorization", format!("Bearer {}", bearers[who])))
+            .to_request();
+        let app = &app;
+        async move {
+            let resp = actix_test::call_service(app, req).await;
+            let status = resp.status().as_u16();
+            let body: Value = actix_test::read_body_json(resp).await;
+            (status, body)
+        }
+    };
+    let sign = json!({ "eoa_address": ALICE, "message_hash": format!("0x{}", "11".repeat(32)) });
+
+    let (status, body) = call(0, actix_test::TestRequest::post().uri("/api/sign").set_json(&sign)).await;
+    assert_eq!((status, body["error"].as_str()), (403, Some("policy_approval_required")));
+
+    let mut request = sign.clone();
+    request["description"] = json!("register with a new AVS");
+    let (status, body) = call(0, actix_test::TestRequest::post().uri("/api/approvals").set_json(&request)).await;
+    assert_eq!(status, 202, "{}", body);
+    let created: ApprovalView = serde_json::from_value(body).unwrap();
+    assert_eq!((created.status, created.threshold, created.requester.as_str()), (ApprovalStatus::Pending, 2, names[0].as_str()));
+    assert_eq!(created.description.as_deref(), Some("register with a new AVS"));
+    let id = created.id;
+
+    let approve = || actix_test::TestRequest::post().uri(&format!("/api/approvals/{}/approve", id));
+    let errors = [
+        (call(0, approve()).await, 403, "self_approval"),
+        (call(1, approve()).await, 200, ""),
+        (call(1, approve()).await, 409, "already_approved"),
+    ];
+    for ((status, body), expected_status, expected_error) in errors {
+        assert_eq!((status, body["error"].as_str().unwrap_or("")), (expected_status, expected_error), "{}", body);
+    }
+
+    let (_, listed) = call(2, actix_test::TestRequest::get().uri("/api/approvals")).await;
+    assert_eq!(listed.as_array().unwrap().len(), 1);
+    assert_eq!(listed[0]["approvals"][0]["approver"], names[1].as_str());
+
+    let (status, body) = call(2, approve()).await;
+    assert_eq!((status, body["status"].as_str()), (200, Some("approved")), "{}", body);
+    assert!(body["signature"].is_null(), "the signature is only shown to the requester");
+    let (status, body) = call(3, approve()).await;
+    assert_eq!((status, body["error"].as_str()), (409, Some("approval_closed")));
+
+    let (status, body) = call(0, actix_test::TestRequest::get().uri(&format!("/api/approvals/{}", id))).await;
+    assert_eq!(status, 200);
+    let released: ApprovalView = serde_json::from_value(body).unwrap();
+    let signature = released.signature.unwrap();
+    assert!(verify_signature(
+        &hash_to_g1(&[0x11; 32]),
+        &signature.product.to_checked_g1_point().unwrap(),
+        &signature.signer_g2.to_checked_g2_point().unwrap(),
+    ));
+
+    let entries = audit.entries().await.unwrap();
+    let steps: Vec<(&str, &str, &str)> = entries
+        .iter()
+        .map(|e| (e.record.operation.as_str(), e.record.principal.as_str(), e.record.result.as_str()))
+        .collect();
+    assert_eq!(
+        steps,
+        vec![
+            ("sign", names[0].as_str(), "policy_approval_required"),
+            ("approval_request", names[0].as_str(), "ok"),
+            ("approval_approve", names[0].as_str(), "self_approval"),
+            ("approval_approve", names[1].as_str(), "ok"),
+            ("approval_approve", names[1].as_str(), "already_approved"),
+            ("approval_approve", names[2].as_str(), "ok"),
+            ("approval_release", names[0].as_str(), "ok"),
+            ("approval_approve", names[3].as_str(), "approval_closed"),
+        ]
+    );
+    assert!(entries[1..].iter().all(|e| e.record.approval_id.as_deref() == Some(id.as_str())));
+    assert!(entries[6].record.output_hash.is_some());
+}
+
+#[actix_web::test]
+async fn test_disabled_keys_record_no_tasks() {
+    let tokens = Arc::new(MemoryTokenStore::new());
+    let mut bearers = Vec::new();
+    let mut names = Vec::new();
+    for operations in [vec![Operation::Sign], vec![Operation::Approve], vec![Operation::Approve]] {
+        let (bearer, token) = ApiToken::generate("test", None, operations, None);
+        tokens.insert_token(token.clone()).await.unwrap();
+        bearers.push(bearer);
+        names.push(token.principal().name);
+    }
+    let store: Arc<dyn KeyStore> = Arc::new(MemoryStore::with_key_pairs(
+        JsonStore::from_file(DEFAULT_JSON_PATH).unwrap().list_key_pairs().await.unwrap(),
+    ));
+    let tasks = Arc::new(MemorySignedTaskStore::new());
+    let app = TestApp {
+        authenticator: web::Data::new(Authenticator::Tokens(tokens)),
+        guard: web::Data::new(EquivocationGuard::new(tasks.clone())),
+        approvals: web::Data::new(ApprovalQueue::new(names[1..].to_vec(), 2, 3600)),
+        ..TestApp::new(store.clone())
+    };
+    let app = actix_test::init_service(app.register(App::new())).await;
+    let call = |who: usize, req: actix_test::TestRequest| {
+        let req = req
+            .insert_header(("Authorization", format!("Bearer {}", bearers[who])))
+            .to_request();
+        let app = &app;
+        async move {
+            let resp = actix_test::call_service(app, req).await;
+            let status = resp.status().as_u16();
+            let body: Value = actix_test::read_body_json(resp).await;
+            (status, body)
+        }
+    };
+    let request = json!({
+        "eoa_address": ALICE,
+        "message_hash": format!("0x{}", "11".repeat(32)),
+        "context": { "avs": "0xAb5801a7D398351b8bE11C439e05C5B3259aeC9B", "task_index": 1 },
+    });
+
+    let (status, body) = call(0, actix_test::TestRequest::post().uri("/api/approvals").set_json(&request)).await;
+    assert_eq!(status, 202, "{}", body);
+    let id = body["id"].as_str().unwrap().to_string();
+
+    // The key is disabled while the request waits, so its release signs nothing
+    store.set_key_status(ALICE, KeyStatus::Disabled).await.unwrap();
+    let approve = || actix_test::TestRequest::post().uri(&format!("/api/approvals/{}/approve", id));
+    assert_eq!(call(1, approve()).await.0, 200);
+    let (status, body) = call(2, approve()).await;
+    assert_eq!((status, body["status"].as_str()), (200, Some("failed")), "{}", body);
+    assert_eq!(body["error"]["error"], "key_disabled");
+
+    let (status, body) = call(0, actix_test::TestRequest::post().uri("/api/approvals").set_json(&request)).await;
+    assert_eq!((status, body["error"].as_str()), (403, Some("key_disabled")));
+    assert!(tasks.list_tasks().await.unwrap().is_empty());
+}
+
+#[test]
+fn test_rejection_and_expiry() {
+    let (alice, bob, carol) = (
+        Principal::unrestricted("token:alice"),
+        Principal::unrestricted("token:bob"),
+        Principal::unrestricted("token:carol"),
+    );
+    let queue = ApprovalQueue::new(vec!["token:bob".to_string(), "token:carol".to_string()], 2, 60);
+    let rejected = queue.create(&alice, pending(), None, 1000);
+    let expiring = queue.create(&alice, pending(), None, 1010);
+
+    assert_eq!(queue.list(&alice).unwrap_err().body.error, "not_an_approver");
+    assert_eq!(queue.list(&bob).unwrap().len(), 2);
+    let view = queue.reject(&rejected.id, &bob, 1001).unwrap();
+    assert_eq!((view.status, view.rejected_by.as_deref()), (ApprovalStatus::Rejected, Some("token:bob")));
+    assert_eq!(queue.approve(&rejected.id, &carol, 1002).unwrap_err().body.error, "approval_closed");
+
+    queue.approve(&expiring.id, &bob, 1011).unwrap();
+    assert!(queue.expire(1069).is_empty());
+    assert_eq!(queue.approve(&expiring.id, &carol, 1070).unwrap_err().body.error, "approval_expired");
+    let expired = queue.expire(1070);
+    assert_eq!(expired.iter().map(|v| v.id.as_str()).collect::<Vec<_>>(), vec![expiring.id.as_str()]);
+    assert_eq!(queue.get(&expiring.id, &alice).unwrap().status, ApprovalStatus::Expired);
+
+    // Closed requests are forgotten once another ttl has passed
+    queue.expire(1120);
+    assert!(queue.get(&rejected.id, &alice).is_none());
+    assert!(queue.get(&expiring.id, &alice).is_some());
+    queue.expire(1130);
+    assert!(queue.get(&expiring.id, &alice).is_none());
+}
+
+#[tokio::test]
+async fn test_sqlite_audit_log_keeps_approval_ids() {
+    let log = SqliteAuditLog::connect("sqlite::memory:").await.unwrap();
+    let mut record = AuditRecord::new(&RequestId("req".to_string()), "token:bob", ALICE, "approval_approve");
+    record.result = "ok".to_string();
+    record.approval_id = Some("0123abcd".to_string());
+    log.append(record.clone()).await.unwrap();
+    record.approval_id = None;
+    log.append(record).await.unwrap();
+    let ids: Vec<Option<String>> = log.entries().await.unwrap().into_iter().map(|e| e.record.approval_id).collect();
+    assert_eq!(ids, vec![Some("0123abcd".to_string()), None]);
+}
+
+#[test]
+fn test_approvals_config() {
+    let config: Config = toml::from_str(
+        r#"
+        [approvals]
+        approvers = ["token:a", "token:b"]
+        threshold = 2
+        ttl_secs = 600
+
+        [[policies]]
+        eoa_address = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"
+        approval_required = true
+        "#,
+    )
+    .unwrap();
+    assert_eq!(
+        config.approvals,
+        ApprovalsConfig {
+            approvers: vec!["token:a".to_string(), "token:b".to_string()],
+            threshold: 2,
+            ttl_secs: 600,
+        }
+    );
+    assert_eq!(config.policies[0], approval_policy());
+    let errors = |config: &Config| {
+        let mut config = config.clone();
+        config.store.backend = StoreBackend::Memory;
+        config.validate().err().map(|e| e.to_string())
+    };
+    assert_eq!(errors(&config), None);
+
+    let mut invalid = config.clone();
+    invalid.approvals.threshold = 4;
+    invalid.approvals.ttl_secs = 0;
+    invalid.approvals.approvers.push("token:a".to_string());
+    let message = errors(&invalid).unwrap();
+    for expected in [
+        "approvals.threshold must be between 1 and the 3 configured approvers",
+        "approvals.ttl_secs must be at least 1",
+        "approvals.approvers[2]: duplicate approver token:a",
+    ] {
+        assert!(message.contains(expected), "{}", message);
+    }
+
+    let mut no_approvers = config.clone();
+    no_approvers.approvals.approvers.clear();
+    assert!(errors(&no_approvers).unwrap().contains("between 1 and the 0 configured approvers"));
+}
@@ -0,0 +1,151 @@
+mod common;
+
+use common::TestApp;
+use actix_web::{test as actix_test, web, App};
+use bn254_rs::web::audit::{verify_chain, AuditEntry, AuditLog, AuditRecord, FileAuditLog, MemoryAuditLog, RequestId};
+use bn254_rs::web::auth::{ApiToken, Authenticator, MemoryTokenStore, Operation, TokenStore};
+use bn254_rs::web::sqlite::SqliteAuditLog;
+use bn254_rs::web::store::{JsonStore, KeyStore, MemoryStore, DEFAULT_JSON_PATH};
+use bn254_rs::{hash_g1_point, hash_to_g1};
+use serde_json::json;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("bn254-rs-audit-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+fn record(n: u8) -> AuditRecord {
+    let mut record = AuditRecord::new(&RequestId(format!("req-{}", n)), "anonymous", "0xabc", "sign");
+    record.set_input(&hash_to_g1(&[n; 32]));
+    record.result = "ok".to_string();
+    record
+}
+
+async fn filled(log: &dyn AuditLog, count: u8) -> Vec<AuditEntry> {
+    for n in 0..count {
+        log.append(record(n)).await.unwrap();
+    }
+    log.entries().await.unwrap()
+}
+
+#[actix_web::test]
+async fn test_signing_requests_are_audited() {
+    let key_pairs = JsonStore::from_file(DEFAULT_JSON_PATH).unwrap().list_key_pairs().await.unwrap();
+    let alice = key_pairs.iter().find(|kp| kp.eoa_address.starts_with("0xf39F")).unwrap().eoa_address.clone();
+    let bob = key_pairs.iter().find(|kp| kp.eoa_address.starts_with("0x7099")).unwrap().eoa_address.clone();
+    let tokens = Arc::new(MemoryTokenStore::new());
+    let (bearer, token) = ApiToken::generate("signer", Some(vec![alice.clone()]), vec![Operation::Sign], None);
+    tokens.insert_token(token.clone()).await.unwrap();
+
+    let store: Arc<dyn KeyStore> = Arc::new(MemoryStore::with_key_pairs(key_pairs));
+    let audit = Arc::new(MemoryAuditLog::new());
+    let app = TestApp {
+        authenticator: web::Data::new(Authenticator::Tokens(tokens)),
+        audit: web::Data::from(audit.clone() as Arc<dyn AuditLog>),
+        ..TestApp::new(store)
+    };
+    let app = actix_test::init_service(app.register(App::new())).await;
+
+    let sign = |eoa: &str| {
+        actix_test::TestRequest::post()
+            .uri("/api/sign")
+            .insert_header(("Authorization", format!("Bearer {}", bearer)))
+            .set_json(json!({ "eoa_address": eoa, "message_hash": format!("0x{}", "11".repeat(32)) }))
+    };
+    let resp = actix_test::call_service(&app, sign(&alice).insert_header(("X-Request-Id", "trace-1")).to_request()).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers().get("x-request-id").unwrap(), "trace-1");
+    let body: serde_json::Value = actix_test::read_body_json(resp).await;
+
+    let resp = actix_test::call_service(&app, sign(&bob).to_request()).await;
+    assert_eq!(resp.status(), 403);
+    let generated = resp.headers().get("x-request-id").unwrap().to_str().unwrap().to_string();
+
+    let entries = audit.entries().await.unwrap();
+    assert_eq!(entries.len(), 2);
+    let expected_input = format!("0x{}", hex::encode(hash_g1_point(&hash_to_g1(&[0x11; 32]))));
+    let ok = &entries[0].record;
+    assert_eq!(
+        (ok.request_id.as_str(), ok.principal.clone(), ok.eoa_address.as_str(), ok.operation.as_str(), ok.result.as_str()),
+        ("trace-1", token.principal().name, alice.as_str(), "sign", "ok")
+    );
+    assert_eq!(ok.input_hash.as_deref(), Some(expected_input.as_str()));
+    let product: bn254_rs::web::models::G1Point = serde_json::from_value(body["product"].clone()).unwrap();
+    let product_hash = format!("0x{}", hex::encode(bn254_rs::hash_g1_point_raw(&product.to_g1_point().unwrap())));
+    assert_eq!(ok.output_hash.as_deref(), Some(product_hash.as_str()));
+
+    let denied = &entries[1].record;
+    assert_eq!((denied.request_id.as_str(), denied.result.as_str()), (generated.as_str(), "forbidden"));
+    assert_eq!((denied.input_hash.as_ref(), denied.output_hash.as_ref()), (None, None));
+
+    assert_eq!(verify_chain(&entries, None).unwrap().head, entries[1].hash);
+}
+
+#[tokio::test]
+async fn test_edits_and_truncation_are_detected() {
+    let entries = filled(&MemoryAuditLog::new(), 4).await;
+    let head = entries[3].hash.clone();
+    assert_eq!(verify_chain(&entries, Some(&head)).unwrap().entries, 4);
+
+    let mut edited = entries.clone();
+    edited[1].record.eoa_address = "0xdef".to_string();
+    assert!(verify_chain(&edited, None).unwrap_err().contains("entry 1: hash mismatch"));
+
+    // Rehashing the edited entry breaks the link to the next one
+    let mut rehashed = edited.clone();
+    rehashed[1] = AuditEntry::chain(Some(&entries[0]), edited[1].record.clone()).unwrap();
+    assert!(verify_chain(&rehashed, None).unwrap_err().contains("entry 2"));
+
+    let mut removed = entries.clone();
+    removed.remove(2);
+    assert!(verify_chain(&removed, None).is_err());
+    assert!(verify_chain(&entries[1..], None).is_err());
+
+    // A truncated tail still chains, but no longer contains the recorded head
+    assert!(verify_chain(&entries[..3], None).is_ok());
+    assert!(verify_chain(&entries[..3], Some(&head)).unwrap_err().contains("removed from the end"));
+}
+
+#[tokio::test]
+async fn test_file_audit_log() {
+    let path = temp_path("file.jsonl");
+    let first = filled(&FileAuditLog::open(&path).unwrap(), 2).await;
+
+    // Reopening continues the chain
+    let log = FileAuditLog::open(&path).unwrap();
+    let entries = filled(&log, 1).await;
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[..2], first[..]);
+    assert_eq!(entries[2].prev_hash, first[1].hash);
+    assert!(verify_chain(&entries, Some(&first[1].hash)).is_ok());
+
+    let content = std::fs::read_to_string(&path).unwrap();
+    std::fs::write(&path, content.replacen("req-1", "req-9", 1)).unwrap();
+    let err = verify_chain(&log.entries().await.unwrap(), None).unwrap_err();
+    assert!(err.contains("entry 1"), "{}", err);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn test_sqlite_audit_log_is_append_only() {
+    let path = temp_path("sqlite.db");
+    let url = format!("sqlite://{}", path.display());
+    let log = SqliteAuditLog::connect(&url).await.unwrap();
+    let entries = filled(&log, 3).await;
+    assert_eq!(entries.iter().map(|e| e.seq).collect::<Vec<_>>(), vec![0, 1, 2]);
+    let expected = AuditRecord { timestamp: entries[1].record.timestamp, ..record(1) };
+    assert_eq!(entries[1].record, expected);
+    assert!(verify_chain(&entries, Some(&entries[2].hash)).is_ok());
+
+    let pool = sqlx::SqlitePool::connect(&url).await.unwrap();
+    for statement in ["UPDATE audit_log SET result = 'ok' WHERE seq = 1", "DELETE FROM audit_log WHERE seq = 2"] {
+        let err = sqlx::query(statement).execute(&pool).await.unwrap_err();
+        assert!(err.to_string().contains("append-only"), "{}", err);
+    }
+    assert_eq!(log.entries().await.unwrap(), entries);
+    pool.close().await;
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,184 @@
+mod common;
+
+use common::TestApp;
+use actix_web::{test as actix_test, web, App};
+use bn254_rs::web::auth::{authenticate_token, ApiToken, Authenticator, MemoryTokenStore, Operation, TokenStore};
+use bn254_rs::web::config::{Config, StoreBackend};
+use bn254_rs::web::models::{ErrorResponse, KeyPair, PublicKeyView};
+use bn254_rs::web::sqlite::SqliteStore;
+use bn254_rs::web::store::{JsonStore, KeyStore, MemoryStore, DEFAULT_JSON_PATH};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+async fn players() -> Vec<KeyPair> {
+    let mut key_pairs = JsonStore::from_file(DEFAULT_JSON_PATH)
+        .unwrap()
+        .list_key_pairs()
+        .await
+        .unwrap();
+    key_pairs.sort_by(|a, b| a.eoa_address.cmp(&b.eoa_address));
+    key_pairs
+}
+
+/// Sends a request to a service whose only token is `token`
+async fn call(
+    token: ApiToken,
+    bearer: Option<&str>,
+    req: actix_test::TestRequest,
+) -> (u16, Value) {
+    let tokens = Arc::new(MemoryTokenStore::new());
+    tokens.insert_token(token).await.unwrap();
+    let store: Arc<dyn KeyStore> = Arc::new(MemoryStore::with_key_pairs(players().await));
+    let app = TestApp {
+        authenticator: web::Data::new(Authenticator::Tokens(tokens)),
+        ..TestApp::new(store)
+    };
+    let app = actix_test::init_service(app.register(App::new())).await;
+    let req = match bearer {
+        Some(bearer) => req.insert_header(("Authorization", format!("Bearer {}", bearer))),
+        None => req,
+    };
+    let resp = actix_test::call_service(&app, req.to_request()).await;
+    let status = resp.status().as_u16();
+    let body = actix_test::read_body(resp).await;
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+fn sign_request(eoa_address: &str) -> actix_test::TestRequest {
+    actix_test::TestRequest::post().uri("/api/sign").set_json(json!({
+        "eoa_address": eoa_address,
+        "message_hash": format!("0x{}", "11".repeat(32)),
+    }))
+}
+
+fn error_of(body: Value) -> String {
+    serde_json::from_value::<ErrorResponse>(body).unwrap().error
+}
+
+#[actix_web::test]
+async fn test_requests_without_a_valid_token_are_rejected() {
+    let alice = players().await[0].eoa_address.clone();
+    let (bearer, token) = ApiToken::generate("test", None, vec![Operation::Sign], None);
+    let (_, other) = ApiToken::generate("test", None, vec![Operation::Sign], None);
+
+    let (status, body) = call(token.clone(), None, sign_request(&alice)).await;
+    assert_eq!((status, error_of(body)), (401, "unauthorized".to_string()));
+
+    // A valid token id with the wrong secret
+    let forged = format!("bn254_{}_{}", token.id, "00".repeat(32));
+    for bearer in [forged.as_str(), "bn254_nope", "not-a-token"] {
+        let (status, _) = call(token.clone(), Some(bearer), sign_request(&alice)).await;
+        assert_eq!(status, 401, "{}", bearer);
+    }
+    let (status, _) = call(other, Some(&bearer), sign_request(&alice)).await;
+    assert_eq!(status, 401);
+
+    let (status, _) = call(token, Some(&bearer), sign_request(&alice)).await;
+    assert_eq!(status, 200);
+}
+
+#[actix_web::test]
+async fn test_tokens_are_scoped_to_operations_and_keys() {
+    let players = players().await;
+    let (alice, bob) = (players[0].eoa_address.clone(), players[1].eoa_address.clone());
+    // Addresses match regardless of case
+    let (bearer, token) = ApiToken::generate("signer", Some(vec![alice.to_uppercase().replace("0X", "0x")]), vec![Operation::Sign], None);
+
+    let (status, _) = call(token.clone(), Some(&bearer), sign_request(&alice)).await;
+    assert_eq!(status, 200);
+    let (status, body) = call(token.clone(), Some(&bearer), sign_request(&bob)).await;
+    assert_eq!((status, error_of(body)), (403, "forbidden".to_string()));
+
+    let scalar_mul = actix_test::TestRequest::post().uri("/api/scalar_mul").set_json(json!({
+        "eoa_address": alice,
+        "hash_x": "1",
+        "hash_y": "2",
+    }));
+    let (status, _) = call(token.clone(), Some(&bearer), scalar_mul).await;
+    assert_eq!(status, 403);
+
+    let list = actix_test::TestRequest::get().uri("/api/keys");
+    let (status, _) = call(token, Some(&bearer), list).await;
+    assert_eq!(status, 403);
+}
+
+#[actix_web::test]
+async fn test_key_listing_is_filtered_by_scope() {
+    let players = players().await;
+    let alice = players[0].eoa_address.clone();
+    let (bearer, token) = ApiToken::generate("reader", Some(vec![alice.clone()]), vec![Operation::ReadKeys], None);
+
+    let list = actix_test::TestRequest::get().uri("/api/keys");
+    let (status, body) = call(token.clone(), Some(&bearer), list).await;
+    assert_eq!(status, 200);
+    let views: Vec<PublicKeyView> = serde_json::from_value(body).unwrap();
+    assert_eq!(views.len(), 1);
+    assert_eq!(views[0].eoa_address, alice);
+
+    let other = actix_test::TestRequest::get().uri(&format!("/api/keys/{}", players[1].eoa_address));
+    let (status, _) = call(token, Some(&bearer), other).await;
+    assert_eq!(status, 403);
+}
+
+#[actix_web::test]
+async fn test_revoked_and_expired_tokens_are_rejected() {
+    let tokens = MemoryTokenStore::new();
+    let (bearer, token) = ApiToken::generate("revoked", None, vec![Operation::Verify], None);
+    let id = token.id.clone();
+    tokens.insert_token(token).await.unwrap();
+    assert!(authenticate_token(&tokens, &bearer).await.is_ok());
+    assert!(tokens.revoke_token(&id).await.unwrap());
+    assert!(!tokens.revoke_token("missing").await.unwrap());
+    assert_eq!(authenticate_token(&tokens, &bearer).await.unwrap_err().status, 401);
+
+    let (bearer, token) = ApiToken::generate("expired", None, vec![Operation::Verify], Some(1));
+    tokens.insert_token(token).await.unwrap();
+    let err = authenticate_token(&tokens, &bearer).await.unwrap_err();
+    assert_eq!(err.body.message, "token has expired");
+}
+
+#[tokio::test]
+async fn test_sqlite_token_store() {
+    let store = SqliteStore::connect("sqlite::memory:", &[7u8; 32]).await.unwrap();
+    let tokens = store.token_store();
+
+    let alice = players().await[0].eoa_address.clone();
+    let (bearer, token) = ApiToken::generate("ci", Some(vec![alice.clone()]), vec![Operation::Sign, Operation::ReadKeys], None);
+    tokens.insert_token(token.clone()).await.unwrap();
+    assert!(tokens.insert_token(token.clone()).await.is_err());
+    assert_eq!(tokens.get_token(&token.id).await.unwrap(), Some(token.clone()));
+    assert_eq!(tokens.list_tokens().await.unwrap(), vec![token.clone()]);
+
+    let principal = authenticate_token(&tokens, &bearer).await.unwrap();
+    assert_eq!(principal.name, format!("token:{}", token.id));
+    assert!(principal.authorize(Operation::Sign, &alice).is_ok());
+    assert!(principal.authorize(Operation::ScalarMul, &alice).is_err());
+
+    // Only the hash of the secret is stored
+    let secret = bearer.rsplit('_').next().unwrap();
+    assert!(!serde_json::to_string(&tokens.list_tokens().await.unwrap()).unwrap().contains(secret));
+
+    assert!(tokens.revoke_token(&token.id).await.unwrap());
+    assert!(tokens.get_token(&token.id).await.unwrap().unwrap().revoked);
+    assert!(authenticate_token(&tokens, &bearer).await.is_err());
+}
+
+#[test]
+fn test_auth_config() {
+    let mut config = Config::default();
+    assert!(config.auth.enabled);
+    assert_eq!(config.auth_database(), "sqlite://bn254-tokens.db");
+
+    config.store.backend = StoreBackend::Sqlite;
+    config.store.path = Some("sqlite://keys.db".to_string());
+    assert_eq!(config.auth_database(), "sqlite://keys.db");
+
+    config
+        .apply_env([
+            ("BN254_AUTH_ENABLED".to_string(), "false".to_string()),
+            ("BN254_AUTH_DATABASE".to_string(), "sqlite://tokens.db".to_string()),
+        ])
+        .unwrap();
+    assert!(!config.auth.enabled);
+    assert_eq!(config.auth_database(), "sqlite://tokens.db");
+}
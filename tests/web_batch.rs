@@ -0,0 +1,164 @@
+mod common;
+
+use common::TestApp;
+use actix_web::{test as actix_test, web, App};
+use bn254_rs::web::config::{Config, EndpointsConfig};
+use bn254_rs::web::guard::{EquivocationGuard, MemorySignedTaskStore, SignedTaskStore};
+use bn254_rs::web::models::{BatchSignResponse, G1Point, KeyPair, KeyStatus};
+use bn254_rs::web::signer::{LocalSigner, Signer};
+use bn254_rs::web::store::{JsonStore, KeyStore, MemoryStore, DEFAULT_JSON_PATH};
+use bn254_rs::{hash_to_g1, G1Point as Point};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+const ALICE: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+const BOB: &str = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8";
+const UNKNOWN: &str = "0x0000000000000000000000000000000000000001";
+
+macro_rules! batch_app {
+    ($store:expr, $endpoints:expr) => {
+        actix_test::init_service(TestApp { endpoints: $endpoints, ..TestApp::new($store) }.register(App::new())).await
+    };
+}
+
+async fn player_store() -> Arc<dyn KeyStore> {
+    Arc::new(MemoryStore::with_key_pairs(
+        JsonStore::from_file(DEFAULT_JSON_PATH).unwrap().list_key_pairs().await.unwrap(),
+    ))
+}
+
+fn hash(byte: u8) -> String {
+    format!("0x{}", hex::encode([byte; 32]))
+}
+
+#[actix_web::test]
+async fn test_sign_batch() {
+    let store = player_store().await;
+    let alice: KeyPair = store.get_key_pair(ALICE).await.unwrap().unwrap();
+    let bob: KeyPair = store.get_key_pair(BOB).await.unwrap().unwrap();
+    let found = store.get_key_pairs(&[BOB.to_string(), UNKNOWN.to_string(), ALICE.to_string()]).await.unwrap();
+    assert_eq!(found[0].as_ref().unwrap().eoa_address, BOB);
+    assert!(found[1].is_none());
+    assert_eq!(found[2].as_ref().unwrap().eoa_address, ALICE);
+
+    let app = batch_app!(store.clone(), EndpointsConfig::default());
+    let point = Point::generator().scalar_mul(ark_bn254::Fr::from(3u64));
+    let context = json!({ "avs": "0xAb5801a7D398351b8bE11C439e05C5B3259aeC9B", "task_index": 1 });
+    let items = json!([
+        { "eoa_address": ALICE, "message_hash": hash(1), "context": context },
+        { "eoa_address": BOB, "message_point": G1Point::from(&point) },
+        { "eoa_address": UNKNOWN, "message_hash": hash(1) },
+        { "eoa_address": ALICE, "message_point": { "x": "1", "y": "1" } },
+        // A second message for the same task is refused, even within one batch
+        { "eoa_address": ALICE, "message_hash": hash(2), "context": context },
+        { "eoa_address": ALICE, "message_hash": hash(1), "message_compressed": hash(1) },
+    ]);
+    let req = actix_test::TestRequest::post()
+        .uri("/api/sign/batch")
+        .set_json(json!({ "items": items }))
+        .to_request();
+    let response: BatchSignResponse = actix_test::call_and_read_body_json(&app, req).await;
+    assert_eq!(response.results.len(), 6);
+    assert!(response.results.iter().enumerate().all(|(i, result)| result.index == i));
+
+    let signature = |i: usize| {
+        let result = response.results[i].result.as_ref().unwrap();
+        result.product.to_checked_g1_point().unwrap()
+    };
+    assert_eq!(signature(0), LocalSigner.scalar_mul(&alice, &hash_to_g1(&[1; 32])).unwrap());
+    assert_eq!(signature(1), LocalSigner.scalar_mul(&bob, &point).unwrap());
+    let errors: Vec<(u16, &str)> = response.results[2..]
+        .iter()
+        .map(|result| (result.status, result.error.as_ref().unwrap().error.as_str()))
+        .collect();
+    assert_eq!(
+        errors,
+        [(404, "unknown_eoa"), (400, "invalid_point"), (409, "equivocation"), (400, "conflicting_fields")]
+    );
+
+    // Disabled keys fail their own items only
+    store.set_key_status(BOB, KeyStatus::Disabled).await.unwrap();
+    let items = json!([
+        { "eoa_address": BOB, "message_hash": hash(3) },
+        { "eoa_address": ALICE, "message_hash": hash(3) },
+    ]);
+    let req = actix_test::TestRequest::post()
+        .uri("/api/sign/batch")
+        .set_json(json!({ "items": items }))
+        .to_request();
+    let response: BatchSignResponse = actix_test::call_and_read_body_json(&app, req).await;
+    assert_eq!(response.results[0].error.as_ref().unwrap().error, "key_disabled");
+    assert_eq!(response.results[1].status, 200);
+}
+
+#[actix_web::test]
+async fn test_sign_batch_unusable_keys_record_no_tasks() {
+    let store = player_store().await;
+    store.set_key_status(BOB, KeyStatus::Disabled).await.unwrap();
+    let tasks = Arc::new(MemorySignedTaskStore::new());
+    let app = TestApp {
+        guard: web::Data::new(EquivocationGuard::new(tasks.clone())),
+        ..TestApp::new(store)
+    };
+    let app = actix_test::init_service(app.register(App::new())).await;
+
+    let context = json!({ "avs": "0xAb5801a7D398351b8bE11C439e05C5B3259aeC9B", "task_index": 1 });
+    let items = json!([
+        { "eoa_address": BOB, "message_hash": hash(1), "context": context },
+        { "eoa_address": UNKNOWN, "message_hash": hash(1), "context": context },
+        { "eoa_address": ALICE, "message_hash": hash(1), "context": context },
+    ]);
+    let req = actix_test::TestRequest::post()
+        .uri("/api/sign/batch")
+        .set_json(json!({ "items": items }))
+        .to_request();
+    let response: BatchSignResponse = actix_test::call_and_read_body_json(&app, req).await;
+    let statuses: Vec<u16> = response.results.iter().map(|result| result.status).collect();
+    assert_eq!(statuses, [403, 404, 200]);
+    let recorded = tasks.list_tasks().await.unwrap();
+    let keys: Vec<&str> = recorded.iter().map(|task| task.eoa_address.as_str()).collect();
+    assert_eq!(keys, [ALICE.to_lowercase()]);
+}
+
+#[actix_web::test]
+async fn test_sign_batch_size() {
+    let endpoints = EndpointsConfig {
+        max_batch_size: 2,
+        ..EndpointsConfig::default()
+    };
+    let app = batch_app!(player_store().await, endpoints);
+    let call = |count: usize| {
+        let item = json!({ "eoa_address": ALICE, "message_hash": hash(1) });
+        let req = actix_test::TestRequest::post()
+            .uri("/api/sign/batch")
+            .set_json(json!({ "items": vec![item; count] }))
+            .to_request();
+        let app = &app;
+        async move {
+            let resp = actix_test::call_service(app, req).await;
+            let status = resp.status().as_u16();
+            let body: Value = actix_test::read_body_json(resp).await;
+            (status, body)
+        }
+    };
+
+    let (status, body) = call(2).await;
+    assert_eq!(status, 200);
+    assert_eq!(body["results"].as_array().unwrap().len(), 2);
+    let (status, body) = call(3).await;
+    assert_eq!((status, body["error"].as_str()), (413, Some("batch_too_large")));
+    let (status, body) = call(0).await;
+    assert_eq!((status, body["error"].as_str()), (400, Some("empty_batch")));
+}
+
+#[test]
+fn test_sign_batch_config() {
+    let mut config = Config::default();
+    assert_eq!(config.endpoints.max_batch_size, 100);
+    config
+        .apply_env(vec![("BN254_ENDPOINTS_MAX_BATCH_SIZE".to_string(), "0".to_string())])
+        .unwrap();
+    assert_eq!(config.endpoints.max_batch_size, 0);
+    let message = config.validate().unwrap_err().to_string();
+    assert!(message.contains("endpoints.max_batch_size must be at least 1"));
+}
@@ -0,0 +1,135 @@
+mod common;
+
+use common::TestApp;
+use actix_web::{test as actix_test, App};
+use bn254_rs::web::config::{Cli, Config, EndpointGroup, LogFormat, StoreBackend};
+use bn254_rs::web::store::{KeyStore, MemoryStore};
+use clap::Parser;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+fn temp_file(name: &str, content: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("bn254-rs-{}-{}", std::process::id(), name));
+    std::fs::write(&path, content).unwrap();
+    path
+}
+
+fn env(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+    vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+}
+
+#[test]
+fn test_defaults_are_valid() {
+    let config = Config::default();
+    assert_eq!(config.server.host, "127.0.0.1");
+    assert_eq!(config.server.port, 8080);
+    assert_eq!(config.store.backend, StoreBackend::Json);
+    assert!(config.endpoints.keys && config.endpoints.signing);
+    config.validate().unwrap();
+}
+
+#[test]
+fn test_layering_file_env_cli() {
+    let key_file = temp_file("store.key", &"ab".repeat(32));
+    let file = temp_file(
+        "config.toml",
+        &format!(
+            r#"
+            [server]
+            host = "0.0.0.0"
+            port = 9000
+            workers = 2
+
+            [store]
+            backend = "sqlite"
+            path = "sqlite::memory:"
+            key_file = "{}"
+
+            [log]
+            format = "json"
+
+            [endpoints]
+            signing = false
+            "#,
+            key_file.display()
+        ),
+    );
+
+    let mut config = Config::from_file(&file).unwrap();
+    assert_eq!(config.server.port, 9000);
+    assert_eq!(config.log.format, LogFormat::Json);
+    assert!(!config.endpoints.signing);
+
+    // Environment overrides the file
+    config
+        .apply_env(env(&[("BN254_PORT", "9100"), ("BN254_LOG_LEVEL", "debug"), ("PATH", "/bin")]))
+        .unwrap();
+    assert_eq!(config.server.port, 9100);
+    assert_eq!(config.server.host, "0.0.0.0");
+    assert_eq!(config.log.level, "debug");
+
+    // Flags override the environment
+    let cli = Cli::try_parse_from(["bn254-key-service", "--port", "9200", "--enable", "signing", "--disable", "keys"]).unwrap();
+    config.apply_cli(&cli);
+    assert_eq!(config.server.port, 9200);
+    assert!(config.endpoints.signing);
+    assert!(!config.endpoints.keys);
+    assert_eq!(config.server.workers, Some(2));
+
+    config.validate().unwrap();
+    assert_eq!(config.store_key().unwrap(), [0xab; 32]);
+
+    let _ = std::fs::remove_file(file);
+    let _ = std::fs::remove_file(key_file);
+}
+
+#[test]
+fn test_invalid_sources_are_rejected() {
+    let file = temp_file("unknown.toml", "[server]\nhots = \"0.0.0.0\"\n");
+    let err = Config::from_file(&file).unwrap_err();
+    assert!(format!("{:#}", err).contains("hots"));
+    let _ = std::fs::remove_file(file);
+
+    let mut config = Config::default();
+    assert!(config.apply_env(env(&[("BN254_PORT", "http")])).is_err());
+    assert!(config.apply_env(env(&[("BN254_STORE", "postgres")])).is_err());
+    assert!(config.apply_env(env(&[("BN254_ENDPOINTS_KEYS", "maybe")])).is_err());
+    assert!(Cli::try_parse_from(["bn254-key-service", "--disable", "everything"]).is_err());
+}
+
+#[test]
+fn test_validation_reports_every_error() {
+    let mut config = Config::default();
+    config.server.workers = Some(0);
+    config.store.backend = StoreBackend::Dir;
+    config.tls.cert = Some("/nonexistent/cert.pem".into());
+
+    let message = config.validate().unwrap_err().to_string();
+    assert!(message.contains("server.workers"));
+    assert!(message.contains("key directory"));
+    assert!(message.contains("store.key_file"));
+    assert!(message.contains("tls.key must be set"));
+    assert!(message.contains("tls.cert: /nonexistent/cert.pem does not exist"));
+}
+
+#[actix_web::test]
+async fn test_disabled_endpoint_groups_are_not_routed() {
+    let mut config = Config::default();
+    config.apply_cli(&Cli {
+        disable: vec![EndpointGroup::Signing],
+        ..Cli::default()
+    });
+
+    let store: Arc<dyn KeyStore> = Arc::new(MemoryStore::new());
+    let endpoints = config.endpoints.clone();
+    let app = actix_test::init_service(TestApp { endpoints, ..TestApp::new(store) }.register(App::new())).await;
+
+    let req = actix_test::TestRequest::get().uri("/api/keys").to_request();
+    assert!(actix_test::call_service(&app, req).await.status().is_success());
+
+    let req = actix_test::TestRequest::post()
+        .uri("/api/sign")
+        .set_json(serde_json::json!({"eoa_address": "0x00", "message_hash": "00".repeat(32)}))
+        .to_request();
+    assert_eq!(actix_test::call_service(&app, req).await.status(), 404);
+}
@@ -0,0 +1,76 @@
+use bn254_rs::web::config::{Config, KekSource, StoreBackend};
+use bn254_rs::web::envelope::{EnvelopeCipher, KekProvider, LocalKek};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("bn254-rs-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+#[test]
+fn test_passphrase_kek() {
+    let params = temp_path("kek-params.json");
+    let kek = LocalKek::from_passphrase("correct horse battery staple", &params).unwrap();
+    assert_eq!(kek.name(), "passphrase");
+    let file = std::fs::read_to_string(&params).unwrap();
+    assert!(file.contains("argon2id"));
+    assert!(!file.contains("correct horse"));
+
+    // The same passphrase derives the same KEK from the saved salt
+    let wrapped = kek.wrap_key(b"0xf39F", &[9u8; 32]).unwrap();
+    let reopened = LocalKek::from_passphrase("correct horse battery staple", &params).unwrap();
+    assert_eq!(*reopened.unwrap_key(b"0xf39F", &wrapped).unwrap(), [9u8; 32]);
+    assert!(reopened.unwrap_key(b"0x7099", &wrapped).is_err());
+
+    let err = LocalKek::from_passphrase("battery staple", &params).err().unwrap();
+    assert!(err.to_string().contains("Wrong KEK passphrase"));
+    assert!(LocalKek::from_passphrase("", &params).is_err());
+    let _ = std::fs::remove_file(&params);
+}
+
+#[test]
+fn test_envelopes_use_fresh_data_keys() {
+    let key_file = temp_path("envelope-kek");
+    std::fs::write(&key_file, hex::encode([3u8; 32])).unwrap();
+    let envelope = EnvelopeCipher::new(Arc::new(LocalKek::from_key_file(&key_file).unwrap()));
+
+    let first = envelope.seal(b"0xf39F", b"12345").unwrap();
+    let second = envelope.seal(b"0xf39F", b"12345").unwrap();
+    assert_eq!(first.kek, "key-file");
+    assert_eq!(first.cipher, "xchacha20-poly1305");
+    assert_ne!(first.wrapped_key, second.wrapped_key);
+    assert_ne!(first.ciphertext, second.ciphertext);
+    assert_eq!(envelope.open(b"0xf39F", &first).unwrap().as_slice(), b"12345");
+
+    // An envelope is bound to its EOA and to its provider
+    assert!(envelope.open(b"0x7099", &first).is_err());
+    let mut relabeled = first.clone();
+    relabeled.kek = "passphrase".to_string();
+    assert!(envelope.open(b"0xf39F", &relabeled).is_err());
+    let mut tampered = first;
+    let mut ciphertext = hex::decode(&tampered.ciphertext).unwrap();
+    *ciphertext.last_mut().unwrap() ^= 1;
+    tampered.ciphertext = hex::encode(ciphertext);
+    assert!(envelope.open(b"0xf39F", &tampered).is_err());
+    let _ = std::fs::remove_file(&key_file);
+}
+
+#[test]
+fn test_kek_config() {
+    let mut config = Config::default();
+    config
+        .apply_env(vec![("BN254_KEK_PROVIDER".to_string(), "key-file".to_string())])
+        .unwrap();
+    assert_eq!(config.store.kek.provider, Some(KekSource::KeyFile));
+    let message = config.validate().unwrap_err().to_string();
+    assert!(message.contains("store.kek.path must be set"));
+
+    config.store.backend = StoreBackend::Sqlite;
+    config.store.kek.path = Some("/nonexistent/kek".into());
+    let message = config.validate().unwrap_err().to_string();
+    assert!(message.contains("only supported by the json store"));
+    assert!(message.contains("KEK file /nonexistent/kek does not exist"));
+    assert!(Config::default().kek_provider().unwrap().is_none());
+}
@@ -0,0 +1,446 @@
+mod common;
+
+use common::TestApp;
+use actix_web::{test as actix_test, web, App};
+use bn254_rs::encoding::g1_to_compressed;
+use bn254_rs::web::grpc::proto::signer::signer_client::SignerClient;
+use bn254_rs::web::grpc::proto::signer::SignGenericRequest;
+use bn254_rs::web::grpc::{GrpcService, EOA_AUTHORIZATION_METADATA, SIGNING_CONTEXT_METADATA};
+use bn254_rs::web::guard::EquivocationGuard;
+use bn254_rs::web::handlers::SigningChecks;
+use bn254_rs::web::policy::PolicyEngine;
+use bn254_rs::web::audit::{AuditLog, MemoryAuditLog};
+use bn254_rs::web::auth::Authenticator;
+use bn254_rs::web::config::{Config, EndpointsConfig, EoaAuthMode};
+use bn254_rs::web::eoa_auth::{
+    authorization_digest, recover_address, AuthorizationDomain, EoaVerifier, MemoryNonceStore, NonceStore,
+    EIP712_DOMAIN_NAME, EIP712_DOMAIN_VERSION,
+};
+use bn254_rs::web::models::{AuthScheme, G1Point};
+use bn254_rs::web::sqlite::SqliteNonceStore;
+use bn254_rs::web::store::{JsonStore, KeyStore, MemoryStore, DEFAULT_JSON_PATH};
+use bn254_rs::web::signer::{LocalSigner, Signer as KeySigner};
+use bn254_rs::web::tls::ClientPrincipals;
+use bn254_rs::{hash_to_g1, G1Point as Point};
+use ethers::abi::{encode, Token};
+use ethers::signers::{LocalWallet, Signer};
+use ethers::types::transaction::eip712::TypedData;
+use ethers::types::U256;
+use ethers::utils::keccak256;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tonic::transport::Channel;
+use tonic::{Code, Request};
+
+const MESSAGE_HASH: [u8; 32] = [0x11; 32];
+const SALT: &str = "0x8f0b0a6a52c2e3a3c7d4c5b5a8e4d2f1c3b2a1908f7e6d5c4b3a291807f6e5d4";
+
+/// The deployment the test services run as
+fn domain() -> AuthorizationDomain {
+    AuthorizationDomain::new(31337, Some(SALT)).unwrap()
+}
+
+/// The EOA address and wallet of a player in `players.json`
+fn player(name: &str) -> (String, LocalWallet) {
+    let players: Value = serde_json::from_str(&std::fs::read_to_string(DEFAULT_JSON_PATH).unwrap()).unwrap();
+    let player = &players[name];
+    let wallet: LocalWallet = player["priv"].as_str().unwrap().trim_start_matches("0x").parse().unwrap();
+    (player["pub"].as_str().unwrap().to_string(), wallet)
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+fn message() -> Point {
+    hash_to_g1(&MESSAGE_HASH)
+}
+
+fn words(message: &Point) -> (U256, U256) {
+    let point = G1Point::from(message);
+    (U256::from_dec_str(&point.x).unwrap(), U256::from_dec_str(&point.y).unwrap())
+}
+
+/// Signs an authorization with `personal_sign`, encoding it independently of the service
+async fn sign_eip191(wallet: &LocalWallet, domain: &AuthorizationDomain, message: &Point, nonce: u64, expiry: u64) -> String {
+    let (x, y) = words(message);
+    let encoded = encode(&[
+        Token::Uint(x),
+        Token::Uint(y),
+        Token::Uint(nonce.into()),
+        Token::Uint(expiry.into()),
+        Token::Uint(domain.chain_id.into()),
+        Token::FixedBytes(domain.salt.to_vec()),
+    ]);
+    let signature = wallet.sign_message(keccak256(encoded)).await.unwrap();
+    format!("0x{}", hex::encode(signature.to_vec()))
+}
+
+/// Signs an authorization with `eth_signTypedData_v4`
+async fn sign_eip712(wallet: &LocalWallet, domain: &AuthorizationDomain, message: &Point, nonce: u64, expiry: u64) -> String {
+    let (x, y) = words(message);
+    let typed: TypedData = serde_json::from_value(json!({
+        "types": {
+            "EIP712Domain": [
+                { "name": "name", "type": "string" },
+                { "name": "version", "type": "string" },
+                { "name": "chainId", "type": "uint256" },
+                { "name": "salt", "type": "bytes32" },
+            ],
+            "SignRequest": [
+                { "name": "messageX", "type": "uint256" },
+                { "name": "messageY", "type": "uint256" },
+                { "name": "nonce", "type": "uint256" },
+                { "name": "expiry", "type": "uint256" },
+            ],
+        },
+        "primaryType": "SignRequest",
+        "domain": {
+            "name": EIP712_DOMAIN_NAME,
+            "version": EIP712_DOMAIN_VERSION,
+            "chainId": domain.chain_id,
+            "salt": domain.salt,
+        },
+        "message": {
+            "messageX": x.to_string(),
+            "messageY": y.to_string(),
+            "nonce": nonce.to_string(),
+            "expiry": expiry.to_string(),
+        },
+    }))
+    .unwrap();
+    let signature = wallet.sign_typed_data(&typed).await.unwrap();
+    format!("0x{}", hex::encode(signature.to_vec()))
+}
+
+fn sign_body(eoa_address: &str, authorization: Option<Value>) -> Value {
+    let mut body = json!({
+        "eoa_address": eoa_address,
+        "message_hash": format!("0x{}", hex::encode(MESSAGE_HASH)),
+    });
+    if let Some(authorization) = authorization {
+        body["authorization"] = authorization;
+    }
+    body
+}
+
+async fn call(verifier: EoaVerifier, requests: Vec<(&str, Value)>) -> Vec<(u16, Value)> {
+    let store: Arc<dyn KeyStore> = Arc::new(MemoryStore::with_key_pairs(
+        JsonStore::from_file(DEFAULT_JSON_PATH).unwrap().list_key_pairs().await.unwrap(),
+    ));
+    let app = TestApp {
+        eoa_verifier: web::Data::new(verifier),
+        ..TestApp::new(store)
+    };
+    let app = actix_test::init_service(app.register(App::new())).await;
+    let mut responses = Vec::new();
+    for (uri, body) in requests {
+        let req = actix_test::TestRequest::post().uri(uri).set_json(body).to_request();
+        let resp = actix_test::call_service(&app, req).await;
+        let status = resp.status().as_u16();
+        responses.push((status, actix_test::read_body_json(resp).await));
+    }
+    responses
+}
+
+fn required() -> EoaVerifier {
+    EoaVerifier::new(EoaAuthMode::Required, 300, domain(), Arc::new(MemoryNonceStore::new()))
+}
+
+#[tokio::test]
+async fn test_digests_match_ethereum_wallets() {
+    let (alice, wallet) = player("Alice");
+    let expiry = now() + 60;
+    for scheme in [AuthScheme::Eip191, AuthScheme::Eip712] {
+        let signature = match scheme {
+            AuthScheme::Eip191 => sign_eip191(&wallet, &domain(), &message(), 7, expiry).await,
+            AuthScheme::Eip712 => sign_eip712(&wallet, &domain(), &message(), 7, expiry).await,
+        };
+        let digest = authorization_digest(scheme, &domain(), &message(), 7, expiry);
+        let signer = recover_address(&digest, &hex::decode(&signature[2..]).unwrap()).unwrap();
+        assert_eq!(signer, alice.to_lowercase(), "{:?}", scheme);
+    }
+}
+
+#[tokio::test]
+async fn test_malformed_signatures_are_rejected() {
+    let (_, wallet) = player("Alice");
+    let expiry = now() + 60;
+    let digest = authorization_digest(AuthScheme::Eip191, &domain(), &message(), 1, expiry);
+    let signature = hex::decode(&sign_eip191(&wallet, &domain(), &message(), 1, expiry).await[2..]).unwrap();
+
+    assert!(recover_address(&digest, &signature[..64]).is_err());
+    let mut bad_v = signature.clone();
+    bad_v[64] = 29;
+    assert!(recover_address(&digest, &bad_v).is_err());
+
+    // The high-s twin recovers the same key but is not accepted
+    let n = U256::from_str_radix("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141", 16).unwrap();
+    let high_s = n - U256::from_big_endian(&signature[32..64]);
+    let mut twin = signature.clone();
+    high_s.to_big_endian(&mut twin[32..64]);
+    twin[64] ^= 1;
+    assert!(recover_address(&digest, &twin).is_err());
+}
+
+#[actix_web::test]
+async fn test_authorized_signing_and_replay() {
+    let (alice, wallet) = player("Alice");
+    let expiry = now() + 60;
+    let authorization = json!({
+        "scheme": "eip712",
+        "nonce": 1,
+        "expiry": expiry,
+        "signature": sign_eip712(&wallet, &domain(), &message(), 1, expiry).await,
+    });
+    let scalar_mul = {
+        let (x, y) = words(&message());
+        json!({
+            "eoa_address": alice,
+            "hash_x": x.to_string(),
+            "hash_y": y.to_string(),
+            "authorization": {
+                "scheme": "eip191",
+                "nonce": 2,
+                "expiry": expiry,
+                "signature": sign_eip191(&wallet, &domain(), &message(), 2, expiry).await,
+            },
+        })
+    };
+    let responses = call(
+        required(),
+        vec![
+            ("/api/sign", sign_body(&alice, Some(authorization.clone()))),
+            ("/api/sign", sign_body(&alice, Some(authorization))),
+            ("/api/scalar_mul", scalar_mul),
+            ("/api/sign", sign_body(&alice, None)),
+        ],
+    )
+    .await;
+    assert_eq!(responses[0].0, 200, "{}", responses[0].1);
+    assert_eq!((responses[1].0, responses[1].1["error"].as_str()), (409, Some("nonce_reused")));
+    assert_eq!(responses[2].0, 200, "{}", responses[2].1);
+    assert_eq!((responses[3].0, responses[3].1["error"].as_str()), (401, Some("eoa_authorization_required")));
+}
+
+#[actix_web::test]
+async fn test_invalid_authorizations_are_rejected() {
+    let (alice, _) = player("Alice");
+    let (_, bob_wallet) = player("Bob");
+    let (_, alice_wallet) = player("Alice");
+    let expiry = now() + 60;
+    let authorization = |nonce: u64, expiry: u64, signature: String| {
+        json!({ "scheme": "eip191", "nonce": nonce, "expiry": expiry, "signature": signature })
+    };
+    let other_message = hash_to_g1(&[0x22; 32]);
+
+    let responses = call(
+        required(),
+        vec![
+            // Signed by another EOA
+            ("/api/sign", sign_body(&alice, Some(authorization(1, expiry, sign_eip191(&bob_wallet, &domain(), &message(), 1, expiry).await)))),
+            // Signed for another message
+            ("/api/sign", sign_body(&alice, Some(authorization(2, expiry, sign_eip191(&alice_wallet, &domain(), &other_message, 2, expiry).await)))),
+            // Expired
+            ("/api/sign", sign_body(&alice, Some(authorization(3, now() - 1, sign_eip191(&alice_wallet, &domain(), &message(), 3, now() - 1).await)))),
+            // Valid for too long
+            ("/api/sign", sign_body(&alice, Some(authorization(4, now() + 3600, sign_eip191(&alice_wallet, &domain(), &message(), 4, now() + 3600).await)))),
+            ("/api/sign", sign_body(&alice, Some(authorization(5, expiry, "0xzz".to_string())))),
+        ],
+    )
+    .await;
+    let errors: Vec<(u16, &str)> = responses
+        .iter()
+        .map(|(status, body)| (*status, body["error"].as_str().unwrap()))
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            (403, "eoa_mismatch"),
+            (403, "eoa_mismatch"),
+            (401, "authorization_expired"),
+            (400, "expiry_too_far"),
+            (400, "invalid_eoa_signature"),
+        ]
+    );
+}
+
+#[actix_web::test]
+async fn test_authorizations_are_bound_to_the_deployment() {
+    let (alice, wallet) = player("Alice");
+    let expiry = now() + 60;
+    let other_chain = AuthorizationDomain::new(1, Some(SALT)).unwrap();
+    let other_salt = AuthorizationDomain::new(31337, Some(&format!("0x{}", "00".repeat(32)))).unwrap();
+    let mut requests = Vec::new();
+    for (nonce, other) in [(1, &other_chain), (2, &other_salt)] {
+        for (scheme, signature) in [
+            ("eip191", sign_eip191(&wallet, other, &message(), nonce, expiry).await),
+            ("eip712", sign_eip712(&wallet, other, &message(), nonce, expiry).await),
+        ] {
+            let authorization = json!({ "scheme": scheme, "nonce": nonce, "expiry": expiry, "signature": signature });
+            requests.push(("/api/sign", sign_body(&alice, Some(authorization))));
+        }
+    }
+    for (status, body) in call(required(), requests).await {
+        assert_eq!((status, body["error"].as_str()), (403, Some("eoa_mismatch")));
+    }
+}
+
+#[test]
+fn test_authorization_domain_config() {
+    let mut config = Config::default();
+    assert_eq!((config.eoa_auth.chain_id, config.eoa_auth.salt.as_deref()), (1, None));
+    config
+        .apply_env(vec![
+            ("BN254_EOA_AUTH_CHAIN_ID".to_string(), "17000".to_string()),
+            ("BN254_EOA_AUTH_SALT".to_string(), SALT.to_string()),
+        ])
+        .unwrap();
+    assert_eq!((config.eoa_auth.chain_id, config.eoa_auth.salt.as_deref()), (17000, Some(SALT)));
+    assert!(config.validate().is_ok());
+
+    config.eoa_auth.salt = Some("0x1234".to_string());
+    config.eoa_auth.chain_id = 0;
+    let message = config.validate().unwrap_err().to_string();
+    assert!(message.contains("eoa_auth: chain id must not be zero"), "{}", message);
+    config.eoa_auth.chain_id = 1;
+    let message = config.validate().unwrap_err().to_string();
+    assert!(message.contains("eoa_auth: salt must be 32 bytes of hex"), "{}", message);
+}
+
+#[actix_web::test]
+async fn test_optional_and_off_modes() {
+    let (alice, _) = player("Alice");
+    let (_, bob_wallet) = player("Bob");
+    let expiry = now() + 60;
+    let forged = json!({
+        "scheme": "eip191",
+        "nonce": 1,
+        "expiry": expiry,
+        "signature": sign_eip191(&bob_wallet, &domain(), &message(), 1, expiry).await,
+    });
+
+    let optional = call(
+        EoaVerifier::default(),
+        vec![("/api/sign", sign_body(&alice, None)), ("/api/sign", sign_body(&alice, Some(forged.clone())))],
+    )
+    .await;
+    assert_eq!((optional[0].0, optional[1].0), (200, 403));
+
+    let off = EoaVerifier::new(EoaAuthMode::Off, 300, domain(), Arc::new(MemoryNonceStore::new()));
+    let off = call(off, vec![("/api/sign", sign_body(&alice, Some(forged)))]).await;
+    assert_eq!(off[0].0, 200);
+}
+
+#[tokio::test]
+async fn test_nonce_stores() {
+    let sqlite = SqliteNonceStore::connect("sqlite::memory:").await.unwrap();
+    let memory = MemoryNonceStore::new();
+    let stores: [&dyn NonceStore; 2] = [&sqlite, &memory];
+    for store in stores {
+        let expiry = now() + 60;
+        assert!(store.use_nonce("0xAbC", u64::MAX, expiry).await.unwrap());
+        assert!(!store.use_nonce("0xabc", u64::MAX, expiry).await.unwrap());
+        assert!(store.use_nonce("0xdef", u64::MAX, expiry).await.unwrap());
+
+        // Nonces of expired authorizations are pruned
+        assert!(store.use_nonce("0xabc", 1, now() - 1).await.unwrap());
+        assert!(store.use_nonce("0xabc", 1, expiry).await.unwrap());
+    }
+}
+
+/// Serves the gRPC API checking authorizations with `verifier`
+async fn grpc_signer(verifier: EoaVerifier) -> SignerClient<Channel> {
+    let store: Arc<dyn KeyStore> = Arc::new(MemoryStore::with_key_pairs(
+        JsonStore::from_file(DEFAULT_JSON_PATH).unwrap().list_key_pairs().await.unwrap(),
+    ));
+    let checks = SigningChecks::new(
+        web::Data::new(verifier),
+        web::Data::new(PolicyEngine::default()),
+        web::Data::new(EquivocationGuard::default()),
+        web::Data::from(Arc::new(LocalSigner) as Arc<dyn KeySigner>),
+    );
+    let service = GrpcService::new(
+        web::Data::from(store),
+        checks,
+        web::Data::from(Arc::new(MemoryAuditLog::new()) as Arc<dyn AuditLog>),
+        web::Data::new(Authenticator::Disabled),
+        web::Data::new(ClientPrincipals::default()),
+        EndpointsConfig::default(),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(service.serve(listener, None));
+    SignerClient::new(Channel::from_shared(format!("http://{}", address)).unwrap().connect().await.unwrap())
+}
+
+/// A `SignGeneric` request for the key of `eoa_address`, with optional JSON metadata
+async fn grpc_request(eoa_address: &str, data: [u8; 32], metadata: &[(&'static str, &str)]) -> Request<SignGenericRequest> {
+    let key_pair = JsonStore::from_file(DEFAULT_JSON_PATH).unwrap().get_key_pair(eoa_address).await.unwrap().unwrap();
+    let public_key = key_pair.public_key_g1.to_checked_g1_point().unwrap();
+    let mut request = Request::new(SignGenericRequest {
+        public_key_g1: format!("0x{}", hex::encode(g1_to_compressed(&public_key))),
+        data: data.to_vec(),
+        ..Default::default()
+    });
+    for (key, value) in metadata {
+        request.metadata_mut().insert(*key, value.parse().unwrap());
+    }
+    request
+}
+
+async fn grpc_sign(signer: &mut SignerClient<Channel>, eoa_address: &str, data: [u8; 32], metadata: &[(&'static str, &str)]) -> Code {
+    match signer.sign_generic(grpc_request(eoa_address, data, metadata).await).await {
+        Ok(_) => Code::Ok,
+        Err(status) => status.code(),
+    }
+}
+
+#[tokio::test]
+async fn test_grpc_carries_authorizations_and_contexts() {
+    let (alice, wallet) = player("Alice");
+    let (_, bob_wallet) = player("Bob");
+    let expiry = now() + 60;
+    let authorization = |nonce: u64, signature: String| {
+        json!({ "scheme": "eip191", "nonce": nonce, "expiry": expiry, "signature": signature }).to_string()
+    };
+    let valid = authorization(1, sign_eip191(&wallet, &domain(), &message(), 1, expiry).await);
+    let forged = authorization(2, sign_eip191(&bob_wallet, &domain(), &message(), 2, expiry).await);
+
+    // Off: authorizations are ignored
+    let mut signer = grpc_signer(EoaVerifier::new(EoaAuthMode::Off, 300, domain(), Arc::new(MemoryNonceStore::new()))).await;
+    assert_eq!(grpc_sign(&mut signer, &alice, MESSAGE_HASH, &[]).await, Code::Ok);
+    assert_eq!(grpc_sign(&mut signer, &alice, MESSAGE_HASH, &[(EOA_AUTHORIZATION_METADATA, &forged)]).await, Code::Ok);
+
+    // Optional: checked only when present
+    let mut signer = grpc_signer(EoaVerifier::new(EoaAuthMode::Optional, 300, domain(), Arc::new(MemoryNonceStore::new()))).await;
+    assert_eq!(grpc_sign(&mut signer, &alice, MESSAGE_HASH, &[]).await, Code::Ok);
+    assert_eq!(grpc_sign(&mut signer, &alice, MESSAGE_HASH, &[(EOA_AUTHORIZATION_METADATA, &valid)]).await, Code::Ok);
+    assert_eq!(
+        grpc_sign(&mut signer, &alice, MESSAGE_HASH, &[(EOA_AUTHORIZATION_METADATA, &valid)]).await,
+        Code::AlreadyExists
+    );
+    assert_eq!(
+        grpc_sign(&mut signer, &alice, MESSAGE_HASH, &[(EOA_AUTHORIZATION_METADATA, &forged)]).await,
+        Code::PermissionDenied
+    );
+    assert_eq!(
+        grpc_sign(&mut signer, &alice, MESSAGE_HASH, &[(EOA_AUTHORIZATION_METADATA, "not json")]).await,
+        Code::InvalidArgument
+    );
+
+    // Required: a signing RPC without an authorization is refused
+    let mut signer = grpc_signer(required()).await;
+    assert_eq!(grpc_sign(&mut signer, &alice, MESSAGE_HASH, &[]).await, Code::Unauthenticated);
+    assert_eq!(grpc_sign(&mut signer, &alice, MESSAGE_HASH, &[(EOA_AUTHORIZATION_METADATA, &valid)]).await, Code::Ok);
+
+    // The task context reaches the anti-equivocation guard
+    let context = json!({ "avs": "0xAb5801a7D398351b8bE11C439e05C5B3259aeC9B", "task_index": 1 }).to_string();
+    let mut signer = grpc_signer(EoaVerifier::default()).await;
+    assert_eq!(grpc_sign(&mut signer, &alice, MESSAGE_HASH, &[(SIGNING_CONTEXT_METADATA, &context)]).await, Code::Ok);
+    assert_eq!(
+        grpc_sign(&mut signer, &alice, [0x22; 32], &[(SIGNING_CONTEXT_METADATA, &context)]).await,
+        Code::AlreadyExists
+    );
+}
@@ -0,0 +1,240 @@
+use actix_web::web;
+use bn254_rs::encoding::{g1_from_bytes, g1_to_bytes, g1_to_compressed};
+use bn254_rs::web::audit::{AuditLog, MemoryAuditLog};
+use bn254_rs::web::auth::{ApiToken, Authenticator, MemoryTokenStore, Operation, TokenStore};
+use bn254_rs::web::config::{Config, EndpointsConfig};
+use bn254_rs::web::eoa_auth::EoaVerifier;
+use bn254_rs::web::grpc::proto::keymanager::key_manager_client::KeyManagerClient;
+use bn254_rs::web::grpc::proto::keymanager::{GenerateKeyPairRequest, ImportKeyRequest, ListKeysRequest};
+use bn254_rs::web::grpc::proto::signer::signer_client::SignerClient;
+use bn254_rs::web::grpc::proto::signer::{SignG1Request, SignGenericRequest};
+use bn254_rs::web::grpc::{GrpcService, EOA_ADDRESS_METADATA, SIGNING_CONTEXT_METADATA};
+use bn254_rs::web::guard::{EquivocationGuard, MemorySignedTaskStore, SignedTaskStore};
+use bn254_rs::web::handlers::SigningChecks;
+use bn254_rs::web::import_key_pairs;
+use bn254_rs::web::models::{KeyPair, KeyStatus};
+use bn254_rs::web::policy::PolicyEngine;
+use bn254_rs::web::signer::{LocalSigner, Signer};
+use bn254_rs::web::store::{JsonStore, KeyStore, MemoryStore, DEFAULT_JSON_PATH};
+use bn254_rs::web::tls::ClientPrincipals;
+use bn254_rs::{hash_to_g1, G1Point};
+use std::sync::Arc;
+use tonic::transport::Channel;
+use tonic::{Code, Request};
+
+const ALICE: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+const CAROL: &str = "0x3C44CdDdB6a900fa2b585dd299e03d12FA4293BC";
+
+/// Serves the gRPC API on a free port and returns a channel to it
+async fn serve(store: Arc<dyn KeyStore>, authenticator: Authenticator, endpoints: EndpointsConfig) -> Channel {
+    serve_with_guard(store, authenticator, endpoints, EquivocationGuard::default()).await
+}
+
+async fn serve_with_guard(
+    store: Arc<dyn KeyStore>,
+    authenticator: Authenticator,
+    endpoints: EndpointsConfig,
+    guard: EquivocationGuard,
+) -> Channel {
+    let checks = SigningChecks::new(
+        web::Data::new(EoaVerifier::default()),
+        web::Data::new(PolicyEngine::default()),
+        web::Data::new(guard),
+        web::Data::from(Arc::new(LocalSigner) as Arc<dyn Signer>),
+    );
+    let service = GrpcService::new(
+        web::Data::from(store),
+        checks,
+        web::Data::from(Arc::new(MemoryAuditLog::new()) as Arc<dyn AuditLog>),
+        web::Data::new(authenticator),
+        web::Data::new(ClientPrincipals::default()),
+        endpoints,
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(service.serve(listener, None));
+    Channel::from_shared(format!("http://{}", address)).unwrap().connect().await.unwrap()
+}
+
+async fn player_store() -> Arc<dyn KeyStore> {
+    let store: Arc<dyn KeyStore> = Arc::new(MemoryStore::new());
+    let json = JsonStore::from_file(DEFAULT_JSON_PATH).unwrap();
+    import_key_pairs(&json, store.as_ref(), &LocalSigner).await.unwrap();
+    store
+}
+
+async fn alice(store: &dyn KeyStore) -> KeyPair {
+    store.get_key_pair(ALICE).await.unwrap().unwrap()
+}
+
+fn compressed_hex(key_pair: &KeyPair) -> String {
+    format!("0x{}", hex::encode(g1_to_compressed(&key_pair.public_key_g1.to_checked_g1_point().unwrap())))
+}
+
+#[tokio::test]
+async fn test_grpc_signer() {
+    let store = player_store().await;
+    let alice = alice(store.as_ref()).await;
+    let channel = serve(store.clone(), Authenticator::Disabled, EndpointsConfig::default()).await;
+    let mut signer = SignerClient::new(channel.clone());
+    let mut keys = KeyManagerClient::new(channel);
+
+    let listed = keys.list_keys(ListKeysRequest {}).await.unwrap().into_inner().public_keys;
+    assert_eq!(listed.len(), store.list_key_pairs().await.unwrap().len());
+    let public_key = compressed_hex(&alice);
+    assert!(listed.contains(&public_key));
+
+    // SignGeneric maps the data to G1 like the contract's hashToG1
+    let data = [7u8; 32];
+    let expected = LocalSigner.scalar_mul(&alice, &hash_to_g1(&data)).unwrap();
+    let request = SignGenericRequest {
+        public_key_g1: public_key.clone(),
+        data: data.to_vec(),
+        password: String::new(),
+    };
+    let signature = signer.sign_generic(request).await.unwrap().into_inner().signature;
+    assert_eq!(g1_from_bytes(&signature).unwrap(), expected);
+
+    // Keys are also found by their uncompressed public key
+    let uncompressed = hex::encode(g1_to_bytes(&alice.public_key_g1.to_checked_g1_point().unwrap()));
+    let message = G1Point::generator().scalar_mul(ark_bn254::Fr::from(17u64));
+    let expected = g1_to_bytes(&LocalSigner.scalar_mul(&alice, &message).unwrap()).to_vec();
+    for data in [g1_to_bytes(&message).to_vec(), g1_to_compressed(&message).to_vec()] {
+        let request = SignG1Request {
+            public_key_g1: uncompressed.clone(),
+            data,
+            password: String::new(),
+        };
+        assert_eq!(signer.sign_g1(request).await.unwrap().into_inner().signature, expected);
+    }
+
+    let sign_g1 = |public_key_g1: &str, data: Vec<u8>| SignG1Request {
+        public_key_g1: public_key_g1.to_string(),
+        data,
+        password: String::new(),
+    };
+    let unknown = format!("0x{}", hex::encode(g1_to_compressed(&message)));
+    let code = signer.sign_g1(sign_g1(&unknown, g1_to_bytes(&message).to_vec())).await.unwrap_err().code();
+    assert_eq!(code, Code::NotFound);
+    let code = signer.sign_g1(sign_g1(&public_key, vec![1; 64])).await.unwrap_err().code();
+    assert_eq!(code, Code::InvalidArgument);
+    let code = signer.sign_g1(sign_g1(&public_key, vec![0; 64])).await.unwrap_err().code();
+    assert_eq!(code, Code::InvalidArgument);
+    let code = signer.sign_g1(sign_g1("0x1234", g1_to_bytes(&message).to_vec())).await.unwrap_err().code();
+    assert_eq!(code, Code::InvalidArgument);
+
+    // Key management is off by default
+    let code = keys.generate_key_pair(GenerateKeyPairRequest::default()).await.unwrap_err().code();
+    assert_eq!(code, Code::Unimplemented);
+}
+
+/// A request with a bearer token and, for new keys, an EOA address
+fn request<T>(message: T, bearer: &str, eoa_address: Option<&str>) -> Request<T> {
+    let mut request = Request::new(message);
+    request.metadata_mut().insert("authorization", bearer.parse().unwrap());
+    if let Some(eoa_address) = eoa_address {
+        request.metadata_mut().insert(EOA_ADDRESS_METADATA, eoa_address.parse().unwrap());
+    }
+    request
+}
+
+#[tokio::test]
+async fn test_grpc_key_management() {
+    let tokens = Arc::new(MemoryTokenStore::new());
+    let bearer = |operations: Vec<Operation>| {
+        let tokens = tokens.clone();
+        async move {
+            let (bearer, token) = ApiToken::generate("test", None, operations, None);
+            tokens.insert_token(token).await.unwrap();
+            format!("Bearer {}", bearer)
+        }
+    };
+    let admin = bearer(vec![Operation::ManageKeys, Operation::ReadKeys, Operation::Sign]).await;
+    let reader = bearer(vec![Operation::ReadKeys]).await;
+    let store: Arc<dyn KeyStore> = Arc::new(MemoryStore::new());
+    let endpoints = EndpointsConfig {
+        lifecycle: true,
+        ..EndpointsConfig::default()
+    };
+    let channel = serve(store.clone(), Authenticator::Tokens(tokens.clone()), endpoints).await;
+    let mut keys = KeyManagerClient::new(channel.clone());
+    let mut signer = SignerClient::new(channel);
+
+    let code = keys.list_keys(ListKeysRequest {}).await.unwrap_err().code();
+    assert_eq!(code, Code::Unauthenticated);
+    let generate = GenerateKeyPairRequest::default;
+    let code = keys.generate_key_pair(request(generate(), &reader, Some(ALICE))).await.unwrap_err().code();
+    assert_eq!(code, Code::PermissionDenied);
+    // A Cerberus client does not name the EOA, which is derived from the key
+    let response = keys.generate_key_pair(request(generate(), &admin, None)).await.unwrap().into_inner();
+    let derived = store.list_key_pairs().await.unwrap().remove(0);
+    assert_eq!(response.public_key_g1, compressed_hex(&derived));
+    let key_id = derived.key_id().unwrap();
+    assert_eq!(derived.eoa_address, format!("0x{}", &key_id[26..]));
+    store.delete_key_pair(&derived.eoa_address).await.unwrap();
+
+    let response = keys.generate_key_pair(request(generate(), &admin, Some(ALICE))).await.unwrap().into_inner();
+    assert!(response.private_key.is_empty() && response.mnemonic.is_empty());
+    assert_eq!(response.public_key_g1, compressed_hex(&alice(store.as_ref()).await));
+    let code = keys.generate_key_pair(request(generate(), &admin, Some(ALICE))).await.unwrap_err().code();
+    assert_eq!(code, Code::AlreadyExists);
+
+    let import = |private_key: &str| ImportKeyRequest {
+        private_key: private_key.to_string(),
+        ..Default::default()
+    };
+    let code = keys.import_key(request(import("0x12"), &admin, Some(CAROL))).await.unwrap_err().code();
+    assert_eq!(code, Code::InvalidArgument);
+    let response = keys.import_key(request(import("42"), &admin, Some(CAROL))).await.unwrap().into_inner();
+    let carol = KeyPair::from_private_key(CAROL, ark_bn254::Fr::from(42u64)).unwrap();
+    assert_eq!(response.public_key_g1, compressed_hex(&carol));
+
+    let listed = keys.list_keys(request(ListKeysRequest {}, &reader, None)).await.unwrap().into_inner();
+    assert_eq!(listed.public_keys.len(), 2);
+
+    // The imported key signs, and the reader may not
+    let sign = SignGenericRequest {
+        public_key_g1: response.public_key_g1,
+        data: vec![3; 32],
+        password: String::new(),
+    };
+    let code = signer.sign_generic(request(sign.clone(), &reader, None)).await.unwrap_err().code();
+    assert_eq!(code, Code::PermissionDenied);
+    let signature = signer.sign_generic(request(sign, &admin, None)).await.unwrap().into_inner().signature;
+    let expected = hash_to_g1(&[3; 32]).scalar_mul(ark_bn254::Fr::from(42u64));
+    assert_eq!(g1_from_bytes(&signature).unwrap(), expected);
+}
+
+#[tokio::test]
+async fn test_grpc_disabled_key_records_no_task() {
+    let store = player_store().await;
+    let alice = alice(store.as_ref()).await;
+    store.set_key_status(ALICE, KeyStatus::Disabled).await.unwrap();
+    let tasks = Arc::new(MemorySignedTaskStore::new());
+    let guard = EquivocationGuard::new(tasks.clone());
+    let channel = serve_with_guard(store, Authenticator::Disabled, EndpointsConfig::default(), guard).await;
+    let mut signer = SignerClient::new(channel);
+
+    let mut request = Request::new(SignGenericRequest {
+        public_key_g1: compressed_hex(&alice),
+        data: vec![7; 32],
+        password: String::new(),
+    });
+    let context = r#"{"avs":"0xAb5801a7D398351b8bE11C439e05C5B3259aeC9B","task_index":1}"#;
+    request.metadata_mut().insert(SIGNING_CONTEXT_METADATA, context.parse().unwrap());
+    let code = signer.sign_generic(request).await.unwrap_err().code();
+    assert_eq!(code, Code::PermissionDenied);
+    assert!(tasks.list_tasks().await.unwrap().is_empty());
+}
+
+#[test]
+fn test_grpc_config() {
+    let mut config = Config::default();
+    assert_eq!(config.grpc.port, None);
+    config
+        .apply_env(vec![("BN254_GRPC_PORT".to_string(), "8080".to_string())])
+        .unwrap();
+    assert_eq!(config.grpc.port, Some(8080));
+    let message = config.validate().unwrap_err().to_string();
+    assert!(message.contains("grpc.port must differ from server.port"));
+}
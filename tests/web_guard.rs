@@ -0,0 +1,181 @@
+mod common;
+
+use common::TestApp;
+use actix_web::{test as actix_test, web, App};
+use bn254_rs::web::guard::{
+    export_interchange, import_interchange, EquivocationGuard, ImportSummary, Interchange, MemorySignedTaskStore,
+    RecordOutcome, SignedTask, SignedTaskStore, INTERCHANGE_FORMAT_VERSION,
+};
+use bn254_rs::web::models::KeyStatus;
+use bn254_rs::web::sqlite::SqliteSignedTaskStore;
+use bn254_rs::web::store::{JsonStore, KeyStore, MemoryStore, DEFAULT_JSON_PATH};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+const ALICE: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+const BOB: &str = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8";
+const AVS: &str = "0xAb5801a7D398351b8bE11C439e05C5B3259aeC9B";
+
+fn task(eoa_address: &str, task_index: u64, hash: u8) -> SignedTask {
+    SignedTask {
+        eoa_address: eoa_address.to_lowercase(),
+        avs: AVS.to_lowercase(),
+        task_index,
+        message_hash: format!("0x{}", hex::encode([hash; 32])),
+        signed_at: 1_718_582_400,
+    }
+}
+
+fn sign(eoa_address: &str, message: u8, context: Option<(&str, u64)>) -> Value {
+    let mut body = json!({ "eoa_address": eoa_address, "message_hash": format!("0x{}", hex::encode([message; 32])) });
+    if let Some((avs, task_index)) = context {
+        body["context"] = json!({ "avs": avs, "task_index": task_index });
+    }
+    body
+}
+
+#[actix_web::test]
+async fn test_conflicting_messages_are_refused() {
+    let store: Arc<dyn KeyStore> = Arc::new(MemoryStore::with_key_pairs(
+        JsonStore::from_file(DEFAULT_JSON_PATH).unwrap().list_key_pairs().await.unwrap(),
+    ));
+    let tasks = Arc::new(MemorySignedTaskStore::new());
+    let app = TestApp {
+        guard: web::Data::new(EquivocationGuard::new(tasks.clone())),
+        ..TestApp::new(store)
+    };
+    let app = actix_test::init_service(app.register(App::new())).await;
+
+    let mut responses = Vec::new();
+    for body in [
+        sign(ALICE, 1, Some((AVS, 7))),
+        // Re-signing the same message is idempotent
+        sign(ALICE, 1, Some((&AVS.to_lowercase(), 7))),
+        sign(ALICE, 2, Some((AVS, 7))),
+        sign(ALICE, 2, Some((AVS, 8))),
+        sign(BOB, 2, Some((AVS, 7))),
+        sign(ALICE, 3, None),
+        sign(ALICE, 3, Some(("", 9))),
+    ] {
+        let req = actix_test::TestRequest::post().uri("/api/sign").set_json(body).to_request();
+        let resp = actix_test::call_service(&app, req).await;
+        let status = resp.status().as_u16();
+        let body: Value = actix_test::read_body_json(resp).await;
+        responses.push((status, body["error"].as_str().unwrap_or("").to_string()));
+    }
+    let expected = [(200, ""), (200, ""), (409, "equivocation"), (200, ""), (200, ""), (200, ""), (400, "invalid_context")];
+    assert_eq!(
+        responses,
+        expected.iter().map(|(status, error)| (*status, error.to_string())).collect::<Vec<_>>()
+    );
+
+    let recorded = tasks.list_tasks().await.unwrap();
+    let slots: Vec<(&str, u64)> = recorded.iter().map(|t| (t.eoa_address.as_str(), t.task_index)).collect();
+    let (alice, bob) = (ALICE.to_lowercase(), BOB.to_lowercase());
+    // Ordered by key, so Bob's lowercase address comes first
+    assert_eq!(slots, vec![(bob.as_str(), 7), (alice.as_str(), 7), (alice.as_str(), 8)]);
+}
+
+#[actix_web::test]
+async fn test_unusable_keys_record_no_tasks() {
+    let store: Arc<dyn KeyStore> = Arc::new(MemoryStore::with_key_pairs(
+        JsonStore::from_file(DEFAULT_JSON_PATH).unwrap().list_key_pairs().await.unwrap(),
+    ));
+    store.set_key_status(BOB, KeyStatus::Disabled).await.unwrap();
+    let tasks = Arc::new(MemorySignedTaskStore::new());
+    let app = TestApp {
+        guard: web::Data::new(EquivocationGuard::new(tasks.clone())),
+        ..TestApp::new(store)
+    };
+    let app = actix_test::init_service(app.register(App::new())).await;
+
+    let mut responses = Vec::new();
+    for body in [sign(AVS, 1, Some((AVS, 7))), sign(BOB, 1, Some((AVS, 7)))] {
+        let req = actix_test::TestRequest::post().uri("/api/sign").set_json(body).to_request();
+        let resp = actix_test::call_service(&app, req).await;
+        let status = resp.status().as_u16();
+        let body: Value = actix_test::read_body_json(resp).await;
+        responses.push((status, body["error"].as_str().unwrap_or("").to_string()));
+    }
+    assert_eq!(responses, vec![(404, "unknown_eoa".to_string()), (403, "key_disabled".to_string())]);
+    assert!(tasks.list_tasks().await.unwrap().is_empty());
+}
+
+async fn check_store(tasks: &dyn SignedTaskStore) {
+    assert_eq!(tasks.record_task(task(ALICE, 10, 1)).await.unwrap(), RecordOutcome::New);
+    assert_eq!(tasks.record_task(task(ALICE, 10, 1)).await.unwrap(), RecordOutcome::Repeat);
+    assert_eq!(
+        tasks.record_task(task(ALICE, 10, 2)).await.unwrap(),
+        RecordOutcome::Conflict { message_hash: task(ALICE, 10, 1).message_hash }
+    );
+    assert_eq!(tasks.record_task(task(ALICE, 9, 2)).await.unwrap(), RecordOutcome::New);
+    assert_eq!(tasks.list_tasks().await.unwrap(), vec![task(ALICE, 9, 2), task(ALICE, 10, 1)]);
+}
+
+#[tokio::test]
+async fn test_signed_task_stores() {
+    check_store(&MemorySignedTaskStore::new()).await;
+
+    let path = std::env::temp_dir().join(format!("bn254-rs-guard-{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let url = format!("sqlite://{}", path.display());
+    check_store(&SqliteSignedTaskStore::connect(&url).await.unwrap()).await;
+    // Records survive a restart
+    let reopened = SqliteSignedTaskStore::connect(&url).await.unwrap();
+    assert_eq!(reopened.list_tasks().await.unwrap().len(), 2);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn test_interchange_roundtrip() {
+    let source = MemorySignedTaskStore::new();
+    for record in [task(ALICE, 1, 1), task(ALICE, 2, 2), task(BOB, 1, 3)] {
+        source.record_task(record).await.unwrap();
+    }
+    let exported = export_interchange(&source).await.unwrap();
+    assert_eq!(exported.metadata.interchange_format_version, INTERCHANGE_FORMAT_VERSION);
+    let json = serde_json::to_value(&exported).unwrap();
+    assert_eq!(json["data"][1]["eoa_address"], ALICE.to_lowercase());
+    assert_eq!(json["data"][1]["signed_tasks"][1]["task_index"], "2");
+    assert_eq!(json["data"][1]["signed_tasks"][1]["signed_at"], "1718582400");
+
+    let target = MemorySignedTaskStore::new();
+    target.record_task(task(ALICE, 1, 1)).await.unwrap();
+    target.record_task(task(BOB, 1, 9)).await.unwrap();
+    let interchange: Interchange = serde_json::from_value(json).unwrap();
+    let summary = import_interchange(&target, &interchange).await.unwrap();
+    assert_eq!(summary, ImportSummary { imported: 1, unchanged: 1, conflicts: 1 });
+    // The conflicting record keeps the message signed on the target
+    assert!(target.list_tasks().await.unwrap().contains(&task(BOB, 1, 9)));
+    assert_eq!(target.list_tasks().await.unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn test_invalid_interchange_is_rejected() {
+    let valid = export_interchange(&MemorySignedTaskStore::new()).await.unwrap();
+    let tasks = MemorySignedTaskStore::new();
+
+    let mut version = valid.clone();
+    version.metadata.interchange_format_version = "2".to_string();
+    let err = import_interchange(&tasks, &version).await.unwrap_err().to_string();
+    assert!(err.contains("Unsupported interchange format version 2"), "{}", err);
+
+    // Nothing is imported from a file with an invalid task
+    let mut malformed: Interchange = serde_json::from_value(json!({
+        "metadata": valid.metadata,
+        "data": [{
+            "eoa_address": ALICE,
+            "signed_tasks": [
+                { "avs": AVS, "task_index": "1", "message_hash": format!("0x{}", "11".repeat(32)), "signed_at": "0" },
+                { "avs": AVS, "task_index": "-2", "message_hash": format!("0x{}", "11".repeat(32)), "signed_at": "0" }
+            ]
+        }]
+    }))
+    .unwrap();
+    let err = import_interchange(&tasks, &malformed).await.unwrap_err().to_string();
+    assert!(err.contains("Invalid task_index"), "{}", err);
+    malformed.data[0].signed_tasks[1].task_index = "2".to_string();
+    malformed.data[0].signed_tasks[1].message_hash = "0x1234".to_string();
+    assert!(import_interchange(&tasks, &malformed).await.is_err());
+    assert!(tasks.list_tasks().await.unwrap().is_empty());
+}
@@ -0,0 +1,294 @@
+mod common;
+
+use common::TestApp;
+use actix_web::{test as actix_test, web, App};
+use aes::cipher::{KeyIvInit, StreamCipher};
+use ark_bn254::Fr;
+use ark_ff::{BigInteger, PrimeField};
+use bn254_rs::web::audit::{AuditLog, MemoryAuditLog};
+use bn254_rs::web::auth::{ApiToken, Authenticator, MemoryTokenStore, Operation, TokenStore};
+use bn254_rs::web::config::{Config, EndpointsConfig, StoreBackend};
+use bn254_rs::web::keystore::{decrypt_keystore, normalize_password, Keystore, KeystoreError};
+use bn254_rs::web::models::{KeyStatus, PublicKeyView};
+use bn254_rs::web::store::{KeyStore, MemoryStore};
+use bn254_rs::{hash_to_g1, verify_signature, G1Point, G2Point};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::str::FromStr;
+use std::sync::Arc;
+
+const ALICE: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+const BOB: &str = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8";
+
+/// The BN254 group order
+const ORDER: &str = "21888242871839275222246405745257275088548364400416034343698204186575808495617";
+
+/// Encrypts a secret into an EIP-2335 keystore with cheap scrypt parameters
+fn scrypt_keystore(secret: &[u8; 32], password: &str) -> Keystore {
+    let salt = [0x5a; 32];
+    let iv = [0x17; 16];
+    let mut key = [0u8; 32];
+    let params = scrypt::Params::new(4, 8, 1, 32).unwrap();
+    scrypt::scrypt(normalize_password(password).as_bytes(), &salt, &params, &mut key).unwrap();
+    let mut ciphertext = secret.to_vec();
+    ctr::Ctr128BE::<aes::Aes128>::new(key[..16].into(), &iv.into()).apply_keystream(&mut ciphertext);
+    let checksum = Sha256::new().chain_update(&key[16..]).chain_update(&ciphertext).finalize();
+    serde_json::from_value(json!({
+        "crypto": {
+            "kdf": { "function": "scrypt", "params": { "dklen": 32, "n": 16, "r": 8, "p": 1, "salt": hex::encode(salt) }, "message": "" },
+            "checksum": { "function": "sha256", "params": {}, "message": hex::encode(checksum) },
+            "cipher": { "function": "aes-128-ctr", "params": { "iv": hex::encode(iv) }, "message": hex::encode(ciphertext) }
+        },
+        "uuid": "1d85ae20-35c5-4611-98e8-aa14a633906f",
+        "version": 4
+    }))
+    .unwrap()
+}
+
+/// A token with the given operations on every key
+async fn token(tokens: &MemoryTokenStore, operations: Vec<Operation>) -> String {
+    let (bearer, token) = ApiToken::generate("test", None, operations, None);
+    tokens.insert_token(token).await.unwrap();
+    format!("Bearer {}", bearer)
+}
+
+macro_rules! lifecycle_app {
+    ($store:expr, $audit:expr, $authenticator:expr) => {{
+        let app = TestApp {
+            authenticator: web::Data::new($authenticator),
+            audit: web::Data::from($audit as Arc<dyn AuditLog>),
+            endpoints: EndpointsConfig { lifecycle: true, ..EndpointsConfig::default() },
+            ..TestApp::new($store)
+        };
+        actix_test::init_service(app.register(App::new())).await
+    }};
+}
+
+fn sign_body(eoa_address: &str) -> Value {
+    json!({ "eoa_address": eoa_address, "message_hash": format!("0x{}", "22".repeat(32)) })
+}
+
+#[actix_web::test]
+async fn test_key_lifecycle() {
+    let tokens = Arc::new(MemoryTokenStore::new());
+    let admin = token(&tokens, vec![Operation::ManageKeys, Operation::ReadKeys, Operation::Sign, Operation::ScalarMul]).await;
+    let signer = token(&tokens, vec![Operation::ReadKeys, Operation::Sign]).await;
+    let store: Arc<dyn KeyStore> = Arc::new(MemoryStore::new());
+    let audit = Arc::new(MemoryAuditLog::new());
+    let app = lifecycle_app!(store.clone(), audit.clone(), Authenticator::Tokens(tokens));
+    let call = |bearer: &str, req: actix_test::TestRequest| {
+        let req = req.insert_header(("Authorization", bearer.to_string())).to_request();
+        let app = &app;
+        async move {
+            let resp = actix_test::call_service(app, req).await;
+            let status = resp.status().as_u16();
+            let body = String::from_utf8(actix_test::read_body(resp).await.to_vec()).unwrap();
+            assert!(!body.contains("private_key"), "{}", body);
+            (status, serde_json::from_str::<Value>(&body).unwrap_or(Value::Null))
+        }
+    };
+    let post = |path: &str| actix_test::TestRequest::post().uri(&format!("/api/keys/{}/{}", ALICE, path));
+    let sign = || actix_test::TestRequest::post().uri("/api/sign").set_json(sign_body(ALICE));
+    let error = |(status, body): (u16, Value)| (status, body["error"].as_str().unwrap_or("").to_string());
+
+    assert_eq!(error(call(&signer, post("generate")).await), (403, "forbidden".to_string()));
+    assert_eq!(
+        error(call(&admin, actix_test::TestRequest::post().uri("/api/keys/0x1234/generate")).await),
+        (400, "invalid_eoa".to_string())
+    );
+    let (status, body) = call(&admin, post("generate")).await;
+    assert_eq!(status, 201, "{}", body);
+    let generated: PublicKeyView = serde_json::from_value(body).unwrap();
+    assert_eq!((generated.eoa_address.as_str(), generated.metadata.status), (ALICE, KeyStatus::Active));
+    assert_eq!(error(call(&admin, post("generate")).await), (409, "key_exists".to_string()));
+
+    // The generated public keys belong to the stored private key
+    let (status, signed) = call(&signer, sign()).await;
+    assert_eq!(status, 200);
+    assert_eq!(signed["signer_g2"], json!(generated.public_key_g2));
+    let g2 = generated.public_key_g2.to_checked_g2_point().unwrap();
+    let signature = bn254_rs::web::models::G1Point { x: signed["product"]["x"].as_str().unwrap().to_string(), y: signed["product"]["y"].as_str().unwrap().to_string() };
+    assert!(verify_signature(&hash_to_g1(&[0x22; 32]), &signature.to_checked_g1_point().unwrap(), &g2));
+
+    let (status, body) = call(&admin, post("disable")).await;
+    assert_eq!((status, body["metadata"]["status"].as_str()), (200, Some("disabled")));
+    assert_eq!(error(call(&signer, sign()).await), (403, "key_disabled".to_string()));
+    let scalar_mul = actix_test::TestRequest::post()
+        .uri("/api/scalar_mul")
+        .set_json(json!({ "eoa_address": ALICE, "hash_x": "1", "hash_y": "2" }));
+    assert_eq!(error(call(&admin, scalar_mul).await), (403, "key_disabled".to_string()));
+    assert_eq!(call(&admin, post("enable")).await.0, 200);
+    assert_eq!(call(&signer, sign()).await.0, 200);
+
+    // Rotation keeps the old public keys readable, but signs with the new key
+    let (status, body) = call(&admin, post("rotate")).await;
+    assert_eq!(status, 200, "{}", body);
+    let rotated: PublicKeyView = serde_json::from_value(body).unwrap();
+    assert_ne!(rotated.key_id, generated.key_id);
+    let (_, body) = call(&signer, actix_test::TestRequest::get().uri(&format!("/api/keys/{}", ALICE))).await;
+    let read: PublicKeyView = serde_json::from_value(body).unwrap();
+    assert_eq!(read.key_id, rotated.key_id);
+    assert_eq!(read.metadata.retired.len(), 1);
+    assert_eq!(read.metadata.retired[0].key_id, generated.key_id);
+    assert_eq!(read.metadata.retired[0].public_key_g2, generated.public_key_g2);
+    let (_, signed) = call(&signer, sign()).await;
+    assert_eq!(signed["signer_g2"], json!(rotated.public_key_g2));
+
+    assert_eq!(error(call(&admin, post("rotate").set_payload("{")).await), (400, "invalid_body".to_string()));
+    assert_eq!(
+        error(call(&admin, actix_test::TestRequest::post().uri(&format!("/api/keys/{}/rotate", BOB))).await),
+        (404, "unknown_eoa".to_string())
+    );
+
+    let delete = || actix_test::TestRequest::delete().uri(&format!("/api/keys/{}", ALICE));
+    assert_eq!(error(call(&signer, delete()).await), (403, "forbidden".to_string()));
+    let (status, body) = call(&admin, delete()).await;
+    assert_eq!((status, body["key_id"].as_str()), (200, Some(rotated.key_id.as_str())));
+    assert!(store.get_key_pair(ALICE).await.unwrap().is_none());
+    assert_eq!(store.key_metadata(ALICE).await.unwrap().retired.len(), 0);
+    assert_eq!(error(call(&admin, delete()).await), (404, "unknown_eoa".to_string()));
+
+    let entries = audit.entries().await.unwrap();
+    let steps: Vec<(&str, &str)> = entries
+        .iter()
+        .filter(|e| e.record.operation.starts_with("key_"))
+        .map(|e| (e.record.operation.as_str(), e.record.result.as_str()))
+        .collect();
+    // Requests denied by the token scope never reach the handlers
+    assert_eq!(
+        steps,
+        vec![
+            ("key_generate", "invalid_eoa"),
+            ("key_generate", "ok"),
+            ("key_generate", "key_exists"),
+            ("key_disable", "ok"),
+            ("key_enable", "ok"),
+            ("key_rotate", "ok"),
+            ("key_rotate", "invalid_body"),
+            ("key_rotate", "unknown_eoa"),
+            ("key_delete", "ok"),
+            ("key_delete", "unknown_eoa"),
+        ]
+    );
+    let output = |operation: &str| {
+        entries
+            .iter()
+            .find(|e| e.record.operation == operation && e.record.result == "ok")
+            .and_then(|e| e.record.output_hash.clone())
+    };
+    assert_eq!(output("key_generate"), Some(generated.key_id.clone()));
+    assert_eq!(output("key_rotate"), Some(rotated.key_id.clone()));
+}
+
+#[actix_web::test]
+async fn test_import_key() {
+    let tokens = Arc::new(MemoryTokenStore::new());
+    let admin = token(&tokens, vec![Operation::ManageKeys]).await;
+    let store: Arc<dyn KeyStore> = Arc::new(MemoryStore::new());
+    let app = lifecycle_app!(store.clone(), Arc::new(MemoryAuditLog::new()), Authenticator::Tokens(tokens));
+    let import = |eoa_address: &str, body: Value| {
+        let req = actix_test::TestRequest::post()
+            .uri(&format!("/api/keys/{}/import", eoa_address))
+            .insert_header(("Authorization", admin.clone()))
+            .set_json(body)
+            .to_request();
+        let app = &app;
+        async move {
+            let resp = actix_test::call_service(app, req).await;
+            let status = resp.status().as_u16();
+            let body: Value = actix_test::read_body_json(resp).await;
+            (status, body)
+        }
+    };
+    let secret = Fr::from(123456789u64);
+    let expected_g2 = bn254_rs::web::models::G2Point::from(&G2Point::generator().scalar_mul(secret));
+
+    let (status, body) = import(ALICE, json!({ "private_key": "123456789" })).await;
+    assert_eq!(status, 201, "{}", body);
+    assert_eq!(body["public_key_g2"], json!(expected_g2));
+    let stored = store.get_key_pair(ALICE).await.unwrap().unwrap();
+    assert_eq!(stored.private_key.expose_secret().unwrap().as_str(), "123456789");
+    assert_eq!(stored.public_key_g1, bn254_rs::web::models::G1Point::from(&G1Point::generator().scalar_mul(secret)));
+
+    let hex_secret = format!("0x{}", hex::encode(secret.into_bigint().to_bytes_be()));
+    let (status, body) = import(ALICE, json!({ "private_key": hex_secret })).await;
+    assert_eq!((status, body["error"].as_str()), (409, Some("key_exists")));
+
+    let keystore = scrypt_keystore(&secret.into_bigint().to_bytes_be().try_into().unwrap(), "correct horse");
+    let (status, body) = import(BOB, json!({ "keystore": keystore, "password": "correct horse" })).await;
+    assert_eq!(status, 201, "{}", body);
+    assert_eq!(body["public_key_g2"], json!(expected_g2));
+
+    let carol = "0x3C44CdDdB6a900fa2b585dd299e03d12FA4293BC";
+    let order_hex = format!("0x{}", hex::encode(Fr::MODULUS.to_bytes_be()));
+    let mut wrong_version = serde_json::to_value(&keystore).unwrap();
+    wrong_version["version"] = json!(3);
+    for (body, status, error) in [
+        (json!({ "keystore": keystore, "password": "wrong" }), 400, "invalid_password"),
+        (json!({ "keystore": wrong_version, "password": "correct horse" }), 400, "invalid_keystore"),
+        (json!({ "keystore": keystore }), 400, "missing_field"),
+        (json!({}), 400, "missing_field"),
+        (json!({ "private_key": "1", "keystore": keystore, "password": "x" }), 400, "conflicting_fields"),
+        (json!({ "private_key": "0" }), 400, "invalid_private_key"),
+        (json!({ "private_key": ORDER }), 400, "invalid_private_key"),
+        (json!({ "private_key": order_hex }), 400, "invalid_private_key"),
+        (json!({ "private_key": "0x1234" }), 400, "invalid_private_key"),
+    ] {
+        let (actual_status, response) = import(carol, body).await;
+        assert_eq!((actual_status, response["error"].as_str()), (status, Some(error)), "{}", response);
+    }
+    assert!(store.get_key_pair(carol).await.unwrap().is_none());
+}
+
+#[actix_web::test]
+async fn test_key_management_needs_authentication() {
+    let store: Arc<dyn KeyStore> = Arc::new(MemoryStore::new());
+    let app = lifecycle_app!(store, Arc::new(MemoryAuditLog::new()), Authenticator::Disabled);
+    let req = actix_test::TestRequest::post().uri(&format!("/api/keys/{}/generate", ALICE)).to_request();
+    assert_eq!(actix_test::call_service(&app, req).await.status(), 403);
+
+    let mut config = Config::default();
+    config.store.backend = StoreBackend::Memory;
+    config.endpoints.lifecycle = true;
+    assert!(config.validate().is_ok());
+    config.auth.enabled = false;
+    assert!(config.validate().unwrap_err().to_string().contains("endpoints.lifecycle requires auth.enabled"));
+}
+
+#[test]
+fn test_eip2335_pbkdf2_test_vector() {
+    let keystore: Keystore = serde_json::from_value(json!({
+        "crypto": {
+            "kdf": {
+                "function": "pbkdf2",
+                "params": {
+                    "dklen": 32,
+                    "c": 262144,
+                    "prf": "hmac-sha256",
+                    "salt": "d4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3"
+                },
+                "message": ""
+            },
+            "checksum": {
+                "function": "sha256",
+                "params": {},
+                "message": "8a9f5d9912ed7e75ea794bc5a89bca5f193721d30868ade6f73043c6ea6febf1"
+            },
+            "cipher": {
+                "function": "aes-128-ctr",
+                "params": { "iv": "264daa3f303d7259501c93d997d84fe6" },
+                "message": "cee03fde2af33149775b7223e7845e4fb2c8ae1792e5f99fe9ecf474cc8c16ad"
+            }
+        },
+        "pubkey": "9612d7a727c9d0a22e185a1c768478dfe919cada9266988cb32359c11f2b7b27f4ae4040902382ae2910c15e2b420d07",
+        "uuid": "64625def-3331-4eea-ab6f-782f3ed16a83",
+        "version": 4
+    }))
+    .unwrap();
+    // The secret of the EIP-2335 test vectors is below the BN254 group order
+    let secret = decrypt_keystore(&keystore, "\u{1d531}\u{1d522}\u{1d530}\u{1d531}\u{1d52d}\u{1d51e}\u{1d530}\u{1d530}\u{1d534}\u{1d52c}\u{1d52f}\u{1d521}\u{1f511}").unwrap();
+    assert_eq!(secret, Fr::from_str("10628944869218562084050143519444549580389464591454674019345556079").unwrap());
+    assert!(matches!(decrypt_keystore(&keystore, "testpassword"), Err(KeystoreError::WrongPassword)));
+
+    assert_eq!(normalize_password("\u{212b}\u{7f}b\n\u{85}").as_str(), "A\u{30a}b");
+}
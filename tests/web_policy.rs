@@ -0,0 +1,232 @@
+mod common;
+
+use common::TestApp;
+use actix_web::{test as actix_test, web, App};
+use bn254_rs::web::auth::Principal;
+use bn254_rs::web::config::{Config, KeyPolicy, MessageSource, TimeWindow, Weekday};
+use bn254_rs::web::metrics::Metrics;
+use bn254_rs::web::models::{MessageMode, SignResponse};
+use bn254_rs::web::policy::PolicyEngine;
+use bn254_rs::web::store::{JsonStore, KeyStore, MemoryStore, DEFAULT_JSON_PATH};
+use bn254_rs::{hash_to_g1, verify_signature};
+use serde_json::{json, Value};
+use sha3::{Digest, Keccak256};
+use std::sync::Arc;
+
+const ALICE: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+const BOB: &str = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8";
+const DOMAIN: [u8; 32] = [0xd0; 32];
+/// Monday 2024-06-17 00:00:00 UTC
+const MONDAY: i64 = 1_718_582_400;
+
+fn policy(eoa_address: &str) -> KeyPolicy {
+    KeyPolicy {
+        eoa_address: eoa_address.to_string(),
+        max_per_hour: None,
+        max_per_day: None,
+        principals: None,
+        time_windows: Vec::new(),
+        expires_at: None,
+        message_sources: None,
+        approval_required: false,
+    }
+}
+
+fn sources() -> Vec<MessageSource> {
+    vec![MessageSource {
+        name: "tasks".to_string(),
+        domain: format!("0x{}", hex::encode(DOMAIN)),
+    }]
+}
+
+fn anonymous() -> Principal {
+    Principal::unrestricted("anonymous")
+}
+
+/// Sends requests to a service with the given policies, returning the responses and the metrics
+async fn call(policies: Vec<KeyPolicy>, requests: Vec<(&str, Value)>) -> (Vec<(u16, Value)>, String) {
+    let store: Arc<dyn KeyStore> = Arc::new(MemoryStore::with_key_pairs(
+        JsonStore::from_file(DEFAULT_JSON_PATH).unwrap().list_key_pairs().await.unwrap(),
+    ));
+    let metrics = Arc::new(Metrics::new());
+    let app = TestApp {
+        policy: web::Data::new(PolicyEngine::new(&policies, &sources(), metrics.clone()).unwrap()),
+        metrics: web::Data::from(metrics),
+        ..TestApp::new(store)
+    };
+    let app = actix_test::init_service(app.register(App::new())).await;
+    let mut responses = Vec::new();
+    for (uri, body) in requests {
+        let req = actix_test::TestRequest::post().uri(uri).set_json(body).to_request();
+        let resp = actix_test::call_service(&app, req).await;
+        let status = resp.status().as_u16();
+        responses.push((status, actix_test::read_body_json(resp).await));
+    }
+    let req = actix_test::TestRequest::get().uri("/metrics").to_request();
+    let metrics = String::from_utf8(actix_test::call_and_read_body(&app, req).await.to_vec()).unwrap();
+    (responses, metrics)
+}
+
+fn sign_hash(eoa_address: &str) -> (&'static str, Value) {
+    ("/api/sign", json!({ "eoa_address": eoa_address, "message_hash": format!("0x{}", "11".repeat(32)) }))
+}
+
+fn sign_sourced(eoa_address: &str, source: &str) -> (&'static str, Value) {
+    ("/api/sign", json!({ "eoa_address": eoa_address, "message_source": { "source": source, "data": "0xcafe" } }))
+}
+
+#[actix_web::test]
+async fn test_rate_limits_are_enforced_and_counted() {
+    let limited = KeyPolicy { max_per_hour: Some(2), ..policy(ALICE) };
+    let (responses, metrics) = call(
+        vec![limited],
+        vec![sign_hash(ALICE), sign_hash(ALICE), sign_hash(ALICE), sign_hash(BOB)],
+    )
+    .await;
+    let statuses: Vec<u16> = responses.iter().map(|(status, _)| *status).collect();
+    assert_eq!(statuses, vec![200, 200, 429, 200]);
+    assert_eq!(responses[2].1["error"], "policy_rate_limited");
+    assert_eq!(responses[2].1["field"], "eoa_address");
+
+    let alice = ALICE.to_lowercase();
+    assert!(metrics.contains(&format!("bn254_policy_denials_total{{eoa_address=\"{}\",rule=\"max_per_hour\"}} 1", alice)), "{}", metrics);
+    assert!(metrics.contains(&format!("bn254_policy_allowed_total{{eoa_address=\"{}\"}} 2", alice)), "{}", metrics);
+}
+
+#[actix_web::test]
+async fn test_message_sources() {
+    let sourced_only = KeyPolicy { message_sources: Some(vec!["tasks".to_string()]), ..policy(ALICE) };
+    let scalar_mul = json!({ "eoa_address": ALICE, "hash_x": "1", "hash_y": "2" });
+    let (responses, _) = call(
+        vec![sourced_only],
+        vec![
+            sign_sourced(ALICE, "tasks"),
+            sign_hash(ALICE),
+            ("/api/scalar_mul", scalar_mul),
+            sign_sourced(ALICE, "unknown"),
+            sign_sourced(BOB, "tasks"),
+        ],
+    )
+    .await;
+    assert_eq!(responses[0].0, 200, "{}", responses[0].1);
+    let response: SignResponse = serde_json::from_value(responses[0].1.clone()).unwrap();
+    assert_eq!(response.mode, MessageMode::Source);
+    let mut hasher = Keccak256::new();
+    hasher.update(DOMAIN);
+    hasher.update([0xca, 0xfe]);
+    let message = hash_to_g1(&hasher.finalize().into());
+    let signature = response.product.to_checked_g1_point().unwrap();
+    assert!(verify_signature(&message, &signature, &response.signer_g2.to_checked_g2_point().unwrap()));
+
+    let errors: Vec<(u16, &str)> = responses[1..]
+        .iter()
+        .map(|(status, body)| (*status, body["error"].as_str().unwrap_or("")))
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            (403, "policy_message_source_required"),
+            (403, "policy_message_source_required"),
+            (400, "unknown_message_source"),
+            (200, ""),
+        ]
+    );
+}
+
+#[test]
+fn test_principals_expiry_and_time_windows() {
+    let engine = PolicyEngine::new(
+        &[
+            KeyPolicy {
+                principals: Some(vec!["cert:aggregator".to_string()]),
+                expires_at: Some(MONDAY + 7 * 86_400),
+                time_windows: vec![TimeWindow {
+                    days: vec![Weekday::Mon, Weekday::Tue],
+                    start: "08:00".to_string(),
+                    end: "18:00".to_string(),
+                }],
+                ..policy(ALICE)
+            },
+            KeyPolicy { max_per_day: Some(1), ..policy("*") },
+        ],
+        &sources(),
+        Arc::new(Metrics::new()),
+    )
+    .unwrap();
+    let aggregator = Principal::unrestricted("cert:aggregator");
+    let error = |principal: &Principal, now: i64| {
+        engine
+            .check_at(principal, &ALICE.to_lowercase(), None, now)
+            .err()
+            .map(|e| e.body.error)
+    };
+
+    assert_eq!(error(&aggregator, MONDAY + 8 * 3600), None);
+    assert_eq!(error(&anonymous(), MONDAY + 8 * 3600).as_deref(), Some("policy_principal_denied"));
+    assert_eq!(error(&aggregator, MONDAY + 18 * 3600).as_deref(), Some("policy_outside_time_window"));
+    // Wednesday
+    assert_eq!(error(&aggregator, MONDAY + 2 * 86_400 + 9 * 3600).as_deref(), Some("policy_outside_time_window"));
+    assert_eq!(error(&aggregator, MONDAY + 7 * 86_400 + 9 * 3600).as_deref(), Some("policy_key_expired"));
+
+    // Keys without a policy of their own fall back to `*`, counted per key
+    assert!(engine.check_at(&anonymous(), BOB, None, MONDAY).is_ok());
+    assert_eq!(engine.check_at(&anonymous(), BOB, None, MONDAY + 3600).unwrap_err().status, 429);
+    assert!(engine.check_at(&anonymous(), "0x01", None, MONDAY + 3600).is_ok());
+    assert!(engine.check_at(&anonymous(), BOB, None, MONDAY + 86_400).is_ok());
+}
+
+#[test]
+fn test_weekdays() {
+    assert_eq!(Weekday::of_timestamp(0), Weekday::Thu);
+    assert_eq!(Weekday::of_timestamp(MONDAY), Weekday::Mon);
+    assert_eq!(Weekday::of_timestamp(MONDAY - 1), Weekday::Sun);
+    assert_eq!(Weekday::of_timestamp(MONDAY + 6 * 86_400 + 86_399), Weekday::Sun);
+}
+
+#[test]
+fn test_policy_config() {
+    let config: Config = toml::from_str(
+        r#"
+        [[message_sources]]
+        name = "tasks"
+        domain = "0xd0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0d0"
+
+        [[policies]]
+        eoa_address = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"
+        max_per_hour = 10
+        principals = ["token:abc"]
+        message_sources = ["tasks"]
+
+        [[policies.time_windows]]
+        days = ["mon", "fri"]
+        start = "09:30"
+        end = "24:00"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(config.policies[0].time_windows[0].minutes().unwrap(), (570, 1440));
+    assert_eq!(config.message_sources, sources());
+    let errors = |config: &Config| {
+        let mut config = config.clone();
+        config.store.backend = bn254_rs::web::config::StoreBackend::Memory;
+        config.validate().err().map(|e| e.to_string())
+    };
+    assert_eq!(errors(&config), None);
+
+    let mut invalid = config.clone();
+    invalid.message_sources[0].domain = "0x1234".to_string();
+    invalid.policies[0].message_sources = Some(vec!["missing".to_string()]);
+    invalid.policies[0].max_per_hour = Some(0);
+    invalid.policies[0].time_windows[0].end = "09:00".to_string();
+    invalid.policies.push(policy(&ALICE.to_lowercase()));
+    let message = errors(&invalid).unwrap();
+    for expected in [
+        "message_sources[0].domain must be 32 bytes of hex",
+        "policies[0]: unknown message source missing",
+        "policies[0]: signature limits must be at least 1",
+        "policies[0].time_windows[0]: start 09:30 is not before end 09:00",
+        "policies[1]: duplicate policy for",
+    ] {
+        assert!(message.contains(expected), "{}", message);
+    }
+}
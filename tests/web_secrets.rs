@@ -0,0 +1,126 @@
+//! Regression tests asserting that no endpoint ever returns secret key material.
+//!
+//! Every route registered by `api_scope` should be exercised here; when adding
+//! an endpoint, add a request for it to `requests`.
+
+mod common;
+
+use common::TestApp;
+use actix_web::{test as actix_test, App};
+use ark_bn254::Fr;
+use ark_ff::{BigInteger, PrimeField};
+use bn254_rs::web::models::{G1Point, KeyPair, PublicKeyView};
+use bn254_rs::web::sqlite::SqliteStore;
+use bn254_rs::web::store::{JsonStore, KeyStore, MemoryStore, DEFAULT_JSON_PATH};
+use serde_json::json;
+use std::str::FromStr;
+use std::sync::Arc;
+
+async fn players() -> Vec<KeyPair> {
+    JsonStore::from_file(DEFAULT_JSON_PATH)
+        .unwrap()
+        .list_key_pairs()
+        .await
+        .unwrap()
+}
+
+/// Every encoding of a secret that could plausibly leak into a response
+fn secret_encodings(key_pair: &KeyPair) -> Vec<String> {
+    let secret = key_pair.private_key.expose_secret().unwrap();
+    let bytes = Fr::from_str(&secret).unwrap().into_bigint().to_bytes_be();
+    vec![
+        secret.to_string(),
+        hex::encode(&bytes),
+        hex::encode_upper(&bytes),
+        hex::encode(bytes.iter().rev().copied().collect::<Vec<u8>>()),
+    ]
+}
+
+/// One request per endpoint, using the first key pair where an EOA is needed
+fn requests(key_pair: &KeyPair) -> Vec<actix_test::TestRequest> {
+    let eoa = &key_pair.eoa_address;
+    let message = bn254_rs::G1Point::generator();
+    let signature = G1Point::from(&message.scalar_mul(key_pair.to_private_key().unwrap()));
+    vec![
+        actix_test::TestRequest::get().uri("/api/keys"),
+        actix_test::TestRequest::get().uri(&format!("/api/keys/{}", eoa)),
+        actix_test::TestRequest::post().uri("/api/sign").set_json(json!({
+            "eoa_address": eoa,
+            "message_hash": "00".repeat(32),
+        })),
+        actix_test::TestRequest::post().uri("/api/scalar_mul").set_json(json!({
+            "eoa_address": eoa,
+            "hash_x": "1",
+            "hash_y": "2",
+        })),
+        actix_test::TestRequest::post().uri("/api/verify").set_json(json!({
+            "message_point": G1Point::from(&message),
+            "signature": signature,
+            "eoa_address": eoa,
+        })),
+        actix_test::TestRequest::post().uri("/api/approvals").set_json(json!({
+            "eoa_address": eoa,
+            "message_hash": "00".repeat(32),
+        })),
+    ]
+}
+
+async fn assert_no_secrets(store: Arc<dyn KeyStore>) {
+    let key_pairs = store.list_key_pairs().await.unwrap();
+    let secrets: Vec<String> = key_pairs.iter().flat_map(secret_encodings).collect();
+    let app = actix_test::init_service(TestApp::new(store).register(App::new())).await;
+
+    for req in requests(&key_pairs[0]) {
+        let req = req.to_request();
+        let uri = req.uri().to_string();
+        let resp = actix_test::call_service(&app, req).await;
+        assert!(resp.status().is_success(), "{} returned {}", uri, resp.status());
+        let body = String::from_utf8(actix_test::read_body(resp).await.to_vec()).unwrap();
+        assert!(!body.contains("private_key"), "{} exposes a private_key field", uri);
+        for secret in &secrets {
+            assert!(!body.contains(secret.as_str()), "{} leaks secret key material", uri);
+        }
+    }
+}
+
+#[actix_web::test]
+async fn test_no_secrets_from_memory_store() {
+    assert_no_secrets(Arc::new(MemoryStore::with_key_pairs(players().await))).await;
+}
+
+#[actix_web::test]
+async fn test_no_secrets_from_sqlite_store() {
+    let store = SqliteStore::connect("sqlite::memory:", &[1u8; 32]).await.unwrap();
+    for key_pair in players().await {
+        store.upsert_key_pair(&key_pair, &["operator".to_string()]).await.unwrap();
+    }
+    assert_no_secrets(Arc::new(store)).await;
+}
+
+#[actix_web::test]
+async fn test_public_key_view() {
+    let key_pair = players().await[0].clone();
+    let store: Arc<dyn KeyStore> = Arc::new(MemoryStore::with_key_pairs([key_pair.clone()]));
+    let app = actix_test::init_service(TestApp::new(store).register(App::new())).await;
+
+    let req = actix_test::TestRequest::get()
+        .uri(&format!("/api/keys/{}", key_pair.eoa_address))
+        .to_request();
+    let view: PublicKeyView = actix_test::call_and_read_body_json(&app, req).await;
+    assert_eq!(view.eoa_address, key_pair.eoa_address);
+    assert_eq!(view.public_key_g1.x, key_pair.public_key_g1.x);
+    assert_eq!(view.public_key_g2.y_b, key_pair.public_key_g2.y_b);
+    assert_eq!(view.key_id, key_pair.key_id().unwrap());
+    assert_eq!(view.key_id.len(), 66);
+}
+
+#[tokio::test]
+async fn test_secret_is_redacted_in_debug_output() {
+    for key_pair in players().await {
+        let debug = format!("{:?}", key_pair);
+        assert!(debug.contains("<redacted>"));
+        for secret in secret_encodings(&key_pair) {
+            assert!(!debug.contains(&secret));
+        }
+    }
+}
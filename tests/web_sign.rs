@@ -0,0 +1,117 @@
+mod common;
+
+use common::TestApp;
+use actix_web::{test as actix_test, App};
+use bn254_rs::encoding::g1_to_compressed;
+use bn254_rs::web::models::{ErrorResponse, G1Point, KeyPair, MessageMode, SignResponse};
+use bn254_rs::web::store::{JsonStore, KeyStore, MemoryStore, DEFAULT_JSON_PATH};
+use bn254_rs::web::signer::{LocalSigner, Signer};
+use bn254_rs::{hash_to_g1, verify_signature, G1Point as Point};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+async fn alice() -> KeyPair {
+    let store = JsonStore::from_file(DEFAULT_JSON_PATH).unwrap();
+    store
+        .get_key_pair("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266")
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+async fn call(body: Value) -> (u16, Value) {
+    call_at("/api/sign", body).await
+}
+
+async fn call_at(uri: &str, body: Value) -> (u16, Value) {
+    let store: Arc<dyn KeyStore> = Arc::new(MemoryStore::with_key_pairs([alice().await]));
+    let app = actix_test::init_service(TestApp::new(store).register(App::new())).await;
+    let req = actix_test::TestRequest::post().uri(uri).set_json(body).to_request();
+    let resp = actix_test::call_service(&app, req).await;
+    let status = resp.status().as_u16();
+    (status, actix_test::read_body_json(resp).await)
+}
+
+/// Signs through the API and checks the signature against the signer's G2 key
+async fn sign_and_check(message: Value, expected: &Point, mode: MessageMode) {
+    let key_pair = alice().await;
+    let mut body = message;
+    body["eoa_address"] = json!(key_pair.eoa_address);
+    let (status, body) = call(body).await;
+    assert_eq!(status, 200, "{}", body);
+    let response: SignResponse = serde_json::from_value(body).unwrap();
+
+    assert_eq!(response.mode, mode);
+    assert_eq!(response.message.x, G1Point::from(expected).x);
+    assert_eq!(response.message.y, G1Point::from(expected).y);
+    let signature = response.product.to_checked_g1_point().unwrap();
+    let pk_g2 = response.signer_g2.to_checked_g2_point().unwrap();
+    assert!(verify_signature(expected, &signature, &pk_g2));
+}
+
+#[actix_web::test]
+async fn test_sign_point() {
+    let m = Point::generator().scalar_mul(ark_bn254::Fr::from(7u64));
+    sign_and_check(json!({ "message_point": G1Point::from(&m) }), &m, MessageMode::Point).await;
+}
+
+#[actix_web::test]
+async fn test_sign_hash() {
+    let hash = [0x11u8; 32];
+    let m = hash_to_g1(&hash);
+    sign_and_check(json!({ "message_hash": hex::encode(hash) }), &m, MessageMode::Hash).await;
+}
+
+#[actix_web::test]
+async fn test_sign_compressed() {
+    let m = Point::generator().scalar_mul(ark_bn254::Fr::from(9u64));
+    let compressed = format!("0x{}", hex::encode(g1_to_compressed(&m)));
+    sign_and_check(json!({ "message_compressed": compressed }), &m, MessageMode::Compressed).await;
+}
+
+#[actix_web::test]
+async fn test_sign_rejects_invalid_messages() {
+    let eoa = alice().await.eoa_address;
+    let cases = [
+        // The old x-only form with an implicit y = 1 is gone
+        (json!({ "eoa_address": eoa, "point": "1" }), 400, "missing_field"),
+        (json!({ "eoa_address": eoa, "message_point": { "x": "1", "y": "1" } }), 400, "invalid_point"),
+        (json!({ "eoa_address": eoa, "message_point": { "x": "0", "y": "0" } }), 400, "invalid_point"),
+        (json!({ "eoa_address": eoa, "message_hash": "abcd" }), 400, "invalid_hash"),
+        (json!({ "eoa_address": eoa, "message_compressed": "zz" }), 400, "invalid_point"),
+        (json!({ "eoa_address": eoa, "message_compressed": format!("40{}", "00".repeat(31)) }), 400, "invalid_point"),
+        (json!({ "eoa_address": eoa, "message_hash": "00".repeat(32), "message_compressed": "00".repeat(32) }), 400, "conflicting_fields"),
+        (json!({ "eoa_address": "0xdead", "message_hash": "00".repeat(32) }), 404, "unknown_eoa"),
+    ];
+    for (body, status, code) in cases {
+        let (actual, response) = call(body.clone()).await;
+        let error: ErrorResponse = serde_json::from_value(response).unwrap();
+        assert_eq!((actual, error.error.as_str()), (status, code), "{}", body);
+    }
+}
+
+#[actix_web::test]
+async fn test_scalar_mul_rejects_invalid_points() {
+    let eoa = alice().await.eoa_address;
+    // p + 1 is not a canonical coordinate
+    let p_plus_one = "21888242871839275222246405745257275088696311157297823662689037894645226208584";
+    let cases = [
+        (json!({ "eoa_address": eoa, "hash_x": "1", "hash_y": "1" }), "hash_x"),
+        (json!({ "eoa_address": eoa, "hash_x": "0", "hash_y": "0" }), "hash_x"),
+        (json!({ "eoa_address": eoa, "hash_x": p_plus_one, "hash_y": "2" }), "hash_x"),
+        (json!({ "eoa_address": eoa, "hash_x": "1", "hash_y": "0x02" }), "hash_y"),
+    ];
+    for (body, field) in cases {
+        let (status, response) = call_at("/api/scalar_mul", body.clone()).await;
+        let error: ErrorResponse = serde_json::from_value(response).unwrap();
+        assert_eq!((status, error.error.as_str(), error.field.as_deref()), (400, "invalid_point", Some(field)), "{}", body);
+    }
+
+    let m = Point::generator().scalar_mul(ark_bn254::Fr::from(7u64));
+    let point = G1Point::from(&m);
+    let body = json!({ "eoa_address": eoa, "hash_x": point.x, "hash_y": point.y });
+    let (status, response) = call_at("/api/scalar_mul", body).await;
+    assert_eq!(status, 200, "{}", response);
+    let signature: G1Point = serde_json::from_value(response["signature"].clone()).unwrap();
+    assert_eq!(signature.to_checked_g1_point().unwrap(), LocalSigner.scalar_mul(&alice().await, &m).unwrap());
+}
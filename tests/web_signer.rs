@@ -0,0 +1,175 @@
+mod common;
+
+use common::TestApp;
+use actix_web::{test as actix_test, web, App};
+use bn254_rs::web::envelope::{Envelope, KekProvider, LocalKek};
+use bn254_rs::web::import_key_pairs;
+use bn254_rs::web::models::{G1Point, KeyPair, SignResponse};
+use bn254_rs::web::pkcs11::Pkcs11Kek;
+use bn254_rs::web::signer::{LocalSigner, Signer, WrappingSigner};
+use bn254_rs::web::store::{JsonStore, KeyStore, MemoryStore, DEFAULT_JSON_PATH};
+use bn254_rs::G1Point as Point;
+use cryptoki::context::{CInitializeArgs, CInitializeFlags, Pkcs11};
+use cryptoki::session::UserType;
+use cryptoki::types::AuthPin;
+use serde_json::json;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+const ALICE: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("bn254-rs-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_dir_all(&path);
+    path
+}
+
+fn wrapping_signer(name: &str) -> WrappingSigner {
+    let path = temp_path(name);
+    std::fs::write(&path, hex::encode([5u8; 32])).unwrap();
+    let kek = LocalKek::from_key_file(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    WrappingSigner::new(Arc::new(kek))
+}
+
+async fn alice() -> KeyPair {
+    JsonStore::from_file(DEFAULT_JSON_PATH)
+        .unwrap()
+        .get_key_pair(ALICE)
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+/// Checks that a wrapping signer keeps keys sealed and signs like the local signer
+async fn check_wrapping_signer(signer: &WrappingSigner) {
+    let key_pair = alice().await;
+    let sealed = signer.seal(key_pair.clone()).unwrap();
+    assert_eq!(sealed.public_key_g1, key_pair.public_key_g1);
+    let secret = sealed.private_key.expose_secret().unwrap();
+    assert!(!secret.contains(key_pair.private_key.expose_secret().unwrap().as_str()));
+    serde_json::from_str::<Envelope>(&secret).unwrap();
+    // Sealing twice keeps the same envelope
+    assert_eq!(signer.seal(sealed.clone()).unwrap().private_key, sealed.private_key);
+
+    let message = Point::generator().scalar_mul(ark_bn254::Fr::from(11u64));
+    let expected = LocalSigner.scalar_mul(&key_pair, &message).unwrap();
+    assert_eq!(signer.scalar_mul(&sealed, &message).unwrap(), expected);
+
+    // Neither signer uses a key in the other's form
+    assert!(LocalSigner.scalar_mul(&sealed, &message).is_err());
+    assert!(signer.scalar_mul(&key_pair, &message).is_err());
+    // A sealed key only opens for its own EOA
+    let moved = KeyPair {
+        eoa_address: "0x70997970C51812dc3A010C7d01b50e0d17dc79C8".to_string(),
+        ..sealed
+    };
+    assert!(signer.scalar_mul(&moved, &message).is_err());
+}
+
+#[tokio::test]
+async fn test_wrapping_signer() {
+    check_wrapping_signer(&wrapping_signer("signer-kek")).await;
+}
+
+#[actix_web::test]
+async fn test_sign_with_wrapping_signer() {
+    let signer: Arc<dyn Signer> = Arc::new(wrapping_signer("endpoint-kek"));
+    let store: Arc<dyn KeyStore> = Arc::new(MemoryStore::new());
+    let json = JsonStore::from_file(DEFAULT_JSON_PATH).unwrap();
+    import_key_pairs(&json, store.as_ref(), signer.as_ref()).await.unwrap();
+    let stored = store.get_key_pair(ALICE).await.unwrap().unwrap();
+    assert!(stored.private_key.expose_secret().unwrap().starts_with('{'));
+
+    let app = TestApp {
+        signer: web::Data::from(signer),
+        ..TestApp::new(store)
+    };
+    let app = actix_test::init_service(app.register(App::new())).await;
+
+    let message = Point::generator().scalar_mul(ark_bn254::Fr::from(13u64));
+    let expected = G1Point::from(&LocalSigner.scalar_mul(&alice().await, &message).unwrap());
+    let req = actix_test::TestRequest::post()
+        .uri("/api/sign")
+        .set_json(json!({ "eoa_address": ALICE, "message_point": G1Point::from(&message) }))
+        .to_request();
+    let response: SignResponse = actix_test::call_and_read_body_json(&app, req).await;
+    assert_eq!(response.product, expected);
+
+    let req = actix_test::TestRequest::post()
+        .uri("/api/scalar_mul")
+        .set_json(json!({
+            "eoa_address": ALICE,
+            "hash_x": G1Point::from(&message).x,
+            "hash_y": G1Point::from(&message).y,
+        }))
+        .to_request();
+    let response: serde_json::Value = actix_test::call_and_read_body_json(&app, req).await;
+    assert_eq!(response["signature"]["x"], json!(expected.x));
+}
+
+/// The SoftHSM module, from `SOFTHSM2_MODULE` or the usual install locations
+fn softhsm_module() -> Option<PathBuf> {
+    let candidates = [
+        "/usr/lib/softhsm/libsofthsm2.so",
+        "/usr/lib/x86_64-linux-gnu/softhsm/libsofthsm2.so",
+        "/usr/local/lib/softhsm/libsofthsm2.so",
+    ];
+    std::env::var_os("SOFTHSM2_MODULE")
+        .map(PathBuf::from)
+        .into_iter()
+        .chain(candidates.iter().map(PathBuf::from))
+        .find(|path| path.is_file())
+}
+
+#[tokio::test]
+async fn test_pkcs11_kek_with_softhsm() {
+    let Some(module) = softhsm_module() else {
+        eprintln!("SoftHSM is not installed; skipping the PKCS#11 test");
+        return;
+    };
+
+    // A throwaway SoftHSM configuration with its own token directory
+    let dir = temp_path("softhsm");
+    std::fs::create_dir_all(dir.join("tokens")).unwrap();
+    let conf = dir.join("softhsm2.conf");
+    std::fs::write(
+        &conf,
+        format!("directories.tokendir = {}\nobjectstore.backend = file\n", dir.join("tokens").display()),
+    )
+    .unwrap();
+    std::env::set_var("SOFTHSM2_CONF", &conf);
+
+    let pkcs11 = Pkcs11::new(&module).unwrap();
+    pkcs11.initialize(CInitializeArgs::new(CInitializeFlags::OS_LOCKING_OK)).unwrap();
+    let slot = pkcs11.get_slots_with_token().unwrap()[0];
+    let so_pin = AuthPin::from("123456");
+    pkcs11.init_token(slot, &so_pin, "bn254").unwrap();
+    let slot = pkcs11
+        .get_slots_with_token()
+        .unwrap()
+        .into_iter()
+        .find(|slot| pkcs11.get_token_info(*slot).unwrap().label() == "bn254")
+        .unwrap();
+    let session = pkcs11.open_rw_session(slot).unwrap();
+    session.login(UserType::So, Some(&so_pin)).unwrap();
+    session.init_pin(&AuthPin::from("1234")).unwrap();
+    session.logout().unwrap();
+    drop(session);
+
+    assert!(Pkcs11Kek::open(&module, "bn254", "1234", "bn254-kek").is_err());
+    let generated = Pkcs11Kek::generate_key(&module, "bn254", "1234", "bn254-kek").unwrap();
+    assert!(Pkcs11Kek::generate_key(&module, "bn254", "1234", "bn254-kek").is_err());
+    assert!(Pkcs11Kek::open(&module, "no-such-token", "1234", "bn254-kek").is_err());
+
+    // Keys wrapped by one session unwrap in another
+    let kek = Pkcs11Kek::open(&module, "bn254", "1234", "bn254-kek").unwrap();
+    assert_eq!(kek.name(), "pkcs11");
+    let wrapped = generated.wrap_key(ALICE.as_bytes(), &[4u8; 32]).unwrap();
+    assert_eq!(*kek.unwrap_key(ALICE.as_bytes(), &wrapped).unwrap(), [4u8; 32]);
+    assert!(kek.unwrap_key(b"0x70997970C51812dc3A010C7d01b50e0d17dc79C8", &wrapped).is_err());
+
+    check_wrapping_signer(&WrappingSigner::new(Arc::new(kek))).await;
+    let _ = std::fs::remove_dir_all(&dir);
+}
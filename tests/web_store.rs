@@ -0,0 +1,356 @@
+mod common;
+
+use common::TestApp;
+use actix_web::{test, App};
+use bn254_rs::web::encrypted_dir::EncryptedDirStore;
+use bn254_rs::web::envelope::{KekProvider, LocalKek};
+use bn254_rs::web::models::{G1Point, KeyPair, KeyStatus};
+use bn254_rs::web::sqlite::SqliteStore;
+use bn254_rs::web::store::{JsonStore, KeyStore, MemoryStore, DEFAULT_JSON_PATH};
+use bn254_rs::web::import_key_pairs;
+use bn254_rs::web::signer::{LocalSigner, Signer};
+use sqlx::Connection;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use zeroize::Zeroizing;
+
+const KEY: [u8; 32] = [7u8; 32];
+
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("bn254-rs-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_dir_all(&path);
+    path
+}
+
+/// A key-file KEK holding `key`
+fn kek_file(name: &str, key: &[u8; 32]) -> LocalKek {
+    let path = temp_path(name);
+    std::fs::write(&path, hex::encode(key)).unwrap();
+    let kek = LocalKek::from_key_file(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    kek
+}
+
+async fn players() -> Vec<KeyPair> {
+    let mut key_pairs = JsonStore::from_file(DEFAULT_JSON_PATH)
+        .unwrap()
+        .list_key_pairs()
+        .await
+        .unwrap();
+    key_pairs.sort_by(|a, b| a.eoa_address.cmp(&b.eoa_address));
+    key_pairs
+}
+
+/// Runs the same CRUD scenario against any backend
+async fn exercise_store(store: &dyn KeyStore) {
+    let key_pairs = players().await;
+    let (first, second) = (&key_pairs[0], &key_pairs[1]);
+
+    for key_pair in &key_pairs {
+        store.insert_key_pair(key_pair.clone()).await.unwrap();
+    }
+    assert!(store.insert_key_pair(first.clone()).await.is_err());
+    assert_eq!(store.list_key_pairs().await.unwrap().len(), key_pairs.len());
+
+    let loaded = store.get_key_pair(&first.eoa_address).await.unwrap().unwrap();
+    assert_eq!(loaded.private_key, first.private_key);
+    assert_eq!(loaded.public_key_g2.y_b, first.public_key_g2.y_b);
+
+    // Rotating gives the EOA the key material of another player
+    let rotated = KeyPair {
+        eoa_address: first.eoa_address.clone(),
+        ..second.clone()
+    };
+    let previous = store.rotate_key_pair(rotated).await.unwrap();
+    assert_eq!(previous.private_key, first.private_key);
+    let loaded = store.get_key_pair(&first.eoa_address).await.unwrap().unwrap();
+    assert_eq!(loaded.private_key, second.private_key);
+    // The replaced public keys stay readable
+    let metadata = store.key_metadata(&first.eoa_address).await.unwrap();
+    assert_eq!(metadata.retired.len(), 1);
+    assert_eq!(metadata.retired[0].key_id, first.key_id().unwrap());
+    assert_eq!(metadata.retired[0].public_key_g1, first.public_key_g1);
+
+    assert!(store.set_key_status(&second.eoa_address, KeyStatus::Disabled).await.unwrap());
+    assert_eq!(store.key_metadata(&second.eoa_address).await.unwrap().status, KeyStatus::Disabled);
+    assert_eq!(store.key_metadata(&first.eoa_address).await.unwrap().status, KeyStatus::Active);
+    assert!(!store.set_key_status("0x0000000000000000000000000000000000000000", KeyStatus::Disabled).await.unwrap());
+
+    assert!(store.delete_key_pair(&first.eoa_address).await.unwrap());
+    assert!(!store.delete_key_pair(&first.eoa_address).await.unwrap());
+    assert!(store.get_key_pair(&first.eoa_address).await.unwrap().is_none());
+    assert!(store.key_metadata(&first.eoa_address).await.unwrap().retired.is_empty());
+    assert!(store.rotate_key_pair(first.clone()).await.is_err());
+    assert_eq!(store.list_key_pairs().await.unwrap().len(), key_pairs.len() - 1);
+}
+
+#[tokio::test]
+async fn test_memory_store() {
+    exercise_store(&MemoryStore::new()).await;
+}
+
+#[tokio::test]
+async fn test_json_store_writes_back() {
+    let path = temp_path("players.json");
+    std::fs::write(&path, "{}").unwrap();
+    exercise_store(&JsonStore::from_file(&path).unwrap()).await;
+
+    // A fresh load sees the changes
+    let reloaded = JsonStore::from_file(&path).unwrap();
+    assert_eq!(reloaded.list_key_pairs().await.unwrap().len(), players().await.len() - 1);
+    let second = &players().await[1];
+    assert_eq!(reloaded.key_metadata(&second.eoa_address).await.unwrap().status, KeyStatus::Disabled);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_json_store_scrubs_replaced_files() {
+    let path = temp_path("scrubbed-players.json");
+    std::fs::write(&path, "{}").unwrap();
+    let store = JsonStore::from_file(&path).unwrap();
+    let players = players().await;
+    for key_pair in &players[..2] {
+        store.insert_key_pair(key_pair.clone()).await.unwrap();
+    }
+
+    // A hard link keeps the replaced file reachable, as its freed blocks would be
+    let old = temp_path("scrubbed-players-old.json");
+    std::fs::hard_link(&path, &old).unwrap();
+    assert!(store.delete_key_pair(&players[0].eoa_address).await.unwrap());
+    let left = std::fs::read(&old).unwrap();
+    assert!(!left.is_empty() && left.iter().all(|byte| *byte == 0));
+    let file = std::fs::read_to_string(&path).unwrap();
+    assert!(!file.contains(players[0].private_key.expose_secret().unwrap().as_str()));
+    assert!(file.contains(&players[1].eoa_address));
+
+    // Rotations replace the file the same way
+    let _ = std::fs::remove_file(&old);
+    std::fs::hard_link(&path, &old).unwrap();
+    let rotated = KeyPair::from_private_key(&players[1].eoa_address, ark_bn254::Fr::from(42u64)).unwrap();
+    store.rotate_key_pair(rotated).await.unwrap();
+    assert!(std::fs::read(&old).unwrap().iter().all(|byte| *byte == 0));
+    let _ = std::fs::remove_file(&old);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_json_store_keeps_unknown_bls_fields() {
+    let path = temp_path("extra-players.json");
+    let mut entries: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(DEFAULT_JSON_PATH).unwrap()).unwrap();
+    for player in entries.as_object_mut().unwrap().values_mut() {
+        player["bls"]["pop"] = serde_json::json!("0x1234");
+    }
+    std::fs::write(&path, entries.to_string()).unwrap();
+    let first = &players().await[0];
+
+    let store = JsonStore::from_file(&path).unwrap();
+    let rotated = KeyPair::from_private_key(&first.eoa_address, ark_bn254::Fr::from(42u64)).unwrap();
+    store.rotate_key_pair(rotated).await.unwrap();
+    drop(store);
+    // Sealing replaces the plaintext private keys and keeps the rest
+    JsonStore::from_file_encrypted(&path, Arc::new(kek_file("extra-kek", &KEY))).unwrap();
+
+    let file: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    for player in file.as_object().unwrap().values() {
+        let bls = player["bls"].as_object().unwrap();
+        assert_eq!(bls["pop"], "0x1234");
+        assert!(bls.contains_key("encrypted_priv_key") && !bls.contains_key("priv_key"));
+    }
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_encrypted_json_store() {
+    let path = temp_path("sealed-players.json");
+    std::fs::write(&path, "{}").unwrap();
+    let kek: Arc<dyn KekProvider> = Arc::new(kek_file("kek", &KEY));
+    exercise_store(&JsonStore::from_file_encrypted(&path, kek.clone()).unwrap()).await;
+
+    // Only envelopes are written, and reading them back needs the KEK
+    let file = std::fs::read_to_string(&path).unwrap();
+    assert!(!file.contains("\"priv_key\""));
+    for key_pair in players().await {
+        assert!(!file.contains(key_pair.private_key.expose_secret().unwrap().as_str()));
+    }
+    let reloaded = JsonStore::from_file_encrypted(&path, kek).unwrap();
+    let second = &players().await[1];
+    let loaded = reloaded.get_key_pair(&second.eoa_address).await.unwrap().unwrap();
+    assert_eq!(loaded.private_key, second.private_key);
+    assert!(JsonStore::from_file(&path).unwrap().list_key_pairs().await.is_err());
+    // Reads leave the keys sealed, so a wrong KEK only fails the signer
+    let wrong = JsonStore::from_file_encrypted(&path, Arc::new(kek_file("wrong-kek", &[8u8; 32]))).unwrap();
+    let sealed = wrong.get_key_pair(&second.eoa_address).await.unwrap().unwrap();
+    assert!(sealed.private_key.is_sealed());
+    assert!(LocalSigner.scalar_mul(&sealed, &bn254_rs::G1Point::generator()).is_err());
+    let _ = std::fs::remove_file(&path);
+}
+
+/// A KEK that counts the data keys it unwraps
+struct CountingKek {
+    kek: LocalKek,
+    unwrapped: AtomicUsize,
+}
+
+impl KekProvider for CountingKek {
+    fn name(&self) -> &str {
+        self.kek.name()
+    }
+
+    fn wrap_key(&self, aad: &[u8], data_key: &[u8; 32]) -> anyhow::Result<Vec<u8>> {
+        self.kek.wrap_key(aad, data_key)
+    }
+
+    fn unwrap_key(&self, aad: &[u8], wrapped: &[u8]) -> anyhow::Result<Zeroizing<[u8; 32]>> {
+        self.unwrapped.fetch_add(1, Ordering::SeqCst);
+        self.kek.unwrap_key(aad, wrapped)
+    }
+}
+
+#[tokio::test]
+async fn test_encrypted_json_store_opens_keys_only_to_sign() {
+    let path = temp_path("lazy-players.json");
+    std::fs::copy(DEFAULT_JSON_PATH, &path).unwrap();
+    let kek = Arc::new(CountingKek {
+        kek: kek_file("lazy-kek", &KEY),
+        unwrapped: AtomicUsize::new(0),
+    });
+    let store = JsonStore::from_file_encrypted(&path, kek.clone()).unwrap();
+    let players = players().await;
+    let eoas: Vec<String> = players.iter().map(|key_pair| key_pair.eoa_address.clone()).collect();
+
+    // Listing and looking up key pairs never unwraps a data key
+    assert_eq!(store.list_key_pairs().await.unwrap().len(), players.len());
+    assert!(store.get_key_pairs(&eoas).await.unwrap().iter().all(Option::is_some));
+    let key_pair = store.get_key_pair(&eoas[0]).await.unwrap().unwrap();
+    assert_eq!(kek.unwrapped.load(Ordering::SeqCst), 0);
+
+    // Each signature opens the key it uses, once
+    let point = bn254_rs::G1Point::generator();
+    let signature = LocalSigner.scalar_mul(&key_pair, &point).unwrap();
+    assert_eq!(kek.unwrapped.load(Ordering::SeqCst), 1);
+    assert_eq!(signature, LocalSigner.scalar_mul(&players[0], &point).unwrap());
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_plaintext_json_store_is_sealed_on_open() {
+    let path = temp_path("plain-players.json");
+    std::fs::copy(DEFAULT_JSON_PATH, &path).unwrap();
+    let store = JsonStore::from_file_encrypted(&path, Arc::new(kek_file("seal-kek", &KEY))).unwrap();
+
+    let file = std::fs::read_to_string(&path).unwrap();
+    assert!(!file.contains("\"priv_key\""));
+    let mut sealed = store.list_key_pairs().await.unwrap();
+    sealed.sort_by(|a, b| a.eoa_address.cmp(&b.eoa_address));
+    let expected = players().await;
+    assert_eq!(sealed.len(), expected.len());
+    for (sealed, expected) in sealed.iter().zip(&expected) {
+        assert_eq!(sealed.private_key, expected.private_key);
+        assert_eq!(sealed.public_key_g1, expected.public_key_g1);
+    }
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_encrypted_dir_store() {
+    let dir = temp_path("keystore");
+    let store = EncryptedDirStore::open(&dir, &KEY).unwrap();
+    exercise_store(&store).await;
+
+    // Key files hold no plaintext private keys and need the store key
+    for key_pair in store.list_key_pairs().await.unwrap() {
+        let file = std::fs::read_to_string(dir.join(format!("{}.json", key_pair.eoa_address))).unwrap();
+        assert!(!file.contains(key_pair.private_key.expose_secret().unwrap().as_str()));
+    }
+    let wrong = EncryptedDirStore::open(&dir, &[8u8; 32]).unwrap();
+    assert!(wrong.list_key_pairs().await.is_err());
+    assert!(store.insert_key_pair(KeyPair { eoa_address: "../escape".to_string(), ..players().await[0].clone() }).await.is_err());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn test_sqlite_store() {
+    exercise_store(&SqliteStore::connect("sqlite::memory:", &KEY).await.unwrap()).await;
+}
+
+#[tokio::test]
+async fn test_sqlite_persists_encrypted_keys() {
+    let path = temp_path("persist.db");
+    let url = format!("sqlite://{}", path.display());
+    let key_pair = players().await[0].clone();
+
+    {
+        let store = SqliteStore::connect(&url, &KEY).await.unwrap();
+        store.upsert_key_pair(&key_pair, &["test".to_string()]).await.unwrap();
+    }
+
+    // The private key must not appear in plaintext anywhere in the database
+    let mut conn = sqlx::SqliteConnection::connect(&url).await.unwrap();
+    let blob: Vec<u8> = sqlx::query_scalar("SELECT private_key_encrypted FROM key_pairs")
+        .fetch_one(&mut conn)
+        .await
+        .unwrap();
+    assert!(!blob.windows(8).any(|w| w == &key_pair.private_key.expose_secret().unwrap().as_bytes()[..8]));
+    conn.close().await.unwrap();
+
+    // Reopening with the same key recovers the key pair, a different key cannot
+    let store = SqliteStore::connect(&url, &KEY).await.unwrap();
+    let loaded = store.get_key_pair(&key_pair.eoa_address).await.unwrap().unwrap();
+    assert_eq!(loaded.private_key, key_pair.private_key);
+    assert_eq!(store.labels(&key_pair.eoa_address).await.unwrap(), Some(vec!["test".to_string()]));
+
+    let wrong = SqliteStore::connect(&url, &[8u8; 32]).await.unwrap();
+    assert!(wrong.get_key_pair(&key_pair.eoa_address).await.is_err());
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_signing_history() {
+    let key_pair = players().await[0].clone();
+    let store = SqliteStore::connect("sqlite::memory:", &KEY).await.unwrap();
+    store.insert_key_pair(key_pair.clone()).await.unwrap();
+
+    let point = |x: &str, y: &str| G1Point { x: x.to_string(), y: y.to_string() };
+    store.record_signature(&key_pair.eoa_address, "sign", &point("1", "2"), &point("3", "4")).await.unwrap();
+    store.record_signature(&key_pair.eoa_address, "scalar_mul", &point("5", "6"), &point("7", "8")).await.unwrap();
+
+    // Rotating a key keeps its history
+    store.rotate_key_pair(key_pair.clone()).await.unwrap();
+    let history = store.signing_history(&key_pair.eoa_address).await.unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].operation, "sign");
+    assert_eq!(history[1].signature.x, "7");
+
+    // Signatures for unknown keys are rejected
+    assert!(store.record_signature("0xunknown", "sign", &point("1", "2"), &point("3", "4")).await.is_err());
+}
+
+#[tokio::test]
+async fn test_import_key_pairs() {
+    let json = JsonStore::from_file(DEFAULT_JSON_PATH).unwrap();
+    let memory = MemoryStore::new();
+    assert_eq!(import_key_pairs(&json, &memory, &LocalSigner).await.unwrap(), players().await.len());
+    assert_eq!(import_key_pairs(&json, &memory, &LocalSigner).await.unwrap(), 0);
+}
+
+#[actix_web::test]
+async fn test_handlers_use_injected_store() {
+    let key_pair = players().await[0].clone();
+    let store: Arc<dyn KeyStore> = Arc::new(MemoryStore::with_key_pairs([key_pair.clone()]));
+    let app = test::init_service(TestApp::new(store).register(App::new())).await;
+
+    let req = test::TestRequest::get().uri("/api/keys").to_request();
+    let listed: Vec<serde_json::Value> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(listed.len(), 1);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/keys/{}", key_pair.eoa_address))
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+
+    let req = test::TestRequest::get().uri("/api/keys/0xmissing").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 404);
+}
@@ -0,0 +1,360 @@
+mod common;
+
+use common::TestApp;
+use actix_web::{web, App, HttpServer};
+use bn254_rs::web::guard::EquivocationGuard;
+use bn254_rs::web::policy::PolicyEngine;
+use bn254_rs::web::audit::{AuditLog, MemoryAuditLog};
+use bn254_rs::web::auth::{ApiToken, Authenticator, MemoryTokenStore, Operation, TokenStore};
+use bn254_rs::web::config::{ClientPrincipal, Config, EndpointsConfig, TlsConfig};
+use bn254_rs::encoding::g1_to_compressed;
+use bn254_rs::web::eoa_auth::EoaVerifier;
+use bn254_rs::web::grpc::proto::signer::signer_client::SignerClient;
+use bn254_rs::web::grpc::proto::signer::SignGenericRequest;
+use bn254_rs::web::grpc::GrpcService;
+use bn254_rs::web::handlers::SigningChecks;
+use bn254_rs::web::store::{JsonStore, KeyStore, MemoryStore, DEFAULT_JSON_PATH};
+use bn254_rs::web::tls::{on_connect, ClientIdentity, ClientPrincipals, TlsReloader};
+use bn254_rs::web::signer::{LocalSigner, Signer};
+use rcgen::{BasicConstraints, Certificate, CertificateParams, DnType, IsCa, SanType};
+use serde_json::{json, Value};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::{self, ClientConfig, RootCertStore, ServerName};
+use tokio_rustls::TlsConnector;
+use hyper_util::rt::TokioIo;
+use tonic::transport::{Channel, Endpoint, Uri};
+use tonic::Code;
+
+const ALICE: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+const BOB: &str = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8";
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("bn254-rs-tls-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn ca(name: &str) -> Certificate {
+    let mut params = CertificateParams::new(Vec::new());
+    params.distinguished_name.push(DnType::CommonName, name);
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    Certificate::from_params(params).unwrap()
+}
+
+/// Issues a certificate signed by `ca`, returning its PEM certificate and key
+fn issue(ca: &Certificate, common_name: &str, alt_names: Vec<SanType>) -> (String, String) {
+    let mut params = CertificateParams::new(Vec::new());
+    params.distinguished_name.push(DnType::CommonName, common_name);
+    params.subject_alt_names = alt_names;
+    let cert = Certificate::from_params(params).unwrap();
+    (cert.serialize_pem_with_signer(ca).unwrap(), cert.serialize_private_key_pem())
+}
+
+fn dns(name: &str) -> SanType {
+    SanType::DnsName(name.to_string())
+}
+
+/// Writes the server certificate and client CA of a test server
+fn write_server_files(dir: &Path, server_ca: &Certificate, client_ca: &Certificate) -> TlsConfig {
+    let (cert, key) = issue(server_ca, "bn254 test server", vec![dns("localhost")]);
+    std::fs::write(dir.join("server.pem"), cert).unwrap();
+    std::fs::write(dir.join("server.key"), key).unwrap();
+    std::fs::write(dir.join("client-ca.pem"), client_ca.serialize_pem().unwrap()).unwrap();
+    TlsConfig {
+        cert: Some(dir.join("server.pem")),
+        key: Some(dir.join("server.key")),
+        client_ca: Some(dir.join("client-ca.pem")),
+        clients: vec![ClientPrincipal {
+            name: "aggregator.test".to_string(),
+            eoa_addresses: Some(vec![ALICE.to_string()]),
+            operations: vec![Operation::Sign],
+        }],
+    }
+}
+
+/// Starts a key service listening with TLS, returning its port and reloader
+async fn start(tls: &TlsConfig, tokens: Arc<dyn TokenStore>) -> (u16, TlsReloader) {
+    let (config, reloader) = TlsReloader::new(tls).unwrap();
+    let store: Arc<dyn KeyStore> = Arc::new(MemoryStore::with_key_pairs(
+        JsonStore::from_file(DEFAULT_JSON_PATH).unwrap().list_key_pairs().await.unwrap(),
+    ));
+    let app = TestApp {
+        authenticator: web::Data::new(Authenticator::Tokens(tokens)),
+        client_principals: web::Data::new(ClientPrincipals(tls.clients.clone())),
+        ..TestApp::new(store)
+    };
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = HttpServer::new(move || app.register(App::new()))
+    .on_connect(on_connect)
+    .workers(1)
+    .listen_rustls_0_21(listener, config)
+    .unwrap()
+    .run();
+    actix_web::rt::spawn(server);
+    (port, reloader)
+}
+
+fn pem_certs(pem: &str) -> Vec<rustls::Certificate> {
+    rustls_pemfile::certs(&mut pem.as_bytes())
+        .unwrap()
+        .into_iter()
+        .map(rustls::Certificate)
+        .collect()
+}
+
+fn client_config(server_ca: &Certificate, client: Option<&(String, String)>) -> ClientConfig {
+    let mut roots = RootCertStore::empty();
+    roots.add(&pem_certs(&server_ca.serialize_pem().unwrap())[0]).unwrap();
+    let builder = ClientConfig::builder().with_safe_defaults().with_root_certificates(roots);
+    match client {
+        Some((cert, key)) => {
+            let key = rustls_pemfile::pkcs8_private_keys(&mut key.as_bytes()).unwrap().remove(0);
+            builder.with_client_auth_cert(pem_certs(cert), rustls::PrivateKey(key)).unwrap()
+        }
+        None => builder.with_no_client_auth(),
+    }
+}
+
+async fn connect(port: u16, server_ca: &Certificate, client: Option<&(String, String)>) -> io::Result<TlsStream<TcpStream>> {
+    let config = client_config(server_ca, client);
+    let tcp = TcpStream::connect(("127.0.0.1", port)).await?;
+    TlsConnector::from(Arc::new(config))
+        .connect(ServerName::try_from("localhost").unwrap(), tcp)
+        .await
+}
+
+/// Sends one request over a keep-alive connection and reads the response
+async fn send(stream: &mut TlsStream<TcpStream>, path: &str, body: Option<Value>, bearer: Option<&str>) -> io::Result<(u16, Value)> {
+    let body = body.map(|b| b.to_string());
+    let mut request = format!(
+        "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: keep-alive\r\n",
+        if body.is_some() { "POST" } else { "GET" },
+        path
+    );
+    if let Some(bearer) = bearer {
+        request.push_str(&format!("Authorization: Bearer {}\r\n", bearer));
+    }
+    let body = body.unwrap_or_default();
+    request.push_str(&format!("Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", body.len(), body));
+    stream.write_all(request.as_bytes()).await?;
+
+    let mut response = Vec::new();
+    let mut chunk = [0u8; 4096];
+    loop {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed"));
+        }
+        response.extend_from_slice(&chunk[..n]);
+        let text = String::from_utf8_lossy(&response).to_string();
+        if let Some(end) = text.find("\r\n\r\n") {
+            let length: usize = text[..end]
+                .lines()
+                .find_map(|line| line.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse().unwrap()))
+                .unwrap_or(0);
+            if response.len() >= end + 4 + length {
+                let status = text[9..12].parse().unwrap();
+                let body = serde_json::from_slice(&response[end + 4..end + 4 + length]).unwrap_or(Value::Null);
+                return Ok((status, body));
+            }
+        }
+    }
+}
+
+async fn request(port: u16, server_ca: &Certificate, client: Option<&(String, String)>, path: &str, body: Option<Value>, bearer: Option<&str>) -> io::Result<(u16, Value)> {
+    let mut stream = connect(port, server_ca, client).await?;
+    send(&mut stream, path, body, bearer).await
+}
+
+fn sign_body(eoa_address: &str) -> Option<Value> {
+    Some(json!({ "eoa_address": eoa_address, "message_hash": format!("0x{}", "11".repeat(32)) }))
+}
+
+#[actix_web::test]
+async fn test_client_certificates_map_to_principals() {
+    let dir = temp_dir("principals");
+    let authority = ca("bn254 test CA");
+    let tls = write_server_files(&dir, &authority, &authority);
+    let tokens = Arc::new(MemoryTokenStore::new());
+    let (bearer, token) = ApiToken::generate("ops", None, vec![Operation::Sign], None);
+    tokens.insert_token(token).await.unwrap();
+    let (port, _reloader) = start(&tls, tokens).await;
+
+    // Matched by its DNS alternative name
+    let aggregator = issue(&authority, "aggregator", vec![dns("aggregator.test")]);
+    let stranger = issue(&authority, "stranger", vec![dns("stranger.test")]);
+    let rogue = issue(&ca("rogue CA"), "aggregator.test", vec![dns("aggregator.test")]);
+
+    let (status, _) = request(port, &authority, Some(&aggregator), "/api/sign", sign_body(ALICE), None).await.unwrap();
+    assert_eq!(status, 200);
+    let (status, body) = request(port, &authority, Some(&aggregator), "/api/sign", sign_body(BOB), None).await.unwrap();
+    assert_eq!((status, body["error"].as_str()), (403, Some("forbidden")));
+    let (status, _) = request(port, &authority, Some(&aggregator), "/api/keys", None, None).await.unwrap();
+    assert_eq!(status, 403);
+
+    // A valid certificate without a principal still needs a token
+    let (status, _) = request(port, &authority, Some(&stranger), "/api/sign", sign_body(ALICE), None).await.unwrap();
+    assert_eq!(status, 401);
+    let (status, _) = request(port, &authority, Some(&stranger), "/api/sign", sign_body(BOB), Some(&bearer)).await.unwrap();
+    assert_eq!(status, 200);
+
+    // Certificates from other CAs and clients without one cannot connect
+    assert!(request(port, &authority, Some(&rogue), "/api/sign", sign_body(ALICE), None).await.is_err());
+    assert!(request(port, &authority, None, "/api/sign", sign_body(ALICE), None).await.is_err());
+}
+
+#[actix_web::test]
+async fn test_reload_keeps_established_connections() {
+    let dir = temp_dir("reload");
+    let (old_ca, new_ca, client_ca) = (ca("old server CA"), ca("new server CA"), ca("client CA"));
+    let tls = write_server_files(&dir, &old_ca, &client_ca);
+    let (port, reloader) = start(&tls, Arc::new(MemoryTokenStore::new())).await;
+    let aggregator = issue(&client_ca, "aggregator.test", Vec::new());
+
+    let mut established = connect(port, &old_ca, Some(&aggregator)).await.unwrap();
+    assert_eq!(send(&mut established, "/api/sign", sign_body(ALICE), None).await.unwrap().0, 200);
+
+    // A broken file is reported and leaves the old material in place
+    std::fs::write(dir.join("server.key"), "not a key").unwrap();
+    assert!(reloader.reload().is_err());
+    assert_eq!(request(port, &old_ca, Some(&aggregator), "/api/sign", sign_body(ALICE), None).await.unwrap().0, 200);
+
+    write_server_files(&dir, &new_ca, &client_ca);
+    reloader.reload().unwrap();
+
+    assert!(connect(port, &old_ca, Some(&aggregator)).await.is_err());
+    assert_eq!(request(port, &new_ca, Some(&aggregator), "/api/sign", sign_body(ALICE), None).await.unwrap().0, 200);
+    assert_eq!(send(&mut established, "/api/sign", sign_body(ALICE), None).await.unwrap().0, 200);
+}
+
+/// Serves the gRPC API with the TLS material of `tls`, returning its port
+async fn start_grpc(tls: &TlsConfig, tokens: Arc<dyn TokenStore>) -> u16 {
+    let (_, reloader) = TlsReloader::new(tls).unwrap();
+    let store: Arc<dyn KeyStore> = Arc::new(MemoryStore::with_key_pairs(
+        JsonStore::from_file(DEFAULT_JSON_PATH).unwrap().list_key_pairs().await.unwrap(),
+    ));
+    let checks = SigningChecks::new(
+        web::Data::new(EoaVerifier::default()),
+        web::Data::new(PolicyEngine::default()),
+        web::Data::new(EquivocationGuard::default()),
+        web::Data::from(Arc::new(LocalSigner) as Arc<dyn Signer>),
+    );
+    let service = GrpcService::new(
+        web::Data::from(store),
+        checks,
+        web::Data::from(Arc::new(MemoryAuditLog::new()) as Arc<dyn AuditLog>),
+        web::Data::new(Authenticator::Tokens(tokens)),
+        web::Data::new(ClientPrincipals(tls.clients.clone())),
+        EndpointsConfig::default(),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(service.serve(listener, Some(Arc::new(reloader.server_config(vec![b"h2".to_vec()])))));
+    port
+}
+
+/// A gRPC channel over TLS, negotiating `h2`
+async fn grpc_channel(port: u16, server_ca: &Certificate, client: Option<&(String, String)>) -> Result<Channel, tonic::transport::Error> {
+    let mut config = client_config(server_ca, client);
+    config.alpn_protocols = vec![b"h2".to_vec()];
+    let connector = TlsConnector::from(Arc::new(config));
+    Endpoint::from_shared(format!("http://127.0.0.1:{}", port))
+        .unwrap()
+        .connect_with_connector(tower::service_fn(move |_: Uri| {
+            let connector = connector.clone();
+            async move {
+                let tcp = TcpStream::connect(("127.0.0.1", port)).await?;
+                let stream = connector.connect(ServerName::try_from("localhost").unwrap(), tcp).await?;
+                Ok::<_, io::Error>(TokioIo::new(stream))
+            }
+        }))
+        .await
+}
+
+/// Signs with the key of `eoa_address` through the gRPC API
+async fn grpc_sign(channel: Channel, eoa_address: &str, bearer: Option<&str>) -> Result<Vec<u8>, tonic::Status> {
+    let key_pair = JsonStore::from_file(DEFAULT_JSON_PATH).unwrap().get_key_pair(eoa_address).await.unwrap().unwrap();
+    let public_key = key_pair.public_key_g1.to_checked_g1_point().unwrap();
+    let mut request = tonic::Request::new(SignGenericRequest {
+        public_key_g1: format!("0x{}", hex::encode(g1_to_compressed(&public_key))),
+        data: vec![1u8; 32],
+        ..Default::default()
+    });
+    if let Some(bearer) = bearer {
+        request.metadata_mut().insert("authorization", format!("Bearer {}", bearer).parse().unwrap());
+    }
+    Ok(SignerClient::new(channel).sign_generic(request).await?.into_inner().signature)
+}
+
+#[tokio::test]
+async fn test_grpc_uses_tls_and_client_certificates() {
+    let dir = temp_dir("grpc");
+    let authority = ca("bn254 test CA");
+    let tls = write_server_files(&dir, &authority, &authority);
+    let tokens = Arc::new(MemoryTokenStore::new());
+    let (bearer, token) = ApiToken::generate("ops", None, vec![Operation::Sign], None);
+    tokens.insert_token(token).await.unwrap();
+    let port = start_grpc(&tls, tokens).await;
+    let aggregator = issue(&authority, "aggregator", vec![dns("aggregator.test")]);
+    let stranger = issue(&authority, "stranger", vec![dns("stranger.test")]);
+
+    // Client certificates map to principals like on the HTTP API
+    let channel = grpc_channel(port, &authority, Some(&aggregator)).await.unwrap();
+    assert_eq!(grpc_sign(channel.clone(), ALICE, None).await.unwrap().len(), 64);
+    let denied = grpc_sign(channel, BOB, None).await.unwrap_err();
+    assert_eq!(denied.code(), Code::PermissionDenied);
+    let channel = grpc_channel(port, &authority, Some(&stranger)).await.unwrap();
+    assert_eq!(grpc_sign(channel.clone(), ALICE, None).await.unwrap_err().code(), Code::Unauthenticated);
+    assert!(grpc_sign(channel, BOB, Some(&bearer)).await.is_ok());
+
+    // Neither plaintext clients nor clients without a certificate get through
+    let plaintext = Channel::from_shared(format!("http://127.0.0.1:{}", port)).unwrap().connect().await;
+    if let Ok(channel) = plaintext {
+        assert!(grpc_sign(channel, ALICE, Some(&bearer)).await.is_err());
+    }
+    if let Ok(channel) = grpc_channel(port, &authority, None).await {
+        assert!(grpc_sign(channel, ALICE, Some(&bearer)).await.is_err());
+    }
+}
+
+#[test]
+fn test_client_identity_names() {
+    let authority = ca("bn254 test CA");
+    let (cert, _) = issue(
+        &authority,
+        "operator-1",
+        vec![
+            dns("operator-1.test"),
+            SanType::URI("spiffe://bn254/operator-1".to_string()),
+            SanType::Rfc822Name("ops@operator-1.test".to_string()),
+        ],
+    );
+    let identity = ClientIdentity::from_der(&pem_certs(&cert)[0].0).unwrap();
+    assert_eq!(identity.common_name.as_deref(), Some("operator-1"));
+    for name in ["operator-1", "operator-1.test", "spiffe://bn254/operator-1", "ops@operator-1.test"] {
+        assert!(identity.has_name(name), "{}", name);
+    }
+    assert!(!identity.has_name("operator-2"));
+}
+
+#[test]
+fn test_client_principals_need_a_client_ca() {
+    let mut config = Config::default();
+    config.tls.clients.push(ClientPrincipal {
+        name: String::new(),
+        eoa_addresses: None,
+        operations: Vec::new(),
+    });
+    let err = config.validate().unwrap_err().to_string();
+    assert!(err.contains("tls.clients requires tls.client_ca"), "{}", err);
+    assert!(err.contains("tls.clients[0].name must not be empty"), "{}", err);
+    assert!(err.contains("tls.clients[0].operations must not be empty"), "{}", err);
+}
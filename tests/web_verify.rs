@@ -0,0 +1,202 @@
+mod common;
+
+use common::TestApp;
+use actix_web::{test as actix_test, App};
+use bn254_rs::web::models::{ErrorResponse, G1Point, KeyPair, VerifyResponse};
+use bn254_rs::web::store::{JsonStore, KeyStore, MemoryStore, DEFAULT_JSON_PATH};
+use bn254_rs::{hash_to_g1, G1Point as Point};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+async fn players() -> Vec<KeyPair> {
+    let mut key_pairs = JsonStore::from_file(DEFAULT_JSON_PATH)
+        .unwrap()
+        .list_key_pairs()
+        .await
+        .unwrap();
+    key_pairs.sort_by(|a, b| a.eoa_address.cmp(&b.eoa_address));
+    key_pairs
+}
+
+fn sign(key_pair: &KeyPair, message: &Point) -> G1Point {
+    G1Point::from(&message.scalar_mul(key_pair.to_private_key().unwrap()))
+}
+
+fn message() -> Point {
+    Point::generator().scalar_mul(ark_bn254::Fr::from(42u64))
+}
+
+async fn call(body: Value) -> (u16, Value) {
+    let store: Arc<dyn KeyStore> = Arc::new(MemoryStore::with_key_pairs(players().await));
+    let app = actix_test::init_service(TestApp::new(store).register(App::new())).await;
+    let req = actix_test::TestRequest::post().uri("/api/verify").set_json(body).to_request();
+    let resp = actix_test::call_service(&app, req).await;
+    let status = resp.status().as_u16();
+    (status, actix_test::read_body_json(resp).await)
+}
+
+async fn verify(body: Value) -> VerifyResponse {
+    let (status, body) = call(body).await;
+    assert_eq!(status, 200, "{}", body);
+    serde_json::from_value(body).unwrap()
+}
+
+async fn reject(body: Value) -> (u16, ErrorResponse) {
+    let (status, body) = call(body).await;
+    (status, serde_json::from_value(body).unwrap())
+}
+
+#[actix_web::test]
+async fn test_verify_single_signature() {
+    let players = players().await;
+    let m = message();
+    let signature = sign(&players[0], &m);
+
+    let by_key = verify(json!({
+        "message_point": G1Point::from(&m),
+        "signature": signature,
+        "public_key_g2": players[0].public_key_g2,
+    }))
+    .await;
+    assert!(by_key.valid);
+    assert_eq!(by_key.mode, "single");
+
+    let by_eoa = verify(json!({
+        "message_point": G1Point::from(&m),
+        "signature": signature,
+        "eoa_address": players[0].eoa_address,
+    }))
+    .await;
+    assert!(by_eoa.valid);
+
+    let wrong_signer = verify(json!({
+        "message_point": G1Point::from(&m),
+        "signature": signature,
+        "eoa_address": players[1].eoa_address,
+    }))
+    .await;
+    assert!(!wrong_signer.valid);
+}
+
+#[actix_web::test]
+async fn test_verify_message_hash() {
+    let players = players().await;
+    let hash = [0x5au8; 32];
+    let signature = sign(&players[0], &hash_to_g1(&hash));
+
+    let response = verify(json!({
+        "message_hash": format!("0x{}", hex::encode(hash)),
+        "signature": signature,
+        "eoa_address": players[0].eoa_address,
+    }))
+    .await;
+    assert!(response.valid);
+    assert_eq!(response.message.x, G1Point::from(&hash_to_g1(&hash)).x);
+}
+
+#[actix_web::test]
+async fn test_verify_aggregate() {
+    let players = players().await;
+    let signers = &players[..3];
+    let m = message();
+    let signatures: Vec<G1Point> = signers.iter().map(|kp| sign(kp, &m)).collect();
+    let eoas: Vec<&str> = signers.iter().map(|kp| kp.eoa_address.as_str()).collect();
+
+    let response = verify(json!({
+        "message_point": G1Point::from(&m),
+        "signatures": signatures,
+        "eoa_addresses": eoas,
+    }))
+    .await;
+    assert!(response.valid);
+    assert_eq!(response.mode, "aggregate");
+    assert_eq!(response.signers, 3);
+
+    // A pre-aggregated signature against individual public keys
+    let aggregate = signers
+        .iter()
+        .map(|kp| m.scalar_mul(kp.to_private_key().unwrap()))
+        .fold(Point::zero(), |acc, s| acc.add(&s));
+    let keys: Vec<_> = signers.iter().map(|kp| kp.public_key_g2.clone()).collect();
+    let response = verify(json!({
+        "message_point": G1Point::from(&m),
+        "signature": G1Point::from(&aggregate),
+        "public_keys_g2": keys,
+    }))
+    .await;
+    assert!(response.valid);
+
+    // Dropping a signer breaks the aggregate
+    let response = verify(json!({
+        "message_point": G1Point::from(&m),
+        "signatures": signatures[..2],
+        "eoa_addresses": eoas,
+    }))
+    .await;
+    assert!(!response.valid);
+}
+
+#[actix_web::test]
+async fn test_verify_validation_errors() {
+    let players = players().await;
+    let m = G1Point::from(&message());
+    let signature = sign(&players[0], &message());
+
+    let (status, err) = reject(json!({ "signature": signature, "eoa_address": players[0].eoa_address })).await;
+    assert_eq!((status, err.error.as_str(), err.field.as_deref()), (400, "missing_field", Some("message_point")));
+
+    let (status, err) = reject(json!({
+        "message_point": { "x": "1", "y": "3" },
+        "signature": signature,
+        "eoa_address": players[0].eoa_address,
+    }))
+    .await;
+    assert_eq!((status, err.error.as_str(), err.field.as_deref()), (400, "invalid_point", Some("message_point")));
+
+    let (status, err) = reject(json!({ "message_hash": "0x1234", "signature": signature, "eoa_address": players[0].eoa_address })).await;
+    assert_eq!((status, err.error.as_str()), (400, "invalid_hash"));
+
+    let (status, err) = reject(json!({
+        "message_point": m,
+        "signatures": [signature, { "x": "1", "y": "2" }, { "x": "1", "y": "3" }],
+        "eoa_address": players[0].eoa_address,
+    }))
+    .await;
+    assert_eq!((status, err.error.as_str(), err.field.as_deref()), (400, "invalid_point", Some("signatures[2]")));
+
+    let (status, err) = reject(json!({ "message_point": m, "signatures": [], "eoa_address": players[0].eoa_address })).await;
+    assert_eq!((status, err.error.as_str()), (400, "empty_list"));
+
+    let (status, err) = reject(json!({
+        "message_point": m,
+        "signature": signature,
+        "eoa_address": players[0].eoa_address,
+        "public_key_g2": players[0].public_key_g2,
+    }))
+    .await;
+    assert_eq!((status, err.error.as_str()), (400, "conflicting_fields"));
+
+    let (status, err) = reject(json!({ "message_point": m, "signature": signature, "eoa_address": "0xdead" })).await;
+    assert_eq!((status, err.error.as_str(), err.field.as_deref()), (404, "unknown_eoa", Some("eoa_address")));
+}
+
+#[tokio::test]
+async fn test_stored_public_keys_use_solidity_order() {
+    for key_pair in players().await {
+        let g1 = key_pair.public_key_g1.to_checked_g1_point().unwrap();
+        let g2 = key_pair.public_key_g2.to_checked_g2_point().unwrap();
+        assert!(bn254_rs::verify_pubkey_pair(&g1, &g2));
+        let roundtrip = bn254_rs::web::models::G2Point::from(&g2);
+        assert_eq!(roundtrip.x_a, key_pair.public_key_g2.x_a);
+        assert_eq!(roundtrip.y_b, key_pair.public_key_g2.y_b);
+    }
+}
+
+#[tokio::test]
+async fn test_to_g2_point_decodes_stored_keys() {
+    for key_pair in players().await {
+        let expected = bn254_rs::G2Point::generator().scalar_mul(key_pair.to_private_key().unwrap());
+        let decoded = key_pair.public_key_g2.to_g2_point().unwrap();
+        assert_eq!(&decoded, expected.inner(), "{}", key_pair.eoa_address);
+    }
+}
@@ -0,0 +1,194 @@
+mod common;
+
+use common::TestApp;
+use actix_web::{test as actix_test, web, App};
+use bn254_rs::encoding::{g1_from_bytes, g1_to_bytes, g1_to_compressed, g2_to_bytes, g2_to_compressed};
+use bn254_rs::web::audit::{AuditLog, MemoryAuditLog};
+use bn254_rs::web::auth::{ApiToken, Authenticator, MemoryTokenStore, Operation, TokenStore};
+use bn254_rs::web::config::{Config, EndpointsConfig};
+use bn254_rs::web::models::{KeyPair, Web3SignerResponse};
+use bn254_rs::web::signer::{LocalSigner, Signer};
+use bn254_rs::web::store::{JsonStore, KeyStore, MemoryStore, DEFAULT_JSON_PATH};
+use bn254_rs::{hash_to_g1, G1Point};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+const ALICE: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+const BOB: &str = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8";
+
+macro_rules! web3signer_app {
+    ($store:expr, $endpoints:expr) => {
+        actix_test::init_service(TestApp { endpoints: $endpoints, ..TestApp::new($store) }.register(App::new())).await
+    };
+}
+
+async fn player_store() -> Arc<dyn KeyStore> {
+    Arc::new(MemoryStore::with_key_pairs(
+        JsonStore::from_file(DEFAULT_JSON_PATH).unwrap().list_key_pairs().await.unwrap(),
+    ))
+}
+
+fn hex_of(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+fn endpoints() -> EndpointsConfig {
+    EndpointsConfig {
+        web3signer: true,
+        ..EndpointsConfig::default()
+    }
+}
+
+#[actix_web::test]
+async fn test_web3signer_sign() {
+    let store = player_store().await;
+    let alice: KeyPair = store.get_key_pair(ALICE).await.unwrap().unwrap();
+    let app = web3signer_app!(store.clone(), endpoints());
+    let g1 = alice.public_key_g1.to_checked_g1_point().unwrap();
+    let g2 = alice.public_key_g2.to_checked_g2_point().unwrap();
+
+    let req = actix_test::TestRequest::get().uri("/api/v1/eth2/publicKeys").to_request();
+    let keys: Vec<String> = actix_test::call_and_read_body_json(&app, req).await;
+    assert_eq!(keys.len(), store.list_key_pairs().await.unwrap().len());
+    assert!(keys.contains(&hex_of(&g1_to_compressed(&g1))));
+
+    // Every encoding of either public key addresses the same key
+    let root = [9u8; 32];
+    let expected = hex_of(&g1_to_bytes(&LocalSigner.scalar_mul(&alice, &hash_to_g1(&root)).unwrap()));
+    let identifiers = [
+        hex_of(&g1_to_compressed(&g1)),
+        hex_of(&g1_to_bytes(&g1)),
+        hex_of(&g2_to_compressed(&g2)),
+        hex_of(&g2_to_bytes(&g2)),
+    ];
+    for identifier in identifiers {
+        let req = actix_test::TestRequest::post()
+            .uri(&format!("/api/v1/eth2/sign/{}", identifier))
+            .set_json(json!({ "type": "HASH", "signingRoot": hex_of(&root) }))
+            .to_request();
+        let resp = actix_test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers().get("content-type").unwrap(), "text/plain");
+        assert_eq!(actix_test::read_body(resp).await, expected.as_bytes());
+    }
+
+    // G1 points, uncompressed or compressed, with a JSON response
+    let message = G1Point::generator().scalar_mul(ark_bn254::Fr::from(5u64));
+    let expected = hex_of(&g1_to_bytes(&LocalSigner.scalar_mul(&alice, &message).unwrap()));
+    for encoded in [hex_of(&g1_to_bytes(&message)), hex_of(&g1_to_compressed(&message))] {
+        let req = actix_test::TestRequest::post()
+            .uri(&format!("/api/v1/eth2/sign/{}", hex_of(&g1_to_compressed(&g1))))
+            .insert_header(("Accept", "application/json"))
+            .set_json(json!({ "type": "G1_POINT", "message": encoded }))
+            .to_request();
+        let response: Web3SignerResponse = actix_test::call_and_read_body_json(&app, req).await;
+        assert_eq!(response.signature, expected);
+        let signature = hex::decode(response.signature.trim_start_matches("0x")).unwrap();
+        assert_eq!(g1_from_bytes(&signature).unwrap(), LocalSigner.scalar_mul(&alice, &message).unwrap());
+    }
+}
+
+#[actix_web::test]
+async fn test_web3signer_errors() {
+    let store = player_store().await;
+    let alice: KeyPair = store.get_key_pair(ALICE).await.unwrap().unwrap();
+    let app = web3signer_app!(store.clone(), endpoints());
+    let identifier = hex_of(&g1_to_compressed(&alice.public_key_g1.to_checked_g1_point().unwrap()));
+    let call = |identifier: &str, body: Value| {
+        let req = actix_test::TestRequest::post()
+            .uri(&format!("/api/v1/eth2/sign/{}", identifier))
+            .set_json(body)
+            .to_request();
+        let app = &app;
+        async move {
+            let resp = actix_test::call_service(app, req).await;
+            let status = resp.status().as_u16();
+            let body: Value = actix_test::read_body_json(resp).await;
+            (status, body["error"].as_str().unwrap_or("").to_string(), body["field"].clone())
+        }
+    };
+    let root = hex_of(&[1u8; 32]);
+
+    let unknown = hex_of(&g1_to_compressed(&G1Point::generator()));
+    assert_eq!(call(&unknown, json!({ "type": "HASH", "signingRoot": root })).await.1, "unknown_key");
+    assert_eq!(call("0x1234", json!({ "type": "HASH", "signingRoot": root })).await.1, "invalid_public_key");
+    let (status, error, field) = call(&identifier, json!({ "type": "G1_POINT", "signingRoot": root })).await;
+    assert_eq!((status, error.as_str(), field), (400, "missing_field", json!("message")));
+    let body = json!({ "type": "HASH", "signingRoot": root, "message": root });
+    assert_eq!(call(&identifier, body).await.1, "conflicting_fields");
+    let body = json!({ "type": "G1_POINT", "message": hex_of(&[1u8; 64]) });
+    assert_eq!(call(&identifier, body).await.1, "invalid_point");
+
+    // A second message for the same task is a precondition failure, like slashing protection
+    let context = json!({ "avs": "0xAb5801a7D398351b8bE11C439e05C5B3259aeC9B", "task_index": 3 });
+    let body = json!({ "type": "HASH", "signingRoot": root, "context": context });
+    let req = actix_test::TestRequest::post()
+        .uri(&format!("/api/v1/eth2/sign/{}", identifier))
+        .set_json(body)
+        .to_request();
+    assert_eq!(actix_test::call_service(&app, req).await.status(), 200);
+    let body = json!({ "type": "HASH", "signingRoot": hex_of(&[2u8; 32]), "context": context });
+    let (status, error, _) = call(&identifier, body).await;
+    assert_eq!((status, error.as_str()), (412, "equivocation"));
+
+    // The endpoints are off by default
+    let app = web3signer_app!(store, EndpointsConfig::default());
+    let req = actix_test::TestRequest::get().uri("/api/v1/eth2/publicKeys").to_request();
+    assert_eq!(actix_test::call_service(&app, req).await.status(), 404);
+}
+
+#[actix_web::test]
+async fn test_web3signer_hides_keys_of_other_eoas() {
+    let store = player_store().await;
+    let alice: KeyPair = store.get_key_pair(ALICE).await.unwrap().unwrap();
+    let tokens = Arc::new(MemoryTokenStore::new());
+    let (bearer, token) = ApiToken::generate("bob", Some(vec![BOB.to_string()]), vec![Operation::Sign], None);
+    tokens.insert_token(token).await.unwrap();
+    let audit = Arc::new(MemoryAuditLog::new());
+    let app = TestApp {
+        authenticator: web::Data::new(Authenticator::Tokens(tokens)),
+        audit: web::Data::from(audit.clone() as Arc<dyn AuditLog>),
+        endpoints: endpoints(),
+        ..TestApp::new(store)
+    };
+    let app = actix_test::init_service(app.register(App::new())).await;
+    let call = |identifier: String| {
+        let req = actix_test::TestRequest::post()
+            .uri(&format!("/api/v1/eth2/sign/{}", identifier))
+            .insert_header(("Authorization", format!("Bearer {}", bearer)))
+            .set_json(json!({ "type": "HASH", "signingRoot": hex_of(&[1u8; 32]) }))
+            .to_request();
+        let app = &app;
+        async move {
+            let resp = actix_test::call_service(app, req).await;
+            let status = resp.status().as_u16();
+            let body: Value = actix_test::read_body_json(resp).await;
+            (status, body["error"].clone(), body["field"].clone())
+        }
+    };
+
+    // Alice's key is reported exactly like a key the service does not hold
+    let other = call(hex_of(&g1_to_compressed(&alice.public_key_g1.to_checked_g1_point().unwrap()))).await;
+    let unknown = call(hex_of(&g1_to_compressed(&G1Point::generator()))).await;
+    assert_eq!(other, (404, json!("unknown_key"), json!("identifier")));
+    assert_eq!(other, unknown);
+
+    // Both attempts are audited, the first under the EOA of the matched key
+    let entries = audit.entries().await.unwrap();
+    let records: Vec<(&str, &str)> = entries
+        .iter()
+        .map(|entry| (entry.record.eoa_address.as_str(), entry.record.result.as_str()))
+        .collect();
+    let generator = hex_of(&g1_to_compressed(&G1Point::generator()));
+    assert_eq!(records, [(ALICE, "unknown_key"), (generator.as_str(), "unknown_key")]);
+}
+
+#[test]
+fn test_web3signer_config() {
+    let mut config = Config::default();
+    assert!(!config.endpoints.web3signer);
+    config
+        .apply_env(vec![("BN254_ENDPOINTS_WEB3SIGNER".to_string(), "true".to_string())])
+        .unwrap();
+    assert!(config.endpoints.web3signer);
+}